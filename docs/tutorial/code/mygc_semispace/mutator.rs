@@ -40,6 +40,7 @@ pub fn mygc_mutator_release<VM: VMBinding>(
             .unwrap()
             .tospace(),
     ));
+    mutator.reset_nonmoving_allocator();
 }
 
 // Modify
@@ -48,6 +49,7 @@ lazy_static! {
         AllocationType::Default => AllocatorSelector::BumpPointer(0),
         AllocationType::Immortal | AllocationType::Code | AllocationType::ReadOnly => AllocatorSelector::BumpPointer(1),
         AllocationType::Los => AllocatorSelector::LargeObject(0),
+        AllocationType::NonMoving => AllocatorSelector::Immix(0),
     };
 }
 
@@ -65,6 +67,7 @@ pub fn create_mygc_mutator<VM: VMBinding>(
                 plan.common.get_immortal(),
             ),
             (AllocatorSelector::LargeObject(0), plan.common.get_los()),
+            (AllocatorSelector::Immix(0), plan.common.get_nonmoving()),
         ],
         prepare_func: &mygc_mutator_prepare, // Modify
         release_func: &mygc_mutator_release, // Modify
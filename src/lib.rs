@@ -60,7 +60,7 @@ pub mod scheduler;
 pub mod vm;
 
 pub use crate::mm::memory_manager;
-pub use crate::mmtk::MMTK;
+pub use crate::mmtk::{MMTKBuilder, MMTK};
 pub use crate::plan::{
     AllocationSemantics, CopyContext, Mutator, MutatorContext, Plan, TraceLocal, TransitiveClosure,
 };
@@ -19,6 +19,9 @@ use crate::util::alloc::allocators::AllocatorSelector;
 use crate::util::constants::LOG_BYTES_IN_PAGE;
 use crate::util::heap::layout::vm_layout_constants::HEAP_END;
 use crate::util::heap::layout::vm_layout_constants::HEAP_START;
+use crate::util::heap::HeapSize;
+use crate::util::options::{OptionDescription, OptionError};
+use crate::util::statistics::sink::StatsSnapshot;
 use crate::util::OpaquePointer;
 use crate::util::{Address, ObjectReference};
 use crate::vm::Collection;
@@ -40,16 +43,19 @@ pub fn start_control_collector<VM: VMBinding>(mmtk: &MMTK<VM>, tls: OpaquePointe
 ///
 /// Arguments:
 /// * `mmtk`: A reference to an MMTk instance to initialize.
-/// * `heap_size`: The heap size for the MMTk instance in bytes.
-pub fn gc_init<VM: VMBinding>(mmtk: &'static mut MMTK<VM>, heap_size: usize) {
+/// * `heap_size`: The heap size for the MMTk instance. Either a fixed size in bytes, or the min and max
+///   heap sizes in bytes, between which the heap is resized after each GC with the given growth policy.
+pub fn gc_init<VM: VMBinding>(mmtk: &'static mut MMTK<VM>, heap_size: HeapSize) {
     match crate::util::logger::try_init() {
         Ok(_) => debug!("MMTk initialized the logger."),
         Err(_) => debug!(
             "MMTk failed to initialize the logger. Possibly a logger has been initialized by user."
         ),
     }
-    assert!(heap_size > 0, "Invalid heap size");
-    mmtk.plan.gc_init(heap_size, &mmtk.vm_map, &mmtk.scheduler);
+    assert!(heap_size.initial_bytes() > 0, "Invalid heap size");
+    mmtk.plan.base().heap_growth_manager.init(heap_size);
+    mmtk.plan
+        .gc_init(heap_size.initial_bytes(), &mmtk.vm_map, &mmtk.scheduler);
     info!("Initialized MMTk with {:?}", mmtk.options.plan);
 }
 
@@ -167,24 +173,68 @@ pub fn start_worker<VM: VMBinding>(
 ///   Collection::spawn_worker_thread() so that the VM knows the context.
 pub fn enable_collection<VM: VMBinding>(mmtk: &'static MMTK<VM>, tls: OpaquePointer) {
     mmtk.scheduler.initialize(mmtk.options.threads, mmtk, tls);
+    if !mmtk.options.work_trace_file.is_empty() {
+        mmtk.scheduler.enable_work_trace(
+            mmtk.options.work_trace_buffer_size,
+            &mmtk.options.work_trace_file,
+        );
+    }
     VM::VMCollection::spawn_worker_thread(tls, None); // spawn controller thread
     mmtk.plan.base().initialized.store(true, Ordering::SeqCst);
 }
 
-/// Process MMTk run-time options.
+/// Process MMTk run-time options. Returns false if the option is not set.
+/// Prefer setting the options with [MMTKBuilder](../mmtk/struct.MMTKBuilder.html) before the MMTk instance is created,
+/// or use `set_option()` to get the reason for an invalid option.
 ///
 /// Arguments:
 /// * `mmtk`: A reference to an MMTk instance.
 /// * `name`: The name of the option.
 /// * `value`: The value of the option (as a string).
 pub fn process<VM: VMBinding>(mmtk: &'static MMTK<VM>, name: &str, value: &str) -> bool {
-    // Note that currently we cannot process options for setting plan,
-    // as we have set plan when creating an MMTK instance, and processing options is after creating on an instance.
-    // The only way to set plan is to use the env var 'MMTK_PLAN'.
-    // FIXME: We should remove this function, and ask for options when creating an MMTk instance.
-    assert!(name != "plan");
+    match set_option(mmtk, name, value) {
+        Ok(()) => true,
+        Err(e) => {
+            warn!("{}. The option is not changed.", e);
+            false
+        }
+    }
+}
+
+/// Set an MMTk run-time option. This should be called before `gc_init()`. The plan
+/// cannot be set this way, as it is created with the MMTk instance.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `name`: The name of the option, in snake case or camel case.
+/// * `value`: The value of the option (as a string).
+pub fn set_option<VM: VMBinding>(
+    mmtk: &'static MMTK<VM>,
+    name: &str,
+    value: &str,
+) -> Result<(), OptionError> {
+    if name == "plan" {
+        return Err(OptionError::SetAfterCreation(name.to_string()));
+    }
+    unsafe { mmtk.options.try_process(name, value) }
+}
+
+/// Return all the options of an MMTk instance with their current values, default values and
+/// documentation, e.g. for the help output of a VM.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+pub fn describe_options<VM: VMBinding>(mmtk: &MMTK<VM>) -> Vec<OptionDescription> {
+    mmtk.options.describe()
+}
 
-    unsafe { mmtk.options.process(name, value) }
+/// Return a snapshot of the statistics gathered since `harness_begin()`, with the totals and a record for each GC.
+/// This should only be called after `enable_collection()`.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+pub fn stats_snapshot<VM: VMBinding>(mmtk: &'static MMTK<VM>) -> StatsSnapshot {
+    mmtk.plan.base().stats.snapshot(mmtk)
 }
 
 /// Return used memory in bytes.
@@ -204,13 +254,15 @@ pub fn free_bytes<VM: VMBinding>(mmtk: &MMTK<VM>) -> usize {
 }
 
 /// Return the starting address of the heap. *Note that currently MMTk uses
-/// a fixed address range as heap.*
+/// a fixed address range as heap. An instance created with `MMTKBuilder::heap_range()`
+/// only uses its own part of the range, from `MMTK::heap_start` to `MMTK::heap_end`.*
 pub fn starting_heap_address() -> Address {
     HEAP_START
 }
 
 /// Return the ending address of the heap. *Note that currently MMTk uses
-/// a fixed address range as heap.*
+/// a fixed address range as heap. An instance created with `MMTKBuilder::heap_range()`
+/// only uses its own part of the range, from `MMTK::heap_start` to `MMTK::heap_end`.*
 pub fn last_heap_address() -> Address {
     HEAP_END
 }
@@ -232,6 +284,53 @@ pub fn handle_user_collection_request<VM: VMBinding>(mmtk: &MMTK<VM>, tls: Opaqu
     mmtk.plan.handle_user_collection_request(tls, false);
 }
 
+/// Write a heap dump of the objects reachable from the roots to `file`. The mutators are stopped
+/// (as for a GC) while the heap dump is taken, and this blocks until it is written.
+/// See the [heap_dump](../util/heap_dump/index.html) module for the format, and `read_heap_dump()` to load it.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `tls`: The thread that requests the heap dump.
+/// * `file`: The file to write the heap dump to.
+pub fn dump_heap<VM: VMBinding>(
+    mmtk: &MMTK<VM>,
+    tls: OpaquePointer,
+    file: &str,
+) -> std::io::Result<()> {
+    if !mmtk.plan.is_initialized() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            "A heap dump can only be taken after enable_collection()",
+        ));
+    }
+    mmtk.heap_dumper.request(file);
+    mmtk.plan.base().control_collector_context.request();
+    VM::VMCollection::block_for_gc(tls);
+    mmtk.heap_dumper.take_result().unwrap_or_else(|| {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            "The heap dump was not taken",
+        ))
+    })
+}
+
+/// Call `f` for each object allocated by MMTk, in no particular order. This includes the objects that are
+/// dead but not yet reclaimed by a GC, but not the objects in the VM space. The VM must make sure that no
+/// mutator allocates and no GC runs while the objects are enumerated (for example, by stopping all the
+/// other mutators at a safepoint).
+///
+/// The spaces find their objects with the global alloc bit if the `global_alloc_bit` feature is enabled.
+/// Otherwise, the bump pointer spaces walk their objects with `ObjectModel::get_object_from_start_address()`,
+/// and the other spaces keep track of their objects themselves.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `f`: The closure to call for each object.
+pub fn enumerate_objects<VM: VMBinding>(mmtk: &MMTK<VM>, mut f: impl FnMut(ObjectReference)) {
+    mmtk.plan
+        .for_each_space(&mut |space| space.enumerate_objects(&mut f));
+}
+
 /// Is the object alive?
 ///
 /// Arguments:
@@ -240,6 +339,16 @@ pub fn is_live_object(object: ObjectReference) -> bool {
     object.is_live()
 }
 
+/// Will the object never be moved by a GC? This is true for the objects in non-moving spaces,
+/// e.g. the objects allocated with `AllocationSemantics::NonMoving`. A pinned object is not moved
+/// while it is pinned, but this still returns false for it if its space may move objects.
+///
+/// Arguments:
+/// * `object`: The object reference to query.
+pub fn will_never_move(object: ObjectReference) -> bool {
+    !object.is_movable()
+}
+
 /// Is the object in the mapped memory?
 ///
 /// Arguments:
@@ -256,6 +365,52 @@ pub fn is_mapped_address(address: Address) -> bool {
     address.is_mapped()
 }
 
+/// Is there an object allocated by MMTk at the address? This is true from when the object is allocated
+/// (`post_alloc()`) or copied, until a GC reclaims it, so it may also be true for a dead object.
+/// Objects that malloc places beyond the address range covered by the SFT map are not recognized.
+/// This needs the `global_alloc_bit` feature.
+///
+/// Arguments:
+/// * `addr`: The address to query. It does not have to be word aligned, or point into the heap.
+#[cfg(feature = "global_alloc_bit")]
+pub fn is_mmtk_object(addr: Address) -> bool {
+    crate::mmtk::SFT_MAP.is_in_any_space(addr) && crate::util::alloc_bit::is_alloced_object(addr)
+}
+
+/// Pin an object, so no GC moves it until it is unpinned. Copy spaces (used by `SemiSpace` and `GenCopy`)
+/// and Immix spaces mark pinned objects in place. Pinning an object in a non-moving space has no effect.
+/// Note that `MarkCompact` does not support pinning, and a pinned object in the nursery of `GenCopy`
+/// makes every later GC a full-heap GC until the object is unpinned.
+/// Returns true if this call pinned the object, and false if it was already pinned.
+/// This needs the `object_pinning` feature.
+///
+/// Arguments:
+/// * `object`: The object to pin.
+#[cfg(feature = "object_pinning")]
+pub fn pin_object(object: ObjectReference) -> bool {
+    crate::util::pin_bit::pin_object(object)
+}
+
+/// Unpin an object, so GCs may move it again.
+/// Returns true if this call unpinned the object, and false if it was not pinned.
+/// This needs the `object_pinning` feature.
+///
+/// Arguments:
+/// * `object`: The object to unpin.
+#[cfg(feature = "object_pinning")]
+pub fn unpin_object(object: ObjectReference) -> bool {
+    crate::util::pin_bit::unpin_object(object)
+}
+
+/// Is the object pinned? This needs the `object_pinning` feature.
+///
+/// Arguments:
+/// * `object`: The object to query.
+#[cfg(feature = "object_pinning")]
+pub fn is_pinned(object: ObjectReference) -> bool {
+    crate::util::pin_bit::is_pinned(object)
+}
+
 /// Check that if a garbage collection is in progress and if the given
 /// object is not movable.  If it is movable error messages are
 /// logged and the system exits.
@@ -312,6 +467,67 @@ pub fn add_phantom_candidate<VM: VMBinding>(
         .add_phantom_candidate::<VM>(reff, referent);
 }
 
+/// Add an ephemeron: an entry of a weak key-value container. The value is only kept alive while the key and
+/// the container are reachable, so the VM should not report the key and the value as edges when it scans the
+/// container. In each GC, MMTk reads the entry with `ReferenceGlue::get_ephemeron_key()` and `get_ephemeron_value()`,
+/// and updates or clears it with `ReferenceGlue::update_ephemeron()`. This is not affected by `no_reference_types`.
+/// The RC and ConcMarkSweep plans do not support ephemerons (see `PlanConstraints::supports_ephemerons`),
+/// and the VM should scan the keys and the values as normal edges with them.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `container`: The object that holds the ephemeron.
+/// * `id`: The id of the ephemeron within its container (e.g. an index). MMTk passes it back to `ReferenceGlue`.
+pub fn add_ephemeron_candidate<VM: VMBinding>(
+    mmtk: &MMTK<VM>,
+    container: ObjectReference,
+    id: usize,
+) {
+    assert!(
+        mmtk.plan.constraints().supports_ephemerons,
+        "add_ephemeron_candidate() is called, but the plan does not support ephemerons"
+    );
+    mmtk.reference_processors
+        .ephemerons
+        .add_candidate(container, id);
+}
+
+/// Register a finalizable object. MMTk will keep the object alive once it becomes unreachable,
+/// and hand it back through `get_finalized_object()` so that the VM can run its finalizer.
+/// The RC and ConcMarkSweep plans do not support finalizers, and ignore the object.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `object`: The object that has a finalizer.
+pub fn add_finalizer<VM: VMBinding>(mmtk: &'static MMTK<VM>, object: ObjectReference) {
+    if mmtk.options.no_finalizer {
+        warn!("add_finalizer() is called when no_finalizer = true");
+    }
+    if !mmtk.plan.constraints().supports_finalizers {
+        warn!("add_finalizer() is called, but the plan does not support finalizers");
+        return;
+    }
+
+    mmtk.finalizable_processor.lock().unwrap().add(object);
+}
+
+/// Get an object that was registered with `add_finalizer()` and has become unreachable. Its finalizer
+/// can be run now, and it is removed from the finalizable objects. Returns `None` if there is no such object.
+/// The VM should call this from a mutator thread, after MMTk informs it with `Collection::schedule_finalization()`.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+pub fn get_finalized_object<VM: VMBinding>(mmtk: &'static MMTK<VM>) -> Option<ObjectReference> {
+    if mmtk.options.no_finalizer {
+        warn!("get_finalized_object() is called when no_finalizer = true");
+    }
+
+    mmtk.finalizable_processor
+        .lock()
+        .unwrap()
+        .get_ready_object()
+}
+
 /// Generic hook to allow benchmarks to be harnessed. We do a full heap
 /// GC, and then start recording statistics for MMTk.
 ///
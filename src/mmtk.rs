@@ -1,20 +1,24 @@
 use crate::plan::Plan;
 use crate::policy::space::SFTMap;
 use crate::scheduler::Scheduler;
+use crate::util::finalizable_processor::FinalizableProcessor;
 use crate::util::heap::layout::heap_layout::Mmapper;
 use crate::util::heap::layout::heap_layout::VMMap;
 use crate::util::heap::layout::map::Map;
-use crate::util::options::{Options, UnsafeOptionsWrapper};
+use crate::util::heap::layout::vm_layout_constants::{HEAP_END, HEAP_START, MAX_SPACE_EXTENT};
+use crate::util::heap::HeapMeta;
+use crate::util::heap_dump::HeapDumper;
+use crate::util::options::{
+    OptionError, Options, OptionsBuilder, PlanSelector, UnsafeOptionsWrapper,
+};
 use crate::util::reference_processor::ReferenceProcessors;
 #[cfg(feature = "sanity")]
 use crate::util::sanity::sanity_checker::SanityChecker;
-use crate::util::OpaquePointer;
+use crate::util::{Address, OpaquePointer};
 use crate::vm::VMBinding;
 use std::default::Default;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-#[cfg(feature = "sanity")]
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
 lazy_static! {
     // I am not sure if we should include these mmappers as part of MMTk struct.
@@ -23,22 +27,198 @@ lazy_static! {
     //    part of MMTK, as creating MMTK requires these mappers. We could use Rc/Arc for these mappers though.
     // 2. These mmappers are possibly global across multiple MMTk instances, as they manage the
     //    entire address space.
-    // TODO: We should refactor this when we know more about how multiple MMTK instances work.
+    // The maps are shared by all the MMTk instances in the process, and partitioned between them by address:
+    // each instance only creates spaces in its own heap range (see `MMTKBuilder::heap_range()`).
     pub static ref VM_MAP: VMMap = VMMap::new();
     pub static ref MMAPPER: Mmapper = Mmapper::new();
     pub static ref SFT_MAP: SFTMap = SFTMap::new();
+    static ref INSTANCES: Mutex<InstanceRegistry> = Mutex::new(InstanceRegistry::default());
+}
+
+/// The MMTk instances in the process.
+#[derive(Default)]
+struct InstanceRegistry {
+    /// The id of the next instance.
+    next_id: usize,
+    /// The heap ranges used by the instances, with the id of each instance. They never overlap.
+    heap_ranges: Vec<(usize, Address, Address)>,
+    /// The instance that uses a malloc space, if any. The objects in malloc spaces are not in the heap range
+    /// of their instance, so their chunks could be shared with another malloc space.
+    malloc_space_user: Option<usize>,
+    /// Whether an instance was ever created. Only used on 32-bit, where the VM map can not be reused.
+    created: bool,
+}
+
+impl InstanceRegistry {
+    /// Check that an instance with the given plan could use the heap range from `start` to `end`. Panics if the
+    /// range is not valid, or if the instance could share any memory with an existing instance.
+    fn check(&self, plan: PlanSelector, start: Address, end: Address) {
+        assert!(start < end, "Empty heap range {} - {}", start, end);
+        if cfg!(target_pointer_width = "64") {
+            // Each space takes one or more slots of MAX_SPACE_EXTENT bytes
+            assert!(
+                start.is_aligned_to(MAX_SPACE_EXTENT) && end.is_aligned_to(MAX_SPACE_EXTENT),
+                "The heap range {} - {} is not aligned to {} bytes",
+                start,
+                end,
+                MAX_SPACE_EXTENT
+            );
+        } else {
+            // Discontiguous spaces share one range of chunks in the 32-bit VM map
+            assert!(
+                !self.created,
+                "Multiple MMTk instances are only supported on 64-bit"
+            );
+        }
+        if let Some((_, s, e)) = self
+            .heap_ranges
+            .iter()
+            .find(|(_, s, e)| start < *e && *s < end)
+        {
+            panic!(
+                "The heap range {} - {} overlaps with the heap range {} - {} of another MMTk instance",
+                start, end, s, e
+            );
+        }
+        self.check_malloc_space(plan);
+    }
+
+    fn check_malloc_space(&self, plan: PlanSelector) {
+        if Self::uses_malloc_space(plan) {
+            assert!(
+                self.malloc_space_user.is_none(),
+                "Only one MMTk instance can use a plan with a malloc space"
+            );
+        }
+    }
+
+    fn uses_malloc_space(plan: PlanSelector) -> bool {
+        matches!(
+            plan,
+            PlanSelector::MarkSweep | PlanSelector::ConcMarkSweep | PlanSelector::RC
+        )
+    }
+
+    /// The largest part of the heap range (`HEAP_START` to `HEAP_END`) that is not used by any instance.
+    fn free_range(&self) -> (Address, Address) {
+        let mut used: Vec<(Address, Address)> =
+            self.heap_ranges.iter().map(|&(_, s, e)| (s, e)).collect();
+        used.sort_by_key(|(s, _)| s.as_usize());
+        let mut best = (HEAP_START, HEAP_START);
+        let mut cursor = HEAP_START;
+        for (s, e) in used
+            .into_iter()
+            .chain(std::iter::once((HEAP_END, HEAP_END)))
+        {
+            if s > cursor && s - cursor > best.1 - best.0 {
+                best = (cursor, s);
+            }
+            if e > cursor {
+                cursor = e;
+            }
+        }
+        assert!(
+            best.0 < best.1,
+            "No free heap range left for another MMTk instance"
+        );
+        best
+    }
+
+    /// Record a new instance with the given plan, which uses the given heap ranges. Returns the id of the instance.
+    fn register(&mut self, plan: PlanSelector, ranges: &[(Address, Address)]) -> usize {
+        self.check_malloc_space(plan);
+        for &(start, end) in ranges {
+            self.check(plan, start, end);
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        if Self::uses_malloc_space(plan) {
+            self.malloc_space_user = Some(id);
+        }
+        self.heap_ranges
+            .extend(ranges.iter().map(|&(start, end)| (id, start, end)));
+        self.created = true;
+        id
+    }
+
+    /// Forget the instance with the given id. Returns the heap ranges it used, which other instances can use from now on.
+    fn unregister(&mut self, id: usize) -> Vec<(Address, Address)> {
+        if self.malloc_space_user == Some(id) {
+            self.malloc_space_user = None;
+        }
+        let (released, kept) = self
+            .heap_ranges
+            .drain(..)
+            .partition(|&(instance, _, _)| instance == id);
+        self.heap_ranges = kept;
+        released.into_iter().map(|(_, s, e)| (s, e)).collect()
+    }
+}
+
+/// Creates an MMTk instance. All the options, including the plan, can be set with the builder
+/// before the instance is created, e.g.
+/// `builder.options.plan(PlanSelector::Immix)?.threads(4)?`.
+pub struct MMTKBuilder {
+    pub options: OptionsBuilder,
+    heap_range: Option<(Address, Address)>,
+}
+
+impl MMTKBuilder {
+    /// Start from the default options, and the options set by `MMTK_*` env vars.
+    /// By default, the instance creates its spaces in the largest part of the heap range (`HEAP_START` to `HEAP_END`)
+    /// that no other instance uses, and only takes the part its spaces need.
+    pub fn new() -> Self {
+        MMTKBuilder {
+            options: OptionsBuilder::new(),
+            heap_range: None,
+        }
+    }
+
+    /// Set the address range of the instance. Each instance in a process needs its own range, and all its
+    /// spaces are created in the range. On 64-bit, each space takes at least `MAX_SPACE_EXTENT` bytes, and
+    /// the range has to be aligned to `MAX_SPACE_EXTENT`.
+    pub fn heap_range(&mut self, start: Address, end: Address) -> &mut Self {
+        self.heap_range = Some((start, end));
+        self
+    }
+
+    /// Set an option from strings. See `OptionsBuilder::set()`.
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<&mut Self, OptionError> {
+        self.options.set(name, value)?;
+        Ok(self)
+    }
+
+    pub fn build<VM: VMBinding>(self) -> MMTK<VM> {
+        MMTK::create(self.options.build(), self.heap_range)
+    }
+}
+
+impl Default for MMTKBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 /// An MMTk instance. MMTk allows mutiple instances to run independently, and each instance gives users a separate heap.
-/// *Note that multi-instances is not fully supported yet*
+/// Each instance has its own plan, scheduler and options, and its own heap range (set with `MMTKBuilder::heap_range()`).
+/// *Note that `VMActivePlan::global()` needs to return the plan of the instance that the calling thread works for,
+/// and that only one instance can use a plan with a malloc space (MarkSweep, ConcMarkSweep and RC).*
 pub struct MMTK<VM: VMBinding> {
     pub plan: Box<dyn Plan<VM = VM>>,
+    /// The start of the address range of this instance
+    pub heap_start: Address,
+    /// The end of the address range of this instance
+    pub heap_end: Address,
+    /// The id of this instance in `INSTANCES`
+    instance_id: usize,
     pub vm_map: &'static VMMap,
     pub mmapper: &'static Mmapper,
     pub sftmap: &'static SFTMap,
     pub reference_processors: ReferenceProcessors,
+    pub finalizable_processor: Mutex<FinalizableProcessor>,
     pub options: Arc<UnsafeOptionsWrapper>,
     pub scheduler: Arc<Scheduler<Self>>,
+    pub heap_dumper: HeapDumper,
     #[cfg(feature = "sanity")]
     pub sanity_checker: Mutex<SanityChecker>,
     inside_harness: AtomicBool,
@@ -48,24 +228,59 @@ unsafe impl<VM: VMBinding> Send for MMTK<VM> {}
 unsafe impl<VM: VMBinding> Sync for MMTK<VM> {}
 
 impl<VM: VMBinding> MMTK<VM> {
+    /// Create an MMTk instance with the default options. Use `MMTKBuilder` to set the options.
     pub fn new() -> Self {
+        MMTKBuilder::new().build()
+    }
+
+    /// Create an MMTk instance with the given options, in the default heap range (see `MMTKBuilder::new()`).
+    pub fn with_options(options: Options) -> Self {
+        Self::create(options, None)
+    }
+
+    fn create(options: Options, heap_range: Option<(Address, Address)>) -> Self {
+        // Hold the lock until the spaces are created, so no other instance can take the same range.
+        let mut instances = INSTANCES.lock().unwrap();
+        let (start, end) = heap_range.unwrap_or_else(|| instances.free_range());
+        instances.check(options.plan, start, end);
         let scheduler = Scheduler::new();
-        let options = Arc::new(UnsafeOptionsWrapper::new(Options::default()));
+        let options = Arc::new(UnsafeOptionsWrapper::new(options));
         let plan = crate::plan::global::create_plan(
             options.plan,
             &VM_MAP,
             &MMAPPER,
             options.clone(),
+            HeapMeta::new(start, end),
             unsafe { &*(scheduler.as_ref() as *const Scheduler<MMTK<VM>>) },
         );
+        // Without an explicit range, only claim the part of the range that the spaces took. The discontiguous
+        // spaces on 32-bit take the rest of the range.
+        let used: Vec<(Address, Address)> =
+            if heap_range.is_none() && cfg!(target_pointer_width = "64") {
+                let heap = &plan.base().heap;
+                vec![(start, heap.heap_cursor), (heap.heap_limit, end)]
+                    .into_iter()
+                    .filter(|(s, e)| s < e)
+                    .collect()
+            } else {
+                vec![(start, end)]
+            };
+        let instance_id = instances.register(options.plan, &used);
+        let heap_start = used.first().map_or(start, |r| r.0);
+        let heap_end = used.last().map_or(start, |r| r.1);
         MMTK {
             plan,
+            heap_start,
+            heap_end,
+            instance_id,
             vm_map: &VM_MAP,
             mmapper: &MMAPPER,
             sftmap: &SFT_MAP,
             reference_processors: ReferenceProcessors::new(),
+            finalizable_processor: Mutex::new(FinalizableProcessor::new()),
             options,
             scheduler,
+            heap_dumper: HeapDumper::new(),
             #[cfg(feature = "sanity")]
             sanity_checker: Mutex::new(SanityChecker::new()),
             inside_harness: AtomicBool::new(false),
@@ -91,3 +306,82 @@ impl<VM: VMBinding> Default for MMTK<VM> {
         Self::new()
     }
 }
+
+/// Dropping an instance gives its heap range back to the other instances. The GC threads of the instance
+/// must have exited, and none of its objects may be used afterwards.
+impl<VM: VMBinding> Drop for MMTK<VM> {
+    fn drop(&mut self) {
+        let mut instances = INSTANCES.lock().unwrap();
+        for (start, end) in instances.unregister(self.instance_id) {
+            self.vm_map.release_range(start, end);
+            for chunk in start.chunk_index()..end.chunk_index() {
+                self.sftmap.clear(chunk);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, target_pointer_width = "64"))]
+mod tests {
+    use super::*;
+
+    fn slot(i: usize) -> Address {
+        HEAP_START + i * MAX_SPACE_EXTENT
+    }
+
+    #[test]
+    fn test_register_disjoint_ranges() {
+        let mut registry = InstanceRegistry::default();
+        let a = registry.register(PlanSelector::SemiSpace, &[(slot(0), slot(2))]);
+        let b = registry.register(PlanSelector::SemiSpace, &[(slot(2), slot(4))]);
+        assert_ne!(a, b);
+        assert_eq!(registry.heap_ranges.len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "overlaps")]
+    fn test_register_overlapping_ranges() {
+        let mut registry = InstanceRegistry::default();
+        registry.register(PlanSelector::SemiSpace, &[(slot(0), slot(2))]);
+        registry.register(PlanSelector::SemiSpace, &[(slot(1), slot(3))]);
+    }
+
+    #[test]
+    #[should_panic(expected = "not aligned")]
+    fn test_register_unaligned_range() {
+        let mut registry = InstanceRegistry::default();
+        registry.register(PlanSelector::SemiSpace, &[(slot(0) + 4096usize, slot(2))]);
+    }
+
+    #[test]
+    #[should_panic(expected = "malloc space")]
+    fn test_register_two_malloc_spaces() {
+        let mut registry = InstanceRegistry::default();
+        registry.register(PlanSelector::MarkSweep, &[(slot(0), slot(1))]);
+        registry.register(PlanSelector::MarkSweep, &[(slot(1), slot(2))]);
+    }
+
+    #[test]
+    fn test_free_range() {
+        let mut registry = InstanceRegistry::default();
+        assert_eq!(registry.free_range(), (HEAP_START, HEAP_END));
+        // The largest gap is above the first instance.
+        let a = registry.register(PlanSelector::SemiSpace, &[(slot(1), slot(3))]);
+        assert_eq!(registry.free_range(), (slot(3), HEAP_END));
+        let b = registry.register(PlanSelector::SemiSpace, &[(slot(3), HEAP_END)]);
+        assert_eq!(registry.free_range(), (HEAP_START, slot(1)));
+        // Once an instance is unregistered, its range can be used again.
+        assert_eq!(registry.unregister(b), vec![(slot(3), HEAP_END)]);
+        assert_eq!(registry.free_range(), (slot(3), HEAP_END));
+        assert_eq!(registry.unregister(a), vec![(slot(1), slot(3))]);
+        assert_eq!(registry.free_range(), (HEAP_START, HEAP_END));
+    }
+
+    #[test]
+    fn test_unregister_malloc_space() {
+        let mut registry = InstanceRegistry::default();
+        let a = registry.register(PlanSelector::MarkSweep, &[(slot(0), slot(1))]);
+        registry.unregister(a);
+        registry.register(PlanSelector::MarkSweep, &[(slot(0), slot(1))]);
+    }
+}
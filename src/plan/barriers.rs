@@ -1,15 +1,22 @@
 //! Read/Write barrier implementations.
 
+use crate::plan::transitive_closure::{EdgeCollector, ReferentCollector};
 use crate::policy::space::Space;
 use crate::scheduler::gc_work::*;
 use crate::scheduler::WorkBucketStage;
+use crate::util::side_metadata::{compare_exchange_atomic, load_atomic, SideMetadataSpec};
 use crate::util::*;
+use crate::vm::{Scanning, VMBinding};
 use crate::MMTK;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 #[derive(Copy, Clone, Debug)]
 pub enum BarrierSelector {
     NoBarrier,
     ObjectBarrier,
+    SATBBarrier,
+    FieldLoggingBarrier,
 }
 
 /// For field writes in HotSpot, we cannot always get the source object pointer and the field address
@@ -20,6 +27,8 @@ pub enum WriteTarget {
 
 pub trait Barrier: 'static + Send + Sync {
     fn flush(&mut self);
+    /// Called before the reference field(s) of the target are overwritten.
+    fn pre_write_barrier(&mut self, target: WriteTarget);
     fn post_write_barrier(&mut self, target: WriteTarget);
 }
 
@@ -27,6 +36,7 @@ pub struct NoBarrier;
 
 impl Barrier for NoBarrier {
     fn flush(&mut self) {}
+    fn pre_write_barrier(&mut self, _target: WriteTarget) {}
     fn post_write_barrier(&mut self, _target: WriteTarget) {}
 }
 
@@ -81,6 +91,7 @@ impl<E: ProcessEdgesWork, S: Space<E::VM>> Barrier for FieldRememberingBarrier<E
         self.mmtk.scheduler.work_buckets[WorkBucketStage::Closure]
             .add(ProcessModBuf::<E>::new(modified_nodes, modified_edges));
     }
+    fn pre_write_barrier(&mut self, _target: WriteTarget) {}
     fn post_write_barrier(&mut self, target: WriteTarget) {
         match target {
             WriteTarget::Object(obj) => {
@@ -96,3 +107,171 @@ impl<E: ProcessEdgesWork, S: Space<E::VM>> Barrier for FieldRememberingBarrier<E
         }
     }
 }
+
+/// A snapshot-at-the-beginning barrier for concurrent marking. While marking is in progress,
+/// it logs the referents that are about to be overwritten, so every object that was reachable
+/// when the marking started gets marked.
+pub struct SATBBarrier<E: ProcessEdgesWork> {
+    mmtk: &'static MMTK<E::VM>,
+    /// The barrier does nothing unless this is set.
+    marking: &'static AtomicBool,
+    mutator_tls: OpaquePointer,
+    satb_buffer: Vec<ObjectReference>,
+}
+
+impl<E: ProcessEdgesWork> SATBBarrier<E> {
+    pub fn new(
+        mmtk: &'static MMTK<E::VM>,
+        marking: &'static AtomicBool,
+        mutator_tls: OpaquePointer,
+    ) -> Self {
+        Self {
+            mmtk,
+            marking,
+            mutator_tls,
+            satb_buffer: vec![],
+        }
+    }
+
+    fn enqueue_referent(&mut self, object: ObjectReference) {
+        if object.is_null() {
+            return;
+        }
+        self.satb_buffer.push(object);
+        if self.satb_buffer.len() >= E::CAPACITY {
+            self.flush();
+        }
+    }
+}
+
+impl<E: ProcessEdgesWork> Barrier for SATBBarrier<E> {
+    fn flush(&mut self) {
+        if self.satb_buffer.is_empty() {
+            return;
+        }
+        let mut satb_buffer = vec![];
+        std::mem::swap(&mut satb_buffer, &mut self.satb_buffer);
+        // Marking runs while mutators are running, so the closure bucket is not open.
+        self.mmtk.scheduler.work_buckets[WorkBucketStage::Unconstrained]
+            .add(TraceObjects::<E>::new(satb_buffer));
+    }
+    fn pre_write_barrier(&mut self, target: WriteTarget) {
+        if !self.marking.load(Ordering::SeqCst) {
+            return;
+        }
+        match target {
+            WriteTarget::Object(obj) => {
+                // We do not know which field is overwritten, so log the referents of all of them.
+                let mut referents = vec![];
+                <E::VM as VMBinding>::VMScanning::scan_object(
+                    &mut ReferentCollector {
+                        referents: &mut referents,
+                    },
+                    obj,
+                    self.mutator_tls,
+                );
+                for referent in referents {
+                    self.enqueue_referent(referent);
+                }
+            }
+            WriteTarget::Slot(slot) => {
+                let referent = unsafe { slot.load::<ObjectReference>() };
+                self.enqueue_referent(referent);
+            }
+        }
+    }
+    fn post_write_barrier(&mut self, _target: WriteTarget) {}
+}
+
+/// A coalescing barrier for deferred reference counting. Each field is logged the first time it is
+/// overwritten between two GCs: its old referent is remembered for a decrement, and the field itself
+/// is remembered so its referent at the next GC gets an increment. Later writes to a logged field
+/// are coalesced, as only the first and the last referents matter.
+///
+/// Each field has a log bit in side metadata (`log_spec`), which is 0 (unlogged) until the field is logged.
+/// `I` must clear the log bits of the fields it processes, so they are logged again after the GC.
+pub struct FieldLoggingBarrier<I: ProcessEdgesWork, D: ProcessEdgesWork<VM = I::VM>> {
+    mmtk: &'static MMTK<I::VM>,
+    log_spec: SideMetadataSpec,
+    mutator_tls: OpaquePointer,
+    /// The fields logged since the last GC. Their current referents are processed by `I` in the next GC.
+    logged_fields: Vec<Address>,
+    /// The referents of the logged fields before they were overwritten. They are traced by `D` in the next GC.
+    old_referents: Vec<ObjectReference>,
+    phantom: PhantomData<D>,
+}
+
+impl<I: ProcessEdgesWork, D: ProcessEdgesWork<VM = I::VM>> FieldLoggingBarrier<I, D> {
+    pub fn new(
+        mmtk: &'static MMTK<I::VM>,
+        log_spec: SideMetadataSpec,
+        mutator_tls: OpaquePointer,
+    ) -> Self {
+        Self {
+            mmtk,
+            log_spec,
+            mutator_tls,
+            logged_fields: vec![],
+            old_referents: vec![],
+            phantom: PhantomData,
+        }
+    }
+
+    fn log_field(&mut self, slot: Address) {
+        if load_atomic(self.log_spec, slot) == 1 {
+            return;
+        }
+        // Read the old referent before we claim the field. Any other mutator that writes to the field
+        // does so only after it has failed to claim the field, i.e. after this read.
+        let old_referent = unsafe { slot.load::<ObjectReference>() };
+        if !compare_exchange_atomic(self.log_spec, slot, 0, 1) {
+            return;
+        }
+        if !old_referent.is_null() {
+            self.old_referents.push(old_referent);
+        }
+        self.logged_fields.push(slot);
+        if self.logged_fields.len() >= I::CAPACITY || self.old_referents.len() >= D::CAPACITY {
+            self.flush();
+        }
+    }
+}
+
+impl<I: ProcessEdgesWork, D: ProcessEdgesWork<VM = I::VM>> Barrier for FieldLoggingBarrier<I, D> {
+    fn flush(&mut self) {
+        if !self.logged_fields.is_empty() {
+            let mut logged_fields = vec![];
+            std::mem::swap(&mut logged_fields, &mut self.logged_fields);
+            self.mmtk.scheduler.work_buckets[WorkBucketStage::Closure].add(I::new(
+                logged_fields,
+                false,
+                self.mmtk,
+            ));
+        }
+        if !self.old_referents.is_empty() {
+            let mut old_referents = vec![];
+            std::mem::swap(&mut old_referents, &mut self.old_referents);
+            // Decrements may free objects, so they wait until the increments in the closure are done.
+            self.mmtk.scheduler.work_buckets[WorkBucketStage::Release]
+                .add(TraceObjects::<D>::new(old_referents));
+        }
+    }
+    fn pre_write_barrier(&mut self, target: WriteTarget) {
+        match target {
+            WriteTarget::Object(obj) => {
+                // We do not know which field is overwritten, so log all of them.
+                let mut edges = vec![];
+                <I::VM as VMBinding>::VMScanning::scan_object(
+                    &mut EdgeCollector { edges: &mut edges },
+                    obj,
+                    self.mutator_tls,
+                );
+                for edge in edges {
+                    self.log_field(edge);
+                }
+            }
+            WriteTarget::Slot(slot) => self.log_field(slot),
+        }
+    }
+    fn post_write_barrier(&mut self, _target: WriteTarget) {}
+}
@@ -0,0 +1,99 @@
+use super::global::{ConcMarkSweep, Phase};
+use crate::plan::global::{GcStatus, NoCopy};
+use crate::plan::MutatorContext;
+use crate::policy::space::Space;
+use crate::scheduler::gc_work::*;
+use crate::scheduler::{CoordinatorWork, GCWork, GCWorker};
+use crate::util::{Address, ObjectReference};
+use crate::vm::{Collection, VMBinding};
+use crate::MMTK;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+
+/// The edge processor for all the marking in this plan, both in the pauses and concurrently with the mutators.
+/// Each instance is counted as pending marking work until it is dropped, so the plan knows
+/// when the concurrent marking runs out of work.
+pub struct CMSProcessEdges<VM: VMBinding> {
+    plan: &'static ConcMarkSweep<VM>,
+    base: ProcessEdgesBase<CMSProcessEdges<VM>>,
+}
+
+impl<VM: VMBinding> ProcessEdgesWork for CMSProcessEdges<VM> {
+    type VM = VM;
+    // The mutators may write to the slots concurrently. Nothing moves, so there is nothing to write back.
+    const OVERWRITE_REFERENCE: bool = false;
+    fn new(edges: Vec<Address>, _roots: bool, mmtk: &'static MMTK<VM>) -> Self {
+        let base = ProcessEdgesBase::new(edges, mmtk);
+        let plan = base.plan().downcast_ref::<ConcMarkSweep<VM>>().unwrap();
+        plan.begin_marking_work();
+        Self { plan, base }
+    }
+    #[inline]
+    fn trace_object(&mut self, object: ObjectReference) -> ObjectReference {
+        if object.is_null() {
+            return object;
+        }
+        if self.plan.ms.in_space(object) {
+            self.plan.ms.trace_object::<Self>(self, object)
+        } else {
+            self.plan
+                .common
+                .trace_object::<Self, NoCopy<VM>>(self, object)
+        }
+    }
+    #[cold]
+    fn flush(&mut self) {
+        let mut new_nodes = vec![];
+        mem::swap(&mut new_nodes, &mut self.nodes);
+        if self.plan.phase() == Phase::InitialMark {
+            // Only mark the objects directly reachable from the roots in the initial mark pause.
+            self.plan.defer_gray_objects(new_nodes);
+            return;
+        }
+        // The closure bucket is only open in a pause.
+        let concurrent = !self.plan.base().gc_in_progress();
+        self.worker()
+            .do_work(ScanObjects::<Self>::new(new_nodes, concurrent));
+    }
+}
+
+impl<VM: VMBinding> Drop for CMSProcessEdges<VM> {
+    fn drop(&mut self) {
+        self.plan.end_marking_work();
+    }
+}
+
+impl<VM: VMBinding> Deref for CMSProcessEdges<VM> {
+    type Target = ProcessEdgesBase<Self>;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl<VM: VMBinding> DerefMut for CMSProcessEdges<VM> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.base
+    }
+}
+
+/// The finalizer of the initial mark pause. Resume the mutators, and start the concurrent marking.
+#[derive(Default)]
+pub struct EndOfInitialMark;
+
+impl<VM: VMBinding> GCWork<VM> for EndOfInitialMark {
+    fn do_work(&mut self, worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        let plan = mmtk.plan.downcast_ref::<ConcMarkSweep<VM>>().unwrap();
+        plan.base().set_gc_status(GcStatus::NotInGC);
+        plan.start_concurrent_marking(mmtk);
+        plan.base().gc_log.gc_finished(
+            &*mmtk.plan,
+            "initial_mark",
+            &mmtk.scheduler.stage_durations(),
+        );
+        <VM as VMBinding>::VMCollection::resume_mutators(worker.tls);
+    }
+}
+
+impl<VM: VMBinding> CoordinatorWork<MMTK<VM>> for EndOfInitialMark {}
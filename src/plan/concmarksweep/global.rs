@@ -0,0 +1,304 @@
+use super::gc_work::{CMSProcessEdges, EndOfInitialMark};
+use crate::mmtk::MMTK;
+use crate::plan::barriers::BarrierSelector;
+use crate::plan::concmarksweep::mutator::ALLOCATOR_MAPPING;
+use crate::plan::global::BasePlan;
+use crate::plan::global::CommonPlan;
+use crate::plan::global::GcStatus;
+use crate::plan::global::NoCopy;
+use crate::plan::global::CORE_GLOBAL_SIDE_METADATA_PER_CHUNK;
+use crate::plan::marksweep::gc_work::MSSweepChunks;
+use crate::plan::AllocationSemantics;
+use crate::plan::Plan;
+use crate::plan::PlanConstraints;
+use crate::policy::mallocspace::MallocSpace;
+use crate::policy::space::Space;
+use crate::scheduler::gc_work::*;
+use crate::scheduler::*;
+use crate::util::alloc::allocators::AllocatorSelector;
+#[cfg(feature = "analysis")]
+use crate::util::analysis::GcHookWork;
+use crate::util::heap::layout::heap_layout::Mmapper;
+use crate::util::heap::layout::heap_layout::VMMap;
+use crate::util::heap::HeapMeta;
+use crate::util::options::UnsafeOptionsWrapper;
+#[cfg(feature = "sanity")]
+use crate::util::sanity::sanity_checker::*;
+use crate::util::side_metadata::meta_bytes_per_chunk;
+use crate::util::{ObjectReference, OpaquePointer};
+use crate::vm::ObjectModel;
+use crate::vm::VMBinding;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use enum_map::EnumMap;
+
+/// Start a concurrent marking once this percentage of the heap is reserved.
+const CONCURRENT_MARKING_TRIGGER_PERCENT: usize = 50;
+
+/// The phases of a concurrent collection cycle.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// No collection is in progress.
+    Idle,
+    /// A concurrent collection is requested, but the initial mark pause has not started yet.
+    InitialMarkRequested,
+    /// The initial mark pause: mark the objects directly reachable from the roots.
+    InitialMark,
+    /// The mutators are running, and the GC workers trace the objects reachable from the roots.
+    ConcurrentMarking,
+    /// The concurrent marking has run out of work, and the final mark pause is requested.
+    FinalMarkRequested,
+    /// The final mark pause (or a stop-the-world collection): finish the marking, and sweep.
+    FinalMark,
+}
+
+/// A mark-sweep plan that marks the heap concurrently with the mutators.
+///
+/// A collection cycle starts with a short initial mark pause that marks the objects directly
+/// reachable from the roots. The rest of the heap is then traced by the GC workers while the
+/// mutators run. A snapshot-at-the-beginning barrier logs the referents that the mutators overwrite,
+/// and the objects allocated during the marking are allocated marked. Once the marking runs out of work,
+/// a final mark pause rescans the roots, finishes the marking and sweeps the malloc space.
+pub struct ConcMarkSweep<VM: VMBinding> {
+    pub common: CommonPlan<VM>,
+    pub ms: MallocSpace<VM>,
+    /// Set while the mutators need to log overwritten referents (`SATBBarrier`).
+    pub marking: AtomicBool,
+    phase: Mutex<Phase>,
+    /// The number of the marking work packets (`CMSProcessEdges`) that are not finished yet.
+    pending_marking_work: AtomicUsize,
+    /// Marked objects found by the initial mark pause. They are scanned once the mutators resume.
+    initial_gray_objects: Mutex<Vec<Vec<ObjectReference>>>,
+}
+
+unsafe impl<VM: VMBinding> Sync for ConcMarkSweep<VM> {}
+
+pub const CMS_CONSTRAINTS: PlanConstraints = PlanConstraints {
+    moves_objects: false,
+    gc_header_bits: 2,
+    gc_header_words: 0,
+    num_specialized_scans: 1,
+    barrier: BarrierSelector::SATBBarrier,
+    needs_concurrent_workers: true,
+    // The concurrent marking does not resurrect the finalizable objects.
+    supports_finalizers: false,
+    // The concurrent marking does not trace the values of the ephemerons.
+    supports_ephemerons: false,
+    ..PlanConstraints::default()
+};
+
+impl<VM: VMBinding> Plan for ConcMarkSweep<VM> {
+    type VM = VM;
+
+    fn constraints(&self) -> &'static PlanConstraints {
+        &CMS_CONSTRAINTS
+    }
+
+    fn create_worker_local(
+        &self,
+        tls: OpaquePointer,
+        mmtk: &'static MMTK<Self::VM>,
+    ) -> GCWorkerLocalPtr {
+        let mut c = NoCopy::new(mmtk);
+        c.init(tls);
+        GCWorkerLocalPtr::new(c)
+    }
+
+    fn gc_init(
+        &mut self,
+        heap_size: usize,
+        vm_map: &'static VMMap,
+        scheduler: &Arc<MMTkScheduler<VM>>,
+    ) {
+        self.common.gc_init(heap_size, vm_map, scheduler);
+        self.ms.init(&vm_map);
+    }
+
+    fn schedule_collection(&'static self, scheduler: &MMTkScheduler<VM>) {
+        self.base().set_collection_kind();
+        self.base().set_gc_status(GcStatus::GcPrepare);
+        let phase = {
+            let mut phase = self.phase.lock().unwrap();
+            let last_phase = *phase;
+            *phase = if last_phase == Phase::InitialMarkRequested {
+                Phase::InitialMark
+            } else {
+                Phase::FinalMark
+            };
+            last_phase
+        };
+        // Stop & scan mutators (mutator scanning can happen before STW)
+        scheduler.work_buckets[WorkBucketStage::Unconstrained]
+            .add(StopMutators::<CMSProcessEdges<VM>>::new());
+        match phase {
+            Phase::InitialMarkRequested => {
+                // Prepare global/collectors/mutators
+                scheduler.work_buckets[WorkBucketStage::Prepare]
+                    .add(Prepare::<Self, NoCopy<VM>>::new(self));
+                // Resume mutators, and then start the concurrent marking
+                scheduler.set_finalizer(Some(EndOfInitialMark));
+                return;
+            }
+            Phase::ConcurrentMarking | Phase::FinalMarkRequested => {
+                // The mutators may still hold referents that are not pushed to the marking
+                scheduler.work_buckets[WorkBucketStage::Prepare].add(FlushMutators::<VM>::new());
+            }
+            Phase::Idle => {
+                // A stop-the-world collection. Prepare global/collectors/mutators
+                scheduler.work_buckets[WorkBucketStage::Prepare]
+                    .add(Prepare::<Self, NoCopy<VM>>::new(self));
+            }
+            Phase::InitialMark | Phase::FinalMark => unreachable!(),
+        }
+        // Let the VM process its weak roots after the closure
+        scheduler.work_buckets[WorkBucketStage::PhantomRefClosure].add(
+            ProcessWeakRoots::<VM>::new(WorkBucketStage::PhantomRefClosure),
+        );
+        // Release global/collectors/mutators
+        scheduler.work_buckets[WorkBucketStage::Release]
+            .add(Release::<Self, NoCopy<VM>>::new(self));
+        // Sweep the malloc space in parallel, once the transitive closure is done
+        scheduler.work_buckets[WorkBucketStage::Release].add(MSSweepChunks::<VM>::new(&self.ms));
+        // Scheduling all the gc hooks of analysis routines. It is generally recommended
+        // to take advantage of the scheduling system we have in place for more performance
+        #[cfg(feature = "analysis")]
+        scheduler.work_buckets[WorkBucketStage::Unconstrained].add(GcHookWork);
+        // Resume mutators
+        #[cfg(feature = "sanity")]
+        scheduler.work_buckets[WorkBucketStage::Final]
+            .add(ScheduleSanityGC::<Self, NoCopy<VM>>::new());
+        scheduler.set_finalizer(Some(EndOfGC));
+    }
+
+    fn get_allocator_mapping(&self) -> &'static EnumMap<AllocationSemantics, AllocatorSelector> {
+        &*ALLOCATOR_MAPPING
+    }
+
+    fn prepare(&self, tls: OpaquePointer) {
+        self.common.prepare(tls, true);
+        if self.phase() == Phase::InitialMark {
+            // The mutators are stopped. Everything they allocate from now on is live in this cycle,
+            // and everything they overwrite needs to be traced.
+            self.ms.set_allocate_marked(true);
+            self.marking.store(true, Ordering::SeqCst);
+        }
+    }
+
+    fn release(&self, tls: OpaquePointer) {
+        self.common.release(tls, true);
+        self.ms.set_allocate_marked(false);
+        self.marking.store(false, Ordering::SeqCst);
+        *self.phase.lock().unwrap() = Phase::Idle;
+    }
+
+    fn concurrent_collection_required(&self) -> bool {
+        if self.get_pages_reserved() * 100
+            < self.get_total_pages() * CONCURRENT_MARKING_TRIGGER_PERCENT
+        {
+            return false;
+        }
+        let mut phase = self.phase.lock().unwrap();
+        if *phase == Phase::Idle {
+            *phase = Phase::InitialMarkRequested;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn get_pages_used(&self) -> usize {
+        self.ms.reserved_pages() + self.common.get_pages_used()
+    }
+
+    fn for_each_space(&self, f: &mut dyn FnMut(&dyn Space<VM>)) {
+        f(&self.ms);
+        self.common.for_each_space(f);
+    }
+
+    fn base(&self) -> &BasePlan<VM> {
+        &self.common.base
+    }
+
+    fn common(&self) -> &CommonPlan<VM> {
+        &self.common
+    }
+
+    fn global_side_metadata_per_chunk(&self) -> usize {
+        CORE_GLOBAL_SIDE_METADATA_PER_CHUNK
+            + if !VM::VMObjectModel::HAS_GC_BYTE {
+                meta_bytes_per_chunk(3, 1)
+            } else {
+                0
+            }
+    }
+}
+
+impl<VM: VMBinding> ConcMarkSweep<VM> {
+    pub fn new(
+        vm_map: &'static VMMap,
+        mmapper: &'static Mmapper,
+        options: Arc<UnsafeOptionsWrapper>,
+        heap: HeapMeta,
+        _scheduler: &'static MMTkScheduler<VM>,
+    ) -> Self {
+        ConcMarkSweep {
+            common: CommonPlan::new(vm_map, mmapper, options, heap, &CMS_CONSTRAINTS),
+            ms: MallocSpace::new(),
+            marking: AtomicBool::new(false),
+            phase: Mutex::new(Phase::Idle),
+            pending_marking_work: AtomicUsize::new(0),
+            initial_gray_objects: Mutex::new(vec![]),
+        }
+    }
+
+    pub fn phase(&self) -> Phase {
+        *self.phase.lock().unwrap()
+    }
+
+    /// Keep the marked objects found by the initial mark pause, so they are scanned once the mutators resume.
+    pub fn defer_gray_objects(&self, objects: Vec<ObjectReference>) {
+        debug_assert_eq!(self.phase(), Phase::InitialMark);
+        self.initial_gray_objects.lock().unwrap().push(objects);
+    }
+
+    /// Called at the end of the initial mark pause. Push the work for the concurrent marking.
+    pub fn start_concurrent_marking(&self, mmtk: &'static MMTK<VM>) {
+        {
+            let mut phase = self.phase.lock().unwrap();
+            debug_assert_eq!(*phase, Phase::InitialMark);
+            *phase = Phase::ConcurrentMarking;
+        }
+        // Do not let the marking finish before all the work is pushed
+        self.begin_marking_work();
+        let gray_objects: Vec<_> = self
+            .initial_gray_objects
+            .lock()
+            .unwrap()
+            .drain(..)
+            .collect();
+        for objects in gray_objects {
+            let mut work = CMSProcessEdges::new(vec![], false, mmtk);
+            work.nodes = objects;
+            mmtk.scheduler.work_buckets[WorkBucketStage::Unconstrained].add(work);
+        }
+        self.end_marking_work();
+    }
+
+    pub fn begin_marking_work(&self) {
+        self.pending_marking_work.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Called when a marking work packet is done. If there is no marking work left
+    /// during the concurrent marking, request the final mark pause.
+    pub fn end_marking_work(&self) {
+        if self.pending_marking_work.fetch_sub(1, Ordering::SeqCst) != 1 {
+            return;
+        }
+        let mut phase = self.phase.lock().unwrap();
+        if *phase == Phase::ConcurrentMarking {
+            *phase = Phase::FinalMarkRequested;
+            self.base().control_collector_context.request();
+        }
+    }
+}
@@ -0,0 +1,8 @@
+//! Plan: concurrent marksweep (with malloc)
+
+pub(super) mod gc_work;
+pub(super) mod global;
+pub(super) mod mutator;
+
+pub use self::global::ConcMarkSweep;
+pub use self::global::CMS_CONSTRAINTS;
@@ -0,0 +1,53 @@
+use super::gc_work::CMSProcessEdges;
+use super::ConcMarkSweep;
+use crate::plan::barriers::SATBBarrier;
+use crate::plan::mutator_context::Mutator;
+use crate::plan::mutator_context::MutatorConfig;
+use crate::plan::AllocationSemantics as AllocationType;
+use crate::util::alloc::allocators::{AllocatorSelector, Allocators};
+use crate::util::OpaquePointer;
+use crate::vm::VMBinding;
+use crate::MMTK;
+use enum_map::enum_map;
+use enum_map::EnumMap;
+
+pub fn cms_mutator_prepare<VM: VMBinding>(_mutator: &mut Mutator<VM>, _tls: OpaquePointer) {
+    // Do nothing
+}
+
+pub fn cms_mutator_release<VM: VMBinding>(_mutator: &mut Mutator<VM>, _tls: OpaquePointer) {
+    // Do nothing
+}
+
+lazy_static! {
+    pub static ref ALLOCATOR_MAPPING: EnumMap<AllocationType, AllocatorSelector> = enum_map! {
+        AllocationType::Default | AllocationType::NonMoving => AllocatorSelector::Malloc(0),
+        AllocationType::Immortal | AllocationType::Code | AllocationType::ReadOnly => AllocatorSelector::BumpPointer(0),
+        AllocationType::Los => AllocatorSelector::LargeObject(0),
+    };
+}
+
+pub fn create_cms_mutator<VM: VMBinding>(
+    mutator_tls: OpaquePointer,
+    mmtk: &'static MMTK<VM>,
+) -> Mutator<VM> {
+    let cms = mmtk.plan.downcast_ref::<ConcMarkSweep<VM>>().unwrap();
+    let config = MutatorConfig {
+        allocator_mapping: &*ALLOCATOR_MAPPING,
+        space_mapping: box vec![
+            (AllocatorSelector::Malloc(0), &cms.ms),
+            (AllocatorSelector::BumpPointer(0), cms.common.get_immortal()),
+            (AllocatorSelector::LargeObject(0), cms.common.get_los()),
+        ],
+        prepare_func: &cms_mutator_prepare,
+        release_func: &cms_mutator_release,
+    };
+
+    Mutator {
+        allocators: Allocators::<VM>::new(mutator_tls, &*mmtk.plan, &config.space_mapping),
+        barrier: box SATBBarrier::<CMSProcessEdges<VM>>::new(mmtk, &cms.marking, mutator_tls),
+        mutator_tls,
+        config,
+        plan: cms,
+    }
+}
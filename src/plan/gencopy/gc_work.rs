@@ -1,12 +1,12 @@
 use super::global::GenCopy;
 use crate::plan::CopyContext;
+use crate::plan::Plan;
 use crate::plan::PlanConstraints;
 use crate::policy::space::Space;
 use crate::scheduler::gc_work::*;
 use crate::scheduler::WorkerLocal;
 use crate::scheduler::{GCWork, GCWorker, WorkBucketStage};
 use crate::util::alloc::{Allocator, BumpAllocator};
-use crate::util::forwarding_word;
 use crate::util::{Address, ObjectReference, OpaquePointer};
 use crate::vm::*;
 use crate::MMTK;
@@ -41,7 +41,7 @@ impl<VM: VMBinding> CopyContext for GenCopyCopyContext<VM> {
         offset: isize,
         _semantics: crate::AllocationSemantics,
     ) -> Address {
-        debug_assert!(VM::VMActivePlan::global().base().gc_in_progress_proper());
+        debug_assert!(self.plan.base().gc_in_progress_proper());
         self.ss.alloc(bytes, align, offset)
     }
     #[inline(always)]
@@ -52,7 +52,7 @@ impl<VM: VMBinding> CopyContext for GenCopyCopyContext<VM> {
         _bytes: usize,
         _semantics: crate::AllocationSemantics,
     ) {
-        forwarding_word::clear_forwarding_bits::<VM>(obj);
+        self.plan.tospace().post_copy(obj);
     }
 }
 
@@ -106,16 +106,32 @@ impl<VM: VMBinding> ProcessEdgesWork for GenCopyNurseryProcessEdges<VM> {
                     unsafe { self.worker().local::<GenCopyCopyContext<VM>>() },
                 );
         }
-        debug_assert!(!self.gencopy().fromspace().in_space(object));
-        debug_assert!(self.gencopy().tospace().in_space(object));
+        // Keep the young large objects alive. The large object space ignores its mature objects in a nursery GC.
+        if self.gencopy().common.get_los().in_space(object) {
+            return self
+                .gencopy()
+                .common
+                .trace_object::<Self, GenCopyCopyContext<VM>>(self, object);
+        }
+        // The mature from-space may hold objects that were kept in place by the last full-heap GC.
+        debug_assert!(
+            !self.gencopy().fromspace().in_space(object)
+                || self.gencopy().fromspace().is_retained()
+        );
         object
     }
     #[inline]
     fn process_edge(&mut self, slot: Address) {
-        debug_assert!(!self.gencopy().fromspace().address_in_space(slot));
+        debug_assert!(
+            !self.gencopy().fromspace().address_in_space(slot)
+                || self.gencopy().fromspace().is_retained()
+        );
         let object = unsafe { slot.load::<ObjectReference>() };
         let new_object = self.trace_object(object);
-        debug_assert!(!self.gencopy().nursery.in_space(new_object));
+        debug_assert!(
+            !self.gencopy().nursery.in_space(new_object)
+                || self.gencopy().nursery.has_objects_kept_in_place()
+        );
         unsafe { slot.store(new_object) };
     }
 }
@@ -3,6 +3,7 @@ use super::mutator::ALLOCATOR_MAPPING;
 use crate::plan::global::BasePlan;
 use crate::plan::global::CommonPlan;
 use crate::plan::global::GcStatus;
+use crate::plan::global::CORE_GLOBAL_SIDE_METADATA_PER_CHUNK;
 use crate::plan::AllocationSemantics;
 use crate::plan::Plan;
 use crate::plan::PlanConstraints;
@@ -12,12 +13,14 @@ use crate::scheduler::gc_work::*;
 use crate::scheduler::*;
 use crate::util::alloc::allocators::AllocatorSelector;
 use crate::util::constants::LOG_BYTES_IN_PAGE;
+use crate::util::ephemeron_processor::schedule_ephemeron_processing;
+use crate::util::finalizable_processor::Finalization;
 use crate::util::heap::layout::heap_layout::Mmapper;
 use crate::util::heap::layout::heap_layout::VMMap;
-use crate::util::heap::layout::vm_layout_constants::{HEAP_END, HEAP_START};
 use crate::util::heap::HeapMeta;
 use crate::util::heap::VMRequest;
 use crate::util::options::UnsafeOptionsWrapper;
+use crate::util::reference_processor::schedule_reference_processing;
 #[cfg(feature = "sanity")]
 use crate::util::sanity::sanity_checker::*;
 use crate::util::side_metadata::meta_bytes_per_chunk;
@@ -108,6 +111,34 @@ impl<VM: VMBinding> Plan for GenCopy<VM> {
         // Prepare global/collectors/mutators
         scheduler.work_buckets[WorkBucketStage::Prepare]
             .add(Prepare::<Self, GenCopyCopyContext<VM>>::new(self));
+        // Process the soft, weak and phantom references after the closure
+        if !self.base().options.no_reference_types {
+            if in_nursery {
+                schedule_reference_processing::<GenCopyNurseryProcessEdges<VM>>(scheduler);
+            } else {
+                schedule_reference_processing::<GenCopyMatureProcessEdges<VM>>(scheduler);
+            }
+        }
+        // Trace the values of the ephemerons whose keys are reachable after the closure
+        if in_nursery {
+            schedule_ephemeron_processing::<GenCopyNurseryProcessEdges<VM>>(scheduler);
+        } else {
+            schedule_ephemeron_processing::<GenCopyMatureProcessEdges<VM>>(scheduler);
+        }
+        // Resurrect the unreachable finalizable objects after the closure
+        if !self.base().options.no_finalizer {
+            if in_nursery {
+                scheduler.work_buckets[WorkBucketStage::FinalRefClosure]
+                    .add(Finalization::<GenCopyNurseryProcessEdges<VM>>::new());
+            } else {
+                scheduler.work_buckets[WorkBucketStage::FinalRefClosure]
+                    .add(Finalization::<GenCopyMatureProcessEdges<VM>>::new());
+            }
+        }
+        // Let the VM process its weak roots after the closure
+        scheduler.work_buckets[WorkBucketStage::PhantomRefClosure].add(
+            ProcessWeakRoots::<VM>::new(WorkBucketStage::PhantomRefClosure),
+        );
         // Release global/collectors/mutators
         scheduler.work_buckets[WorkBucketStage::Release]
             .add(Release::<Self, GenCopyCopyContext<VM>>::new(self));
@@ -123,7 +154,8 @@ impl<VM: VMBinding> Plan for GenCopy<VM> {
     }
 
     fn prepare(&self, tls: OpaquePointer) {
-        self.common.prepare(tls, true);
+        // A nursery GC must not sweep the mature objects in the common spaces, as it does not trace them.
+        self.common.prepare(tls, !self.in_nursery());
         self.nursery.prepare(true);
         if !self.in_nursery() {
             self.hi
@@ -135,10 +167,11 @@ impl<VM: VMBinding> Plan for GenCopy<VM> {
     }
 
     fn release(&self, tls: OpaquePointer) {
-        self.common.release(tls, true);
+        self.common.release(tls, !self.in_nursery());
         self.nursery.release();
         if !self.in_nursery() {
             self.fromspace().release();
+            self.tospace().release_to_space();
         }
     }
 
@@ -152,6 +185,13 @@ impl<VM: VMBinding> Plan for GenCopy<VM> {
             + self.common.get_pages_used()
     }
 
+    fn for_each_space(&self, f: &mut dyn FnMut(&dyn Space<VM>)) {
+        f(&self.nursery);
+        f(&self.copyspace0);
+        f(&self.copyspace1);
+        self.common.for_each_space(f);
+    }
+
     fn base(&self) -> &BasePlan<VM> {
         &self.common.base
     }
@@ -165,11 +205,12 @@ impl<VM: VMBinding> Plan for GenCopy<VM> {
     }
 
     fn global_side_metadata_per_chunk(&self) -> usize {
-        if !VM::VMObjectModel::HAS_GC_BYTE {
-            meta_bytes_per_chunk(3, 1)
-        } else {
-            0
-        }
+        CORE_GLOBAL_SIDE_METADATA_PER_CHUNK
+            + if !VM::VMObjectModel::HAS_GC_BYTE {
+                meta_bytes_per_chunk(3, 1)
+            } else {
+                0
+            }
     }
 }
 
@@ -178,10 +219,9 @@ impl<VM: VMBinding> GenCopy<VM> {
         vm_map: &'static VMMap,
         mmapper: &'static Mmapper,
         options: Arc<UnsafeOptionsWrapper>,
+        mut heap: HeapMeta,
         scheduler: &'static MMTkScheduler<VM>,
     ) -> Self {
-        let mut heap = HeapMeta::new(HEAP_START, HEAP_END);
-
         GenCopy {
             nursery: CopySpace::new(
                 "nursery",
@@ -218,7 +258,9 @@ impl<VM: VMBinding> GenCopy<VM> {
     }
 
     fn request_full_heap_collection(&self) -> bool {
-        self.get_total_pages() <= self.get_pages_reserved()
+        // The remembered sets do not cover the objects kept in place in the nursery by the last GC,
+        // so they have to be found with a full-heap trace.
+        self.get_total_pages() <= self.get_pages_reserved() || self.nursery.is_retained()
     }
 
     pub fn tospace(&self) -> &CopySpace<VM> {
@@ -27,6 +27,7 @@ pub fn gencopy_mutator_release<VM: VMBinding>(mutator: &mut Mutator<VM>, _tls: O
     .downcast_mut::<BumpAllocator<VM>>()
     .unwrap();
     bump_allocator.reset();
+    mutator.reset_nonmoving_allocator();
 }
 
 lazy_static! {
@@ -34,6 +35,7 @@ lazy_static! {
         AllocationType::Default => AllocatorSelector::BumpPointer(0),
         AllocationType::Immortal | AllocationType::Code | AllocationType::ReadOnly => AllocatorSelector::BumpPointer(1),
         AllocationType::Los => AllocatorSelector::LargeObject(0),
+        AllocationType::NonMoving => AllocatorSelector::Immix(0),
     };
 }
 
@@ -51,6 +53,7 @@ pub fn create_gencopy_mutator<VM: VMBinding>(
                 gencopy.common.get_immortal(),
             ),
             (AllocatorSelector::LargeObject(0), gencopy.common.get_los()),
+            (AllocatorSelector::Immix(0), gencopy.common.get_nonmoving()),
         ],
         prepare_func: &gencopy_mutator_prepare,
         release_func: &gencopy_mutator_release,
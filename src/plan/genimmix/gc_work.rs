@@ -0,0 +1,212 @@
+use super::global::GenImmix;
+use crate::plan::CopyContext;
+use crate::plan::Plan;
+use crate::plan::PlanConstraints;
+use crate::policy::space::Space;
+use crate::scheduler::gc_work::*;
+use crate::scheduler::WorkerLocal;
+use crate::util::alloc::{Allocator, ImmixAllocator};
+use crate::util::{Address, ObjectReference, OpaquePointer};
+use crate::vm::*;
+use crate::MMTK;
+use std::ops::{Deref, DerefMut};
+
+/// Allocates the copies of promoted nursery objects (and of evacuated mature objects,
+/// during a defrag GC) in the immix mature space.
+pub struct GenImmixCopyContext<VM: VMBinding> {
+    plan: &'static GenImmix<VM>,
+    immix: ImmixAllocator<VM>,
+}
+
+impl<VM: VMBinding> CopyContext for GenImmixCopyContext<VM> {
+    type VM = VM;
+
+    fn constraints(&self) -> &'static PlanConstraints {
+        &super::global::GENIMMIX_CONSTRAINTS
+    }
+    fn init(&mut self, tls: OpaquePointer) {
+        self.immix.tls = tls;
+    }
+    fn prepare(&mut self) {
+        self.immix.reset();
+    }
+    fn release(&mut self) {
+        self.immix.reset();
+    }
+    #[inline(always)]
+    fn alloc_copy(
+        &mut self,
+        _original: ObjectReference,
+        bytes: usize,
+        align: usize,
+        offset: isize,
+        _semantics: crate::AllocationSemantics,
+    ) -> Address {
+        debug_assert!(self.plan.base().gc_in_progress_proper());
+        self.immix.alloc(bytes, align, offset)
+    }
+    #[inline(always)]
+    fn post_copy(
+        &mut self,
+        obj: ObjectReference,
+        _tib: Address,
+        bytes: usize,
+        _semantics: crate::AllocationSemantics,
+    ) {
+        self.plan.immix_space.post_copy(obj, bytes);
+    }
+}
+
+impl<VM: VMBinding> GenImmixCopyContext<VM> {
+    pub fn new(mmtk: &'static MMTK<VM>) -> Self {
+        let plan = mmtk.plan.downcast_ref::<GenImmix<VM>>().unwrap();
+        Self {
+            plan,
+            immix: ImmixAllocator::new(
+                OpaquePointer::UNINITIALIZED,
+                Some(&plan.immix_space),
+                &*mmtk.plan,
+                true,
+            ),
+        }
+    }
+}
+
+impl<VM: VMBinding> WorkerLocal for GenImmixCopyContext<VM> {
+    fn init(&mut self, tls: OpaquePointer) {
+        CopyContext::init(self, tls);
+    }
+}
+
+pub struct GenImmixNurseryProcessEdges<VM: VMBinding> {
+    plan: &'static GenImmix<VM>,
+    base: ProcessEdgesBase<GenImmixNurseryProcessEdges<VM>>,
+}
+
+impl<VM: VMBinding> GenImmixNurseryProcessEdges<VM> {
+    fn genimmix(&self) -> &'static GenImmix<VM> {
+        self.plan
+    }
+}
+
+impl<VM: VMBinding> ProcessEdgesWork for GenImmixNurseryProcessEdges<VM> {
+    type VM = VM;
+    fn new(edges: Vec<Address>, _roots: bool, mmtk: &'static MMTK<VM>) -> Self {
+        let base = ProcessEdgesBase::new(edges, mmtk);
+        let plan = base.plan().downcast_ref::<GenImmix<VM>>().unwrap();
+        Self { base, plan }
+    }
+    #[inline]
+    fn trace_object(&mut self, object: ObjectReference) -> ObjectReference {
+        if object.is_null() {
+            return object;
+        }
+        // Promote nursery objects into the mature space
+        if self.genimmix().nursery.in_space(object) {
+            return self
+                .genimmix()
+                .nursery
+                .trace_object::<Self, GenImmixCopyContext<VM>>(
+                    self,
+                    object,
+                    super::global::ALLOC_GENIMMIX,
+                    unsafe { self.worker().local::<GenImmixCopyContext<VM>>() },
+                );
+        }
+        // Keep the young large objects alive. The large object space ignores its mature objects in a nursery GC.
+        if self.genimmix().common.get_los().in_space(object) {
+            return self
+                .genimmix()
+                .common
+                .trace_object::<Self, GenImmixCopyContext<VM>>(self, object);
+        }
+        // Mature objects are live by definition. Their references into the nursery are in the remembered set.
+        object
+    }
+    #[inline]
+    fn process_edge(&mut self, slot: Address) {
+        let object = unsafe { slot.load::<ObjectReference>() };
+        let new_object = self.trace_object(object);
+        debug_assert!(!self.genimmix().nursery.in_space(new_object));
+        unsafe { slot.store(new_object) };
+    }
+}
+
+impl<VM: VMBinding> Deref for GenImmixNurseryProcessEdges<VM> {
+    type Target = ProcessEdgesBase<Self>;
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl<VM: VMBinding> DerefMut for GenImmixNurseryProcessEdges<VM> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.base
+    }
+}
+
+pub struct GenImmixMatureProcessEdges<VM: VMBinding> {
+    plan: &'static GenImmix<VM>,
+    base: ProcessEdgesBase<GenImmixMatureProcessEdges<VM>>,
+}
+
+impl<VM: VMBinding> GenImmixMatureProcessEdges<VM> {
+    fn genimmix(&self) -> &'static GenImmix<VM> {
+        self.plan
+    }
+}
+
+impl<VM: VMBinding> ProcessEdgesWork for GenImmixMatureProcessEdges<VM> {
+    type VM = VM;
+    fn new(edges: Vec<Address>, _roots: bool, mmtk: &'static MMTK<VM>) -> Self {
+        let base = ProcessEdgesBase::new(edges, mmtk);
+        let plan = base.plan().downcast_ref::<GenImmix<VM>>().unwrap();
+        Self { base, plan }
+    }
+    #[inline]
+    fn trace_object(&mut self, object: ObjectReference) -> ObjectReference {
+        if object.is_null() {
+            return object;
+        }
+        // Promote nursery objects into the mature space
+        if self.genimmix().nursery.in_space(object) {
+            return self
+                .genimmix()
+                .nursery
+                .trace_object::<Self, GenImmixCopyContext<VM>>(
+                    self,
+                    object,
+                    super::global::ALLOC_GENIMMIX,
+                    unsafe { self.worker().local::<GenImmixCopyContext<VM>>() },
+                );
+        }
+        // Mark mature objects, and evacuate those in defrag source blocks
+        if self.genimmix().immix_space.in_space(object) {
+            return self
+                .genimmix()
+                .immix_space
+                .trace_object::<Self, GenImmixCopyContext<VM>>(
+                    self,
+                    object,
+                    super::global::ALLOC_GENIMMIX,
+                    unsafe { self.worker().local::<GenImmixCopyContext<VM>>() },
+                );
+        }
+        self.genimmix()
+            .common
+            .trace_object::<Self, GenImmixCopyContext<VM>>(self, object)
+    }
+}
+
+impl<VM: VMBinding> Deref for GenImmixMatureProcessEdges<VM> {
+    type Target = ProcessEdgesBase<Self>;
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl<VM: VMBinding> DerefMut for GenImmixMatureProcessEdges<VM> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.base
+    }
+}
@@ -0,0 +1,245 @@
+use super::gc_work::{
+    GenImmixCopyContext, GenImmixMatureProcessEdges, GenImmixNurseryProcessEdges,
+};
+use super::mutator::ALLOCATOR_MAPPING;
+use crate::plan::global::BasePlan;
+use crate::plan::global::CommonPlan;
+use crate::plan::global::GcStatus;
+use crate::plan::global::CORE_GLOBAL_SIDE_METADATA_PER_CHUNK;
+use crate::plan::AllocationSemantics;
+use crate::plan::Plan;
+use crate::plan::PlanConstraints;
+use crate::policy::copyspace::CopySpace;
+use crate::policy::immix::{ImmixSpace, MAX_IMMIX_OBJECT_SIZE};
+use crate::policy::space::Space;
+use crate::scheduler::gc_work::*;
+use crate::scheduler::*;
+use crate::util::alloc::allocators::AllocatorSelector;
+use crate::util::constants::LOG_BYTES_IN_PAGE;
+use crate::util::ephemeron_processor::schedule_ephemeron_processing;
+use crate::util::finalizable_processor::Finalization;
+use crate::util::heap::layout::heap_layout::Mmapper;
+use crate::util::heap::layout::heap_layout::VMMap;
+use crate::util::heap::HeapMeta;
+use crate::util::heap::VMRequest;
+use crate::util::options::UnsafeOptionsWrapper;
+use crate::util::reference_processor::schedule_reference_processing;
+#[cfg(feature = "sanity")]
+use crate::util::sanity::sanity_checker::*;
+use crate::util::side_metadata::meta_bytes_per_chunk;
+use crate::util::OpaquePointer;
+use crate::vm::ObjectModel;
+use crate::vm::*;
+use crate::{mmtk::MMTK, plan::barriers::BarrierSelector};
+use enum_map::EnumMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+pub const ALLOC_GENIMMIX: AllocationSemantics = AllocationSemantics::Default;
+pub const NURSERY_SIZE: usize = 16 * 1024 * 1024;
+
+/// A generational plan with a copying nursery and an immix mature space. Unlike `GenCopy`,
+/// full heap GCs do not need to reserve half of the mature space for copying.
+pub struct GenImmix<VM: VMBinding> {
+    pub nursery: CopySpace<VM>,
+    pub immix_space: ImmixSpace<VM>,
+    pub common: CommonPlan<VM>,
+    in_nursery: AtomicBool,
+}
+
+unsafe impl<VM: VMBinding> Sync for GenImmix<VM> {}
+
+pub const GENIMMIX_CONSTRAINTS: PlanConstraints = PlanConstraints {
+    moves_objects: true,
+    gc_header_bits: 2,
+    gc_header_words: 0,
+    num_specialized_scans: 1,
+    max_non_los_copy_bytes: MAX_IMMIX_OBJECT_SIZE,
+    // Larger nursery objects could not be promoted into the immix space.
+    max_non_los_default_alloc_bytes: MAX_IMMIX_OBJECT_SIZE,
+    barrier: BarrierSelector::ObjectBarrier,
+    ..PlanConstraints::default()
+};
+
+impl<VM: VMBinding> Plan for GenImmix<VM> {
+    type VM = VM;
+
+    fn constraints(&self) -> &'static PlanConstraints {
+        &GENIMMIX_CONSTRAINTS
+    }
+
+    fn create_worker_local(
+        &self,
+        tls: OpaquePointer,
+        mmtk: &'static MMTK<Self::VM>,
+    ) -> GCWorkerLocalPtr {
+        let mut c = GenImmixCopyContext::new(mmtk);
+        c.init(tls);
+        GCWorkerLocalPtr::new(c)
+    }
+
+    fn collection_required(&self, space_full: bool, _space: &dyn Space<Self::VM>) -> bool
+    where
+        Self: Sized,
+    {
+        let nursery_full = self.nursery.reserved_pages() >= (NURSERY_SIZE >> LOG_BYTES_IN_PAGE);
+        let heap_full = self.get_pages_reserved() > self.get_total_pages();
+        space_full || nursery_full || heap_full
+    }
+
+    fn gc_init(
+        &mut self,
+        heap_size: usize,
+        vm_map: &'static VMMap,
+        scheduler: &Arc<MMTkScheduler<VM>>,
+    ) {
+        self.common.gc_init(heap_size, vm_map, scheduler);
+        self.nursery.init(&vm_map);
+        self.immix_space.init(&vm_map);
+    }
+
+    fn schedule_collection(&'static self, scheduler: &MMTkScheduler<VM>) {
+        let in_nursery = !self.request_full_heap_collection();
+        self.in_nursery.store(in_nursery, Ordering::SeqCst);
+        self.base().set_collection_kind();
+        self.base().set_gc_status(GcStatus::GcPrepare);
+
+        // Stop & scan mutators (mutator scanning can happen before STW)
+        if in_nursery {
+            scheduler.work_buckets[WorkBucketStage::Unconstrained]
+                .add(StopMutators::<GenImmixNurseryProcessEdges<VM>>::new());
+        } else {
+            scheduler.work_buckets[WorkBucketStage::Unconstrained]
+                .add(StopMutators::<GenImmixMatureProcessEdges<VM>>::new());
+        }
+        // Prepare global/collectors/mutators
+        scheduler.work_buckets[WorkBucketStage::Prepare]
+            .add(Prepare::<Self, GenImmixCopyContext<VM>>::new(self));
+        // Process the soft, weak and phantom references after the closure
+        if !self.base().options.no_reference_types {
+            if in_nursery {
+                schedule_reference_processing::<GenImmixNurseryProcessEdges<VM>>(scheduler);
+            } else {
+                schedule_reference_processing::<GenImmixMatureProcessEdges<VM>>(scheduler);
+            }
+        }
+        // Trace the values of the ephemerons whose keys are reachable after the closure
+        if in_nursery {
+            schedule_ephemeron_processing::<GenImmixNurseryProcessEdges<VM>>(scheduler);
+        } else {
+            schedule_ephemeron_processing::<GenImmixMatureProcessEdges<VM>>(scheduler);
+        }
+        // Resurrect the unreachable finalizable objects after the closure
+        if !self.base().options.no_finalizer {
+            if in_nursery {
+                scheduler.work_buckets[WorkBucketStage::FinalRefClosure]
+                    .add(Finalization::<GenImmixNurseryProcessEdges<VM>>::new());
+            } else {
+                scheduler.work_buckets[WorkBucketStage::FinalRefClosure]
+                    .add(Finalization::<GenImmixMatureProcessEdges<VM>>::new());
+            }
+        }
+        // Let the VM process its weak roots after the closure
+        scheduler.work_buckets[WorkBucketStage::PhantomRefClosure].add(
+            ProcessWeakRoots::<VM>::new(WorkBucketStage::PhantomRefClosure),
+        );
+        // Release global/collectors/mutators
+        scheduler.work_buckets[WorkBucketStage::Release]
+            .add(Release::<Self, GenImmixCopyContext<VM>>::new(self));
+        // Resume mutators
+        #[cfg(feature = "sanity")]
+        scheduler.work_buckets[WorkBucketStage::Final]
+            .add(ScheduleSanityGC::<Self, GenImmixCopyContext<VM>>::new());
+        scheduler.set_finalizer(Some(EndOfGC));
+    }
+
+    fn get_allocator_mapping(&self) -> &'static EnumMap<AllocationSemantics, AllocatorSelector> {
+        &*ALLOCATOR_MAPPING
+    }
+
+    fn prepare(&self, tls: OpaquePointer) {
+        let full_heap = !self.in_nursery();
+        self.common.prepare(tls, full_heap);
+        self.nursery.prepare(true);
+        // A nursery GC does not trace the mature space, so the marks from the last full heap GC
+        // (and from the promoted objects since then) must stay intact.
+        if full_heap {
+            self.immix_space.prepare(self.is_emergency_collection());
+        }
+    }
+
+    fn release(&self, tls: OpaquePointer) {
+        let full_heap = !self.in_nursery();
+        self.common.release(tls, full_heap);
+        self.nursery.release();
+        if full_heap {
+            self.immix_space.release();
+        }
+    }
+
+    fn get_collection_reserve(&self) -> usize {
+        self.nursery.reserved_pages() + self.immix_space.defrag_headroom_pages()
+    }
+
+    fn get_pages_used(&self) -> usize {
+        self.nursery.reserved_pages()
+            + self.immix_space.reserved_pages()
+            + self.common.get_pages_used()
+    }
+
+    fn for_each_space(&self, f: &mut dyn FnMut(&dyn Space<VM>)) {
+        f(&self.nursery);
+        f(&self.immix_space);
+        self.common.for_each_space(f);
+    }
+
+    fn base(&self) -> &BasePlan<VM> {
+        &self.common.base
+    }
+
+    fn common(&self) -> &CommonPlan<VM> {
+        &self.common
+    }
+
+    fn in_nursery(&self) -> bool {
+        self.in_nursery.load(Ordering::SeqCst)
+    }
+
+    fn global_side_metadata_per_chunk(&self) -> usize {
+        CORE_GLOBAL_SIDE_METADATA_PER_CHUNK
+            + if !VM::VMObjectModel::HAS_GC_BYTE {
+                meta_bytes_per_chunk(3, 1)
+            } else {
+                0
+            }
+    }
+}
+
+impl<VM: VMBinding> GenImmix<VM> {
+    pub fn new(
+        vm_map: &'static VMMap,
+        mmapper: &'static Mmapper,
+        options: Arc<UnsafeOptionsWrapper>,
+        mut heap: HeapMeta,
+        _scheduler: &'static MMTkScheduler<VM>,
+    ) -> Self {
+        GenImmix {
+            nursery: CopySpace::new(
+                "nursery",
+                false,
+                true,
+                VMRequest::fixed_extent(NURSERY_SIZE, false),
+                vm_map,
+                mmapper,
+                &mut heap,
+            ),
+            immix_space: ImmixSpace::new("immix", true, vm_map, mmapper, &mut heap),
+            common: CommonPlan::new(vm_map, mmapper, options, heap, &GENIMMIX_CONSTRAINTS),
+            in_nursery: AtomicBool::default(),
+        }
+    }
+
+    fn request_full_heap_collection(&self) -> bool {
+        self.get_total_pages() <= self.get_pages_reserved()
+    }
+}
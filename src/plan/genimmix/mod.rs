@@ -0,0 +1,8 @@
+//! Plan: generational immix (a copying nursery with a non-moving immix mature space)
+
+pub(super) mod gc_work;
+pub(super) mod global;
+pub(super) mod mutator;
+
+pub use self::global::GenImmix;
+pub use self::global::GENIMMIX_CONSTRAINTS;
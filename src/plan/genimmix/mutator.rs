@@ -0,0 +1,72 @@
+use super::gc_work::*;
+use super::GenImmix;
+use crate::plan::barriers::*;
+use crate::plan::mutator_context::Mutator;
+use crate::plan::mutator_context::MutatorConfig;
+use crate::plan::AllocationSemantics as AllocationType;
+use crate::policy::copyspace::CopySpace;
+use crate::util::alloc::allocators::{AllocatorSelector, Allocators};
+use crate::util::alloc::BumpAllocator;
+use crate::util::OpaquePointer;
+use crate::vm::VMBinding;
+use crate::MMTK;
+use enum_map::enum_map;
+use enum_map::EnumMap;
+
+pub fn genimmix_mutator_prepare<VM: VMBinding>(_mutator: &mut Mutator<VM>, _tls: OpaquePointer) {
+    // Do nothing
+}
+
+pub fn genimmix_mutator_release<VM: VMBinding>(mutator: &mut Mutator<VM>, _tls: OpaquePointer) {
+    // reset nursery allocator
+    let bump_allocator = unsafe {
+        mutator
+            .allocators
+            .get_allocator_mut(mutator.config.allocator_mapping[AllocationType::Default])
+    }
+    .downcast_mut::<BumpAllocator<VM>>()
+    .unwrap();
+    bump_allocator.reset();
+    mutator.reset_nonmoving_allocator();
+}
+
+lazy_static! {
+    pub static ref ALLOCATOR_MAPPING: EnumMap<AllocationType, AllocatorSelector> = enum_map! {
+        AllocationType::Default => AllocatorSelector::BumpPointer(0),
+        AllocationType::Immortal | AllocationType::Code | AllocationType::ReadOnly => AllocatorSelector::BumpPointer(1),
+        AllocationType::Los => AllocatorSelector::LargeObject(0),
+        AllocationType::NonMoving => AllocatorSelector::Immix(0),
+    };
+}
+
+pub fn create_genimmix_mutator<VM: VMBinding>(
+    mutator_tls: OpaquePointer,
+    mmtk: &'static MMTK<VM>,
+) -> Mutator<VM> {
+    let genimmix = mmtk.plan.downcast_ref::<GenImmix<VM>>().unwrap();
+    let config = MutatorConfig {
+        allocator_mapping: &*ALLOCATOR_MAPPING,
+        space_mapping: box vec![
+            (AllocatorSelector::BumpPointer(0), &genimmix.nursery),
+            (
+                AllocatorSelector::BumpPointer(1),
+                genimmix.common.get_immortal(),
+            ),
+            (AllocatorSelector::LargeObject(0), genimmix.common.get_los()),
+            (AllocatorSelector::Immix(0), genimmix.common.get_nonmoving()),
+        ],
+        prepare_func: &genimmix_mutator_prepare,
+        release_func: &genimmix_mutator_release,
+    };
+
+    Mutator {
+        allocators: Allocators::<VM>::new(mutator_tls, &*mmtk.plan, &config.space_mapping),
+        barrier: box FieldRememberingBarrier::<GenImmixNurseryProcessEdges<VM>, CopySpace<VM>>::new(
+            mmtk,
+            &genimmix.nursery,
+        ),
+        mutator_tls,
+        config,
+        plan: genimmix,
+    }
+}
@@ -5,6 +5,7 @@ use super::PlanConstraints;
 use crate::mmtk::MMTK;
 use crate::plan::transitive_closure::TransitiveClosure;
 use crate::plan::Mutator;
+use crate::policy::immix::ImmixSpace;
 use crate::policy::immortalspace::ImmortalSpace;
 use crate::policy::largeobjectspace::LargeObjectSpace;
 use crate::policy::space::Space;
@@ -13,13 +14,16 @@ use crate::util::alloc::allocators::AllocatorSelector;
 #[cfg(feature = "analysis")]
 use crate::util::analysis::AnalysisManager;
 use crate::util::conversions::bytes_to_pages;
+use crate::util::gc_log::{GCLog, GCTrigger};
 use crate::util::heap::layout::heap_layout::Mmapper;
 use crate::util::heap::layout::heap_layout::VMMap;
 use crate::util::heap::layout::map::Map;
+use crate::util::heap::HeapGrowthManager;
 use crate::util::heap::HeapMeta;
 use crate::util::heap::VMRequest;
 use crate::util::options::PlanSelector;
 use crate::util::options::{Options, UnsafeOptionsWrapper};
+use crate::util::pin_bit::{PIN_SIDE_METADATA_PER_CHUNK, PIN_SIDE_METADATA_SPEC};
 use crate::util::statistics::stats::Stats;
 use crate::util::OpaquePointer;
 use crate::util::{Address, ObjectReference};
@@ -121,6 +125,20 @@ pub fn create_mutator<VM: VMBinding>(
             crate::plan::semispace::mutator::create_ss_mutator(tls, &*mmtk.plan)
         }
         PlanSelector::GenCopy => crate::plan::gencopy::mutator::create_gencopy_mutator(tls, mmtk),
+        PlanSelector::GenImmix => {
+            crate::plan::genimmix::mutator::create_genimmix_mutator(tls, mmtk)
+        }
+        PlanSelector::MarkSweep => {
+            crate::plan::marksweep::mutator::create_ms_mutator(tls, &*mmtk.plan)
+        }
+        PlanSelector::Immix => crate::plan::immix::mutator::create_immix_mutator(tls, &*mmtk.plan),
+        PlanSelector::MarkCompact => {
+            crate::plan::markcompact::mutator::create_markcompact_mutator(tls, &*mmtk.plan)
+        }
+        PlanSelector::ConcMarkSweep => {
+            crate::plan::concmarksweep::mutator::create_cms_mutator(tls, mmtk)
+        }
+        PlanSelector::RC => crate::plan::rc::mutator::create_rc_mutator(tls, mmtk),
     })
 }
 
@@ -129,21 +147,45 @@ pub fn create_plan<VM: VMBinding>(
     vm_map: &'static VMMap,
     mmapper: &'static Mmapper,
     options: Arc<UnsafeOptionsWrapper>,
+    heap: HeapMeta,
     scheduler: &'static MMTkScheduler<VM>,
 ) -> Box<dyn Plan<VM = VM>> {
     match plan {
         PlanSelector::NoGC => Box::new(crate::plan::nogc::NoGC::new(
-            vm_map, mmapper, options, scheduler,
+            vm_map, mmapper, options, heap, scheduler,
         )),
         PlanSelector::SemiSpace => Box::new(crate::plan::semispace::SemiSpace::new(
-            vm_map, mmapper, options, scheduler,
+            vm_map, mmapper, options, heap, scheduler,
         )),
         PlanSelector::GenCopy => Box::new(crate::plan::gencopy::GenCopy::new(
-            vm_map, mmapper, options, scheduler,
+            vm_map, mmapper, options, heap, scheduler,
+        )),
+        PlanSelector::GenImmix => Box::new(crate::plan::genimmix::GenImmix::new(
+            vm_map, mmapper, options, heap, scheduler,
+        )),
+        PlanSelector::MarkSweep => Box::new(crate::plan::marksweep::MarkSweep::new(
+            vm_map, mmapper, options, heap, scheduler,
+        )),
+        PlanSelector::Immix => Box::new(crate::plan::immix::Immix::new(
+            vm_map, mmapper, options, heap, scheduler,
+        )),
+        PlanSelector::MarkCompact => Box::new(crate::plan::markcompact::MarkCompact::new(
+            vm_map, mmapper, options, heap, scheduler,
+        )),
+        PlanSelector::ConcMarkSweep => Box::new(crate::plan::concmarksweep::ConcMarkSweep::new(
+            vm_map, mmapper, options, heap, scheduler,
+        )),
+        PlanSelector::RC => Box::new(crate::plan::rc::RC::new(
+            vm_map, mmapper, options, heap, scheduler,
         )),
     }
 }
 
+/// The bytes of global side metadata per chunk that MMTk maintains for every plan: the alloc bit and
+/// the pin bit, if their features are enabled. The global side metadata of a plan starts at this offset.
+pub const CORE_GLOBAL_SIDE_METADATA_PER_CHUNK: usize =
+    PIN_SIDE_METADATA_SPEC.offset + PIN_SIDE_METADATA_PER_CHUNK;
+
 /// A plan describes the global core functionality for all memory management schemes.
 /// All global MMTk plans should implement this trait.
 ///
@@ -218,24 +260,33 @@ pub trait Plan: 'static + Sync + Send + Downcast {
                 self.common().control_collector_context.request();
                 return false;
             }*/
+            if space_full {
+                self.base()
+                    .gc_log
+                    .set_trigger(GCTrigger::SpaceFull(space.get_name()));
+            } else {
+                self.base().gc_log.set_trigger_if_unset(GCTrigger::HeapFull);
+            }
             self.log_poll(space, "Triggering collection");
             self.base().control_collector_context.request();
             return true;
         }
 
-        // FIXME
-        /*if self.concurrent_collection_required() {
-            // FIXME
-            /*if space == self.common().meta_data_space {
-                self.log_poll(space, "Triggering async concurrent collection");
-                Self::trigger_internal_collection_request();
-                return false;
-            } else {*/
+        if self.constraints().needs_concurrent_workers && self.concurrent_collection_required() {
+            // The mutators are stopped at their next safepoint for the initial pause
+            // of the concurrent collection, so this allocation can go ahead.
             self.log_poll(space, "Triggering concurrent collection");
-            Self::trigger_internal_collection_request();
-            return true;
-        }*/
+            self.base().gc_log.set_trigger(GCTrigger::Concurrent);
+            self.base().control_collector_context.request();
+            return false;
+        }
+
+        false
+    }
 
+    /// Should a concurrent collection be started? This is only asked when the plan
+    /// needs concurrent workers, and no (stop-the-world) collection is required.
+    fn concurrent_collection_required(&self) -> bool {
         false
     }
 
@@ -281,6 +332,9 @@ pub trait Plan: 'static + Sync + Send + Downcast {
 
     fn get_pages_used(&self) -> usize;
 
+    /// Call `f` for each space of the plan.
+    fn for_each_space(&self, f: &mut dyn FnMut(&dyn Space<Self::VM>));
+
     fn is_emergency_collection(&self) -> bool {
         self.base().emergency_collection.load(Ordering::Relaxed)
     }
@@ -302,6 +356,7 @@ pub trait Plan: 'static + Sync + Send + Downcast {
             );
             trace!("Doing stress GC");
             self.base().allocation_bytes.store(0, Ordering::SeqCst);
+            self.base().gc_log.set_trigger_if_unset(GCTrigger::Stress);
             true
         } else {
             false
@@ -313,6 +368,7 @@ pub trait Plan: 'static + Sync + Send + Downcast {
             self.base()
                 .user_triggered_collection
                 .store(true, Ordering::Relaxed);
+            self.base().gc_log.set_trigger(GCTrigger::User);
             self.base().control_collector_context.request();
             <Self::VM as VMBinding>::VMCollection::block_for_gc(tls);
         }
@@ -334,7 +390,7 @@ pub trait Plan: 'static + Sync + Send + Downcast {
     }
 
     fn global_side_metadata_per_chunk(&self) -> usize {
-        0
+        CORE_GLOBAL_SIDE_METADATA_PER_CHUNK
     }
 }
 
@@ -368,10 +424,14 @@ pub struct BasePlan<VM: VMBinding> {
     pub oom_lock: Mutex<()>,
     pub control_collector_context: ControllerCollectorContext<VM>,
     pub stats: Stats,
+    /// The per-GC log (see the `gc_log_file` option).
+    pub gc_log: GCLog,
     mmapper: &'static Mmapper,
     pub vm_map: &'static VMMap,
     pub options: Arc<UnsafeOptionsWrapper>,
     pub heap: HeapMeta,
+    /// Resizes the heap (`heap.total_pages`) after each GC, if the heap size is dynamic.
+    pub heap_growth_manager: HeapGrowthManager,
     #[cfg(feature = "base_spaces")]
     pub unsync: UnsafeCell<BaseUnsync<VM>>,
     #[cfg(feature = "sanity")]
@@ -481,8 +541,10 @@ impl<VM: VMBinding> BasePlan<VM> {
             oom_lock: Mutex::new(()),
             control_collector_context: ControllerCollectorContext::new(),
             stats,
+            gc_log: GCLog::new(),
             mmapper,
             heap,
+            heap_growth_manager: HeapGrowthManager::new(),
             vm_map,
             options,
             #[cfg(feature = "sanity")]
@@ -554,6 +616,20 @@ impl<VM: VMBinding> BasePlan<VM> {
         0
     }
 
+    #[allow(unused_variables)] // 'f' is only needed for certain features
+    pub fn for_each_space(&self, f: &mut dyn FnMut(&dyn Space<VM>)) {
+        #[cfg(feature = "base_spaces")]
+        {
+            let unsync = unsafe { &*self.unsync.get() };
+            #[cfg(feature = "code_space")]
+            f(&unsync.code_space);
+            #[cfg(feature = "ro_space")]
+            f(&unsync.ro_space);
+            #[cfg(feature = "vm_space")]
+            f(&unsync.vm_space);
+        }
+    }
+
     pub fn trace_object<T: TransitiveClosure, C: CopyContext>(
         &self,
         _trace: &mut T,
@@ -633,15 +709,35 @@ impl<VM: VMBinding> BasePlan<VM> {
         }
     }
 
+    /// Grow or shrink the heap at the end of a GC, given the pages that are still reserved.
+    pub fn consider_heap_size(&self, reserved_pages: usize) {
+        if !self.options.variable_size_heap {
+            return;
+        }
+        let total_pages = self.heap.get_total_pages();
+        if let Some(new_pages) = self
+            .heap_growth_manager
+            .consider_heap_size(total_pages, reserved_pages)
+        {
+            info!(
+                "Resize the heap from {} to {} pages",
+                total_pages, new_pages
+            );
+            self.heap.total_pages.store(new_pages, Ordering::Relaxed);
+        }
+    }
+
     pub fn set_gc_status(&self, s: GcStatus) {
         let mut gc_status = self.gc_status.lock().unwrap();
         if *gc_status == GcStatus::NotInGC {
             self.stacks_prepared.store(false, Ordering::SeqCst);
+            self.heap_growth_manager.gc_started();
             // FIXME stats
             self.stats.start_gc();
         }
         *gc_status = s;
         if *gc_status == GcStatus::NotInGC {
+            self.heap_growth_manager.gc_finished();
             // FIXME stats
             if self.stats.get_gathering_stats() {
                 self.stats.end_gc();
@@ -709,6 +805,9 @@ pub struct CommonPlan<VM: VMBinding> {
 pub struct CommonUnsync<VM: VMBinding> {
     pub immortal: ImmortalSpace<VM>,
     pub los: LargeObjectSpace<VM>,
+    /// The space for `AllocationSemantics::NonMoving`: an Immix space that never moves its objects.
+    /// The malloc-based plans use their own malloc space instead.
+    pub nonmoving: ImmixSpace<VM>,
 }
 
 impl<VM: VMBinding> CommonPlan<VM> {
@@ -739,6 +838,7 @@ impl<VM: VMBinding> CommonPlan<VM> {
                     &mut heap,
                     constraints,
                 ),
+                nonmoving: ImmixSpace::new("nonmoving", false, vm_map, mmapper, &mut heap),
             }),
             base: BasePlan::new(vm_map, mmapper, options, heap, constraints),
         }
@@ -754,11 +854,23 @@ impl<VM: VMBinding> CommonPlan<VM> {
         let unsync = unsafe { &mut *self.unsync.get() };
         unsync.immortal.init(vm_map);
         unsync.los.init(vm_map);
+        unsync.nonmoving.init(vm_map);
     }
 
     pub fn get_pages_used(&self) -> usize {
         let unsync = unsafe { &*self.unsync.get() };
-        unsync.immortal.reserved_pages() + unsync.los.reserved_pages() + self.base.get_pages_used()
+        unsync.immortal.reserved_pages()
+            + unsync.los.reserved_pages()
+            + unsync.nonmoving.reserved_pages()
+            + self.base.get_pages_used()
+    }
+
+    pub fn for_each_space(&self, f: &mut dyn FnMut(&dyn Space<VM>)) {
+        let unsync = unsafe { &*self.unsync.get() };
+        f(&unsync.immortal);
+        f(&unsync.los);
+        f(&unsync.nonmoving);
+        self.base.for_each_space(f);
     }
 
     pub fn trace_object<T: TransitiveClosure, C: CopyContext>(
@@ -776,12 +888,22 @@ impl<VM: VMBinding> CommonPlan<VM> {
             trace!("trace_object: object in los");
             return unsync.los.trace_object(trace, object);
         }
+        if unsync.nonmoving.in_space(object) {
+            trace!("trace_object: object in nonmoving space");
+            return unsync.nonmoving.trace_object_without_moving(trace, object);
+        }
         self.base.trace_object::<T, C>(trace, object)
     }
 
+    /// Generational plans pass `primary = false` for a nursery GC. Such a GC only traces the
+    /// nursery objects in the large object space, and does not trace the immortal space or the
+    /// non-moving space at all.
     pub fn prepare(&self, tls: OpaquePointer, primary: bool) {
         let unsync = unsafe { &mut *self.unsync.get() };
-        unsync.immortal.prepare();
+        if primary {
+            unsync.immortal.prepare();
+            unsync.nonmoving.prepare(false);
+        }
         unsync.los.prepare(primary);
         self.base.prepare(tls, primary)
     }
@@ -790,6 +912,9 @@ impl<VM: VMBinding> CommonPlan<VM> {
         let unsync = unsafe { &mut *self.unsync.get() };
         unsync.immortal.release();
         unsync.los.release(primary);
+        if primary {
+            unsync.nonmoving.release();
+        }
         self.base.release(tls, primary)
     }
 
@@ -806,6 +931,11 @@ impl<VM: VMBinding> CommonPlan<VM> {
         let unsync = unsafe { &*self.unsync.get() };
         &unsync.los
     }
+
+    pub fn get_nonmoving(&self) -> &'static ImmixSpace<VM> {
+        let unsync = unsafe { &*self.unsync.get() };
+        &unsync.nonmoving
+    }
 }
 
 use enum_map::Enum;
@@ -819,4 +949,10 @@ pub enum AllocationSemantics {
     Los = 2,
     Code = 3,
     ReadOnly = 4,
+    /// The object is never moved, but it is collected once it is dead. Every plan maps this to a
+    /// non-moving space that it collects: the malloc space of the malloc-based plans, or the
+    /// non-moving Immix space of `CommonPlan` in the other plans. Objects larger than an Immix block
+    /// can hold go to the large object space, which never moves objects either.
+    /// `NoGC` never moves nor collects objects.
+    NonMoving = 5,
 }
@@ -0,0 +1,177 @@
+use super::gc_work::{ImmixCopyContext, ImmixProcessEdges};
+use crate::mmtk::MMTK;
+use crate::plan::global::BasePlan;
+use crate::plan::global::CommonPlan;
+use crate::plan::global::GcStatus;
+use crate::plan::global::CORE_GLOBAL_SIDE_METADATA_PER_CHUNK;
+use crate::plan::immix::mutator::ALLOCATOR_MAPPING;
+use crate::plan::AllocationSemantics;
+use crate::plan::Plan;
+use crate::plan::PlanConstraints;
+use crate::policy::immix::{ImmixSpace, MAX_IMMIX_OBJECT_SIZE};
+use crate::policy::space::Space;
+use crate::scheduler::gc_work::*;
+use crate::scheduler::*;
+use crate::util::alloc::allocators::AllocatorSelector;
+#[cfg(feature = "analysis")]
+use crate::util::analysis::GcHookWork;
+use crate::util::ephemeron_processor::schedule_ephemeron_processing;
+use crate::util::finalizable_processor::Finalization;
+use crate::util::heap::layout::heap_layout::Mmapper;
+use crate::util::heap::layout::heap_layout::VMMap;
+use crate::util::heap::HeapMeta;
+use crate::util::options::UnsafeOptionsWrapper;
+use crate::util::reference_processor::schedule_reference_processing;
+#[cfg(feature = "sanity")]
+use crate::util::sanity::sanity_checker::*;
+use crate::util::side_metadata::meta_bytes_per_chunk;
+use crate::util::OpaquePointer;
+use crate::vm::ObjectModel;
+use crate::vm::VMBinding;
+use std::sync::Arc;
+
+use enum_map::EnumMap;
+
+pub const ALLOC_IMMIX: AllocationSemantics = AllocationSemantics::Default;
+
+pub struct Immix<VM: VMBinding> {
+    pub immix_space: ImmixSpace<VM>,
+    pub common: CommonPlan<VM>,
+}
+
+unsafe impl<VM: VMBinding> Sync for Immix<VM> {}
+
+pub const IMMIX_CONSTRAINTS: PlanConstraints = PlanConstraints {
+    moves_objects: true,
+    gc_header_bits: 2,
+    gc_header_words: 0,
+    num_specialized_scans: 1,
+    max_non_los_copy_bytes: MAX_IMMIX_OBJECT_SIZE,
+    max_non_los_default_alloc_bytes: MAX_IMMIX_OBJECT_SIZE,
+    ..PlanConstraints::default()
+};
+
+impl<VM: VMBinding> Plan for Immix<VM> {
+    type VM = VM;
+
+    fn constraints(&self) -> &'static PlanConstraints {
+        &IMMIX_CONSTRAINTS
+    }
+
+    fn create_worker_local(
+        &self,
+        tls: OpaquePointer,
+        mmtk: &'static MMTK<Self::VM>,
+    ) -> GCWorkerLocalPtr {
+        let mut c = ImmixCopyContext::new(mmtk);
+        c.init(tls);
+        GCWorkerLocalPtr::new(c)
+    }
+
+    fn gc_init(
+        &mut self,
+        heap_size: usize,
+        vm_map: &'static VMMap,
+        scheduler: &Arc<MMTkScheduler<VM>>,
+    ) {
+        self.common.gc_init(heap_size, vm_map, scheduler);
+        self.immix_space.init(&vm_map);
+    }
+
+    fn schedule_collection(&'static self, scheduler: &MMTkScheduler<VM>) {
+        self.base().set_collection_kind();
+        self.base().set_gc_status(GcStatus::GcPrepare);
+        // Stop & scan mutators (mutator scanning can happen before STW)
+        scheduler.work_buckets[WorkBucketStage::Unconstrained]
+            .add(StopMutators::<ImmixProcessEdges<VM>>::new());
+        // Prepare global/collectors/mutators
+        scheduler.work_buckets[WorkBucketStage::Prepare]
+            .add(Prepare::<Self, ImmixCopyContext<VM>>::new(self));
+        // Process the soft, weak and phantom references after the closure
+        if !self.base().options.no_reference_types {
+            schedule_reference_processing::<ImmixProcessEdges<VM>>(scheduler);
+        }
+        // Trace the values of the ephemerons whose keys are reachable after the closure
+        schedule_ephemeron_processing::<ImmixProcessEdges<VM>>(scheduler);
+        // Resurrect the unreachable finalizable objects after the closure
+        if !self.base().options.no_finalizer {
+            scheduler.work_buckets[WorkBucketStage::FinalRefClosure]
+                .add(Finalization::<ImmixProcessEdges<VM>>::new());
+        }
+        // Let the VM process its weak roots after the closure
+        scheduler.work_buckets[WorkBucketStage::PhantomRefClosure].add(
+            ProcessWeakRoots::<VM>::new(WorkBucketStage::PhantomRefClosure),
+        );
+        // Release global/collectors/mutators
+        scheduler.work_buckets[WorkBucketStage::Release]
+            .add(Release::<Self, ImmixCopyContext<VM>>::new(self));
+        // Scheduling all the gc hooks of analysis routines. It is generally recommended
+        // to take advantage of the scheduling system we have in place for more performance
+        #[cfg(feature = "analysis")]
+        scheduler.work_buckets[WorkBucketStage::Unconstrained].add(GcHookWork);
+        // Resume mutators
+        #[cfg(feature = "sanity")]
+        scheduler.work_buckets[WorkBucketStage::Final]
+            .add(ScheduleSanityGC::<Self, ImmixCopyContext<VM>>::new());
+        scheduler.set_finalizer(Some(EndOfGC));
+    }
+
+    fn get_allocator_mapping(&self) -> &'static EnumMap<AllocationSemantics, AllocatorSelector> {
+        &*ALLOCATOR_MAPPING
+    }
+
+    fn prepare(&self, tls: OpaquePointer) {
+        self.common.prepare(tls, true);
+        self.immix_space.prepare(self.is_emergency_collection());
+    }
+
+    fn release(&self, tls: OpaquePointer) {
+        self.common.release(tls, true);
+        self.immix_space.release();
+    }
+
+    fn get_collection_reserve(&self) -> usize {
+        self.immix_space.defrag_headroom_pages()
+    }
+
+    fn get_pages_used(&self) -> usize {
+        self.immix_space.reserved_pages() + self.common.get_pages_used()
+    }
+
+    fn for_each_space(&self, f: &mut dyn FnMut(&dyn Space<VM>)) {
+        f(&self.immix_space);
+        self.common.for_each_space(f);
+    }
+
+    fn base(&self) -> &BasePlan<VM> {
+        &self.common.base
+    }
+
+    fn common(&self) -> &CommonPlan<VM> {
+        &self.common
+    }
+
+    fn global_side_metadata_per_chunk(&self) -> usize {
+        CORE_GLOBAL_SIDE_METADATA_PER_CHUNK
+            + if !VM::VMObjectModel::HAS_GC_BYTE {
+                meta_bytes_per_chunk(3, 1)
+            } else {
+                0
+            }
+    }
+}
+
+impl<VM: VMBinding> Immix<VM> {
+    pub fn new(
+        vm_map: &'static VMMap,
+        mmapper: &'static Mmapper,
+        options: Arc<UnsafeOptionsWrapper>,
+        mut heap: HeapMeta,
+        _scheduler: &'static MMTkScheduler<VM>,
+    ) -> Self {
+        Immix {
+            immix_space: ImmixSpace::new("immix", true, vm_map, mmapper, &mut heap),
+            common: CommonPlan::new(vm_map, mmapper, options, heap, &IMMIX_CONSTRAINTS),
+        }
+    }
+}
@@ -0,0 +1,8 @@
+//! Plan: immix
+
+pub(super) mod gc_work;
+pub(super) mod global;
+pub(super) mod mutator;
+
+pub use self::global::Immix;
+pub use self::global::IMMIX_CONSTRAINTS;
@@ -0,0 +1,167 @@
+use super::global::MarkCompact;
+use crate::plan::global::NoCopy;
+use crate::policy::markcompactspace::MarkCompactSpace;
+use crate::policy::space::Space;
+use crate::scheduler::gc_work::*;
+use crate::scheduler::{GCWork, GCWorker, WorkBucketStage};
+use crate::util::{Address, ObjectReference};
+use crate::vm::{ActivePlan, Scanning, VMBinding};
+use crate::MMTK;
+use std::ops::{Deref, DerefMut};
+
+/// Compute the forwarding addresses of the live objects, once the marking closure is done.
+pub struct CalculateForwardingAddress<VM: VMBinding> {
+    mc_space: &'static MarkCompactSpace<VM>,
+}
+
+impl<VM: VMBinding> CalculateForwardingAddress<VM> {
+    pub fn new(mc_space: &'static MarkCompactSpace<VM>) -> Self {
+        Self { mc_space }
+    }
+}
+
+impl<VM: VMBinding> GCWork<VM> for CalculateForwardingAddress<VM> {
+    fn do_work(&mut self, _worker: &mut GCWorker<VM>, _mmtk: &'static MMTK<VM>) {
+        self.mc_space.calculate_forwarding_pointer();
+    }
+}
+
+/// Scan the roots again, and start a second closure that updates each reference to its forwarding address.
+#[derive(Default)]
+pub struct UpdateReferences<VM: VMBinding>(std::marker::PhantomData<VM>);
+
+impl<VM: VMBinding> UpdateReferences<VM> {
+    pub fn new() -> Self {
+        Self(std::marker::PhantomData)
+    }
+}
+
+impl<VM: VMBinding> GCWork<VM> for UpdateReferences<VM> {
+    fn do_work(&mut self, _worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        // Mutators are still stopped, so we only need to rescan their stacks.
+        if VM::VMScanning::SINGLE_THREAD_MUTATOR_SCANNING {
+            mmtk.scheduler.work_buckets[WorkBucketStage::RefForwarding]
+                .add(ScanStackRoots::<ForwardingProcessEdges<VM>>::new());
+        } else {
+            for mutator in VM::VMActivePlan::mutators() {
+                mmtk.scheduler.work_buckets[WorkBucketStage::RefForwarding].add(ScanStackRoot::<
+                    ForwardingProcessEdges<VM>,
+                >(
+                    mutator
+                ));
+            }
+        }
+        mmtk.scheduler.work_buckets[WorkBucketStage::RefForwarding]
+            .add(ScanVMSpecificRoots::<ForwardingProcessEdges<VM>>::new());
+    }
+}
+
+/// Slide the live objects down to their forwarding addresses. This runs as one packet,
+/// as the objects need to be moved in address order.
+pub struct Compact<VM: VMBinding> {
+    mc_space: &'static MarkCompactSpace<VM>,
+}
+
+impl<VM: VMBinding> Compact<VM> {
+    pub fn new(mc_space: &'static MarkCompactSpace<VM>) -> Self {
+        Self { mc_space }
+    }
+}
+
+impl<VM: VMBinding> GCWork<VM> for Compact<VM> {
+    fn do_work(&mut self, _worker: &mut GCWorker<VM>, _mmtk: &'static MMTK<VM>) {
+        self.mc_space.compact();
+    }
+}
+
+/// The edge processor for the marking closure.
+pub struct MarkingProcessEdges<VM: VMBinding> {
+    plan: &'static MarkCompact<VM>,
+    base: ProcessEdgesBase<MarkingProcessEdges<VM>>,
+}
+
+impl<VM: VMBinding> ProcessEdgesWork for MarkingProcessEdges<VM> {
+    type VM = VM;
+    fn new(edges: Vec<Address>, _roots: bool, mmtk: &'static MMTK<VM>) -> Self {
+        let base = ProcessEdgesBase::new(edges, mmtk);
+        let plan = base.plan().downcast_ref::<MarkCompact<VM>>().unwrap();
+        Self { plan, base }
+    }
+    #[inline]
+    fn trace_object(&mut self, object: ObjectReference) -> ObjectReference {
+        if object.is_null() {
+            return object;
+        }
+        if self.plan.mc_space.in_space(object) {
+            self.plan.mc_space.trace_mark_object::<Self>(self, object)
+        } else {
+            self.plan
+                .common
+                .trace_object::<Self, NoCopy<VM>>(self, object)
+        }
+    }
+}
+
+impl<VM: VMBinding> Deref for MarkingProcessEdges<VM> {
+    type Target = ProcessEdgesBase<Self>;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl<VM: VMBinding> DerefMut for MarkingProcessEdges<VM> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.base
+    }
+}
+
+/// The edge processor for the reference updating closure. It replaces each reference
+/// into the mark compact space with the forwarding address of the object.
+pub struct ForwardingProcessEdges<VM: VMBinding> {
+    plan: &'static MarkCompact<VM>,
+    base: ProcessEdgesBase<ForwardingProcessEdges<VM>>,
+}
+
+impl<VM: VMBinding> ProcessEdgesWork for ForwardingProcessEdges<VM> {
+    type VM = VM;
+    fn new(edges: Vec<Address>, _roots: bool, mmtk: &'static MMTK<VM>) -> Self {
+        let base = ProcessEdgesBase::new(edges, mmtk);
+        let plan = base.plan().downcast_ref::<MarkCompact<VM>>().unwrap();
+        Self { plan, base }
+    }
+    #[inline]
+    fn trace_object(&mut self, object: ObjectReference) -> ObjectReference {
+        if object.is_null() {
+            return object;
+        }
+        if self.plan.mc_space.in_space(object) {
+            self.plan
+                .mc_space
+                .trace_forward_object::<Self>(self, object)
+        } else {
+            // Objects in the other spaces do not move, but their fields may still
+            // point into the mark compact space.
+            if self.plan.visit_non_moving_object(object) {
+                self.process_node(object);
+            }
+            object
+        }
+    }
+}
+
+impl<VM: VMBinding> Deref for ForwardingProcessEdges<VM> {
+    type Target = ProcessEdgesBase<Self>;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl<VM: VMBinding> DerefMut for ForwardingProcessEdges<VM> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.base
+    }
+}
@@ -0,0 +1,221 @@
+use super::gc_work::{
+    CalculateForwardingAddress, Compact, ForwardingProcessEdges, MarkingProcessEdges,
+    UpdateReferences,
+};
+use crate::mmtk::MMTK;
+use crate::plan::global::BasePlan;
+use crate::plan::global::CommonPlan;
+use crate::plan::global::GcStatus;
+use crate::plan::global::NoCopy;
+use crate::plan::global::CORE_GLOBAL_SIDE_METADATA_PER_CHUNK;
+use crate::plan::markcompact::mutator::ALLOCATOR_MAPPING;
+use crate::plan::AllocationSemantics;
+use crate::plan::Plan;
+use crate::plan::PlanConstraints;
+use crate::policy::markcompactspace::MarkCompactSpace;
+use crate::policy::space::Space;
+use crate::scheduler::gc_work::*;
+use crate::scheduler::*;
+use crate::util::alloc::allocators::AllocatorSelector;
+#[cfg(feature = "analysis")]
+use crate::util::analysis::GcHookWork;
+use crate::util::constants::LOG_BYTES_IN_WORD;
+use crate::util::ephemeron_processor::{
+    schedule_ephemeron_forwarding, schedule_ephemeron_processing,
+};
+use crate::util::finalizable_processor::{Finalization, ForwardFinalization};
+use crate::util::heap::layout::heap_layout::Mmapper;
+use crate::util::heap::layout::heap_layout::VMMap;
+use crate::util::heap::layout::vm_layout_constants::BYTES_IN_CHUNK;
+use crate::util::heap::HeapMeta;
+use crate::util::options::UnsafeOptionsWrapper;
+use crate::util::reference_processor::{
+    schedule_reference_forwarding, schedule_reference_processing,
+};
+#[cfg(feature = "sanity")]
+use crate::util::sanity::sanity_checker::*;
+use crate::util::side_metadata::{
+    bzero_metadata_for_chunk, compare_exchange_atomic, meta_bytes_per_chunk, SideMetadataScope,
+    SideMetadataSpec,
+};
+use crate::util::{ObjectReference, OpaquePointer};
+use crate::vm::VMBinding;
+use std::sync::Arc;
+
+use enum_map::EnumMap;
+
+pub struct MarkCompact<VM: VMBinding> {
+    pub mc_space: MarkCompactSpace<VM>,
+    pub common: CommonPlan<VM>,
+}
+
+/// One bit per word, set for the objects outside the mark compact space once they are scanned by the
+/// reference updating closure. The other spaces keep no second mark bit we could use instead.
+/// The bits are bulk-zeroed in `prepare()`. It follows the side gc byte in the global side metadata.
+pub const VISITED_SPEC: SideMetadataSpec = SideMetadataSpec {
+    scope: SideMetadataScope::Global,
+    offset: CORE_GLOBAL_SIDE_METADATA_PER_CHUNK + meta_bytes_per_chunk(3, 1),
+    log_num_of_bits: 0,
+    log_min_obj_size: LOG_BYTES_IN_WORD as usize,
+};
+
+unsafe impl<VM: VMBinding> Sync for MarkCompact<VM> {}
+
+pub const MC_CONSTRAINTS: PlanConstraints = PlanConstraints {
+    moves_objects: true,
+    gc_header_bits: 2,
+    gc_header_words: 1,
+    num_specialized_scans: 2,
+    // The compactor slides every live object in the mark compact space down.
+    can_pin_objects: false,
+    ..PlanConstraints::default()
+};
+
+impl<VM: VMBinding> Plan for MarkCompact<VM> {
+    type VM = VM;
+
+    fn constraints(&self) -> &'static PlanConstraints {
+        &MC_CONSTRAINTS
+    }
+
+    fn create_worker_local(
+        &self,
+        tls: OpaquePointer,
+        mmtk: &'static MMTK<Self::VM>,
+    ) -> GCWorkerLocalPtr {
+        let mut c = NoCopy::new(mmtk);
+        c.init(tls);
+        GCWorkerLocalPtr::new(c)
+    }
+
+    fn gc_init(
+        &mut self,
+        heap_size: usize,
+        vm_map: &'static VMMap,
+        scheduler: &Arc<MMTkScheduler<VM>>,
+    ) {
+        self.common.gc_init(heap_size, vm_map, scheduler);
+        self.mc_space.init(&vm_map);
+    }
+
+    fn schedule_collection(&'static self, scheduler: &MMTkScheduler<VM>) {
+        self.base().set_collection_kind();
+        self.base().set_gc_status(GcStatus::GcPrepare);
+        // Stop & scan mutators (mutator scanning can happen before STW)
+        scheduler.work_buckets[WorkBucketStage::Unconstrained]
+            .add(StopMutators::<MarkingProcessEdges<VM>>::new());
+        // Prepare global/collectors/mutators
+        scheduler.work_buckets[WorkBucketStage::Prepare]
+            .add(Prepare::<Self, NoCopy<VM>>::new(self));
+        // Compute forwarding addresses once the marking closure is done
+        scheduler.work_buckets[WorkBucketStage::CalculateForwarding]
+            .add(CalculateForwardingAddress::<VM>::new(&self.mc_space));
+        // Update all the references with a second closure
+        scheduler.work_buckets[WorkBucketStage::RefForwarding].add(UpdateReferences::<VM>::new());
+        // Slide the objects down
+        scheduler.work_buckets[WorkBucketStage::Compact].add(Compact::<VM>::new(&self.mc_space));
+        // Process the soft, weak and phantom references after the closure
+        if !self.base().options.no_reference_types {
+            schedule_reference_processing::<MarkingProcessEdges<VM>>(scheduler);
+            schedule_reference_forwarding::<ForwardingProcessEdges<VM>>(scheduler);
+        }
+        // Trace the values of the ephemerons whose keys are reachable after the closure
+        schedule_ephemeron_processing::<MarkingProcessEdges<VM>>(scheduler);
+        schedule_ephemeron_forwarding::<ForwardingProcessEdges<VM>>(scheduler);
+        // Resurrect the unreachable finalizable objects after the closure
+        if !self.base().options.no_finalizer {
+            scheduler.work_buckets[WorkBucketStage::FinalRefClosure]
+                .add(Finalization::<MarkingProcessEdges<VM>>::new());
+            scheduler.work_buckets[WorkBucketStage::RefForwarding]
+                .add(ForwardFinalization::<ForwardingProcessEdges<VM>>::new());
+        }
+        // Let the VM process its weak roots once the forwarding addresses are computed
+        scheduler.work_buckets[WorkBucketStage::RefForwarding]
+            .add(ProcessWeakRoots::<VM>::new(WorkBucketStage::RefForwarding));
+        // Release global/collectors/mutators
+        scheduler.work_buckets[WorkBucketStage::Release]
+            .add(Release::<Self, NoCopy<VM>>::new(self));
+        // Scheduling all the gc hooks of analysis routines. It is generally recommended
+        // to take advantage of the scheduling system we have in place for more performance
+        #[cfg(feature = "analysis")]
+        scheduler.work_buckets[WorkBucketStage::Unconstrained].add(GcHookWork);
+        // Resume mutators
+        #[cfg(feature = "sanity")]
+        scheduler.work_buckets[WorkBucketStage::Final]
+            .add(ScheduleSanityGC::<Self, NoCopy<VM>>::new());
+        scheduler.set_finalizer(Some(EndOfGC));
+    }
+
+    fn get_allocator_mapping(&self) -> &'static EnumMap<AllocationSemantics, AllocatorSelector> {
+        &*ALLOCATOR_MAPPING
+    }
+
+    fn prepare(&self, tls: OpaquePointer) {
+        self.common.prepare(tls, true);
+        self.clear_visited_bits();
+    }
+
+    fn release(&self, tls: OpaquePointer) {
+        self.common.release(tls, true);
+    }
+
+    fn get_pages_used(&self) -> usize {
+        self.mc_space.reserved_pages() + self.common.get_pages_used()
+    }
+
+    fn for_each_space(&self, f: &mut dyn FnMut(&dyn Space<VM>)) {
+        f(&self.mc_space);
+        self.common.for_each_space(f);
+    }
+
+    fn base(&self) -> &BasePlan<VM> {
+        &self.common.base
+    }
+
+    fn common(&self) -> &CommonPlan<VM> {
+        &self.common
+    }
+
+    fn global_side_metadata_per_chunk(&self) -> usize {
+        // The side gc byte is always reserved, so the offset of `VISITED_SPEC` is constant.
+        CORE_GLOBAL_SIDE_METADATA_PER_CHUNK
+            + meta_bytes_per_chunk(3, 1)
+            + meta_bytes_per_chunk(LOG_BYTES_IN_WORD as usize, 0)
+    }
+}
+
+impl<VM: VMBinding> MarkCompact<VM> {
+    pub fn new(
+        vm_map: &'static VMMap,
+        mmapper: &'static Mmapper,
+        options: Arc<UnsafeOptionsWrapper>,
+        mut heap: HeapMeta,
+        _scheduler: &'static MMTkScheduler<VM>,
+    ) -> Self {
+        MarkCompact {
+            mc_space: MarkCompactSpace::new("mc", true, vm_map, mmapper, &mut heap),
+            common: CommonPlan::new(vm_map, mmapper, options, heap, &MC_CONSTRAINTS),
+        }
+    }
+
+    /// Zero the visited bits of the spaces outside the mark compact space, chunk by chunk, so the
+    /// reference updating closure of this GC scans their objects again.
+    fn clear_visited_bits(&self) {
+        self.common.for_each_space(&mut |space| {
+            space.for_each_chunk_range(&mut |start, end| {
+                let mut chunk = start;
+                while chunk < end {
+                    bzero_metadata_for_chunk(VISITED_SPEC, chunk);
+                    chunk += BYTES_IN_CHUNK;
+                }
+            })
+        });
+    }
+
+    /// Record that `object` (which is not in the mark compact space) is visited by the
+    /// reference updating closure. Returns true if this is the first visit.
+    #[inline]
+    pub fn visit_non_moving_object(&self, object: ObjectReference) -> bool {
+        compare_exchange_atomic(VISITED_SPEC, object.to_address(), 0, 1)
+    }
+}
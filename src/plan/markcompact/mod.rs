@@ -0,0 +1,8 @@
+//! Plan: markcompact (Lisp-2 style sliding compaction)
+
+pub(super) mod gc_work;
+pub(super) mod global;
+pub(super) mod mutator;
+
+pub use self::global::MarkCompact;
+pub use self::global::MC_CONSTRAINTS;
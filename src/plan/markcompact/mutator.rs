@@ -0,0 +1,64 @@
+use super::MarkCompact;
+use crate::plan::barriers::NoBarrier;
+use crate::plan::mutator_context::Mutator;
+use crate::plan::mutator_context::MutatorConfig;
+use crate::plan::AllocationSemantics as AllocationType;
+use crate::plan::Plan;
+use crate::util::alloc::allocators::{AllocatorSelector, Allocators};
+use crate::util::alloc::MarkCompactAllocator;
+use crate::util::OpaquePointer;
+use crate::vm::VMBinding;
+use enum_map::enum_map;
+use enum_map::EnumMap;
+
+pub fn mc_mutator_prepare<VM: VMBinding>(_mutator: &mut Mutator<VM>, _tls: OpaquePointer) {
+    // Do nothing
+}
+
+pub fn mc_mutator_release<VM: VMBinding>(mutator: &mut Mutator<VM>, _tls: OpaquePointer) {
+    // The memory we are bumping into has been compacted over or released.
+    let markcompact_allocator = unsafe {
+        mutator
+            .allocators
+            .get_allocator_mut(mutator.config.allocator_mapping[AllocationType::Default])
+    }
+    .downcast_mut::<MarkCompactAllocator<VM>>()
+    .unwrap();
+    markcompact_allocator.reset();
+    mutator.reset_nonmoving_allocator();
+}
+
+lazy_static! {
+    pub static ref ALLOCATOR_MAPPING: EnumMap<AllocationType, AllocatorSelector> = enum_map! {
+        AllocationType::Default => AllocatorSelector::MarkCompact(0),
+        AllocationType::Immortal | AllocationType::Code | AllocationType::ReadOnly => AllocatorSelector::BumpPointer(0),
+        AllocationType::Los => AllocatorSelector::LargeObject(0),
+        AllocationType::NonMoving => AllocatorSelector::Immix(0),
+    };
+}
+
+pub fn create_markcompact_mutator<VM: VMBinding>(
+    mutator_tls: OpaquePointer,
+    plan: &'static dyn Plan<VM = VM>,
+) -> Mutator<VM> {
+    let mc = plan.downcast_ref::<MarkCompact<VM>>().unwrap();
+    let config = MutatorConfig {
+        allocator_mapping: &*ALLOCATOR_MAPPING,
+        space_mapping: box vec![
+            (AllocatorSelector::MarkCompact(0), &mc.mc_space),
+            (AllocatorSelector::BumpPointer(0), mc.common.get_immortal()),
+            (AllocatorSelector::LargeObject(0), mc.common.get_los()),
+            (AllocatorSelector::Immix(0), mc.common.get_nonmoving()),
+        ],
+        prepare_func: &mc_mutator_prepare,
+        release_func: &mc_mutator_release,
+    };
+
+    Mutator {
+        allocators: Allocators::<VM>::new(mutator_tls, plan, &config.space_mapping),
+        barrier: box NoBarrier,
+        mutator_tls,
+        config,
+        plan,
+    }
+}
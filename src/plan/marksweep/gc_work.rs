@@ -0,0 +1,84 @@
+use super::global::MarkSweep;
+use crate::plan::global::NoCopy;
+use crate::policy::mallocspace::MallocSpace;
+use crate::policy::space::Space;
+use crate::scheduler::gc_work::*;
+use crate::scheduler::{GCWork, GCWorker, WorkBucketStage};
+use crate::util::{Address, ObjectReference};
+use crate::vm::VMBinding;
+use crate::MMTK;
+use std::ops::{Deref, DerefMut};
+
+pub struct MSProcessEdges<VM: VMBinding> {
+    plan: &'static MarkSweep<VM>,
+    base: ProcessEdgesBase<MSProcessEdges<VM>>,
+}
+
+impl<VM: VMBinding> ProcessEdgesWork for MSProcessEdges<VM> {
+    type VM = VM;
+    fn new(edges: Vec<Address>, _roots: bool, mmtk: &'static MMTK<VM>) -> Self {
+        let base = ProcessEdgesBase::new(edges, mmtk);
+        let plan = base.plan().downcast_ref::<MarkSweep<VM>>().unwrap();
+        Self { plan, base }
+    }
+    #[inline]
+    fn trace_object(&mut self, object: ObjectReference) -> ObjectReference {
+        if object.is_null() {
+            return object;
+        }
+        if self.plan.ms.in_space(object) {
+            self.plan.ms.trace_object::<Self>(self, object)
+        } else {
+            self.plan
+                .common
+                .trace_object::<Self, NoCopy<VM>>(self, object)
+        }
+    }
+}
+
+impl<VM: VMBinding> Deref for MSProcessEdges<VM> {
+    type Target = ProcessEdgesBase<Self>;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl<VM: VMBinding> DerefMut for MSProcessEdges<VM> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.base
+    }
+}
+
+/// Create a `MSSweepChunk` work packet for each chunk of the malloc space, so the sweep runs in parallel.
+pub struct MSSweepChunks<VM: VMBinding> {
+    ms: &'static MallocSpace<VM>,
+}
+
+impl<VM: VMBinding> MSSweepChunks<VM> {
+    pub fn new(ms: &'static MallocSpace<VM>) -> Self {
+        Self { ms }
+    }
+}
+
+impl<VM: VMBinding> GCWork<VM> for MSSweepChunks<VM> {
+    fn do_work(&mut self, _worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        let ms = self.ms;
+        for chunk in ms.active_chunks() {
+            mmtk.scheduler.work_buckets[WorkBucketStage::Release].add(MSSweepChunk { ms, chunk });
+        }
+    }
+}
+
+/// Free the dead objects in one chunk of the malloc space.
+pub struct MSSweepChunk<VM: VMBinding> {
+    ms: &'static MallocSpace<VM>,
+    chunk: Address,
+}
+
+impl<VM: VMBinding> GCWork<VM> for MSSweepChunk<VM> {
+    fn do_work(&mut self, _worker: &mut GCWorker<VM>, _mmtk: &'static MMTK<VM>) {
+        self.ms.sweep_chunk(self.chunk);
+    }
+}
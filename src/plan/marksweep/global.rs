@@ -0,0 +1,170 @@
+use super::gc_work::{MSProcessEdges, MSSweepChunks};
+use crate::mmtk::MMTK;
+use crate::plan::global::BasePlan;
+use crate::plan::global::CommonPlan;
+use crate::plan::global::GcStatus;
+use crate::plan::global::NoCopy;
+use crate::plan::global::CORE_GLOBAL_SIDE_METADATA_PER_CHUNK;
+use crate::plan::marksweep::mutator::ALLOCATOR_MAPPING;
+use crate::plan::AllocationSemantics;
+use crate::plan::Plan;
+use crate::plan::PlanConstraints;
+use crate::policy::mallocspace::MallocSpace;
+use crate::policy::space::Space;
+use crate::scheduler::gc_work::*;
+use crate::scheduler::*;
+use crate::util::alloc::allocators::AllocatorSelector;
+#[cfg(feature = "analysis")]
+use crate::util::analysis::GcHookWork;
+use crate::util::ephemeron_processor::schedule_ephemeron_processing;
+use crate::util::finalizable_processor::Finalization;
+use crate::util::heap::layout::heap_layout::Mmapper;
+use crate::util::heap::layout::heap_layout::VMMap;
+use crate::util::heap::HeapMeta;
+use crate::util::options::UnsafeOptionsWrapper;
+use crate::util::reference_processor::schedule_reference_processing;
+#[cfg(feature = "sanity")]
+use crate::util::sanity::sanity_checker::*;
+use crate::util::side_metadata::meta_bytes_per_chunk;
+use crate::util::OpaquePointer;
+use crate::vm::ObjectModel;
+use crate::vm::VMBinding;
+use std::sync::Arc;
+
+use enum_map::EnumMap;
+
+pub struct MarkSweep<VM: VMBinding> {
+    pub common: CommonPlan<VM>,
+    pub ms: MallocSpace<VM>,
+}
+
+unsafe impl<VM: VMBinding> Sync for MarkSweep<VM> {}
+
+pub const MS_CONSTRAINTS: PlanConstraints = PlanConstraints {
+    moves_objects: false,
+    gc_header_bits: 2,
+    gc_header_words: 0,
+    num_specialized_scans: 1,
+    ..PlanConstraints::default()
+};
+
+impl<VM: VMBinding> Plan for MarkSweep<VM> {
+    type VM = VM;
+
+    fn constraints(&self) -> &'static PlanConstraints {
+        &MS_CONSTRAINTS
+    }
+
+    fn create_worker_local(
+        &self,
+        tls: OpaquePointer,
+        mmtk: &'static MMTK<Self::VM>,
+    ) -> GCWorkerLocalPtr {
+        let mut c = NoCopy::new(mmtk);
+        c.init(tls);
+        GCWorkerLocalPtr::new(c)
+    }
+
+    fn gc_init(
+        &mut self,
+        heap_size: usize,
+        vm_map: &'static VMMap,
+        scheduler: &Arc<MMTkScheduler<VM>>,
+    ) {
+        self.common.gc_init(heap_size, vm_map, scheduler);
+        self.ms.init(&vm_map);
+    }
+
+    fn schedule_collection(&'static self, scheduler: &MMTkScheduler<VM>) {
+        self.base().set_collection_kind();
+        self.base().set_gc_status(GcStatus::GcPrepare);
+        // Stop & scan mutators (mutator scanning can happen before STW)
+        scheduler.work_buckets[WorkBucketStage::Unconstrained]
+            .add(StopMutators::<MSProcessEdges<VM>>::new());
+        // Prepare global/collectors/mutators
+        scheduler.work_buckets[WorkBucketStage::Prepare]
+            .add(Prepare::<Self, NoCopy<VM>>::new(self));
+        // Process the soft, weak and phantom references after the closure
+        if !self.base().options.no_reference_types {
+            schedule_reference_processing::<MSProcessEdges<VM>>(scheduler);
+        }
+        // Trace the values of the ephemerons whose keys are reachable after the closure
+        schedule_ephemeron_processing::<MSProcessEdges<VM>>(scheduler);
+        // Resurrect the unreachable finalizable objects after the closure
+        if !self.base().options.no_finalizer {
+            scheduler.work_buckets[WorkBucketStage::FinalRefClosure]
+                .add(Finalization::<MSProcessEdges<VM>>::new());
+        }
+        // Let the VM process its weak roots after the closure
+        scheduler.work_buckets[WorkBucketStage::PhantomRefClosure].add(
+            ProcessWeakRoots::<VM>::new(WorkBucketStage::PhantomRefClosure),
+        );
+        // Release global/collectors/mutators
+        scheduler.work_buckets[WorkBucketStage::Release]
+            .add(Release::<Self, NoCopy<VM>>::new(self));
+        // Sweep the malloc space in parallel, once the transitive closure is done
+        scheduler.work_buckets[WorkBucketStage::Release].add(MSSweepChunks::<VM>::new(&self.ms));
+        // Scheduling all the gc hooks of analysis routines. It is generally recommended
+        // to take advantage of the scheduling system we have in place for more performance
+        #[cfg(feature = "analysis")]
+        scheduler.work_buckets[WorkBucketStage::Unconstrained].add(GcHookWork);
+        // Resume mutators
+        #[cfg(feature = "sanity")]
+        scheduler.work_buckets[WorkBucketStage::Final]
+            .add(ScheduleSanityGC::<Self, NoCopy<VM>>::new());
+        scheduler.set_finalizer(Some(EndOfGC));
+    }
+
+    fn get_allocator_mapping(&self) -> &'static EnumMap<AllocationSemantics, AllocatorSelector> {
+        &*ALLOCATOR_MAPPING
+    }
+
+    fn prepare(&self, tls: OpaquePointer) {
+        self.common.prepare(tls, true);
+    }
+
+    fn release(&self, tls: OpaquePointer) {
+        self.common.release(tls, true);
+    }
+
+    fn get_pages_used(&self) -> usize {
+        self.ms.reserved_pages() + self.common.get_pages_used()
+    }
+
+    fn for_each_space(&self, f: &mut dyn FnMut(&dyn Space<VM>)) {
+        f(&self.ms);
+        self.common.for_each_space(f);
+    }
+
+    fn base(&self) -> &BasePlan<VM> {
+        &self.common.base
+    }
+
+    fn common(&self) -> &CommonPlan<VM> {
+        &self.common
+    }
+
+    fn global_side_metadata_per_chunk(&self) -> usize {
+        CORE_GLOBAL_SIDE_METADATA_PER_CHUNK
+            + if !VM::VMObjectModel::HAS_GC_BYTE {
+                meta_bytes_per_chunk(3, 1)
+            } else {
+                0
+            }
+    }
+}
+
+impl<VM: VMBinding> MarkSweep<VM> {
+    pub fn new(
+        vm_map: &'static VMMap,
+        mmapper: &'static Mmapper,
+        options: Arc<UnsafeOptionsWrapper>,
+        heap: HeapMeta,
+        _scheduler: &'static MMTkScheduler<VM>,
+    ) -> Self {
+        MarkSweep {
+            common: CommonPlan::new(vm_map, mmapper, options, heap, &MS_CONSTRAINTS),
+            ms: MallocSpace::new(),
+        }
+    }
+}
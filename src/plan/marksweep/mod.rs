@@ -0,0 +1,8 @@
+//! Plan: marksweep (with malloc)
+
+pub(super) mod gc_work;
+pub(super) mod global;
+pub(super) mod mutator;
+
+pub use self::global::MarkSweep;
+pub use self::global::MS_CONSTRAINTS;
@@ -30,6 +30,12 @@ pub use self::plan_constraints::PlanConstraints;
 pub use self::tracelocal::TraceLocal;
 pub use self::transitive_closure::TransitiveClosure;
 
+pub mod concmarksweep;
 pub mod gencopy;
+pub mod genimmix;
+pub mod immix;
+pub mod markcompact;
+pub mod marksweep;
 pub mod nogc;
+pub mod rc;
 pub mod semispace;
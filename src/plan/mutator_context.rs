@@ -3,8 +3,10 @@
 use crate::plan::barriers::{Barrier, WriteTarget};
 use crate::plan::global::Plan;
 use crate::plan::AllocationSemantics as AllocationType;
+use crate::policy::immix::MAX_IMMIX_OBJECT_SIZE;
 use crate::policy::space::Space;
 use crate::util::alloc::allocators::{AllocatorSelector, Allocators};
+use crate::util::alloc::ImmixAllocator;
 use crate::util::OpaquePointer;
 use crate::util::{Address, ObjectReference};
 use crate::vm::VMBinding;
@@ -48,6 +50,38 @@ pub struct Mutator<VM: VMBinding> {
     pub config: MutatorConfig<VM>,
 }
 
+impl<VM: VMBinding> Mutator<VM> {
+    /// The allocator for an object of `size` bytes with the given semantics. Default objects that are
+    /// too large for the default space of the plan go to the large object space, and so do non-moving
+    /// objects that are too large for an Immix block.
+    #[inline(always)]
+    fn get_allocator_selector(&self, size: usize, semantics: AllocationType) -> AllocatorSelector {
+        let semantics = match semantics {
+            AllocationType::Default
+                if size > self.plan.constraints().max_non_los_default_alloc_bytes =>
+            {
+                AllocationType::Los
+            }
+            AllocationType::NonMoving if size > MAX_IMMIX_OBJECT_SIZE => AllocationType::Los,
+            _ => semantics,
+        };
+        self.config.allocator_mapping[semantics]
+    }
+
+    /// Drop the lines that the allocator for `AllocationSemantics::NonMoving` is allocating into, as the
+    /// GC may free or reuse them. The plans that use the non-moving Immix space of `CommonPlan` call this
+    /// when they release a mutator.
+    pub fn reset_nonmoving_allocator(&mut self) {
+        let immix_allocator = unsafe {
+            self.allocators
+                .get_allocator_mut(self.config.allocator_mapping[AllocationType::NonMoving])
+        }
+        .downcast_mut::<ImmixAllocator<VM>>()
+        .unwrap();
+        immix_allocator.reset();
+    }
+}
+
 impl<VM: VMBinding> MutatorContext<VM> for Mutator<VM> {
     fn prepare(&mut self, tls: OpaquePointer) {
         (*self.config.prepare_func)(self, tls)
@@ -64,22 +98,19 @@ impl<VM: VMBinding> MutatorContext<VM> for Mutator<VM> {
         offset: isize,
         allocator: AllocationType,
     ) -> Address {
-        unsafe {
-            self.allocators
-                .get_allocator_mut(self.config.allocator_mapping[allocator])
-        }
-        .alloc(size, align, offset)
+        let allocator = self.get_allocator_selector(size, allocator);
+        unsafe { self.allocators.get_allocator_mut(allocator) }.alloc(size, align, offset)
     }
 
     // Note that this method is slow, and we expect VM bindings that care about performance to implement allocation fastpath sequence in their bindings.
-    fn post_alloc(&mut self, refer: ObjectReference, _bytes: usize, allocator: AllocationType) {
-        unsafe {
-            self.allocators
-                .get_allocator_mut(self.config.allocator_mapping[allocator])
-        }
-        .get_space()
-        .unwrap()
-        .initialize_header(refer, true)
+    fn post_alloc(&mut self, refer: ObjectReference, bytes: usize, allocator: AllocationType) {
+        #[cfg(feature = "global_alloc_bit")]
+        crate::util::alloc_bit::set_alloc_bit(refer);
+        let allocator = self.get_allocator_selector(bytes, allocator);
+        unsafe { self.allocators.get_allocator_mut(allocator) }
+            .get_space()
+            .unwrap()
+            .initialize_header(refer, true)
     }
 
     fn get_tls(&self) -> OpaquePointer {
@@ -116,6 +147,14 @@ pub trait MutatorContext<VM: VMBinding>: Send + Sync + 'static {
     fn get_tls(&self) -> OpaquePointer;
     fn barrier(&mut self) -> &mut dyn Barrier;
 
+    /// Call this before the reference fields of `obj` are modified.
+    fn record_pre_write_node(&mut self, obj: ObjectReference) {
+        self.barrier().pre_write_barrier(WriteTarget::Object(obj));
+    }
+    /// Call this before the reference in `slot` is modified.
+    fn record_pre_write_edge(&mut self, slot: Address) {
+        self.barrier().pre_write_barrier(WriteTarget::Slot(slot));
+    }
     fn record_modified_node(&mut self, obj: ObjectReference) {
         self.barrier().post_write_barrier(WriteTarget::Object(obj));
     }
@@ -11,7 +11,6 @@ use crate::scheduler::MMTkScheduler;
 use crate::util::alloc::allocators::AllocatorSelector;
 use crate::util::heap::layout::heap_layout::Mmapper;
 use crate::util::heap::layout::heap_layout::VMMap;
-use crate::util::heap::layout::vm_layout_constants::{HEAP_END, HEAP_START};
 use crate::util::heap::HeapMeta;
 #[allow(unused_imports)]
 use crate::util::heap::VMRequest;
@@ -64,6 +63,11 @@ impl<VM: VMBinding> Plan for NoGC<VM> {
         self.nogc_space.init(&vm_map);
     }
 
+    fn for_each_space(&self, f: &mut dyn FnMut(&dyn Space<VM>)) {
+        f(&self.nogc_space);
+        self.base.for_each_space(f);
+    }
+
     fn base(&self) -> &BasePlan<VM> {
         &self.base
     }
@@ -98,16 +102,15 @@ impl<VM: VMBinding> NoGC<VM> {
         vm_map: &'static VMMap,
         mmapper: &'static Mmapper,
         options: Arc<UnsafeOptionsWrapper>,
+        heap: HeapMeta,
         _scheduler: &'static MMTkScheduler<VM>,
     ) -> Self {
         #[cfg(not(feature = "nogc_lock_free"))]
-        let mut heap = HeapMeta::new(HEAP_START, HEAP_END);
-        #[cfg(feature = "nogc_lock_free")]
-        let heap = HeapMeta::new(HEAP_START, HEAP_END);
+        let mut heap = heap;
 
         #[cfg(feature = "nogc_lock_free")]
         let nogc_space =
-            NoGCImmortalSpace::new("nogc_space", cfg!(not(feature = "nogc_no_zeroing")));
+            NoGCImmortalSpace::new("nogc_space", cfg!(not(feature = "nogc_no_zeroing")), &heap);
         #[cfg(not(feature = "nogc_lock_free"))]
         let nogc_space = NoGCImmortalSpace::new(
             "nogc_space",
@@ -13,6 +13,18 @@ pub struct PlanConstraints {
     pub gc_header_words: usize,
     pub num_specialized_scans: usize,
     pub max_non_los_copy_bytes: usize,
+    /// Objects larger than this are allocated in the large object space, even if they are requested
+    /// with `AllocationSemantics::Default`. Bindings that implement the allocation fast path must do the same.
+    pub max_non_los_default_alloc_bytes: usize,
+    /// Can the plan keep any object in place during a GC? Plans that cannot do not support ambiguous roots
+    /// (see `ProcessAmbiguousRoots`).
+    pub can_pin_objects: bool,
+    /// Does the plan process the finalizable objects (see `memory_manager::add_finalizer()`)?
+    pub supports_finalizers: bool,
+    /// Does the plan process the ephemerons (see `memory_manager::add_ephemeron_candidate()`)?
+    /// RC and ConcMarkSweep do not, so `add_ephemeron_candidate()` panics with them, and the VM should
+    /// scan the keys and the values of its ephemerons as normal edges instead.
+    pub supports_ephemerons: bool,
     pub needs_log_bit_in_header: bool,
     pub needs_log_bit_in_header_num: usize,
     pub barrier: BarrierSelector,
@@ -31,6 +43,10 @@ impl PlanConstraints {
             gc_header_words: 0,
             num_specialized_scans: 0,
             max_non_los_copy_bytes: MAX_INT,
+            max_non_los_default_alloc_bytes: MAX_INT,
+            can_pin_objects: true,
+            supports_finalizers: true,
+            supports_ephemerons: true,
             needs_log_bit_in_header: false,
             needs_log_bit_in_header_num: 0,
             needs_linear_scan: SUPPORT_CARD_SCANNING || LAZY_SWEEP,
@@ -0,0 +1,257 @@
+use super::global::{decrement_rc, get_rc, increment_rc, FIELD_LOG_SPEC, RC};
+use crate::plan::global::NoCopy;
+use crate::policy::space::{Space, SFT};
+use crate::scheduler::gc_work::*;
+use crate::scheduler::{GCWork, GCWorker, WorkBucketStage};
+use crate::util::side_metadata::store_atomic;
+use crate::util::{Address, ObjectReference};
+use crate::vm::VMBinding;
+use crate::MMTK;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+
+/// Increment the referents of the roots, and of the fields logged by the barriers.
+/// In a backup trace, the referents of the roots are also marked, and the marking closure starts from them.
+pub struct RCIncProcessEdges<VM: VMBinding> {
+    plan: &'static RC<VM>,
+    base: ProcessEdgesBase<RCIncProcessEdges<VM>>,
+    roots: bool,
+    /// The root referents incremented by this packet.
+    root_objects: Vec<ObjectReference>,
+}
+
+impl<VM: VMBinding> ProcessEdgesWork for RCIncProcessEdges<VM> {
+    type VM = VM;
+    // Nothing moves
+    const OVERWRITE_REFERENCE: bool = false;
+    fn new(edges: Vec<Address>, roots: bool, mmtk: &'static MMTK<VM>) -> Self {
+        let base = ProcessEdgesBase::new(edges, mmtk);
+        let plan = base.plan().downcast_ref::<RC<VM>>().unwrap();
+        Self {
+            plan,
+            base,
+            roots,
+            root_objects: vec![],
+        }
+    }
+    #[inline]
+    fn trace_object(&mut self, object: ObjectReference) -> ObjectReference {
+        if object.is_null() {
+            return object;
+        }
+        if self.plan.ms.in_space(object) {
+            increment_rc(object);
+            if self.roots {
+                self.root_objects.push(object);
+            }
+        }
+        if self.roots && self.plan.in_backup_trace() {
+            if self.plan.ms.in_space(object) {
+                self.plan.ms.trace_object::<Self>(self, object);
+            } else {
+                self.plan
+                    .common
+                    .trace_object::<Self, NoCopy<VM>>(self, object);
+            }
+        }
+        object
+    }
+    #[inline]
+    fn process_edge(&mut self, slot: Address) {
+        if !self.roots {
+            // The field is logged again when it is next overwritten
+            store_atomic(FIELD_LOG_SPEC, slot, 0);
+        }
+        let object = unsafe { slot.load::<ObjectReference>() };
+        self.trace_object(object);
+    }
+    #[cold]
+    fn flush(&mut self) {
+        // Only a backup trace marks objects here. The rest of the heap is marked by `RCMarkProcessEdges`.
+        let mut new_nodes = vec![];
+        mem::swap(&mut new_nodes, &mut self.nodes);
+        self.worker()
+            .do_work(ScanObjects::<RCMarkProcessEdges<VM>>::new(new_nodes, false));
+    }
+}
+
+impl<VM: VMBinding> Drop for RCIncProcessEdges<VM> {
+    fn drop(&mut self) {
+        if !self.root_objects.is_empty() {
+            self.plan
+                .add_root_objects(mem::take(&mut self.root_objects));
+        }
+    }
+}
+
+impl<VM: VMBinding> Deref for RCIncProcessEdges<VM> {
+    type Target = ProcessEdgesBase<Self>;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl<VM: VMBinding> DerefMut for RCIncProcessEdges<VM> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.base
+    }
+}
+
+/// Decrement the objects passed to `trace_object()`. The objects whose count drops to zero are freed,
+/// and their referents are decremented in turn. This is only used with `TraceObjects`, in the release stage.
+pub struct RCDecProcessEdges<VM: VMBinding> {
+    plan: &'static RC<VM>,
+    base: ProcessEdgesBase<RCDecProcessEdges<VM>>,
+}
+
+impl<VM: VMBinding> ProcessEdgesWork for RCDecProcessEdges<VM> {
+    type VM = VM;
+    const OVERWRITE_REFERENCE: bool = false;
+    fn new(edges: Vec<Address>, _roots: bool, mmtk: &'static MMTK<VM>) -> Self {
+        let base = ProcessEdgesBase::new(edges, mmtk);
+        let plan = base.plan().downcast_ref::<RC<VM>>().unwrap();
+        Self { plan, base }
+    }
+    #[inline]
+    fn trace_object(&mut self, object: ObjectReference) -> ObjectReference {
+        if object.is_null() || !self.plan.ms.in_space(object) {
+            return object;
+        }
+        // The sweep may have freed the object in the meantime, so we still need to claim it.
+        if decrement_rc(object) == 1 && self.plan.ms.claim_dead_object(object) {
+            self.process_node(object);
+        }
+        object
+    }
+    #[cold]
+    fn flush(&mut self) {
+        let mut dead_objects = vec![];
+        mem::swap(&mut dead_objects, &mut self.nodes);
+        let tls = self.worker().tls;
+        let referents = self.plan.free_dead_objects(&dead_objects, tls);
+        if !referents.is_empty() {
+            self.worker().add_work(
+                WorkBucketStage::Release,
+                TraceObjects::<Self>::new(referents),
+            );
+        }
+    }
+}
+
+impl<VM: VMBinding> Deref for RCDecProcessEdges<VM> {
+    type Target = ProcessEdgesBase<Self>;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl<VM: VMBinding> DerefMut for RCDecProcessEdges<VM> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.base
+    }
+}
+
+/// The edge processor for the marking closure of a backup trace.
+pub struct RCMarkProcessEdges<VM: VMBinding> {
+    plan: &'static RC<VM>,
+    base: ProcessEdgesBase<RCMarkProcessEdges<VM>>,
+}
+
+impl<VM: VMBinding> ProcessEdgesWork for RCMarkProcessEdges<VM> {
+    type VM = VM;
+    const OVERWRITE_REFERENCE: bool = false;
+    fn new(edges: Vec<Address>, _roots: bool, mmtk: &'static MMTK<VM>) -> Self {
+        let base = ProcessEdgesBase::new(edges, mmtk);
+        let plan = base.plan().downcast_ref::<RC<VM>>().unwrap();
+        Self { plan, base }
+    }
+    #[inline]
+    fn trace_object(&mut self, object: ObjectReference) -> ObjectReference {
+        if object.is_null() {
+            return object;
+        }
+        if self.plan.ms.in_space(object) {
+            self.plan.ms.trace_object::<Self>(self, object)
+        } else {
+            self.plan
+                .common
+                .trace_object::<Self, NoCopy<VM>>(self, object)
+        }
+    }
+}
+
+impl<VM: VMBinding> Deref for RCMarkProcessEdges<VM> {
+    type Target = ProcessEdgesBase<Self>;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl<VM: VMBinding> DerefMut for RCMarkProcessEdges<VM> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.base
+    }
+}
+
+/// Create a `RCSweepChunk` work packet for each chunk of the malloc space, so the sweep runs in parallel.
+pub struct RCSweepChunks<VM: VMBinding> {
+    plan: &'static RC<VM>,
+}
+
+impl<VM: VMBinding> RCSweepChunks<VM> {
+    pub fn new(plan: &'static RC<VM>) -> Self {
+        Self { plan }
+    }
+}
+
+impl<VM: VMBinding> GCWork<VM> for RCSweepChunks<VM> {
+    fn do_work(&mut self, _worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        let plan = self.plan;
+        for chunk in plan.ms.active_chunks() {
+            mmtk.scheduler.work_buckets[WorkBucketStage::Release].add(RCSweepChunk { plan, chunk });
+        }
+    }
+}
+
+/// Free the objects in one chunk of the malloc space whose count is zero, or in a backup trace,
+/// those that are not marked. Their referents are decremented.
+pub struct RCSweepChunk<VM: VMBinding> {
+    plan: &'static RC<VM>,
+    chunk: Address,
+}
+
+impl<VM: VMBinding> GCWork<VM> for RCSweepChunk<VM> {
+    fn do_work(&mut self, worker: &mut GCWorker<VM>, _mmtk: &'static MMTK<VM>) {
+        let ms = &self.plan.ms;
+        let backup_trace = self.plan.in_backup_trace();
+        let mut dead_objects = vec![];
+        ms.for_each_object_in_chunk(self.chunk, |object| {
+            let live = if backup_trace {
+                ms.is_marked(object)
+            } else {
+                get_rc(object) != 0
+            };
+            if !live {
+                // A decrement may have claimed the object in the meantime.
+                if ms.claim_dead_object(object) {
+                    dead_objects.push(object);
+                }
+            } else if backup_trace {
+                ms.unmark_object(object);
+            }
+        });
+        let referents = self.plan.free_dead_objects(&dead_objects, worker.tls);
+        if !referents.is_empty() {
+            worker.add_work(
+                WorkBucketStage::Release,
+                TraceObjects::<RCDecProcessEdges<VM>>::new(referents),
+            );
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use super::gc_work::{RCDecProcessEdges, RCIncProcessEdges, RCSweepChunks};
+use crate::mmtk::MMTK;
+use crate::plan::barriers::BarrierSelector;
+use crate::plan::global::BasePlan;
+use crate::plan::global::CommonPlan;
+use crate::plan::global::GcStatus;
+use crate::plan::global::NoCopy;
+use crate::plan::global::CORE_GLOBAL_SIDE_METADATA_PER_CHUNK;
+use crate::plan::rc::mutator::ALLOCATOR_MAPPING;
+use crate::plan::transitive_closure::ReferentCollector;
+use crate::plan::AllocationSemantics;
+use crate::plan::Plan;
+use crate::plan::PlanConstraints;
+use crate::policy::mallocspace::MallocSpace;
+use crate::policy::space::Space;
+use crate::scheduler::gc_work::*;
+use crate::scheduler::*;
+use crate::util::alloc::allocators::AllocatorSelector;
+#[cfg(feature = "analysis")]
+use crate::util::analysis::GcHookWork;
+use crate::util::constants::LOG_BYTES_IN_WORD;
+use crate::util::heap::layout::heap_layout::Mmapper;
+use crate::util::heap::layout::heap_layout::VMMap;
+use crate::util::heap::HeapMeta;
+use crate::util::options::UnsafeOptionsWrapper;
+#[cfg(feature = "sanity")]
+use crate::util::sanity::sanity_checker::*;
+use crate::util::side_metadata::{
+    compare_exchange_atomic, load_atomic, meta_bytes_per_chunk, store_atomic, SideMetadataScope,
+    SideMetadataSpec,
+};
+use crate::util::{ObjectReference, OpaquePointer};
+use crate::vm::{Scanning, VMBinding};
+use std::mem;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use enum_map::EnumMap;
+
+/// Do a backup trace at least once every this many GCs, to reclaim cyclic garbage and the objects with stuck counts.
+const BACKUP_TRACE_INTERVAL: usize = 8;
+
+/// One log bit per field for the `FieldLoggingBarrier`. It follows the side gc byte in the global side metadata.
+pub const FIELD_LOG_SPEC: SideMetadataSpec = SideMetadataSpec {
+    scope: SideMetadataScope::Global,
+    offset: CORE_GLOBAL_SIDE_METADATA_PER_CHUNK + meta_bytes_per_chunk(3, 1),
+    log_num_of_bits: 0,
+    log_min_obj_size: LOG_BYTES_IN_WORD as usize,
+};
+
+/// A 4-bit reference count per object in the malloc space.
+pub const RC_SPEC: SideMetadataSpec = SideMetadataSpec {
+    scope: SideMetadataScope::Global,
+    offset: FIELD_LOG_SPEC.offset + meta_bytes_per_chunk(LOG_BYTES_IN_WORD as usize, 0),
+    log_num_of_bits: 2,
+    log_min_obj_size: LOG_BYTES_IN_WORD as usize,
+};
+
+/// A reference count that reaches this value is stuck. Only a backup trace can reclaim the object.
+pub const MAX_RC: usize = (1 << (1 << RC_SPEC.log_num_of_bits)) - 1;
+
+/// A deferred reference counting plan, in the style of LXR.
+///
+/// The mutators do not count references as they write them. Instead, a `FieldLoggingBarrier` logs each
+/// field the first time it is overwritten between two GCs. In each pause, the current referents of the
+/// logged fields are incremented, then the old referents of the logged fields are decremented, and the
+/// objects whose count drops to zero are freed. The counts of the referents of the roots are incremented
+/// in a pause, and decremented in the next pause. Objects that are never stored in the heap are
+/// freed in the first pause after they are allocated.
+///
+/// Reference counting cannot reclaim cycles or objects whose count is stuck at `MAX_RC`, and the large object
+/// and immortal spaces are not counted. Every `BACKUP_TRACE_INTERVAL` GCs (and in emergency GCs),
+/// the pause also marks the whole heap, and frees all the unmarked objects.
+pub struct RC<VM: VMBinding> {
+    pub common: CommonPlan<VM>,
+    pub ms: MallocSpace<VM>,
+    /// The objects referenced by the roots in the current pause. They are decremented in the next pause.
+    root_objects: Mutex<Vec<ObjectReference>>,
+    /// Whether the current pause does a backup trace.
+    in_backup_trace: AtomicBool,
+    gcs_since_backup_trace: AtomicUsize,
+}
+
+unsafe impl<VM: VMBinding> Sync for RC<VM> {}
+
+pub const RC_CONSTRAINTS: PlanConstraints = PlanConstraints {
+    moves_objects: false,
+    gc_header_bits: 2,
+    gc_header_words: 0,
+    num_specialized_scans: 1,
+    barrier: BarrierSelector::FieldLoggingBarrier,
+    // Dead objects are freed as soon as their count drops to zero, or by the cycle collector.
+    supports_finalizers: false,
+    // Nothing traces the values of the ephemerons once their keys are found reachable.
+    supports_ephemerons: false,
+    ..PlanConstraints::default()
+};
+
+impl<VM: VMBinding> Plan for RC<VM> {
+    type VM = VM;
+
+    fn constraints(&self) -> &'static PlanConstraints {
+        &RC_CONSTRAINTS
+    }
+
+    fn create_worker_local(
+        &self,
+        tls: OpaquePointer,
+        mmtk: &'static MMTK<Self::VM>,
+    ) -> GCWorkerLocalPtr {
+        let mut c = NoCopy::new(mmtk);
+        c.init(tls);
+        GCWorkerLocalPtr::new(c)
+    }
+
+    fn gc_init(
+        &mut self,
+        heap_size: usize,
+        vm_map: &'static VMMap,
+        scheduler: &Arc<MMTkScheduler<VM>>,
+    ) {
+        self.common.gc_init(heap_size, vm_map, scheduler);
+        self.ms.init(&vm_map);
+    }
+
+    fn schedule_collection(&'static self, scheduler: &MMTkScheduler<VM>) {
+        self.base().set_collection_kind();
+        self.base().set_gc_status(GcStatus::GcPrepare);
+        let backup_trace = self.is_emergency_collection()
+            || self.gcs_since_backup_trace.fetch_add(1, Ordering::SeqCst) + 1
+                >= BACKUP_TRACE_INTERVAL;
+        if backup_trace {
+            self.gcs_since_backup_trace.store(0, Ordering::SeqCst);
+        }
+        self.in_backup_trace.store(backup_trace, Ordering::SeqCst);
+        // Stop & scan mutators (mutator scanning can happen before STW). Increment the root referents.
+        scheduler.work_buckets[WorkBucketStage::Unconstrained]
+            .add(StopMutators::<RCIncProcessEdges<VM>>::new());
+        // Prepare global/collectors/mutators
+        scheduler.work_buckets[WorkBucketStage::Prepare]
+            .add(Prepare::<Self, NoCopy<VM>>::new(self));
+        // Push the fields logged by the barriers to the closure, and their old referents to the release stage
+        scheduler.work_buckets[WorkBucketStage::Prepare].add(FlushMutators::<VM>::new());
+        // Decrement the root referents of the last pause, once all the increments are done
+        let last_root_objects = mem::take(&mut *self.root_objects.lock().unwrap());
+        scheduler.work_buckets[WorkBucketStage::Release].add(
+            TraceObjects::<RCDecProcessEdges<VM>>::new(last_root_objects),
+        );
+        // Release global/collectors/mutators
+        scheduler.work_buckets[WorkBucketStage::Release]
+            .add(Release::<Self, NoCopy<VM>>::new(self));
+        // Free the objects with a zero count (or the unmarked objects in a backup trace)
+        scheduler.work_buckets[WorkBucketStage::Release].add(RCSweepChunks::<VM>::new(self));
+        // Let the VM process its weak roots once we know which objects are dead: after the closure
+        // of a backup trace, or once all the objects whose count drops to zero are freed
+        let weak_roots_stage = if backup_trace {
+            WorkBucketStage::PhantomRefClosure
+        } else {
+            WorkBucketStage::Final
+        };
+        scheduler.work_buckets[weak_roots_stage].add(ProcessWeakRoots::<VM>::new(weak_roots_stage));
+        // Scheduling all the gc hooks of analysis routines. It is generally recommended
+        // to take advantage of the scheduling system we have in place for more performance
+        #[cfg(feature = "analysis")]
+        scheduler.work_buckets[WorkBucketStage::Unconstrained].add(GcHookWork);
+        // Resume mutators
+        #[cfg(feature = "sanity")]
+        scheduler.work_buckets[WorkBucketStage::Final]
+            .add(ScheduleSanityGC::<Self, NoCopy<VM>>::new());
+        scheduler.set_finalizer(Some(EndOfGC));
+    }
+
+    fn get_allocator_mapping(&self) -> &'static EnumMap<AllocationSemantics, AllocatorSelector> {
+        &*ALLOCATOR_MAPPING
+    }
+
+    fn prepare(&self, tls: OpaquePointer) {
+        // The other spaces are not counted. They are only traced (and swept) by a backup trace.
+        if self.in_backup_trace() {
+            self.common.prepare(tls, true);
+            // Until the sweep, the objects that are not marked are dead
+            self.ms.set_live_until_freed(false);
+        }
+    }
+
+    fn release(&self, tls: OpaquePointer) {
+        if self.in_backup_trace() {
+            self.common.release(tls, true);
+            self.ms.set_live_until_freed(true);
+        }
+    }
+
+    fn get_pages_used(&self) -> usize {
+        self.ms.reserved_pages() + self.common.get_pages_used()
+    }
+
+    fn for_each_space(&self, f: &mut dyn FnMut(&dyn Space<VM>)) {
+        f(&self.ms);
+        self.common.for_each_space(f);
+    }
+
+    fn base(&self) -> &BasePlan<VM> {
+        &self.common.base
+    }
+
+    fn common(&self) -> &CommonPlan<VM> {
+        &self.common
+    }
+
+    fn global_side_metadata_per_chunk(&self) -> usize {
+        // The side gc byte is always reserved, so the offsets of our specs are constant.
+        CORE_GLOBAL_SIDE_METADATA_PER_CHUNK
+            + meta_bytes_per_chunk(3, 1)
+            + meta_bytes_per_chunk(LOG_BYTES_IN_WORD as usize, 0)
+            + meta_bytes_per_chunk(LOG_BYTES_IN_WORD as usize, RC_SPEC.log_num_of_bits)
+    }
+}
+
+impl<VM: VMBinding> RC<VM> {
+    pub fn new(
+        vm_map: &'static VMMap,
+        mmapper: &'static Mmapper,
+        options: Arc<UnsafeOptionsWrapper>,
+        heap: HeapMeta,
+        _scheduler: &'static MMTkScheduler<VM>,
+    ) -> Self {
+        RC {
+            common: CommonPlan::new(vm_map, mmapper, options, heap, &RC_CONSTRAINTS),
+            ms: MallocSpace::new_reference_counted(),
+            root_objects: Mutex::new(vec![]),
+            in_backup_trace: AtomicBool::new(false),
+            gcs_since_backup_trace: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn in_backup_trace(&self) -> bool {
+        self.in_backup_trace.load(Ordering::SeqCst)
+    }
+
+    /// Remember the root referents incremented in this pause, so they are decremented in the next pause.
+    pub fn add_root_objects(&self, objects: Vec<ObjectReference>) {
+        self.root_objects.lock().unwrap().extend(objects);
+    }
+
+    /// Free the dead objects that have been claimed with `MallocSpace::claim_dead_object()`.
+    /// Returns the referents of their fields, whose counts need to be decremented.
+    pub fn free_dead_objects(
+        &self,
+        objects: &[ObjectReference],
+        tls: OpaquePointer,
+    ) -> Vec<ObjectReference> {
+        let mut referents = vec![];
+        for object in objects {
+            VM::VMScanning::scan_object(
+                &mut ReferentCollector {
+                    referents: &mut referents,
+                },
+                *object,
+                tls,
+            );
+            // The next object at this address starts from zero.
+            store_atomic(RC_SPEC, object.to_address(), 0);
+            self.ms.free_object(*object);
+        }
+        referents
+    }
+}
+
+pub fn get_rc(object: ObjectReference) -> usize {
+    load_atomic(RC_SPEC, object.to_address())
+}
+
+/// Increment the count of an object, unless it is stuck.
+pub fn increment_rc(object: ObjectReference) {
+    loop {
+        let old = get_rc(object);
+        if old == MAX_RC || compare_exchange_atomic(RC_SPEC, object.to_address(), old, old + 1) {
+            return;
+        }
+    }
+}
+
+/// Decrement the count of an object, unless it is stuck or zero. Returns the old count.
+pub fn decrement_rc(object: ObjectReference) -> usize {
+    loop {
+        let old = get_rc(object);
+        if old == 0
+            || old == MAX_RC
+            || compare_exchange_atomic(RC_SPEC, object.to_address(), old, old - 1)
+        {
+            return old;
+        }
+    }
+}
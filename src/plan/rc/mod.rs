@@ -0,0 +1,8 @@
+//! Plan: rc (deferred reference counting with a backup trace, with malloc)
+
+pub(super) mod gc_work;
+pub(super) mod global;
+pub(super) mod mutator;
+
+pub use self::global::RC;
+pub use self::global::RC_CONSTRAINTS;
@@ -0,0 +1,58 @@
+use super::gc_work::{RCDecProcessEdges, RCIncProcessEdges};
+use super::global::FIELD_LOG_SPEC;
+use super::RC;
+use crate::plan::barriers::FieldLoggingBarrier;
+use crate::plan::mutator_context::Mutator;
+use crate::plan::mutator_context::MutatorConfig;
+use crate::plan::AllocationSemantics as AllocationType;
+use crate::util::alloc::allocators::{AllocatorSelector, Allocators};
+use crate::util::OpaquePointer;
+use crate::vm::VMBinding;
+use crate::MMTK;
+use enum_map::enum_map;
+use enum_map::EnumMap;
+
+pub fn rc_mutator_prepare<VM: VMBinding>(_mutator: &mut Mutator<VM>, _tls: OpaquePointer) {
+    // Do nothing
+}
+
+pub fn rc_mutator_release<VM: VMBinding>(_mutator: &mut Mutator<VM>, _tls: OpaquePointer) {
+    // Do nothing
+}
+
+lazy_static! {
+    pub static ref ALLOCATOR_MAPPING: EnumMap<AllocationType, AllocatorSelector> = enum_map! {
+        AllocationType::Default | AllocationType::NonMoving => AllocatorSelector::Malloc(0),
+        AllocationType::Immortal | AllocationType::Code | AllocationType::ReadOnly => AllocatorSelector::BumpPointer(0),
+        AllocationType::Los => AllocatorSelector::LargeObject(0),
+    };
+}
+
+pub fn create_rc_mutator<VM: VMBinding>(
+    mutator_tls: OpaquePointer,
+    mmtk: &'static MMTK<VM>,
+) -> Mutator<VM> {
+    let rc = mmtk.plan.downcast_ref::<RC<VM>>().unwrap();
+    let config = MutatorConfig {
+        allocator_mapping: &*ALLOCATOR_MAPPING,
+        space_mapping: box vec![
+            (AllocatorSelector::Malloc(0), &rc.ms),
+            (AllocatorSelector::BumpPointer(0), rc.common.get_immortal()),
+            (AllocatorSelector::LargeObject(0), rc.common.get_los()),
+        ],
+        prepare_func: &rc_mutator_prepare,
+        release_func: &rc_mutator_release,
+    };
+
+    Mutator {
+        allocators: Allocators::<VM>::new(mutator_tls, &*mmtk.plan, &config.space_mapping),
+        barrier: box FieldLoggingBarrier::<RCIncProcessEdges<VM>, RCDecProcessEdges<VM>>::new(
+            mmtk,
+            FIELD_LOG_SPEC,
+            mutator_tls,
+        ),
+        mutator_tls,
+        config,
+        plan: rc,
+    }
+}
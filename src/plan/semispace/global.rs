@@ -3,6 +3,7 @@ use crate::mmtk::MMTK;
 use crate::plan::global::BasePlan;
 use crate::plan::global::CommonPlan;
 use crate::plan::global::GcStatus;
+use crate::plan::global::CORE_GLOBAL_SIDE_METADATA_PER_CHUNK;
 use crate::plan::semispace::mutator::ALLOCATOR_MAPPING;
 use crate::plan::AllocationSemantics;
 use crate::plan::Plan;
@@ -14,12 +15,14 @@ use crate::scheduler::*;
 use crate::util::alloc::allocators::AllocatorSelector;
 #[cfg(feature = "analysis")]
 use crate::util::analysis::GcHookWork;
+use crate::util::ephemeron_processor::schedule_ephemeron_processing;
+use crate::util::finalizable_processor::Finalization;
 use crate::util::heap::layout::heap_layout::Mmapper;
 use crate::util::heap::layout::heap_layout::VMMap;
-use crate::util::heap::layout::vm_layout_constants::{HEAP_END, HEAP_START};
 use crate::util::heap::HeapMeta;
 use crate::util::heap::VMRequest;
 use crate::util::options::UnsafeOptionsWrapper;
+use crate::util::reference_processor::schedule_reference_processing;
 #[cfg(feature = "sanity")]
 use crate::util::sanity::sanity_checker::*;
 use crate::util::side_metadata::meta_bytes_per_chunk;
@@ -88,6 +91,21 @@ impl<VM: VMBinding> Plan for SemiSpace<VM> {
         // Prepare global/collectors/mutators
         scheduler.work_buckets[WorkBucketStage::Prepare]
             .add(Prepare::<Self, SSCopyContext<VM>>::new(self));
+        // Process the soft, weak and phantom references after the closure
+        if !self.base().options.no_reference_types {
+            schedule_reference_processing::<SSProcessEdges<VM>>(scheduler);
+        }
+        // Trace the values of the ephemerons whose keys are reachable after the closure
+        schedule_ephemeron_processing::<SSProcessEdges<VM>>(scheduler);
+        // Resurrect the unreachable finalizable objects after the closure
+        if !self.base().options.no_finalizer {
+            scheduler.work_buckets[WorkBucketStage::FinalRefClosure]
+                .add(Finalization::<SSProcessEdges<VM>>::new());
+        }
+        // Let the VM process its weak roots after the closure
+        scheduler.work_buckets[WorkBucketStage::PhantomRefClosure].add(
+            ProcessWeakRoots::<VM>::new(WorkBucketStage::PhantomRefClosure),
+        );
         // Release global/collectors/mutators
         scheduler.work_buckets[WorkBucketStage::Release]
             .add(Release::<Self, SSCopyContext<VM>>::new(self));
@@ -121,6 +139,7 @@ impl<VM: VMBinding> Plan for SemiSpace<VM> {
         self.common.release(tls, true);
         // release the collected region
         self.fromspace().release();
+        self.tospace().release_to_space();
     }
 
     fn get_collection_reserve(&self) -> usize {
@@ -131,6 +150,12 @@ impl<VM: VMBinding> Plan for SemiSpace<VM> {
         self.tospace().reserved_pages() + self.common.get_pages_used()
     }
 
+    fn for_each_space(&self, f: &mut dyn FnMut(&dyn Space<VM>)) {
+        f(&self.copyspace0);
+        f(&self.copyspace1);
+        self.common.for_each_space(f);
+    }
+
     fn base(&self) -> &BasePlan<VM> {
         &self.common.base
     }
@@ -140,11 +165,12 @@ impl<VM: VMBinding> Plan for SemiSpace<VM> {
     }
 
     fn global_side_metadata_per_chunk(&self) -> usize {
-        if !VM::VMObjectModel::HAS_GC_BYTE {
-            meta_bytes_per_chunk(3, 1)
-        } else {
-            0
-        }
+        CORE_GLOBAL_SIDE_METADATA_PER_CHUNK
+            + if !VM::VMObjectModel::HAS_GC_BYTE {
+                meta_bytes_per_chunk(3, 1)
+            } else {
+                0
+            }
     }
 }
 
@@ -153,10 +179,9 @@ impl<VM: VMBinding> SemiSpace<VM> {
         vm_map: &'static VMMap,
         mmapper: &'static Mmapper,
         options: Arc<UnsafeOptionsWrapper>,
+        mut heap: HeapMeta,
         _scheduler: &'static MMTkScheduler<VM>,
     ) -> Self {
-        let mut heap = HeapMeta::new(HEAP_START, HEAP_END);
-
         SemiSpace {
             hi: AtomicBool::new(false),
             copyspace0: CopySpace::new(
@@ -31,6 +31,7 @@ pub fn ss_mutator_release<VM: VMBinding>(mutator: &mut Mutator<VM>, _tls: Opaque
             .unwrap()
             .tospace(),
     ));
+    mutator.reset_nonmoving_allocator();
 }
 
 lazy_static! {
@@ -38,6 +39,7 @@ lazy_static! {
         AllocationType::Default => AllocatorSelector::BumpPointer(0),
         AllocationType::Immortal | AllocationType::Code | AllocationType::ReadOnly => AllocatorSelector::BumpPointer(1),
         AllocationType::Los => AllocatorSelector::LargeObject(0),
+        AllocationType::NonMoving => AllocatorSelector::Immix(0),
     };
 }
 
@@ -52,6 +54,7 @@ pub fn create_ss_mutator<VM: VMBinding>(
             (AllocatorSelector::BumpPointer(0), ss.tospace()),
             (AllocatorSelector::BumpPointer(1), ss.common.get_immortal()),
             (AllocatorSelector::LargeObject(0), ss.common.get_los()),
+            (AllocatorSelector::Immix(0), ss.common.get_nonmoving()),
         ],
         prepare_func: &ss_mutator_prepare,
         release_func: &ss_mutator_release,
@@ -22,3 +22,35 @@ impl<T: ProcessEdgesWork> TransitiveClosure for T {
         ProcessEdgesWork::process_node(self, object);
     }
 }
+
+/// A closure for `Scanning::scan_object()` that collects the fields of an object, without tracing them.
+pub struct EdgeCollector<'a> {
+    pub edges: &'a mut Vec<Address>,
+}
+
+impl<'a> TransitiveClosure for EdgeCollector<'a> {
+    fn process_edge(&mut self, slot: Address) {
+        self.edges.push(slot);
+    }
+    fn process_node(&mut self, _object: ObjectReference) {
+        unreachable!();
+    }
+}
+
+/// A closure for `Scanning::scan_object()` that collects the non-null referents of the fields of an object,
+/// without tracing them.
+pub struct ReferentCollector<'a> {
+    pub referents: &'a mut Vec<ObjectReference>,
+}
+
+impl<'a> TransitiveClosure for ReferentCollector<'a> {
+    fn process_edge(&mut self, slot: Address) {
+        let object = unsafe { slot.load::<ObjectReference>() };
+        if !object.is_null() {
+            self.referents.push(object);
+        }
+    }
+    fn process_node(&mut self, _object: ObjectReference) {
+        unreachable!();
+    }
+}
@@ -2,12 +2,20 @@ use crate::plan::TransitiveClosure;
 use crate::plan::{AllocationSemantics, CopyContext};
 use crate::policy::space::SpaceOptions;
 use crate::policy::space::{CommonSpace, Space, SFT};
-use crate::util::constants::CARD_META_PAGES_PER_REGION;
+use crate::util::alloc::linear_scan::{scan_alloc_bits, scan_bump_region};
+use crate::util::constants::{CARD_META_PAGES_PER_REGION, LOG_BYTES_IN_WORD};
+use crate::util::conversions;
 use crate::util::forwarding_word as ForwardingWord;
 use crate::util::heap::layout::heap_layout::{Mmapper, VMMap};
+use crate::util::heap::layout::vm_layout_constants::BYTES_IN_CHUNK;
 use crate::util::heap::HeapMeta;
 use crate::util::heap::VMRequest;
 use crate::util::heap::{MonotonePageResource, PageResource};
+use crate::util::pin_bit;
+use crate::util::side_metadata::{
+    bzero_metadata_for_chunk, compare_exchange_atomic, load_atomic, meta_bytes_per_chunk,
+    store_atomic, SideMetadataScope, SideMetadataSpec,
+};
 use crate::util::{Address, ObjectReference};
 use crate::vm::*;
 use libc::{mprotect, PROT_EXEC, PROT_NONE, PROT_READ, PROT_WRITE};
@@ -18,10 +26,25 @@ unsafe impl<VM: VMBinding> Sync for CopySpace<VM> {}
 
 const META_DATA_PAGES_PER_REGION: usize = CARD_META_PAGES_PER_REGION;
 
+/// One bit per word, set for each object that is kept in place during the current GC (e.g. because
+/// it is referenced by an ambiguous root), and for each object copied into this space by it.
+/// The bits are cleared in `prepare()`.
+const MARK_METADATA_SPEC: SideMetadataSpec = SideMetadataSpec {
+    scope: SideMetadataScope::PolicySpecific,
+    offset: 0,
+    log_num_of_bits: 0,
+    log_min_obj_size: LOG_BYTES_IN_WORD as usize,
+};
+
 pub struct CopySpace<VM: VMBinding> {
     common: UnsafeCell<CommonSpace<VM>>,
     pr: MonotonePageResource<VM>,
     from_space: AtomicBool,
+    /// Set if an object in the from-space is kept in place during the current GC.
+    kept_in_place: AtomicBool,
+    /// Set if the space kept its pages at the last release, because some of its objects were kept in place.
+    /// The objects that were in the space before the current GC are then traced in place.
+    retained: AtomicBool,
 }
 
 impl<VM: VMBinding> SFT for CopySpace<VM> {
@@ -29,16 +52,29 @@ impl<VM: VMBinding> SFT for CopySpace<VM> {
         self.get_name()
     }
     fn is_live(&self, object: ObjectReference) -> bool {
-        !self.from_space() || ForwardingWord::is_forwarded::<VM>(object)
+        !self.from_space() || ForwardingWord::is_forwarded::<VM>(object) || Self::is_marked(object)
     }
     fn is_movable(&self) -> bool {
         true
     }
+    fn get_forwarded_object(&self, object: ObjectReference) -> Option<ObjectReference> {
+        if !self.is_live(object) {
+            return None;
+        }
+        Some(ForwardingWord::get_forwarded_object::<VM>(object).unwrap_or(object))
+    }
     #[cfg(feature = "sanity")]
     fn is_sane(&self) -> bool {
         !self.from_space()
     }
     fn initialize_header(&self, _object: ObjectReference, _alloc: bool) {}
+    fn pin_for_gc(&self, object: ObjectReference) -> bool {
+        if !self.from_space() {
+            // The object will not be moved. It is traced in place as in `trace_object()`.
+            return self.is_retained() && Self::attempt_mark(object);
+        }
+        self.attempt_mark_in_place(object)
+    }
 }
 
 impl<VM: VMBinding> Space<VM> for CopySpace<VM> {
@@ -68,6 +104,22 @@ impl<VM: VMBinding> Space<VM> for CopySpace<VM> {
     fn release_multiple_pages(&mut self, _start: Address) {
         panic!("copyspace only releases pages enmasse")
     }
+
+    fn local_side_metadata_per_chunk(&self) -> usize {
+        let last = MARK_METADATA_SPEC;
+        last.offset + meta_bytes_per_chunk(last.log_min_obj_size, last.log_num_of_bits)
+    }
+
+    fn enumerate_objects(&self, f: &mut dyn FnMut(ObjectReference)) {
+        // A retained space still holds the old copies of the objects it evacuated.
+        for (start, end) in self.pr.allocated_regions() {
+            scan_bump_region::<VM>(start, end, &mut |object| {
+                if !ForwardingWord::is_forwarded::<VM>(object) {
+                    f(object)
+                }
+            });
+        }
+    }
 }
 
 impl<VM: VMBinding> CopySpace<VM> {
@@ -105,24 +157,133 @@ impl<VM: VMBinding> CopySpace<VM> {
             },
             common: UnsafeCell::new(common),
             from_space: AtomicBool::new(from_space),
+            kept_in_place: AtomicBool::new(false),
+            retained: AtomicBool::new(false),
         }
     }
 
     pub fn prepare(&self, from_space: bool) {
         self.from_space.store(from_space, Ordering::SeqCst);
+        self.kept_in_place.store(false, Ordering::SeqCst);
+        self.for_each_chunk(|chunk| bzero_metadata_for_chunk(MARK_METADATA_SPEC, chunk));
     }
 
     pub fn release(&self) {
-        unsafe {
-            self.pr.reset();
+        if self.has_objects_kept_in_place() {
+            // We cannot free the pages of the objects that stay here. Only the marked objects survive,
+            // and the space keeps its pages up to the last of them if it is contiguous, or all of its
+            // pages otherwise, as a discontiguous page resource can only be reset as a whole.
+            self.for_each_chunk(|chunk| {
+                #[cfg(feature = "global_alloc_bit")]
+                crate::util::side_metadata::bcopy_metadata_for_chunk(
+                    crate::util::alloc_bit::ALLOC_SIDE_METADATA_SPEC,
+                    MARK_METADATA_SPEC,
+                    chunk,
+                );
+                pin_bit::bzero_pin_bits_of_unmarked(MARK_METADATA_SPEC, chunk);
+            });
+            if self.common().contiguous {
+                let mut top = unsafe { Address::zero() };
+                for (start, end) in self.pr.allocated_regions() {
+                    scan_alloc_bits(MARK_METADATA_SPEC, start, end, &mut |object| {
+                        top = VM::VMObjectModel::object_start_ref(object)
+                            + VM::VMObjectModel::get_current_size(object);
+                    });
+                }
+                debug_assert!(!top.is_zero());
+                unsafe {
+                    self.pr.reset_cursor(top);
+                }
+            }
+            self.retained.store(true, Ordering::SeqCst);
+        } else {
+            self.for_each_chunk(|chunk| {
+                bzero_metadata_for_chunk(MARK_METADATA_SPEC, chunk);
+                #[cfg(feature = "global_alloc_bit")]
+                crate::util::alloc_bit::bzero_alloc_bits(chunk);
+                pin_bit::bzero_pin_bits(chunk);
+            });
+            unsafe {
+                self.pr.reset();
+            }
+            self.retained.store(false, Ordering::SeqCst);
         }
         self.from_space.store(false, Ordering::SeqCst);
     }
 
+    /// Called at the end of a GC that used this space as the to-space, and traced all of it. If the space kept
+    /// objects in place at an earlier GC, the ones that were not reached in this GC are dead, so only the marked
+    /// objects keep their alloc bits and pin bits.
+    pub fn release_to_space(&self) {
+        debug_assert!(!self.from_space());
+        if self.is_retained() {
+            self.for_each_chunk(|chunk| {
+                #[cfg(feature = "global_alloc_bit")]
+                crate::util::side_metadata::bcopy_metadata_for_chunk(
+                    crate::util::alloc_bit::ALLOC_SIDE_METADATA_SPEC,
+                    MARK_METADATA_SPEC,
+                    chunk,
+                );
+                pin_bit::bzero_pin_bits_of_unmarked(MARK_METADATA_SPEC, chunk);
+            });
+        }
+    }
+
+    /// Called by the copy context once an object has been copied into this space.
+    #[inline]
+    pub fn post_copy(&self, object: ObjectReference) {
+        ForwardingWord::clear_forwarding_bits::<VM>(object);
+        store_atomic(MARK_METADATA_SPEC, object.to_address(), 1);
+    }
+
     fn from_space(&self) -> bool {
         self.from_space.load(Ordering::SeqCst)
     }
 
+    /// Is any object in the from-space kept in place during the current GC?
+    pub fn has_objects_kept_in_place(&self) -> bool {
+        self.kept_in_place.load(Ordering::SeqCst)
+    }
+
+    /// Did the space keep its pages at the last release, because some of its objects were kept in place?
+    pub fn is_retained(&self) -> bool {
+        self.retained.load(Ordering::SeqCst)
+    }
+
+    /// Call `f` with the start of each chunk that holds allocated objects.
+    fn for_each_chunk(&self, mut f: impl FnMut(Address)) {
+        for (start, end) in self.pr.allocated_regions() {
+            let mut chunk = conversions::chunk_align_down(start);
+            while chunk < end {
+                f(chunk);
+                chunk += BYTES_IN_CHUNK;
+            }
+        }
+    }
+
+    #[inline]
+    fn is_marked(object: ObjectReference) -> bool {
+        load_atomic(MARK_METADATA_SPEC, object.to_address()) != 0
+    }
+
+    /// Atomically mark an object. Returns true if this call marked the object.
+    #[inline]
+    fn attempt_mark(object: ObjectReference) -> bool {
+        compare_exchange_atomic(MARK_METADATA_SPEC, object.to_address(), 0, 1)
+    }
+
+    /// Mark a from-space object, so it is kept in place during this GC.
+    /// Returns true if this call marked the object.
+    #[inline]
+    fn attempt_mark_in_place(&self, object: ObjectReference) -> bool {
+        if Self::attempt_mark(object) {
+            self.kept_in_place.store(true, Ordering::SeqCst);
+            true
+        } else {
+            false
+        }
+    }
+
     #[inline]
     pub fn trace_object<T: TransitiveClosure, C: CopyContext>(
         &self,
@@ -133,6 +294,22 @@ impl<VM: VMBinding> CopySpace<VM> {
     ) -> ObjectReference {
         trace!("copyspace.trace_object(, {:?}, {:?})", object, semantics,);
         if !self.from_space() {
+            // The objects that were kept in place by an earlier GC are traced like in a non-moving space.
+            // The objects copied by this GC are already marked.
+            if self.is_retained() && Self::attempt_mark(object) {
+                trace.process_node(object);
+            }
+            return object;
+        }
+        if Self::is_marked(object) {
+            // The object is kept in place during this GC, and it was enqueued when it was marked.
+            return object;
+        }
+        if pin_bit::is_pinned(object) {
+            trace!("... pinned. Marking in place");
+            if self.attempt_mark_in_place(object) {
+                trace.process_node(object);
+            }
             return object;
         }
         trace!("attempting to forward");
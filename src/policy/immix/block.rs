@@ -0,0 +1,186 @@
+use super::line::{Line, LINE_MARK_SPEC};
+use super::{BYTES_IN_BLOCK, LINES_IN_BLOCK, LOG_BYTES_IN_BLOCK};
+use crate::util::side_metadata::{
+    load_atomic, meta_bytes_per_chunk, store_atomic, SideMetadataScope, SideMetadataSpec,
+};
+use crate::util::Address;
+
+/// One byte per block, holding a `BlockState`.
+pub const BLOCK_STATE_SPEC: SideMetadataSpec = SideMetadataSpec {
+    scope: SideMetadataScope::PolicySpecific,
+    offset: LINE_MARK_SPEC.offset
+        + meta_bytes_per_chunk(
+            LINE_MARK_SPEC.log_min_obj_size,
+            LINE_MARK_SPEC.log_num_of_bits,
+        ),
+    log_num_of_bits: 3,
+    log_min_obj_size: LOG_BYTES_IN_BLOCK,
+};
+
+/// One byte per block, non-zero if the block is selected for evacuation in the current GC.
+pub const BLOCK_DEFRAG_SPEC: SideMetadataSpec = SideMetadataSpec {
+    scope: SideMetadataScope::PolicySpecific,
+    offset: BLOCK_STATE_SPEC.offset
+        + meta_bytes_per_chunk(
+            BLOCK_STATE_SPEC.log_min_obj_size,
+            BLOCK_STATE_SPEC.log_num_of_bits,
+        ),
+    log_num_of_bits: 3,
+    log_min_obj_size: LOG_BYTES_IN_BLOCK,
+};
+
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BlockState {
+    /// The block is not used by any allocator.
+    Unallocated = 0,
+    /// The block is allocated, and no live object has been found in it (yet) in this GC.
+    Unmarked = 1,
+    /// The block is allocated, and contains live objects.
+    Marked = 2,
+}
+
+impl From<usize> for BlockState {
+    fn from(state: usize) -> Self {
+        match state {
+            0 => BlockState::Unallocated,
+            1 => BlockState::Unmarked,
+            2 => BlockState::Marked,
+            _ => unreachable!("Invalid block state {}", state),
+        }
+    }
+}
+
+/// A 32KB Immix block.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Block(Address);
+
+impl Block {
+    /// Get the block from its start address.
+    #[inline(always)]
+    pub fn from_start(start: Address) -> Self {
+        debug_assert!(start.is_aligned_to(BYTES_IN_BLOCK));
+        Block(start)
+    }
+
+    /// The block that contains the given address.
+    #[inline(always)]
+    pub fn containing(address: Address) -> Self {
+        Block(address.align_down(BYTES_IN_BLOCK))
+    }
+
+    #[inline(always)]
+    pub fn start(&self) -> Address {
+        self.0
+    }
+
+    #[inline(always)]
+    pub fn end(&self) -> Address {
+        self.0 + BYTES_IN_BLOCK
+    }
+
+    /// The `index`-th line of this block.
+    #[inline(always)]
+    pub fn line(&self, index: usize) -> Line {
+        debug_assert!(index < LINES_IN_BLOCK);
+        Line::containing(self.0 + (index << super::LOG_BYTES_IN_LINE))
+    }
+
+    #[inline(always)]
+    pub fn state(&self) -> BlockState {
+        BlockState::from(load_atomic(BLOCK_STATE_SPEC, self.0))
+    }
+
+    #[inline(always)]
+    pub fn set_state(&self, state: BlockState) {
+        store_atomic(BLOCK_STATE_SPEC, self.0, state as usize);
+    }
+
+    #[inline(always)]
+    pub fn is_defrag_source(&self) -> bool {
+        load_atomic(BLOCK_DEFRAG_SPEC, self.0) != 0
+    }
+
+    #[inline(always)]
+    pub fn set_as_defrag_source(&self, defrag: bool) {
+        store_atomic(BLOCK_DEFRAG_SPEC, self.0, defrag as usize);
+    }
+
+    /// Count the lines in this block that were marked in the last GC.
+    pub fn marked_lines(&self) -> usize {
+        (0..LINES_IN_BLOCK)
+            .filter(|&i| self.line(i).is_marked())
+            .count()
+    }
+
+    /// Find the next hole (a run of unmarked lines) at or after the line `search_start`.
+    /// Returns the index of the first line in the hole, and the index of the line after the hole.
+    pub fn next_hole(&self, search_start: usize) -> Option<(usize, usize)> {
+        let mut start = search_start;
+        while start < LINES_IN_BLOCK && self.line(start).is_marked() {
+            start += 1;
+        }
+        if start >= LINES_IN_BLOCK {
+            return None;
+        }
+        let mut end = start + 1;
+        while end < LINES_IN_BLOCK && !self.line(end).is_marked() {
+            end += 1;
+        }
+        Some((start, end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::immix::BYTES_IN_LINE;
+    use crate::util::heap::layout::vm_layout_constants::{BYTES_IN_CHUNK, HEAP_START};
+    use crate::util::side_metadata::{ensure_munmap_metadata_chunk, try_map_metadata_space};
+    use crate::util::test_util::serial_test;
+
+    fn local_metadata_bytes() -> usize {
+        BLOCK_DEFRAG_SPEC.offset
+            + meta_bytes_per_chunk(
+                BLOCK_DEFRAG_SPEC.log_min_obj_size,
+                BLOCK_DEFRAG_SPEC.log_num_of_bits,
+            )
+    }
+
+    #[test]
+    fn test_next_hole() {
+        serial_test(|| {
+            let block = Block::from_start(HEAP_START + (BYTES_IN_CHUNK << 5));
+            assert!(try_map_metadata_space(
+                block.start(),
+                BYTES_IN_BLOCK,
+                0,
+                local_metadata_bytes()
+            ));
+
+            // An object that spans lines 2 and 3, and an object in the last line
+            Line::mark_lines_for_object(block.line(2).start() + 8usize, BYTES_IN_LINE);
+            block.line(LINES_IN_BLOCK - 1).mark();
+            assert_eq!(block.marked_lines(), 3);
+            assert_eq!(block.next_hole(0), Some((0, 2)));
+            assert_eq!(block.next_hole(1), Some((1, 2)));
+            assert_eq!(block.next_hole(2), Some((4, LINES_IN_BLOCK - 1)));
+            assert_eq!(block.next_hole(LINES_IN_BLOCK - 1), None);
+            assert_eq!(block.line(3).index(), 3);
+            assert_eq!(
+                Line::containing(block.line(3).end() - 1usize),
+                block.line(3)
+            );
+            assert_eq!(block.line(3).block(), block);
+
+            assert_eq!(block.state(), BlockState::Unallocated);
+            block.set_state(BlockState::Marked);
+            assert_eq!(block.state(), BlockState::Marked);
+            assert!(!block.is_defrag_source());
+            block.set_as_defrag_source(true);
+            assert!(block.is_defrag_source());
+
+            ensure_munmap_metadata_chunk(block.start(), 0, local_metadata_bytes());
+        })
+    }
+}
@@ -0,0 +1,139 @@
+use super::block::Block;
+use super::LINES_IN_BLOCK;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// Percentage of the heap set aside as a copy reserve for defragmentation.
+pub const DEFRAG_HEADROOM_PERCENT: usize = 2;
+
+/// Defragment if at least this percentage of the allocated blocks were only partially
+/// occupied after the last GC.
+pub const DEFRAG_FRAGMENTED_BLOCKS_PERCENT: usize = 10;
+
+/// Bookkeeping for opportunistic defragmentation.
+#[derive(Default)]
+pub struct Defrag {
+    in_defrag: AtomicBool,
+    /// The number of clean blocks that evacuation may still take in the current GC.
+    available_clean_blocks: AtomicUsize,
+    /// Set once evacuation has taken all the clean blocks we budgeted for it. The objects
+    /// traced afterwards are marked in place, even if they are in defrag sources.
+    space_exhausted: AtomicBool,
+}
+
+impl Defrag {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Is the current GC a defrag GC?
+    #[inline(always)]
+    pub fn in_defrag(&self) -> bool {
+        self.in_defrag.load(Ordering::Acquire)
+    }
+
+    /// Decide whether the current GC should defragment, given the state of the heap after the last GC.
+    pub fn decide_whether_to_defrag(
+        &self,
+        emergency_collection: bool,
+        fragmented_blocks: usize,
+        allocated_blocks: usize,
+    ) {
+        let fragmented = allocated_blocks > 0
+            && fragmented_blocks * 100 >= allocated_blocks * DEFRAG_FRAGMENTED_BLOCKS_PERCENT;
+        self.in_defrag
+            .store(emergency_collection || fragmented, Ordering::Release);
+    }
+
+    /// Pick the defrag sources among the fragmented blocks, sparsest first, so that their
+    /// live lines (as of the last GC) fit into `available_blocks` blocks.
+    /// Returns the blocks that were selected.
+    pub fn select_defrag_sources(
+        &self,
+        mut candidates: Vec<(Block, usize)>,
+        available_blocks: usize,
+    ) -> Vec<Block> {
+        debug_assert!(self.in_defrag());
+        candidates.sort_by_key(|&(_, live_lines)| live_lines);
+        let mut available_lines = available_blocks * LINES_IN_BLOCK;
+        let mut sources = vec![];
+        for (block, live_lines) in candidates {
+            if live_lines > available_lines {
+                break;
+            }
+            available_lines -= live_lines;
+            block.set_as_defrag_source(true);
+            sources.push(block);
+        }
+        self.available_clean_blocks
+            .store(available_blocks, Ordering::SeqCst);
+        self.space_exhausted
+            .store(available_blocks == 0, Ordering::SeqCst);
+        sources
+    }
+
+    /// Has evacuation used up its clean blocks in the current GC?
+    #[inline(always)]
+    pub fn space_exhausted(&self) -> bool {
+        self.space_exhausted.load(Ordering::Acquire)
+    }
+
+    /// Called when evacuation takes a clean block (`acquired` is true), or fails to get one.
+    pub fn notify_new_clean_block(&self, acquired: bool) {
+        if acquired {
+            let prev =
+                self.available_clean_blocks
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                        Some(n.saturating_sub(1))
+                    });
+            if prev.unwrap() > 1 {
+                return;
+            }
+        }
+        self.space_exhausted.store(true, Ordering::Release);
+    }
+
+    pub fn reset(&self) {
+        self.in_defrag.store(false, Ordering::Release);
+        self.available_clean_blocks.store(0, Ordering::SeqCst);
+        self.space_exhausted.store(false, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_space_exhausted_after_budget() {
+        let defrag = Defrag::new();
+        defrag.decide_whether_to_defrag(true, 0, 0);
+        assert!(defrag.in_defrag());
+        defrag.select_defrag_sources(vec![], 2);
+        assert!(!defrag.space_exhausted());
+        defrag.notify_new_clean_block(true);
+        assert!(!defrag.space_exhausted());
+        defrag.notify_new_clean_block(true);
+        assert!(defrag.space_exhausted());
+        defrag.reset();
+        assert!(!defrag.in_defrag());
+        assert!(!defrag.space_exhausted());
+    }
+
+    #[test]
+    fn test_space_exhausted_on_failure() {
+        let defrag = Defrag::new();
+        defrag.decide_whether_to_defrag(true, 0, 0);
+        defrag.select_defrag_sources(vec![], 10);
+        assert!(!defrag.space_exhausted());
+        defrag.notify_new_clean_block(false);
+        assert!(defrag.space_exhausted());
+    }
+
+    #[test]
+    fn test_no_budget() {
+        let defrag = Defrag::new();
+        defrag.decide_whether_to_defrag(true, 0, 0);
+        defrag.select_defrag_sources(vec![], 0);
+        assert!(defrag.space_exhausted());
+    }
+}
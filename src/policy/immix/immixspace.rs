@@ -0,0 +1,432 @@
+use super::block::{Block, BlockState, BLOCK_DEFRAG_SPEC};
+use super::defrag::{Defrag, DEFRAG_HEADROOM_PERCENT};
+use super::line::{Line, LINE_MARK_SPEC};
+use super::{BLOCKS_IN_CHUNK, BYTES_IN_BLOCK, LINES_IN_BLOCK, PAGES_IN_BLOCK};
+use crate::plan::TransitiveClosure;
+use crate::plan::{AllocationSemantics, CopyContext};
+use crate::policy::space::SpaceOptions;
+use crate::policy::space::{CommonSpace, Space, SFT};
+use crate::util::alloc::linear_scan::scan_alloc_bits;
+use crate::util::constants::LOG_BYTES_IN_WORD;
+use crate::util::forwarding_word as ForwardingWord;
+use crate::util::heap::layout::heap_layout::{Mmapper, VMMap};
+use crate::util::heap::layout::vm_layout_constants::{BYTES_IN_CHUNK, PAGES_IN_CHUNK};
+use crate::util::heap::HeapMeta;
+use crate::util::heap::{FreeListPageResource, PageResource, VMRequest};
+use crate::util::memory;
+use crate::util::pin_bit;
+use crate::util::side_metadata::{
+    bcopy_metadata_for_chunk, bzero_metadata_for_chunk, compare_exchange_atomic, load_atomic,
+    meta_bytes_per_chunk, store_atomic, SideMetadataScope, SideMetadataSpec,
+};
+use crate::util::OpaquePointer;
+use crate::util::{Address, ObjectReference};
+use crate::vm::*;
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// The object mark table: one bit per word, set if the object starting at that word is live.
+pub const OBJECT_MARK_SPEC: SideMetadataSpec = SideMetadataSpec {
+    scope: SideMetadataScope::PolicySpecific,
+    offset: 0,
+    log_num_of_bits: 0,
+    log_min_obj_size: LOG_BYTES_IN_WORD as usize,
+};
+
+/// One bit per word, set for each object in the space, so `enumerate_objects()` can find them.
+/// This is the global alloc bit if it is enabled, or a bit of our own after the block metadata otherwise.
+#[cfg(feature = "global_alloc_bit")]
+const ALLOC_SPEC: SideMetadataSpec = crate::util::alloc_bit::ALLOC_SIDE_METADATA_SPEC;
+#[cfg(not(feature = "global_alloc_bit"))]
+const ALLOC_SPEC: SideMetadataSpec = SideMetadataSpec {
+    scope: SideMetadataScope::PolicySpecific,
+    offset: BLOCK_DEFRAG_SPEC.offset
+        + meta_bytes_per_chunk(
+            BLOCK_DEFRAG_SPEC.log_min_obj_size,
+            BLOCK_DEFRAG_SPEC.log_num_of_bits,
+        ),
+    log_num_of_bits: 0,
+    log_min_obj_size: LOG_BYTES_IN_WORD as usize,
+};
+
+pub struct ImmixSpace<VM: VMBinding> {
+    common: UnsafeCell<CommonSpace<VM>>,
+    pr: FreeListPageResource<VM>,
+    /// Chunks acquired from the page resource. Chunks are never returned.
+    chunks: Mutex<Vec<Address>>,
+    /// Blocks that are not used by any allocator.
+    free_blocks: Mutex<Vec<Block>>,
+    /// Blocks that had free lines after the last GC.
+    reusable_blocks: Mutex<Vec<Block>>,
+    /// Number of blocks that are not free. This is what we report as reserved pages.
+    allocated_blocks: AtomicUsize,
+    defrag: Defrag,
+}
+
+unsafe impl<VM: VMBinding> Sync for ImmixSpace<VM> {}
+
+impl<VM: VMBinding> SFT for ImmixSpace<VM> {
+    fn name(&self) -> &str {
+        self.get_name()
+    }
+    fn is_live(&self, object: ObjectReference) -> bool {
+        ForwardingWord::is_forwarded::<VM>(object) || Self::is_marked(object)
+    }
+    fn is_movable(&self) -> bool {
+        self.common().movable
+    }
+    fn get_forwarded_object(&self, object: ObjectReference) -> Option<ObjectReference> {
+        if !self.is_live(object) {
+            return None;
+        }
+        Some(ForwardingWord::get_forwarded_object::<VM>(object).unwrap_or(object))
+    }
+    #[cfg(feature = "sanity")]
+    fn is_sane(&self) -> bool {
+        true
+    }
+    fn initialize_header(&self, object: ObjectReference, _alloc: bool) {
+        // The memory may have been used by an evacuated object before.
+        ForwardingWord::clear_forwarding_bits::<VM>(object);
+        if !self.common().movable {
+            // A non-moving space is not traced by a nursery GC, so its new objects are live until
+            // the next GC that prepares the space.
+            store_atomic(OBJECT_MARK_SPEC, object.to_address(), 1);
+        }
+        #[cfg(not(feature = "global_alloc_bit"))]
+        store_atomic(ALLOC_SPEC, object.to_address(), 1);
+    }
+    fn pin_for_gc(&self, object: ObjectReference) -> bool {
+        // Mark the object before the closure. `trace_object()` never evacuates a marked object.
+        if Self::attempt_mark(object) {
+            Self::mark_lines_and_block(object, VM::VMObjectModel::get_current_size(object));
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl<VM: VMBinding> Space<VM> for ImmixSpace<VM> {
+    fn as_space(&self) -> &dyn Space<VM> {
+        self
+    }
+    fn as_sft(&self) -> &(dyn SFT + Sync + 'static) {
+        self
+    }
+    fn get_page_resource(&self) -> &dyn PageResource<VM> {
+        &self.pr
+    }
+    fn common(&self) -> &CommonSpace<VM> {
+        unsafe { &*self.common.get() }
+    }
+    unsafe fn unsafe_common_mut(&self) -> &mut CommonSpace<VM> {
+        &mut *self.common.get()
+    }
+
+    fn init(&mut self, _vm_map: &'static VMMap) {
+        // Borrow-checker fighting so that we can have a cyclic reference
+        let me = unsafe { &*(self as *const Self) };
+        self.pr.bind_space(me);
+        self.common().init(self.as_space());
+    }
+
+    fn release_multiple_pages(&mut self, _start: Address) {
+        panic!("immixspace only releases blocks")
+    }
+
+    fn reserved_pages(&self) -> usize {
+        self.allocated_blocks.load(Ordering::SeqCst) * PAGES_IN_BLOCK
+    }
+
+    fn local_side_metadata_per_chunk(&self) -> usize {
+        #[cfg(feature = "global_alloc_bit")]
+        let last = BLOCK_DEFRAG_SPEC;
+        #[cfg(not(feature = "global_alloc_bit"))]
+        let last = ALLOC_SPEC;
+        last.offset + meta_bytes_per_chunk(last.log_min_obj_size, last.log_num_of_bits)
+    }
+
+    fn enumerate_objects(&self, f: &mut dyn FnMut(ObjectReference)) {
+        let chunks = self.chunks.lock().unwrap().clone();
+        for chunk in chunks {
+            for block in Self::blocks_in_chunk(chunk) {
+                if block.state() != BlockState::Unallocated {
+                    scan_alloc_bits(ALLOC_SPEC, block.start(), block.start() + BYTES_IN_BLOCK, f);
+                }
+            }
+        }
+    }
+}
+
+impl<VM: VMBinding> ImmixSpace<VM> {
+    /// Create an Immix space. A space that is not `movable` never defragments, and marks all its objects
+    /// in place, so it can hold the objects that must not move (see `AllocationSemantics::NonMoving`).
+    pub fn new(
+        name: &'static str,
+        movable: bool,
+        vm_map: &'static VMMap,
+        mmapper: &'static Mmapper,
+        heap: &mut HeapMeta,
+    ) -> Self {
+        let common = CommonSpace::new(
+            SpaceOptions {
+                name,
+                movable,
+                immortal: false,
+                zeroed: true,
+                vmrequest: VMRequest::discontiguous(),
+            },
+            vm_map,
+            mmapper,
+            heap,
+        );
+        ImmixSpace {
+            pr: FreeListPageResource::new_discontiguous(0, vm_map),
+            common: UnsafeCell::new(common),
+            chunks: Mutex::new(vec![]),
+            free_blocks: Mutex::new(vec![]),
+            reusable_blocks: Mutex::new(vec![]),
+            allocated_blocks: AtomicUsize::new(0),
+            defrag: Defrag::new(),
+        }
+    }
+
+    /// The number of pages we keep aside as the copy reserve for defragmentation.
+    pub fn defrag_headroom_pages(&self) -> usize {
+        VM::VMActivePlan::global().get_total_pages() * DEFRAG_HEADROOM_PERCENT / 100
+    }
+
+    pub fn prepare(&self, emergency_collection: bool) {
+        // Line marks from the last GC are still intact here. Use them to pick defrag sources
+        // among the blocks that were partially occupied.
+        let fragmented_blocks = std::mem::take(&mut *self.reusable_blocks.lock().unwrap());
+        if self.common().movable {
+            self.defrag.decide_whether_to_defrag(
+                emergency_collection,
+                fragmented_blocks.len(),
+                self.allocated_blocks.load(Ordering::SeqCst),
+            );
+        }
+        if self.defrag.in_defrag() {
+            let candidates = fragmented_blocks
+                .into_iter()
+                .map(|block| (block, block.marked_lines()))
+                .collect();
+            let available_blocks = self.free_blocks.lock().unwrap().len()
+                + self.defrag_headroom_pages() / PAGES_IN_BLOCK;
+            let sources = self
+                .defrag
+                .select_defrag_sources(candidates, available_blocks);
+            debug!("Immix defrag: {} source blocks", sources.len());
+        }
+
+        for &chunk in self.chunks.lock().unwrap().iter() {
+            bzero_metadata_for_chunk(OBJECT_MARK_SPEC, chunk);
+            bzero_metadata_for_chunk(LINE_MARK_SPEC, chunk);
+            for block in Self::blocks_in_chunk(chunk) {
+                if block.state() != BlockState::Unallocated {
+                    block.set_state(BlockState::Unmarked);
+                }
+            }
+        }
+    }
+
+    pub fn release(&self) {
+        let mut free_blocks = self.free_blocks.lock().unwrap();
+        let mut reusable_blocks = self.reusable_blocks.lock().unwrap();
+        for &chunk in self.chunks.lock().unwrap().iter() {
+            // The marked objects are exactly the objects that survived, wherever they were allocated.
+            bcopy_metadata_for_chunk(ALLOC_SPEC, OBJECT_MARK_SPEC, chunk);
+            pin_bit::bzero_pin_bits_of_unmarked(OBJECT_MARK_SPEC, chunk);
+            for block in Self::blocks_in_chunk(chunk) {
+                match block.state() {
+                    BlockState::Unallocated => {}
+                    BlockState::Unmarked => {
+                        block.set_state(BlockState::Unallocated);
+                        free_blocks.push(block);
+                        self.allocated_blocks.fetch_sub(1, Ordering::SeqCst);
+                    }
+                    BlockState::Marked => {
+                        if block.marked_lines() < LINES_IN_BLOCK {
+                            reusable_blocks.push(block);
+                        }
+                    }
+                }
+                block.set_as_defrag_source(false);
+            }
+        }
+        self.defrag.reset();
+    }
+
+    fn blocks_in_chunk(chunk: Address) -> impl Iterator<Item = Block> {
+        (0..BLOCKS_IN_CHUNK).map(move |i| Block::from_start(chunk + i * BYTES_IN_BLOCK))
+    }
+
+    /// Block the mutator for a GC if the heap is full. Returns true if a GC happened.
+    fn poll_for_gc(&self, tls: OpaquePointer) -> bool {
+        if unsafe { VM::VMActivePlan::is_mutator(tls) }
+            && VM::VMActivePlan::global().poll(false, self.as_space())
+        {
+            if !VM::VMActivePlan::global().is_initialized() {
+                panic!("Collection is not enabled.");
+            }
+            VM::VMCollection::block_for_gc(tls);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Get a free block for an allocator. Blocks for mutators are zeroed.
+    /// Returns `None` if a GC was triggered instead.
+    pub fn get_clean_block(&self, tls: OpaquePointer, copy: bool) -> Option<Block> {
+        // A free block counts as used as soon as we hand it out, so check the heap first.
+        if !copy && self.poll_for_gc(tls) {
+            return None;
+        }
+        let block = loop {
+            if let Some(block) = self.free_blocks.lock().unwrap().pop() {
+                break block;
+            }
+            let chunk = self.acquire(tls, PAGES_IN_CHUNK);
+            if chunk.is_zero() {
+                if copy {
+                    self.defrag.notify_new_clean_block(false);
+                }
+                return None;
+            }
+            self.add_chunk(chunk);
+        };
+        self.allocated_blocks.fetch_add(1, Ordering::SeqCst);
+        if copy {
+            self.defrag.notify_new_clean_block(true);
+            // Blocks used for evacuation hold live objects by definition.
+            block.set_state(BlockState::Marked);
+        } else {
+            block.set_state(BlockState::Unmarked);
+            memory::zero(block.start(), BYTES_IN_BLOCK);
+        }
+        Some(block)
+    }
+
+    /// Get a partially occupied block, whose holes can be reused by a mutator.
+    pub fn get_reusable_block(&self) -> Option<Block> {
+        self.reusable_blocks.lock().unwrap().pop()
+    }
+
+    fn add_chunk(&self, chunk: Address) {
+        debug_assert!(chunk.is_aligned_to(BYTES_IN_CHUNK));
+        self.chunks.lock().unwrap().push(chunk);
+        // Push in reverse order, so we hand out blocks in increasing addresses.
+        let mut free_blocks = self.free_blocks.lock().unwrap();
+        for i in (0..BLOCKS_IN_CHUNK).rev() {
+            free_blocks.push(Block::from_start(chunk + i * BYTES_IN_BLOCK));
+        }
+    }
+
+    #[inline(always)]
+    fn is_marked(object: ObjectReference) -> bool {
+        load_atomic(OBJECT_MARK_SPEC, object.to_address()) != 0
+    }
+
+    /// Atomically mark an object. Returns true if this call marked the object.
+    #[inline(always)]
+    fn attempt_mark(object: ObjectReference) -> bool {
+        compare_exchange_atomic(OBJECT_MARK_SPEC, object.to_address(), 0, 1)
+    }
+
+    /// Mark the lines and the block that the object occupies.
+    #[inline(always)]
+    fn mark_lines_and_block(object: ObjectReference, bytes: usize) {
+        let start = VM::VMObjectModel::object_start_ref(object);
+        Line::mark_lines_for_object(start, bytes);
+        Block::containing(start).set_state(BlockState::Marked);
+    }
+
+    #[inline]
+    pub fn trace_object<T: TransitiveClosure, C: CopyContext>(
+        &self,
+        trace: &mut T,
+        object: ObjectReference,
+        semantics: AllocationSemantics,
+        copy_context: &mut C,
+    ) -> ObjectReference {
+        if self.defrag.in_defrag() && Block::containing(object.to_address()).is_defrag_source() {
+            self.trace_object_with_opportunistic_copy(trace, object, semantics, copy_context)
+        } else {
+            self.trace_object_without_moving(trace, object)
+        }
+    }
+
+    /// Mark an object in place. This is how a space that is not movable traces all its objects.
+    #[inline]
+    pub fn trace_object_without_moving<T: TransitiveClosure>(
+        &self,
+        trace: &mut T,
+        object: ObjectReference,
+    ) -> ObjectReference {
+        if Self::attempt_mark(object) {
+            Self::mark_lines_and_block(object, VM::VMObjectModel::get_current_size(object));
+            trace.process_node(object);
+        }
+        object
+    }
+
+    /// Trace an object in a defrag source block. The thread that starts forwarding the object
+    /// decides whether to copy it or to mark it in place, and every other thread waits for that
+    /// decision, so all the references to the object end up pointing to the same copy.
+    #[inline]
+    fn trace_object_with_opportunistic_copy<T: TransitiveClosure, C: CopyContext>(
+        &self,
+        trace: &mut T,
+        object: ObjectReference,
+        semantics: AllocationSemantics,
+        copy_context: &mut C,
+    ) -> ObjectReference {
+        let forwarding_status = ForwardingWord::attempt_to_forward::<VM>(object);
+        if ForwardingWord::state_is_forwarded_or_being_forwarded(forwarding_status) {
+            return ForwardingWord::spin_and_get_forwarded_or_retained_object::<VM>(
+                object,
+                forwarding_status,
+            );
+        }
+        if Self::is_marked(object) {
+            // An earlier thread has marked the object in place.
+            ForwardingWord::clear_forwarding_bits::<VM>(object);
+            return object;
+        }
+        // Copies go to clean blocks. Once evacuation has taken the blocks we budgeted for it
+        // (the free blocks and the headroom), we mark the remaining objects in place instead.
+        // A copy may still fail if the space cannot grow, and then the object stays in place too.
+        let new_object = if pin_bit::is_pinned(object) || self.defrag.space_exhausted() {
+            None
+        } else {
+            ForwardingWord::try_forward_object::<VM, _>(object, semantics, copy_context)
+        };
+        match new_object {
+            Some(new_object) => {
+                trace.process_node(new_object);
+                new_object
+            }
+            None => {
+                // Mark the object before we clear the forwarding bits, so a thread that forwards
+                // it next sees that it stays in place.
+                self.trace_object_without_moving(trace, object);
+                ForwardingWord::clear_forwarding_bits::<VM>(object);
+                object
+            }
+        }
+    }
+
+    /// Called by the copy context once an object has been evacuated into this space.
+    #[inline]
+    pub fn post_copy(&self, object: ObjectReference, bytes: usize) {
+        ForwardingWord::clear_forwarding_bits::<VM>(object);
+        store_atomic(OBJECT_MARK_SPEC, object.to_address(), 1);
+        #[cfg(not(feature = "global_alloc_bit"))]
+        store_atomic(ALLOC_SPEC, object.to_address(), 1);
+        Self::mark_lines_and_block(object, bytes);
+    }
+}
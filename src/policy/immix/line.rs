@@ -0,0 +1,79 @@
+use super::block::Block;
+use super::immixspace::OBJECT_MARK_SPEC;
+use super::{BYTES_IN_LINE, LOG_BYTES_IN_LINE};
+use crate::util::side_metadata::{
+    load_atomic, meta_bytes_per_chunk, store_atomic, SideMetadataScope, SideMetadataSpec,
+};
+use crate::util::Address;
+
+/// The line mark table: one byte per line, non-zero if the line holds (part of) a live object.
+pub const LINE_MARK_SPEC: SideMetadataSpec = SideMetadataSpec {
+    scope: SideMetadataScope::PolicySpecific,
+    offset: OBJECT_MARK_SPEC.offset
+        + meta_bytes_per_chunk(
+            OBJECT_MARK_SPEC.log_min_obj_size,
+            OBJECT_MARK_SPEC.log_num_of_bits,
+        ),
+    log_num_of_bits: 3,
+    log_min_obj_size: LOG_BYTES_IN_LINE,
+};
+
+/// A line in an Immix block.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd)]
+pub struct Line(Address);
+
+impl Line {
+    /// The line that contains the given address.
+    #[inline(always)]
+    pub fn containing(address: Address) -> Self {
+        Line(address.align_down(BYTES_IN_LINE))
+    }
+
+    #[inline(always)]
+    pub fn start(&self) -> Address {
+        self.0
+    }
+
+    #[inline(always)]
+    pub fn end(&self) -> Address {
+        self.0 + BYTES_IN_LINE
+    }
+
+    #[inline(always)]
+    pub fn block(&self) -> Block {
+        Block::containing(self.0)
+    }
+
+    /// The index of this line in its block.
+    #[inline(always)]
+    pub fn index(&self) -> usize {
+        (self.0 - self.block().start()) >> LOG_BYTES_IN_LINE
+    }
+
+    #[inline(always)]
+    pub fn next(&self) -> Self {
+        Line(self.0 + BYTES_IN_LINE)
+    }
+
+    #[inline(always)]
+    pub fn is_marked(&self) -> bool {
+        load_atomic(LINE_MARK_SPEC, self.0) != 0
+    }
+
+    #[inline(always)]
+    pub fn mark(&self) {
+        store_atomic(LINE_MARK_SPEC, self.0, 1);
+    }
+
+    /// Mark all the lines that overlap with `[start, start + bytes)`.
+    #[inline]
+    pub fn mark_lines_for_object(start: Address, bytes: usize) {
+        debug_assert!(bytes > 0);
+        let last = Line::containing(start + bytes - 1);
+        let mut line = Line::containing(start);
+        while line <= last {
+            line.mark();
+            line = line.next();
+        }
+    }
+}
@@ -0,0 +1,33 @@
+//! Immix: a mark-region policy (Blackburn and McKinley, PLDI 2008).
+//!
+//! The space is divided into blocks, and each block into lines. Objects are allocated
+//! by bump pointer into free blocks, or into holes (runs of free lines) in blocks that were
+//! partially occupied after the last GC. Liveness is recorded at both object and line granularity.
+//! When the heap is fragmented, the space picks the sparsest blocks as defrag sources and
+//! opportunistically evacuates their live objects.
+
+pub mod block;
+pub mod defrag;
+pub mod immixspace;
+pub mod line;
+
+pub use self::block::Block;
+pub use self::immixspace::ImmixSpace;
+pub use self::line::Line;
+
+use crate::util::constants::LOG_BYTES_IN_PAGE;
+use crate::util::heap::layout::vm_layout_constants::LOG_BYTES_IN_CHUNK;
+
+/// log2 of the block size. Blocks are 32KB.
+pub const LOG_BYTES_IN_BLOCK: usize = 15;
+pub const BYTES_IN_BLOCK: usize = 1 << LOG_BYTES_IN_BLOCK;
+pub const PAGES_IN_BLOCK: usize = 1 << (LOG_BYTES_IN_BLOCK - LOG_BYTES_IN_PAGE as usize);
+pub const BLOCKS_IN_CHUNK: usize = 1 << (LOG_BYTES_IN_CHUNK - LOG_BYTES_IN_BLOCK);
+
+/// log2 of the line size. Lines are 256B.
+pub const LOG_BYTES_IN_LINE: usize = 8;
+pub const BYTES_IN_LINE: usize = 1 << LOG_BYTES_IN_LINE;
+pub const LINES_IN_BLOCK: usize = 1 << (LOG_BYTES_IN_BLOCK - LOG_BYTES_IN_LINE);
+
+/// Objects larger than this should be allocated in the large object space.
+pub const MAX_IMMIX_OBJECT_SIZE: usize = BYTES_IN_BLOCK >> 1;
@@ -2,6 +2,7 @@ use crate::policy::space::{CommonSpace, Space, SFT};
 use crate::util::address::Address;
 use crate::util::heap::{MonotonePageResource, PageResource, VMRequest};
 
+use crate::util::alloc::linear_scan::scan_bump_region;
 use crate::util::constants::CARD_META_PAGES_PER_REGION;
 use crate::util::ObjectReference;
 
@@ -79,6 +80,14 @@ impl<VM: VMBinding> Space<VM> for ImmortalSpace<VM> {
     fn release_multiple_pages(&mut self, _start: Address) {
         panic!("immortalspace only releases pages enmasse")
     }
+
+    fn enumerate_objects(&self, f: &mut dyn FnMut(ObjectReference)) {
+        // Objects in an externally mapped space (such as the VM space) were not allocated by us,
+        // so we do not know about them.
+        for (start, end) in self.pr.allocated_regions() {
+            scan_bump_region::<VM>(start, end, f);
+        }
+    }
 }
 
 impl<VM: VMBinding> ImmortalSpace<VM> {
@@ -58,13 +58,7 @@ impl<VM: VMBinding> SFT for LargeObjectSpace<VM> {
             new_value |= NURSERY_BIT;
         }
         gc_byte::write_gc_byte::<VM>(object, new_value);
-        let cell = VM::VMObjectModel::object_start_ref(object)
-            - if USE_PRECEEDING_GC_HEADER {
-                PRECEEDING_GC_HEADER_BYTES
-            } else {
-                0
-            };
-        self.treadmill.add_to_treadmill(cell, alloc);
+        self.treadmill.add_to_treadmill(object, alloc);
         if self.header_byte.needs_unlogged_bit {
             gc_byte::write_gc_byte::<VM>(
                 object,
@@ -100,6 +94,12 @@ impl<VM: VMBinding> Space<VM> for LargeObjectSpace<VM> {
     fn release_multiple_pages(&mut self, start: Address) {
         self.pr.release_pages(start);
     }
+
+    fn enumerate_objects(&self, f: &mut dyn FnMut(ObjectReference)) {
+        for object in self.treadmill.objects() {
+            f(object);
+        }
+    }
 }
 
 impl<VM: VMBinding> LargeObjectSpace<VM> {
@@ -166,13 +166,7 @@ impl<VM: VMBinding> LargeObjectSpace<VM> {
         if !self.in_nursery_gc || nursery_object {
             // Note that test_and_mark() has side effects
             if self.test_and_mark(object, self.mark_state) {
-                let cell = VM::VMObjectModel::object_start_ref(object)
-                    - if USE_PRECEEDING_GC_HEADER {
-                        PRECEEDING_GC_HEADER_BYTES
-                    } else {
-                        0
-                    };
-                self.treadmill.copy(cell, nursery_object);
+                self.treadmill.copy(object, nursery_object);
                 trace.process_node(object);
             }
         }
@@ -184,14 +178,18 @@ impl<VM: VMBinding> LargeObjectSpace<VM> {
         // didn't call self.release_multiple_pages
         // so the compiler knows I'm borrowing two different fields
         if sweep_nursery {
-            for cell in self.treadmill.collect_nursery() {
-                // println!("- cn {}", cell);
-                self.pr.release_pages(get_super_page(cell));
+            for object in self.treadmill.collect_nursery() {
+                #[cfg(feature = "global_alloc_bit")]
+                crate::util::alloc_bit::unset_alloc_bit(object);
+                crate::util::pin_bit::unset_pin_bit(object);
+                self.pr.release_pages(get_super_page::<VM>(object));
             }
         } else {
-            for cell in self.treadmill.collect() {
-                // println!("- ts {}", cell);
-                self.pr.release_pages(get_super_page(cell));
+            for object in self.treadmill.collect() {
+                #[cfg(feature = "global_alloc_bit")]
+                crate::util::alloc_bit::unset_alloc_bit(object);
+                crate::util::pin_bit::unset_pin_bit(object);
+                self.pr.release_pages(get_super_page::<VM>(object));
             }
         }
     }
@@ -242,6 +240,13 @@ impl<VM: VMBinding> LargeObjectSpace<VM> {
     }
 }
 
-fn get_super_page(cell: Address) -> Address {
+/// The first page of the cell that holds an object.
+fn get_super_page<VM: VMBinding>(object: ObjectReference) -> Address {
+    let cell = VM::VMObjectModel::object_start_ref(object)
+        - if USE_PRECEEDING_GC_HEADER {
+            PRECEEDING_GC_HEADER_BYTES
+        } else {
+            0
+        };
     cell.align_down(BYTES_IN_PAGE)
 }
@@ -1,6 +1,7 @@
 use crate::mmtk::SFT_MAP;
 use crate::policy::space::{CommonSpace, Space, SFT};
 use crate::util::address::Address;
+use crate::util::alloc::linear_scan::scan_bump_region;
 use crate::util::conversions::bytes_to_chunks_up;
 use crate::util::heap::PageResource;
 use crate::util::side_metadata::try_map_metadata_space;
@@ -9,9 +10,8 @@ use crate::util::ObjectReference;
 
 use crate::util::conversions;
 use crate::util::heap::layout::heap_layout::VMMap;
-use crate::util::heap::layout::vm_layout_constants::{
-    AVAILABLE_BYTES, AVAILABLE_END, AVAILABLE_START,
-};
+use crate::util::heap::layout::vm_layout_constants::VM_SPACE_SIZE;
+use crate::util::heap::HeapMeta;
 use crate::util::opaque_pointer::OpaquePointer;
 use crate::vm::VMBinding;
 use crate::vm::*;
@@ -19,8 +19,9 @@ use std::marker::PhantomData;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 pub struct LockFreeImmortalSpace<VM: VMBinding> {
-    #[allow(unused)]
     name: &'static str,
+    /// The start of the space
+    start: Address,
     /// Heap range start
     ///
     /// We use `AtomicUsize` instead of `Address` here to atomically bumping this cursor.
@@ -43,14 +44,14 @@ impl<VM: VMBinding> SFT for LockFreeImmortalSpace<VM> {
         unimplemented!()
     }
     fn is_movable(&self) -> bool {
-        unimplemented!()
+        false
     }
     #[cfg(feature = "sanity")]
     fn is_sane(&self) -> bool {
         unimplemented!()
     }
     fn initialize_header(&self, _object: ObjectReference, _alloc: bool) {
-        unimplemented!()
+        // The space never collects, so the objects need no header bits.
     }
 }
 
@@ -76,24 +77,25 @@ impl<VM: VMBinding> Space<VM> for LockFreeImmortalSpace<VM> {
     }
 
     fn init(&mut self, _vm_map: &'static VMMap) {
+        // Map enough memory for the largest heap size
         let total_pages = VM::VMActivePlan::global()
             .base()
-            .heap
-            .total_pages
-            .load(Ordering::SeqCst);
+            .heap_growth_manager
+            .max_heap_pages();
         let total_bytes = conversions::pages_to_bytes(total_pages);
         assert!(total_pages > 0);
+        let available_bytes = self.limit - self.start;
         assert!(
-            total_bytes <= AVAILABLE_BYTES,
+            total_bytes <= available_bytes,
             "Initial requested memory ({} bytes) overflows the heap. Max heap size is {} bytes.",
             total_bytes,
-            AVAILABLE_BYTES
+            available_bytes
         );
-        self.limit = AVAILABLE_START + total_bytes;
+        self.limit = self.start + total_bytes;
         // Eagerly memory map the entire heap (also zero all the memory)
-        crate::util::memory::dzmmap(AVAILABLE_START, total_bytes).unwrap();
+        crate::util::memory::dzmmap(self.start, total_bytes).unwrap();
         if !try_map_metadata_space(
-            AVAILABLE_START,
+            self.start,
             total_bytes,
             VM::VMActivePlan::global().global_side_metadata_per_chunk(),
             self.local_side_metadata_per_chunk(),
@@ -101,11 +103,12 @@ impl<VM: VMBinding> Space<VM> for LockFreeImmortalSpace<VM> {
             // TODO(Javad): handle meta space allocation failure
             panic!("failed to mmap meta memory");
         }
-        SFT_MAP.update(
-            self.as_sft(),
-            AVAILABLE_START,
-            bytes_to_chunks_up(total_bytes),
-        );
+        SFT_MAP.update(self.as_sft(), self.start, bytes_to_chunks_up(total_bytes));
+    }
+
+    fn get_name(&self) -> &'static str {
+        // This space has no common space.
+        self.name
     }
 
     fn reserved_pages(&self) -> usize {
@@ -124,14 +127,22 @@ impl<VM: VMBinding> Space<VM> for LockFreeImmortalSpace<VM> {
         }
         start
     }
+
+    fn enumerate_objects(&self, f: &mut dyn FnMut(ObjectReference)) {
+        let cursor = unsafe { Address::from_usize(self.cursor.load(Ordering::Relaxed)) };
+        scan_bump_region::<VM>(self.start, cursor, f);
+    }
 }
 
 impl<VM: VMBinding> LockFreeImmortalSpace<VM> {
-    pub fn new(name: &'static str, slow_path_zeroing: bool) -> Self {
+    /// Create the space in the heap range of the plan, above the address range kept for the VM space.
+    pub fn new(name: &'static str, slow_path_zeroing: bool, heap: &HeapMeta) -> Self {
+        let start = heap.get_discontig_start() + VM_SPACE_SIZE;
         Self {
             name,
-            cursor: AtomicUsize::new(AVAILABLE_START.as_usize()),
-            limit: AVAILABLE_END,
+            start,
+            cursor: AtomicUsize::new(start.as_usize()),
+            limit: heap.heap_limit,
             slow_path_zeroing,
             phantom: PhantomData,
         }
@@ -0,0 +1,365 @@
+use crate::mmtk::SFT_MAP;
+use crate::plan::TransitiveClosure;
+use crate::policy::space::{CommonSpace, Space, SFT};
+use crate::util::address::Address;
+use crate::util::constants::{BYTES_IN_WORD, LOG_BITS_IN_WORD, LOG_BYTES_IN_WORD};
+use crate::util::conversions;
+use crate::util::heap::layout::heap_layout::VMMap;
+use crate::util::heap::layout::vm_layout_constants::{BYTES_IN_CHUNK, MAX_CHUNKS};
+use crate::util::heap::PageResource;
+use crate::util::malloc::*;
+use crate::util::side_metadata::{
+    address_to_meta_address, compare_exchange_atomic, load_atomic, meta_bytes_per_chunk,
+    store_atomic, try_map_metadata_space, SideMetadataScope, SideMetadataSpec,
+};
+use crate::util::ObjectReference;
+use crate::util::OpaquePointer;
+use crate::vm::VMBinding;
+use crate::vm::{ActivePlan, Collection, ObjectModel};
+use std::collections::HashSet;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::RwLock;
+
+/// One bit per word, set for each object reference returned by `alloc()`.
+const ALLOC_METADATA_SPEC: SideMetadataSpec = SideMetadataSpec {
+    scope: SideMetadataScope::PolicySpecific,
+    offset: 0,
+    log_num_of_bits: 0,
+    log_min_obj_size: LOG_BYTES_IN_WORD as usize,
+};
+
+/// The number of bytes whose alloc bits are held in one word of the alloc bit metadata.
+const BYTES_PER_ALLOC_WORD: usize = BYTES_IN_WORD << LOG_BITS_IN_WORD;
+
+/// One bit per word, set when an object is marked during a GC, and cleared by the sweep.
+const MARKING_METADATA_SPEC: SideMetadataSpec = SideMetadataSpec {
+    scope: SideMetadataScope::PolicySpecific,
+    offset: meta_bytes_per_chunk(LOG_BYTES_IN_WORD as usize, 0),
+    log_num_of_bits: 0,
+    log_min_obj_size: LOG_BYTES_IN_WORD as usize,
+};
+
+/// A non-moving space whose cells are allocated by the malloc library selected
+/// by the `malloc_*` features (see `util::malloc`).
+///
+/// Malloc does not give us a contiguous or discontiguous range of chunks as our other spaces do,
+/// so this space has no page resource. Instead, it remembers each chunk that holds a malloc'd cell,
+/// and maps side metadata for those chunks on demand. Liveness is recorded in a side metadata
+/// mark bitmap, and dead cells are returned to malloc by `sweep_chunk()`.
+pub struct MallocSpace<VM: VMBinding> {
+    /// Bytes currently allocated from malloc (as reported by `malloc_usable_size`)
+    active_bytes: AtomicUsize,
+    /// Chunks that contain (or have contained) objects of this space. Side metadata is mapped for these chunks.
+    active_chunks: RwLock<HashSet<Address>>,
+    /// Mark new objects as they are allocated. This is set while a concurrent marking is in progress,
+    /// as the marking does not trace the objects allocated after it started.
+    allocate_marked: AtomicBool,
+    /// Objects are live until they are freed, rather than only if they are marked (see `new_reference_counted()`).
+    live_until_freed: AtomicBool,
+    phantom: PhantomData<VM>,
+}
+
+unsafe impl<VM: VMBinding> Sync for MallocSpace<VM> {}
+
+impl<VM: VMBinding> SFT for MallocSpace<VM> {
+    fn name(&self) -> &str {
+        self.get_name()
+    }
+    fn is_live(&self, object: ObjectReference) -> bool {
+        if self.live_until_freed.load(Ordering::SeqCst) {
+            load_atomic(ALLOC_METADATA_SPEC, object.to_address()) == 1
+        } else {
+            is_marked(object)
+        }
+    }
+    fn is_movable(&self) -> bool {
+        false
+    }
+    #[cfg(feature = "sanity")]
+    fn is_sane(&self) -> bool {
+        true
+    }
+    fn initialize_header(&self, object: ObjectReference, _alloc: bool) {
+        debug_assert!(object.to_address().is_aligned_to(BYTES_IN_WORD));
+        if self.allocate_marked.load(Ordering::SeqCst) {
+            store_atomic(MARKING_METADATA_SPEC, object.to_address(), 1);
+        }
+        store_atomic(ALLOC_METADATA_SPEC, object.to_address(), 1);
+    }
+}
+
+impl<VM: VMBinding> Space<VM> for MallocSpace<VM> {
+    fn as_space(&self) -> &dyn Space<VM> {
+        self
+    }
+    fn as_sft(&self) -> &(dyn SFT + Sync + 'static) {
+        self
+    }
+    fn get_page_resource(&self) -> &dyn PageResource<VM> {
+        unreachable!("MallocSpace does not have a page resource")
+    }
+    fn common(&self) -> &CommonSpace<VM> {
+        unreachable!("MallocSpace does not have a common space")
+    }
+    unsafe fn unsafe_common_mut(&self) -> &mut CommonSpace<VM> {
+        unreachable!("MallocSpace does not have a common space")
+    }
+
+    fn init(&mut self, _vm_map: &'static VMMap) {
+        // Do nothing. Chunks are added lazily as malloc hands out memory.
+    }
+
+    fn release_multiple_pages(&mut self, _start: Address) {
+        unreachable!("MallocSpace releases memory by freeing individual cells")
+    }
+
+    fn address_in_space(&self, start: Address) -> bool {
+        // The alloc bit can only be read once we know the metadata for the chunk is mapped.
+        self.is_active_chunk(conversions::chunk_align_down(start))
+            && load_atomic(ALLOC_METADATA_SPEC, start) == 1
+    }
+
+    fn in_space(&self, object: ObjectReference) -> bool {
+        self.address_in_space(object.to_address())
+    }
+
+    fn reserved_pages(&self) -> usize {
+        conversions::bytes_to_pages_up(self.active_bytes.load(Ordering::SeqCst))
+    }
+
+    fn resident_pages(&self) -> usize {
+        // We do not know which of the pages malloc gave us are committed.
+        self.reserved_pages()
+    }
+
+    fn get_name(&self) -> &'static str {
+        "MallocSpace"
+    }
+
+    fn local_side_metadata_per_chunk(&self) -> usize {
+        meta_bytes_per_chunk(LOG_BYTES_IN_WORD as usize, 0) * 2
+    }
+
+    fn for_each_chunk_range(&self, f: &mut dyn FnMut(Address, Address)) {
+        for chunk in self.active_chunks() {
+            f(chunk, chunk + BYTES_IN_CHUNK);
+        }
+    }
+
+    fn enumerate_objects(&self, f: &mut dyn FnMut(ObjectReference)) {
+        for chunk in self.active_chunks() {
+            self.for_each_object_in_chunk(chunk, |object| f(object));
+        }
+    }
+}
+
+impl<VM: VMBinding> MallocSpace<VM> {
+    pub fn new() -> Self {
+        MallocSpace {
+            active_bytes: AtomicUsize::new(0),
+            active_chunks: RwLock::new(HashSet::new()),
+            allocate_marked: AtomicBool::new(false),
+            live_until_freed: AtomicBool::new(false),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Create a space for a reference counting plan, which frees each object as soon as it is found dead.
+    /// An object is live until it is freed, so `is_live()` does not read the mark bits, unless a backup
+    /// trace uses them (see `set_live_until_freed()`).
+    pub fn new_reference_counted() -> Self {
+        MallocSpace {
+            live_until_freed: AtomicBool::new(true),
+            ..Self::new()
+        }
+    }
+
+    /// Allocate `size` zeroed bytes from malloc. Returns zero if a GC was triggered instead.
+    pub fn alloc(&self, tls: OpaquePointer, size: usize) -> Address {
+        // Poll for a GC before we allocate, in the same way as `Space::acquire()`.
+        if unsafe { VM::VMActivePlan::is_mutator(tls) }
+            && VM::VMActivePlan::global().poll(false, self.as_space())
+        {
+            if !VM::VMActivePlan::global().is_initialized() {
+                panic!("Collection is not enabled.");
+            }
+            VM::VMCollection::block_for_gc(tls);
+            return unsafe { Address::zero() };
+        }
+
+        let raw = unsafe { calloc(1, size) };
+        let address = Address::from_mut_ptr(raw);
+        if address.is_zero() {
+            VM::VMCollection::out_of_memory(tls);
+            return address;
+        }
+
+        let actual_size = unsafe { malloc_usable_size(raw) };
+        self.map_chunks_for(address, actual_size);
+        self.active_bytes.fetch_add(actual_size, Ordering::SeqCst);
+        address
+    }
+
+    /// Is the chunk an active chunk of this space? The SFT map answers this for most chunks, and only
+    /// chunks beyond the SFT map need to take the lock.
+    fn is_active_chunk(&self, chunk: Address) -> bool {
+        if chunk.chunk_index() < MAX_CHUNKS {
+            SFT_MAP.get(chunk) as *const dyn SFT as *const () == self as *const Self as *const ()
+        } else {
+            self.active_chunks.read().unwrap().contains(&chunk)
+        }
+    }
+
+    /// Make sure each chunk in `[start, start + size)` is an active chunk with side metadata mapped.
+    fn map_chunks_for(&self, start: Address, size: usize) {
+        let end = conversions::chunk_align_up(start + size);
+        let mut chunk = conversions::chunk_align_down(start);
+        while chunk < end {
+            if !self.is_active_chunk(chunk) {
+                let mut active_chunks = self.active_chunks.write().unwrap();
+                // Check again, in case another thread has mapped the chunk while we wait for the lock.
+                if !active_chunks.contains(&chunk) {
+                    if !try_map_metadata_space(
+                        chunk,
+                        BYTES_IN_CHUNK,
+                        VM::VMActivePlan::global().global_side_metadata_per_chunk(),
+                        self.local_side_metadata_per_chunk(),
+                    ) {
+                        // TODO: handle meta space allocation failure
+                        panic!("failed to mmap meta memory");
+                    }
+                    // Malloc may hand out addresses beyond what the SFT map covers.
+                    if chunk.chunk_index() < MAX_CHUNKS {
+                        SFT_MAP.update(self.as_sft(), chunk, 1);
+                    }
+                    active_chunks.insert(chunk);
+                }
+            }
+            chunk += BYTES_IN_CHUNK;
+        }
+    }
+
+    /// Set whether an object is live until it is freed, or only if it is marked.
+    pub fn set_live_until_freed(&self, live_until_freed: bool) {
+        self.live_until_freed
+            .store(live_until_freed, Ordering::SeqCst);
+    }
+
+    /// Set whether new objects are marked when they are allocated.
+    pub fn set_allocate_marked(&self, allocate_marked: bool) {
+        self.allocate_marked
+            .store(allocate_marked, Ordering::SeqCst);
+    }
+
+    /// Return all the chunks that may contain objects of this space.
+    pub fn active_chunks(&self) -> Vec<Address> {
+        self.active_chunks.read().unwrap().iter().copied().collect()
+    }
+
+    #[inline]
+    pub fn trace_object<T: TransitiveClosure>(
+        &self,
+        trace: &mut T,
+        object: ObjectReference,
+    ) -> ObjectReference {
+        if object.is_null() {
+            return object;
+        }
+        if compare_exchange_atomic(MARKING_METADATA_SPEC, object.to_address(), 0, 1) {
+            trace.process_node(object);
+        }
+        object
+    }
+
+    /// Free all the unmarked objects in the given chunk, and clear the mark bits of the live ones.
+    pub fn sweep_chunk(&self, chunk_start: Address) {
+        self.for_each_object_in_chunk(chunk_start, |object| {
+            if is_marked(object) {
+                self.unmark_object(object);
+            } else {
+                store_atomic(ALLOC_METADATA_SPEC, object.to_address(), 0);
+                self.free_object(object);
+            }
+        });
+    }
+
+    /// Call `f` on each object in the given chunk.
+    pub fn for_each_object_in_chunk<F: FnMut(ObjectReference)>(
+        &self,
+        chunk_start: Address,
+        mut f: F,
+    ) {
+        debug_assert!(chunk_start.is_aligned_to(BYTES_IN_CHUNK));
+        let chunk_end = chunk_start + BYTES_IN_CHUNK;
+        let mut region = chunk_start;
+        while region < chunk_end {
+            let region_end = region + BYTES_PER_ALLOC_WORD;
+            // Skip the whole region at once if none of its alloc bits is set.
+            let alloc_bits: usize =
+                unsafe { address_to_meta_address(ALLOC_METADATA_SPEC, region).load() };
+            if alloc_bits != 0 {
+                let mut address = region;
+                while address < region_end {
+                    if load_atomic(ALLOC_METADATA_SPEC, address) == 1 {
+                        f(unsafe { address.to_object_reference() });
+                    }
+                    address += BYTES_IN_WORD;
+                }
+            }
+            region = region_end;
+        }
+    }
+
+    /// Is the object marked in the current GC?
+    pub fn is_marked(&self, object: ObjectReference) -> bool {
+        is_marked(object)
+    }
+
+    /// Clear the mark bit of an object.
+    pub fn unmark_object(&self, object: ObjectReference) {
+        store_atomic(MARKING_METADATA_SPEC, object.to_address(), 0);
+    }
+
+    /// Remove a dead object from the space, so `in_space()` is false for it. Returns false if
+    /// another thread has already done so. The object can still be scanned until `free_object()` is called.
+    pub fn claim_dead_object(&self, object: ObjectReference) -> bool {
+        compare_exchange_atomic(ALLOC_METADATA_SPEC, object.to_address(), 1, 0)
+    }
+
+    /// Return the cell of a claimed dead object to malloc.
+    pub fn free_object(&self, object: ObjectReference) {
+        debug_assert_eq!(load_atomic(ALLOC_METADATA_SPEC, object.to_address()), 0);
+        self.unmark_object(object);
+        #[cfg(feature = "global_alloc_bit")]
+        crate::util::alloc_bit::unset_alloc_bit(object);
+        crate::util::pin_bit::unset_pin_bit(object);
+        let cell = VM::VMObjectModel::object_start_ref(object);
+        let raw = cell.to_mut_ptr();
+        let freed_bytes = unsafe { malloc_usable_size(raw) };
+        unsafe { free(raw) };
+        self.active_bytes.fetch_sub(freed_bytes, Ordering::SeqCst);
+    }
+}
+
+impl<VM: VMBinding> Drop for MallocSpace<VM> {
+    fn drop(&mut self) {
+        // Malloc hands out cells from one process-wide heap, so the malloc space of the next instance
+        // may reuse our chunks. Make sure the SFT no longer points to us.
+        for chunk in self.active_chunks() {
+            if chunk.chunk_index() < MAX_CHUNKS && self.is_active_chunk(chunk) {
+                SFT_MAP.clear(chunk.chunk_index());
+            }
+        }
+    }
+}
+
+impl<VM: VMBinding> Default for MallocSpace<VM> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn is_marked(object: ObjectReference) -> bool {
+    load_atomic(MARKING_METADATA_SPEC, object.to_address()) == 1
+}
@@ -0,0 +1,276 @@
+use crate::plan::TransitiveClosure;
+use crate::policy::space::SpaceOptions;
+use crate::policy::space::{CommonSpace, Space, SFT};
+use crate::util::alloc::allocator::align_allocation_no_fill;
+use crate::util::constants::{BYTES_IN_WORD, LOG_BYTES_IN_WORD};
+use crate::util::heap::layout::heap_layout::{Mmapper, VMMap};
+use crate::util::heap::HeapMeta;
+use crate::util::heap::VMRequest;
+use crate::util::heap::{MonotonePageResource, PageResource};
+use crate::util::side_metadata::{
+    compare_exchange_atomic, load_atomic, meta_bytes_per_chunk, store_atomic, SideMetadataScope,
+    SideMetadataSpec,
+};
+use crate::util::{Address, ObjectReference};
+use crate::vm::*;
+use std::cell::UnsafeCell;
+
+/// Each object in this space is preceded by a hidden header word, which holds the
+/// forwarding address of the object during a GC.
+pub const GC_EXTRA_HEADER_BYTES: usize = BYTES_IN_WORD;
+
+/// One bit per word, set for each object reference allocated in (or compacted into) this space.
+/// This lets us walk the objects in address order.
+const ALLOC_METADATA_SPEC: SideMetadataSpec = SideMetadataSpec {
+    scope: SideMetadataScope::PolicySpecific,
+    offset: 0,
+    log_num_of_bits: 0,
+    log_min_obj_size: LOG_BYTES_IN_WORD as usize,
+};
+
+/// One bit per word, set when an object is marked, and cleared again when the references
+/// to the object are updated.
+const MARKING_METADATA_SPEC: SideMetadataSpec = SideMetadataSpec {
+    scope: SideMetadataScope::PolicySpecific,
+    offset: meta_bytes_per_chunk(LOG_BYTES_IN_WORD as usize, 0),
+    log_num_of_bits: 0,
+    log_min_obj_size: LOG_BYTES_IN_WORD as usize,
+};
+
+/// A space for a Lisp-2 style sliding compactor. A collection goes through four phases:
+/// 1. mark the live objects (`trace_mark_object()`),
+/// 2. compute the forwarding address of each live object in address order (`calculate_forwarding_pointer()`),
+/// 3. update all the references to the forwarding addresses (`trace_forward_object()`),
+/// 4. slide the live objects down to their forwarding addresses (`compact()`).
+///
+/// The space only supports contiguous address ranges, so compaction can slide objects
+/// from one chunk to an earlier one.
+pub struct MarkCompactSpace<VM: VMBinding> {
+    common: UnsafeCell<CommonSpace<VM>>,
+    pr: MonotonePageResource<VM>,
+}
+
+unsafe impl<VM: VMBinding> Sync for MarkCompactSpace<VM> {}
+
+impl<VM: VMBinding> SFT for MarkCompactSpace<VM> {
+    fn name(&self) -> &str {
+        self.get_name()
+    }
+    fn is_live(&self, object: ObjectReference) -> bool {
+        // Only meaningful during the marking closure.
+        Self::is_marked(object)
+    }
+    fn is_movable(&self) -> bool {
+        true
+    }
+    fn get_forwarded_object(&self, object: ObjectReference) -> Option<ObjectReference> {
+        // Only meaningful after the forwarding addresses are computed, which forgets the dead objects.
+        if load_atomic(ALLOC_METADATA_SPEC, object.to_address()) == 1 {
+            Some(Self::get_forwarding_pointer(object))
+        } else {
+            None
+        }
+    }
+    #[cfg(feature = "sanity")]
+    fn is_sane(&self) -> bool {
+        true
+    }
+    fn initialize_header(&self, object: ObjectReference, _alloc: bool) {
+        debug_assert!(object.to_address().is_aligned_to(BYTES_IN_WORD));
+        store_atomic(ALLOC_METADATA_SPEC, object.to_address(), 1);
+    }
+}
+
+impl<VM: VMBinding> Space<VM> for MarkCompactSpace<VM> {
+    fn as_space(&self) -> &dyn Space<VM> {
+        self
+    }
+    fn as_sft(&self) -> &(dyn SFT + Sync + 'static) {
+        self
+    }
+    fn get_page_resource(&self) -> &dyn PageResource<VM> {
+        &self.pr
+    }
+    fn common(&self) -> &CommonSpace<VM> {
+        unsafe { &*self.common.get() }
+    }
+    unsafe fn unsafe_common_mut(&self) -> &mut CommonSpace<VM> {
+        &mut *self.common.get()
+    }
+
+    fn init(&mut self, _vm_map: &'static VMMap) {
+        // Borrow-checker fighting so that we can have a cyclic reference
+        let me = unsafe { &*(self as *const Self) };
+        self.pr.bind_space(me);
+        self.common().init(self.as_space());
+    }
+
+    fn release_multiple_pages(&mut self, _start: Address) {
+        panic!("markcompactspace only releases pages enmasse")
+    }
+
+    fn local_side_metadata_per_chunk(&self) -> usize {
+        meta_bytes_per_chunk(LOG_BYTES_IN_WORD as usize, 0) * 2
+    }
+
+    fn enumerate_objects(&self, f: &mut dyn FnMut(ObjectReference)) {
+        self.walk_objects(|object| f(object));
+    }
+}
+
+impl<VM: VMBinding> MarkCompactSpace<VM> {
+    pub fn new(
+        name: &'static str,
+        zeroed: bool,
+        vm_map: &'static VMMap,
+        mmapper: &'static Mmapper,
+        heap: &mut HeapMeta,
+    ) -> Self {
+        // Compaction slides objects towards the start of the space, so it has to be contiguous.
+        // On 64-bit this is the usual per-space extent, and on 32-bit we take half of the available address space.
+        let vmrequest = VMRequest::fraction(0.5);
+        debug_assert!(!vmrequest.is_discontiguous());
+        let common = CommonSpace::new(
+            SpaceOptions {
+                name,
+                movable: true,
+                immortal: false,
+                zeroed,
+                vmrequest,
+            },
+            vm_map,
+            mmapper,
+            heap,
+        );
+        MarkCompactSpace {
+            pr: MonotonePageResource::new_contiguous(common.start, common.extent, 0, vm_map),
+            common: UnsafeCell::new(common),
+        }
+    }
+
+    /// The address of the hidden header word of an object.
+    #[inline]
+    fn header_address(object: ObjectReference) -> Address {
+        VM::VMObjectModel::object_start_ref(object) - GC_EXTRA_HEADER_BYTES
+    }
+
+    #[inline]
+    fn get_forwarding_pointer(object: ObjectReference) -> ObjectReference {
+        unsafe { Self::header_address(object).load::<ObjectReference>() }
+    }
+
+    #[inline]
+    fn set_forwarding_pointer(object: ObjectReference, new_object: ObjectReference) {
+        unsafe { Self::header_address(object).store(new_object) }
+    }
+
+    #[inline]
+    fn is_marked(object: ObjectReference) -> bool {
+        load_atomic(MARKING_METADATA_SPEC, object.to_address()) == 1
+    }
+
+    /// Trace an object in the marking closure.
+    #[inline]
+    pub fn trace_mark_object<T: TransitiveClosure>(
+        &self,
+        trace: &mut T,
+        object: ObjectReference,
+    ) -> ObjectReference {
+        if compare_exchange_atomic(MARKING_METADATA_SPEC, object.to_address(), 0, 1) {
+            trace.process_node(object);
+        }
+        object
+    }
+
+    /// Trace an object in the reference updating closure, and return its forwarding address.
+    /// This clears the mark bit, so each object is scanned once in this closure.
+    #[inline]
+    pub fn trace_forward_object<T: TransitiveClosure>(
+        &self,
+        trace: &mut T,
+        object: ObjectReference,
+    ) -> ObjectReference {
+        if compare_exchange_atomic(MARKING_METADATA_SPEC, object.to_address(), 1, 0) {
+            trace.process_node(object);
+        }
+        Self::get_forwarding_pointer(object)
+    }
+
+    /// Visit each object in this space in address order. The alloc bit of an object
+    /// may be cleared by `visitor`, but only bits at or below the object may be set.
+    fn walk_objects<F: FnMut(ObjectReference)>(&self, mut visitor: F) {
+        let start = self.common().start;
+        let end = self.pr.cursor();
+        let mut address = start;
+        while address < end {
+            if load_atomic(ALLOC_METADATA_SPEC, address) == 1 {
+                visitor(unsafe { address.to_object_reference() });
+            }
+            address += BYTES_IN_WORD;
+        }
+    }
+
+    /// Reserve room for an object (and its hidden header word) at the compaction cursor `to`, in the
+    /// same way as `MarkCompactAllocator` does. Returns the region for the object, and the new cursor.
+    /// Both `calculate_forwarding_pointer()` and `compact()` use this, so they agree on where each object goes.
+    #[inline]
+    fn reserve_copy(to: Address, object: ObjectReference) -> (Address, Address) {
+        let size = VM::VMObjectModel::get_size_when_copied(object);
+        let align = VM::VMObjectModel::get_align_when_copied(object);
+        let offset = VM::VMObjectModel::get_align_offset_when_copied(object);
+        let header =
+            align_allocation_no_fill::<VM>(to, align, offset + GC_EXTRA_HEADER_BYTES as isize);
+        let region = header + GC_EXTRA_HEADER_BYTES;
+        (region, (region + size).align_up(BYTES_IN_WORD))
+    }
+
+    /// Compute the forwarding address of each live object, and forget the dead ones.
+    pub fn calculate_forwarding_pointer(&self) {
+        let mut to = self.common().start;
+        self.walk_objects(|object| {
+            if !Self::is_marked(object) {
+                store_atomic(ALLOC_METADATA_SPEC, object.to_address(), 0);
+                #[cfg(feature = "global_alloc_bit")]
+                crate::util::alloc_bit::unset_alloc_bit(object);
+                crate::util::pin_bit::unset_pin_bit(object);
+                return;
+            }
+            let (region, next) = Self::reserve_copy(to, object);
+            let new_object = VM::VMObjectModel::get_reference_when_copied_to(object, region);
+            Self::set_forwarding_pointer(object, new_object);
+            to = next;
+        });
+    }
+
+    /// Slide each live object down to its forwarding address, and release the memory above the last one.
+    /// The target of a copy may overlap with the object itself, so `ObjectModel::copy_to()`
+    /// must be able to copy between overlapping regions.
+    pub fn compact(&self) {
+        let mut to = self.common().start;
+        self.walk_objects(|object| {
+            let (region, next) = Self::reserve_copy(to, object);
+            let new_object = Self::get_forwarding_pointer(object);
+            debug_assert_eq!(
+                new_object,
+                VM::VMObjectModel::get_reference_when_copied_to(object, region)
+            );
+            // Clear the old alloc bit first, as the object may not move at all. We do not support
+            // pinning, so the pin bit is not carried over.
+            store_atomic(ALLOC_METADATA_SPEC, object.to_address(), 0);
+            #[cfg(feature = "global_alloc_bit")]
+            crate::util::alloc_bit::unset_alloc_bit(object);
+            crate::util::pin_bit::unset_pin_bit(object);
+            if new_object != object {
+                VM::VMObjectModel::copy_to(object, new_object, region);
+            }
+            unsafe { (region - GC_EXTRA_HEADER_BYTES).store(0usize) };
+            store_atomic(ALLOC_METADATA_SPEC, new_object.to_address(), 1);
+            #[cfg(feature = "global_alloc_bit")]
+            crate::util::alloc_bit::set_alloc_bit(new_object);
+            to = next;
+        });
+        unsafe {
+            self.pr.reset_cursor(to);
+        }
+    }
+}
@@ -14,6 +14,9 @@
 pub mod space;
 
 pub mod copyspace;
+pub mod immix;
 pub mod immortalspace;
 pub mod largeobjectspace;
 pub mod lockfreeimmortalspace;
+pub mod mallocspace;
+pub mod markcompactspace;
@@ -53,6 +53,21 @@ pub trait SFT {
     #[cfg(feature = "sanity")]
     fn is_sane(&self) -> bool;
     fn initialize_header(&self, object: ObjectReference, alloc: bool);
+    /// Keep an object at its address for the rest of the current GC, as it is referenced by an
+    /// ambiguous root. This is only called for objects in movable spaces, before the transitive closure.
+    /// Returns true if this call kept the object in place and the object needs to be scanned.
+    fn pin_for_gc(&self, _object: ObjectReference) -> bool {
+        panic!("{} cannot keep objects in place", self.name())
+    }
+    /// Get the address of an object after the current GC, or `None` if the object is dead.
+    /// This is only meaningful after the transitive closure (see `Scanning::process_weak_roots()`).
+    fn get_forwarded_object(&self, object: ObjectReference) -> Option<ObjectReference> {
+        if self.is_live(object) {
+            Some(object)
+        } else {
+            None
+        }
+    }
 }
 
 /// Print debug info for SFT. Should be false when committed.
@@ -122,7 +137,11 @@ impl SFTMap {
     }
 
     pub fn get(&self, address: Address) -> &'static dyn SFT {
-        let res = self.sft[address.chunk_index()];
+        // Spaces such as MallocSpace may have objects beyond the address range covered by the SFT map.
+        let res = match self.sft.get(address.chunk_index()) {
+            Some(&sft) => sft,
+            None => &EMPTY_SPACE_SFT as *const (dyn SFT + Sync),
+        };
         if DEBUG_SFT {
             trace!(
                 "Get SFT for {} #{} = {}",
@@ -191,6 +210,11 @@ impl SFTMap {
         }
     }
 
+    /// Is the address in a chunk that belongs to a space? The side metadata is mapped for these chunks.
+    pub fn is_in_any_space(&self, address: Address) -> bool {
+        self.get(address).name() != EMPTY_SFT_NAME
+    }
+
     pub fn clear(&self, chunk_idx: usize) {
         self.set(chunk_idx, &EMPTY_SPACE_SFT);
     }
@@ -367,6 +391,11 @@ pub trait Space<VM: VMBinding>: 'static + SFT + Sync + Downcast {
         self.get_page_resource().reserved_pages()
     }
 
+    /// The pages of this space that are committed (mapped and not decommitted).
+    fn resident_pages(&self) -> usize {
+        self.get_page_resource().resident_pages()
+    }
+
     fn get_name(&self) -> &'static str {
         self.common().name
     }
@@ -448,6 +477,31 @@ pub trait Space<VM: VMBinding>: 'static + SFT + Sync + Downcast {
     fn local_side_metadata_per_chunk(&self) -> usize {
         0
     }
+
+    /// Call `f` with the start and end of each contiguous range of chunks that this space owns.
+    /// Side metadata is mapped for all of these chunks.
+    fn for_each_chunk_range(&self, f: &mut dyn FnMut(Address, Address)) {
+        let common = self.common();
+        if common.contiguous {
+            f(
+                common.start,
+                conversions::chunk_align_up(common.start + common.extent),
+            );
+        } else {
+            let mut start = common.head_discontiguous_region;
+            while !start.is_zero() {
+                f(
+                    start,
+                    start + common.vm_map().get_contiguous_region_size(start),
+                );
+                start = common.vm_map().get_next_contiguous_region(start);
+            }
+        }
+    }
+
+    /// Call `f` for each object allocated in this space (and not yet reclaimed), in no particular order.
+    /// Nothing may allocate into or collect the space meanwhile. See `memory_manager::enumerate_objects()`.
+    fn enumerate_objects(&self, f: &mut dyn FnMut(ObjectReference));
 }
 
 impl_downcast!(Space<VM> where VM: VMBinding);
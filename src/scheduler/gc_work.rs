@@ -15,6 +15,14 @@ unsafe impl Sync for ScheduleCollection {}
 
 impl<VM: VMBinding> GCWork<VM> for ScheduleCollection {
     fn do_work(&mut self, worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        // A requested heap dump is taken in this pause, instead of a GC
+        if mmtk
+            .heap_dumper
+            .schedule_if_requested(worker.scheduler(), mmtk)
+        {
+            return;
+        }
+        mmtk.plan.base().gc_log.gc_started(&*mmtk.plan);
         mmtk.plan.schedule_collection(worker.scheduler());
     }
 }
@@ -117,8 +125,8 @@ impl<P: Plan, W: CopyContext + WorkerLocal> GCWork<P::VM> for Release<P, W> {
         for w in &mmtk.scheduler.worker_group().workers {
             w.local_work_bucket.add(ReleaseCollector::<W>(PhantomData));
         }
-        // TODO: Process weak references properly
-        mmtk.reference_processors.clear();
+        mmtk.reference_processors
+            .release(self.plan.get_free_pages());
     }
 }
 
@@ -220,6 +228,19 @@ pub struct EndOfGC;
 impl<VM: VMBinding> GCWork<VM> for EndOfGC {
     fn do_work(&mut self, worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
         mmtk.plan.common().base.set_gc_status(GcStatus::NotInGC);
+        // Resize the heap, now that we know how much of it is live
+        mmtk.plan
+            .base()
+            .consider_heap_size(mmtk.plan.get_pages_reserved());
+        let kind = if mmtk.plan.in_nursery() {
+            "nursery"
+        } else {
+            "full"
+        };
+        mmtk.plan
+            .base()
+            .gc_log
+            .gc_finished(&*mmtk.plan, kind, &mmtk.scheduler.stage_durations());
         <VM as VMBinding>::VMCollection::resume_mutators(worker.tls);
     }
 }
@@ -310,6 +331,122 @@ impl<E: ProcessEdgesWork> GCWork<E::VM> for ScanVMSpecificRoots<E> {
     }
 }
 
+/// Tells the VM whether an object survived the current GC, and where it is after the GC.
+/// It is given to `Scanning::process_weak_roots()`, after the transitive closure. It can be
+/// copied into the work packets of the VM, so the VM can process its weak roots in parallel.
+#[derive(Clone, Copy)]
+pub struct WeakRootTracer {
+    stage: WorkBucketStage,
+}
+
+impl WeakRootTracer {
+    /// Create a tracer for the weak roots processed in `stage`. MMTk gives the VM a tracer in
+    /// `Scanning::process_weak_roots()`, so a VM only needs this in its tests.
+    pub fn new(stage: WorkBucketStage) -> Self {
+        Self { stage }
+    }
+
+    /// Is the object alive after the current GC?
+    pub fn is_live(&self, object: ObjectReference) -> bool {
+        self.get_forwarded_object(object).is_some()
+    }
+
+    /// Get the address of the object after the current GC, or `None` if the object is dead.
+    pub fn get_forwarded_object(&self, object: ObjectReference) -> Option<ObjectReference> {
+        crate::mmtk::SFT_MAP
+            .get(object.to_address())
+            .get_forwarded_object(object)
+    }
+
+    /// The stage in which the weak roots are processed. The work packets added to its bucket
+    /// are done before the GC moves on to the next stage.
+    pub fn stage(&self) -> WorkBucketStage {
+        self.stage
+    }
+}
+
+/// Let the VM process its weak roots (see `Scanning::process_weak_roots()`). The plans add this to the
+/// `PhantomRefClosure` bucket, or to a later one if the objects are moved or freed after the closure.
+pub struct ProcessWeakRoots<VM: VMBinding> {
+    stage: WorkBucketStage,
+    phantom: PhantomData<VM>,
+}
+
+impl<VM: VMBinding> ProcessWeakRoots<VM> {
+    pub fn new(stage: WorkBucketStage) -> Self {
+        Self {
+            stage,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<VM: VMBinding> GCWork<VM> for ProcessWeakRoots<VM> {
+    fn do_work(&mut self, worker: &mut GCWorker<VM>, _mmtk: &'static MMTK<VM>) {
+        trace!("ProcessWeakRoots");
+        VM::VMScanning::process_weak_roots(WeakRootTracer::new(self.stage), worker);
+    }
+}
+
+/// Process ambiguous roots: words that may or may not refer to objects, e.g. the words found by scanning
+/// a stack conservatively. Each word that is the reference of an object allocated by MMTk keeps the object
+/// alive, and the object is kept in place until the end of the GC. Words that point into an object
+/// are not recognized. This needs the `global_alloc_bit` feature.
+///
+/// A binding adds the work packets to the `PinningRoots` bucket, e.g. in `Scanning::scan_thread_roots()`,
+/// so they are done after all the spaces are prepared, and before any object is moved.
+/// Plans that cannot keep objects in place (see `PlanConstraints::can_pin_objects`) reject ambiguous roots.
+#[cfg(feature = "global_alloc_bit")]
+pub struct ProcessAmbiguousRoots<E: ProcessEdgesWork> {
+    roots: Vec<Address>,
+    phantom: PhantomData<E>,
+}
+
+#[cfg(feature = "global_alloc_bit")]
+impl<E: ProcessEdgesWork> ProcessAmbiguousRoots<E> {
+    pub fn new(roots: Vec<Address>) -> Self {
+        Self {
+            roots,
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "global_alloc_bit")]
+impl<E: ProcessEdgesWork> GCWork<E::VM> for ProcessAmbiguousRoots<E> {
+    fn do_work(&mut self, worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
+        trace!("ProcessAmbiguousRoots");
+        assert!(
+            mmtk.plan.constraints().can_pin_objects,
+            "{:?} cannot keep objects in place, so it does not support ambiguous roots",
+            mmtk.options.plan
+        );
+        let mut closure = E::new(vec![], true, mmtk);
+        closure.set_worker(worker);
+        for &root in &self.roots {
+            if !crate::memory_manager::is_mmtk_object(root) {
+                continue;
+            }
+            let object = unsafe { root.to_object_reference() };
+            let sft = crate::mmtk::SFT_MAP.get(root);
+            if sft.is_movable() {
+                if sft.pin_for_gc(object) {
+                    ProcessEdgesWork::process_node(&mut closure, object);
+                }
+            } else {
+                closure.trace_object(object);
+            }
+        }
+        // Scan the objects in the closure stage, so no object is traced before all the ambiguous roots are pinned.
+        let nodes = mem::take(&mut closure.nodes);
+        if !nodes.is_empty() {
+            mmtk.scheduler.work_buckets[WorkBucketStage::Closure]
+                .add(ScanObjects::<E>::new(nodes, false));
+        }
+        trace!("ProcessAmbiguousRoots End");
+    }
+}
+
 pub struct ProcessEdgesBase<E: ProcessEdgesWork> {
     pub edges: Vec<Address>,
     pub nodes: Vec<ObjectReference>,
@@ -420,10 +557,54 @@ impl<E: ProcessEdgesWork> GCWork<E::VM> for E {
     }
 }
 
+/// Lets the code written against `TraceLocal` (e.g. the finalizable processor) trace objects with a
+/// `ProcessEdgesWork`. The traced objects are buffered in the `ProcessEdgesWork`, which should be
+/// flushed afterwards. The trace itself is done by the work packets, so the methods to process roots
+/// and to complete the trace are not supported.
+pub struct ProcessEdgesTraceLocal<'a, E: ProcessEdgesWork>(pub &'a mut E);
+
+impl<'a, E: ProcessEdgesWork> TransitiveClosure for ProcessEdgesTraceLocal<'a, E> {
+    fn process_edge(&mut self, slot: Address) {
+        ProcessEdgesWork::process_edge(&mut *self.0, slot);
+    }
+    fn process_node(&mut self, object: ObjectReference) {
+        ProcessEdgesWork::process_node(&mut *self.0, object);
+    }
+}
+
+impl<'a, E: ProcessEdgesWork> TraceLocal for ProcessEdgesTraceLocal<'a, E> {
+    fn process_roots(&mut self) {
+        unreachable!()
+    }
+    fn process_root_edge(&mut self, slot: Address, _untraced: bool) {
+        ProcessEdgesWork::process_edge(&mut *self.0, slot);
+    }
+    fn trace_object(&mut self, object: ObjectReference) -> ObjectReference {
+        ProcessEdgesWork::trace_object(&mut *self.0, object)
+    }
+    fn complete_trace(&mut self) {
+        unreachable!()
+    }
+    fn release(&mut self) {
+        unreachable!()
+    }
+    fn process_interior_edge(&mut self, _target: ObjectReference, _slot: Address, _root: bool) {
+        unreachable!()
+    }
+    fn report_delayed_root_edge(&mut self, _slot: Address) {
+        unreachable!()
+    }
+    fn will_not_move_in_current_collection(&self, object: ObjectReference) -> bool {
+        !object.is_movable()
+    }
+}
+
 /// Scan & update a list of object slots
+///
+/// A concurrent `ScanObjects` may run while mutators are running. The closure bucket is not open at that time,
+/// so the objects are scanned one by one, and the edges are pushed to the unconstrained bucket instead.
 pub struct ScanObjects<Edges: ProcessEdgesWork> {
     buffer: Vec<ObjectReference>,
-    #[allow(unused)]
     concurrent: bool,
     phantom: PhantomData<Edges>,
 }
@@ -439,13 +620,63 @@ impl<Edges: ProcessEdgesWork> ScanObjects<Edges> {
 }
 
 impl<E: ProcessEdgesWork> GCWork<E::VM> for ScanObjects<E> {
-    fn do_work(&mut self, worker: &mut GCWorker<E::VM>, _mmtk: &'static MMTK<E::VM>) {
+    fn do_work(&mut self, worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
         trace!("ScanObjects");
-        <E::VM as VMBinding>::VMScanning::scan_objects::<E>(&self.buffer, worker);
+        if self.concurrent {
+            let mut closure = ConcurrentScanClosure::<E>::new(mmtk);
+            for object in &self.buffer {
+                <E::VM as VMBinding>::VMScanning::scan_object(&mut closure, *object, worker.tls);
+            }
+            closure.flush();
+        } else {
+            <E::VM as VMBinding>::VMScanning::scan_objects::<E>(&self.buffer, worker);
+        }
         trace!("ScanObjects End");
     }
 }
 
+/// Collect the edges found by a concurrent `ScanObjects`, and push them to the unconstrained bucket
+/// as `E` work packets.
+struct ConcurrentScanClosure<E: ProcessEdgesWork> {
+    edges: Vec<Address>,
+    mmtk: &'static MMTK<E::VM>,
+}
+
+impl<E: ProcessEdgesWork> ConcurrentScanClosure<E> {
+    fn new(mmtk: &'static MMTK<E::VM>) -> Self {
+        Self {
+            edges: vec![],
+            mmtk,
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.edges.is_empty() {
+            return;
+        }
+        let mut edges = vec![];
+        mem::swap(&mut edges, &mut self.edges);
+        self.mmtk.scheduler.work_buckets[WorkBucketStage::Unconstrained]
+            .add(E::new(edges, false, self.mmtk));
+    }
+}
+
+impl<E: ProcessEdgesWork> TransitiveClosure for ConcurrentScanClosure<E> {
+    #[inline]
+    fn process_edge(&mut self, slot: Address) {
+        if self.edges.is_empty() {
+            self.edges.reserve(E::CAPACITY);
+        }
+        self.edges.push(slot);
+        if self.edges.len() >= E::CAPACITY {
+            self.flush();
+        }
+    }
+    fn process_node(&mut self, _object: ObjectReference) {
+        unreachable!();
+    }
+}
+
 #[derive(Default)]
 pub struct ProcessModBuf<E: ProcessEdgesWork> {
     modified_nodes: Vec<ObjectReference>,
@@ -484,3 +715,52 @@ impl<E: ProcessEdgesWork> GCWork<E::VM> for ProcessModBuf<E> {
         }
     }
 }
+
+/// Call `E::trace_object()` on each of a list of objects that are not reached from any edge, e.g. the referents
+/// logged by a snapshot-at-the-beginning barrier, which were reachable when the marking started and must
+/// be marked even though the references to them have been overwritten.
+pub struct TraceObjects<E: ProcessEdgesWork> {
+    objects: Vec<ObjectReference>,
+    phantom: PhantomData<E>,
+}
+
+impl<E: ProcessEdgesWork> TraceObjects<E> {
+    pub fn new(objects: Vec<ObjectReference>) -> Self {
+        Self {
+            objects,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E: ProcessEdgesWork> GCWork<E::VM> for TraceObjects<E> {
+    #[inline]
+    fn do_work(&mut self, worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
+        let mut trace = E::new(vec![], false, mmtk);
+        trace.set_worker(worker);
+        for object in &self.objects {
+            trace.trace_object(*object);
+        }
+        if !trace.nodes.is_empty() {
+            trace.flush();
+        }
+    }
+}
+
+/// Flush the barrier buffers of all the mutators. The mutators must be stopped.
+#[derive(Default)]
+pub struct FlushMutators<VM: VMBinding>(PhantomData<VM>);
+
+impl<VM: VMBinding> FlushMutators<VM> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<VM: VMBinding> GCWork<VM> for FlushMutators<VM> {
+    fn do_work(&mut self, _worker: &mut GCWorker<VM>, _mmtk: &'static MMTK<VM>) {
+        for mutator in VM::VMActivePlan::mutators() {
+            mutator.flush();
+        }
+    }
+}
@@ -5,6 +5,7 @@ mod mmtk_context;
 #[allow(clippy::module_inception)]
 mod scheduler;
 pub mod stat;
+pub mod trace;
 mod work;
 mod work_bucket;
 mod worker;
@@ -1,4 +1,5 @@
 use super::stat::SchedulerStat;
+use super::trace::write_chrome_trace;
 use super::work::Work;
 use super::work_bucket::*;
 use super::worker::{Worker, WorkerGroup};
@@ -7,10 +8,13 @@ use crate::mmtk::MMTK;
 use crate::util::OpaquePointer;
 use crate::vm::VMBinding;
 use enum_map::{enum_map, EnumMap};
-use std::collections::HashMap;
-use std::sync::atomic::Ordering;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::time::{Duration, Instant};
 
 pub enum CoordinatorMessage<C: Context> {
     Work(Box<dyn CoordinatorWork<C>>),
@@ -35,6 +39,14 @@ pub struct Scheduler<C: Context> {
     ),
     startup: Mutex<Option<Box<dyn CoordinatorWork<C>>>>,
     finalizer: Mutex<Option<Box<dyn CoordinatorWork<C>>>>,
+    /// The time spent in each work bucket stage in the last GC
+    stage_durations: Mutex<Vec<(WorkBucketStage, Duration)>>,
+    /// The prefix of the work packet trace files, if the trace is enabled
+    work_trace_file: Mutex<Option<String>>,
+    /// The number of GCs whose work packet trace was written
+    traced_gcs: AtomicUsize,
+    /// The timestamps in the work packet traces are relative to this
+    trace_epoch: Instant,
 }
 
 unsafe impl<C: Context> Send for Scheduler<C> {}
@@ -47,7 +59,15 @@ impl<C: Context> Scheduler<C> {
             work_buckets: enum_map! {
                 WorkBucketStage::Unconstrained => WorkBucket::new(true, worker_monitor.clone()),
                 WorkBucketStage::Prepare => WorkBucket::new(false, worker_monitor.clone()),
+                WorkBucketStage::PinningRoots => WorkBucket::new(false, worker_monitor.clone()),
                 WorkBucketStage::Closure => WorkBucket::new(false, worker_monitor.clone()),
+                WorkBucketStage::SoftRefClosure => WorkBucket::new(false, worker_monitor.clone()),
+                WorkBucketStage::WeakRefClosure => WorkBucket::new(false, worker_monitor.clone()),
+                WorkBucketStage::FinalRefClosure => WorkBucket::new(false, worker_monitor.clone()),
+                WorkBucketStage::PhantomRefClosure => WorkBucket::new(false, worker_monitor.clone()),
+                WorkBucketStage::CalculateForwarding => WorkBucket::new(false, worker_monitor.clone()),
+                WorkBucketStage::RefForwarding => WorkBucket::new(false, worker_monitor.clone()),
+                WorkBucketStage::Compact => WorkBucket::new(false, worker_monitor.clone()),
                 WorkBucketStage::Release => WorkBucket::new(false, worker_monitor.clone()),
                 WorkBucketStage::Final => WorkBucket::new(false, worker_monitor.clone()),
             },
@@ -59,6 +79,10 @@ impl<C: Context> Scheduler<C> {
             channel: channel(),
             startup: Mutex::new(None),
             finalizer: Mutex::new(None),
+            stage_durations: Mutex::new(vec![]),
+            work_trace_file: Mutex::new(None),
+            traced_gcs: AtomicUsize::new(0),
+            trace_epoch: Instant::now(),
         })
     }
 
@@ -91,24 +115,26 @@ impl<C: Context> Scheduler<C> {
             .unwrap()
             .spawn_workers(tls, context);
 
-        self_mut.work_buckets[WorkBucketStage::Closure].set_open_condition(move || {
-            self.work_buckets[WorkBucketStage::Unconstrained].is_drained()
-                && self.work_buckets[WorkBucketStage::Prepare].is_drained()
-                && self.worker_group().all_parked()
-        });
-        self_mut.work_buckets[WorkBucketStage::Release].set_open_condition(move || {
-            self.work_buckets[WorkBucketStage::Unconstrained].is_drained()
-                && self.work_buckets[WorkBucketStage::Prepare].is_drained()
-                && self.work_buckets[WorkBucketStage::Closure].is_drained()
-                && self.worker_group().all_parked()
-        });
-        self_mut.work_buckets[WorkBucketStage::Final].set_open_condition(move || {
-            self.work_buckets[WorkBucketStage::Unconstrained].is_drained()
-                && self.work_buckets[WorkBucketStage::Prepare].is_drained()
-                && self.work_buckets[WorkBucketStage::Closure].is_drained()
-                && self.work_buckets[WorkBucketStage::Release].is_drained()
-                && self.worker_group().all_parked()
-        });
+        // Each stage opens once all the earlier stages are drained and all the workers are parked.
+        let open_stages: &[WorkBucketStage] = &[
+            WorkBucketStage::PinningRoots,
+            WorkBucketStage::Closure,
+            WorkBucketStage::SoftRefClosure,
+            WorkBucketStage::WeakRefClosure,
+            WorkBucketStage::FinalRefClosure,
+            WorkBucketStage::PhantomRefClosure,
+            WorkBucketStage::CalculateForwarding,
+            WorkBucketStage::RefForwarding,
+            WorkBucketStage::Compact,
+            WorkBucketStage::Release,
+            WorkBucketStage::Final,
+        ];
+        for stage in open_stages {
+            let stage = *stage;
+            self_mut.work_buckets[stage].set_open_condition(move || {
+                self.are_buckets_drained_before(stage) && self.worker_group().all_parked()
+            });
+        }
     }
 
     pub fn initialize_worker(self: &Arc<Self>, tls: OpaquePointer) {
@@ -128,12 +154,32 @@ impl<C: Context> Scheduler<C> {
         self.worker_group.as_ref().unwrap().clone()
     }
 
+    /// Are all the buckets for the stages before `stage` drained?
+    fn are_buckets_drained_before(&self, stage: WorkBucketStage) -> bool {
+        self.work_buckets
+            .iter()
+            .take_while(|(id, _)| *id != stage)
+            .all(|(_, bucket)| bucket.is_drained())
+    }
+
     fn all_buckets_empty(&self) -> bool {
-        self.work_buckets.values().all(|bucket| bucket.is_empty())
+        self.work_buckets
+            .values()
+            .all(|bucket| bucket.is_empty() && !bucket.has_sentinel())
     }
 
-    /// Open buckets if their conditions are met
+    /// Open buckets if their conditions are met. Once all the work in the open buckets is done,
+    /// the sentinels of the open buckets are run first.
     fn update_buckets(&self) {
+        if self.worker_group().all_parked()
+            && self.work_buckets.values().all(|bucket| bucket.is_empty())
+        {
+            for (_, bucket) in self.work_buckets.iter() {
+                if bucket.is_activated() && bucket.schedule_sentinel() {
+                    return;
+                }
+            }
+        }
         let mut buckets_updated = false;
         for (id, bucket) in self.work_buckets.iter() {
             if id == WorkBucketStage::Unconstrained {
@@ -152,7 +198,11 @@ impl<C: Context> Scheduler<C> {
     fn process_coordinator_work(&self, mut work: Box<dyn CoordinatorWork<C>>) {
         let mut coordinator_worker = self.coordinator_worker.as_ref().unwrap().write().unwrap();
         let context = self.context.unwrap();
+        let start = coordinator_worker.trace.start_work();
         work.do_work_with_stat(&mut coordinator_worker, context);
+        coordinator_worker
+            .trace
+            .end_work(start, work.get_type_name(), None);
     }
 
     /// Drain the message queue and execute coordinator work
@@ -181,6 +231,7 @@ impl<C: Context> Scheduler<C> {
                 self.process_coordinator_work(work);
             }
         }
+        self.record_stage_durations();
         self.deactivate_all();
         // Finalization: Resume mutators, reset gc states
         // Note: Resume-mutators must happen after all work buckets are closed.
@@ -190,22 +241,54 @@ impl<C: Context> Scheduler<C> {
         if let Some(finalizer) = self.finalizer.lock().unwrap().take() {
             self.process_coordinator_work(finalizer);
         }
-        debug_assert!(!self.work_buckets[WorkBucketStage::Prepare].is_activated());
-        debug_assert!(!self.work_buckets[WorkBucketStage::Closure].is_activated());
-        debug_assert!(!self.work_buckets[WorkBucketStage::Release].is_activated());
-        debug_assert!(!self.work_buckets[WorkBucketStage::Final].is_activated());
+        debug_assert!(self
+            .work_buckets
+            .iter()
+            .all(|(id, bucket)| id == WorkBucketStage::Unconstrained || !bucket.is_activated()));
+        if let Some(file) = self.work_trace_file.lock().unwrap().as_ref() {
+            if let Err(e) = self.dump_work_trace(file) {
+                warn!("Failed to write the work packet trace: {}", e);
+            }
+        }
+    }
+
+    /// Record the time spent in each stage of this GC: from when its bucket was opened
+    /// until the next bucket was opened, or until now for the last one.
+    fn record_stage_durations(&self) {
+        let now = Instant::now();
+        let opened: Vec<_> = self
+            .work_buckets
+            .iter()
+            .filter(|(id, bucket)| *id != WorkBucketStage::Unconstrained && bucket.is_activated())
+            .filter_map(|(id, bucket)| bucket.activated_at().map(|t| (id, t)))
+            .collect();
+        *self.stage_durations.lock().unwrap() = stage_durations(opened, now);
+    }
+
+    /// The time spent in each work bucket stage in the last GC, in the order the stages were opened.
+    pub fn stage_durations(&self) -> Vec<(WorkBucketStage, Duration)> {
+        self.stage_durations.lock().unwrap().clone()
     }
 
     pub fn deactivate_all(&self) {
-        self.work_buckets[WorkBucketStage::Prepare].deactivate();
-        self.work_buckets[WorkBucketStage::Closure].deactivate();
-        self.work_buckets[WorkBucketStage::Release].deactivate();
-        self.work_buckets[WorkBucketStage::Final].deactivate();
+        for (id, bucket) in self.work_buckets.iter() {
+            if id != WorkBucketStage::Unconstrained {
+                bucket.deactivate();
+            }
+        }
     }
 
     pub fn reset_state(&self) {
         // self.work_buckets[WorkBucketStage::Prepare].deactivate();
+        self.work_buckets[WorkBucketStage::PinningRoots].deactivate();
         self.work_buckets[WorkBucketStage::Closure].deactivate();
+        self.work_buckets[WorkBucketStage::SoftRefClosure].deactivate();
+        self.work_buckets[WorkBucketStage::WeakRefClosure].deactivate();
+        self.work_buckets[WorkBucketStage::FinalRefClosure].deactivate();
+        self.work_buckets[WorkBucketStage::PhantomRefClosure].deactivate();
+        self.work_buckets[WorkBucketStage::CalculateForwarding].deactivate();
+        self.work_buckets[WorkBucketStage::RefForwarding].deactivate();
+        self.work_buckets[WorkBucketStage::Compact].deactivate();
         self.work_buckets[WorkBucketStage::Release].deactivate();
         self.work_buckets[WorkBucketStage::Final].deactivate();
     }
@@ -217,30 +300,55 @@ impl<C: Context> Scheduler<C> {
             .unwrap();
     }
 
+    /// Wake up a parked worker, if there is one, to steal the spare work of a busy worker.
+    #[inline]
+    pub fn notify_parked_worker(&self) {
+        let worker_group = self.worker_group.as_ref().unwrap();
+        if worker_group.workers.iter().any(|w| w.is_parked()) {
+            let _guard = self.worker_monitor.0.lock().unwrap();
+            self.worker_monitor.1.notify_one();
+        }
+    }
+
     #[inline]
-    fn pop_scheduable_work(&self, worker: &Worker<C>) -> Option<(Box<dyn Work<C>>, bool)> {
+    /// Pop a work packet, with the stage of the bucket it came from (`None` for the worker's local bucket),
+    /// and whether the bucket is empty now. The worker's own packets are taken first, then the packets
+    /// in the open buckets, and finally the packets in the local deques of the other workers.
+    #[allow(clippy::type_complexity)]
+    fn pop_scheduable_work(
+        &self,
+        worker: &Worker<C>,
+    ) -> Option<(Option<WorkBucketStage>, Box<dyn Work<C>>, bool)> {
+        if let Some((bucket, work)) = worker.pop_local_work() {
+            debug_assert!(self.work_buckets[bucket].is_activated());
+            return Some((Some(bucket), work, false));
+        }
         if let Some(work) = worker.local_work_bucket.poll() {
-            return Some((work, worker.local_work_bucket.is_empty()));
+            return Some((None, work, worker.local_work_bucket.is_empty()));
         }
-        for work_bucket in self.work_buckets.values() {
+        for (id, work_bucket) in self.work_buckets.iter() {
             if let Some(work) = work_bucket.poll() {
-                return Some((work, work_bucket.is_empty()));
+                return Some((Some(id), work, work_bucket.is_empty()));
             }
         }
+        if let Some((bucket, work)) = self.worker_group.as_ref().unwrap().steal(worker) {
+            debug_assert!(self.work_buckets[bucket].is_activated());
+            return Some((Some(bucket), work, false));
+        }
         None
     }
 
-    /// Get a scheduable work. Called by workers
+    /// Get a scheduable work, with the stage of the bucket it came from. Called by workers
     #[inline]
-    pub fn poll(&self, worker: &Worker<C>) -> Box<dyn Work<C>> {
-        let work = if let Some((work, bucket_is_empty)) = self.pop_scheduable_work(worker) {
+    pub fn poll(&self, worker: &Worker<C>) -> (Option<WorkBucketStage>, Box<dyn Work<C>>) {
+        let work = if let Some((bucket, work, bucket_is_empty)) = self.pop_scheduable_work(worker) {
             if bucket_is_empty {
                 worker
                     .sender
                     .send(CoordinatorMessage::BucketDrained)
                     .unwrap();
             }
-            work
+            (bucket, work)
         } else {
             self.poll_slow(worker)
         };
@@ -248,19 +356,19 @@ impl<C: Context> Scheduler<C> {
     }
 
     #[cold]
-    fn poll_slow(&self, worker: &Worker<C>) -> Box<dyn Work<C>> {
+    fn poll_slow(&self, worker: &Worker<C>) -> (Option<WorkBucketStage>, Box<dyn Work<C>>) {
         debug_assert!(!worker.is_parked());
         let mut guard = self.worker_monitor.0.lock().unwrap();
         loop {
             debug_assert!(!worker.is_parked());
-            if let Some((work, bucket_is_empty)) = self.pop_scheduable_work(worker) {
+            if let Some((bucket, work, bucket_is_empty)) = self.pop_scheduable_work(worker) {
                 if bucket_is_empty {
                     worker
                         .sender
                         .send(CoordinatorMessage::BucketDrained)
                         .unwrap();
                 }
-                return work;
+                return (bucket, work);
             }
             // Park this worker
             worker.parked.store(true, Ordering::SeqCst);
@@ -285,7 +393,38 @@ impl<C: Context> Scheduler<C> {
         coordinator_worker.stat.enable();
     }
 
-    pub fn statistics(&self) -> HashMap<String, String> {
+    /// Record the work packets run by each worker in a ring buffer of `capacity` events, and write them
+    /// to `<file>.<n>.json` in the Chrome Trace Event format at the end of the n-th GC.
+    pub fn enable_work_trace(&self, capacity: usize, file: &str) {
+        *self.work_trace_file.lock().unwrap() = Some(file.to_owned());
+        for worker in &self.worker_group().workers {
+            worker.trace.enable(capacity);
+        }
+        let coordinator_worker = self.coordinator_worker.as_ref().unwrap().read().unwrap();
+        coordinator_worker.trace.enable(capacity);
+    }
+
+    /// Write the work packets run since the last GC. This is called when all the workers are parked.
+    fn dump_work_trace(&self, file: &str) -> std::io::Result<()> {
+        let mut threads = vec![];
+        let mut dropped = 0;
+        {
+            let coordinator_worker = self.coordinator_worker.as_ref().unwrap().read().unwrap();
+            let (events, n) = coordinator_worker.trace.take();
+            threads.push(("Coordinator".to_owned(), events));
+            dropped += n;
+        }
+        for worker in &self.worker_group().workers {
+            let (events, n) = worker.trace.take();
+            threads.push((format!("Worker {}", worker.ordinal), events));
+            dropped += n;
+        }
+        let gc = self.traced_gcs.fetch_add(1, Ordering::SeqCst);
+        let mut out = BufWriter::new(File::create(format!("{}.{}.json", file, gc))?);
+        write_chrome_trace(&mut out, self.trace_epoch, &threads, dropped)
+    }
+
+    pub fn statistics(&self) -> BTreeMap<String, String> {
         let mut summary = SchedulerStat::default();
         for worker in &self.worker_group().workers {
             summary.merge(&worker.stat);
@@ -307,3 +446,109 @@ impl<VM: VMBinding> MMTkScheduler<VM> {
         self.worker_monitor.1.notify_all();
     }
 }
+
+/// The time spent in each stage, given when each stage was opened: from when it was opened until
+/// the next stage was opened, or until `now` for the last one. The stages are sorted by the time they were opened.
+fn stage_durations(
+    mut opened: Vec<(WorkBucketStage, Instant)>,
+    now: Instant,
+) -> Vec<(WorkBucketStage, Duration)> {
+    opened.sort_by_key(|(_, t)| *t);
+    opened
+        .iter()
+        .enumerate()
+        .map(|(i, (id, t))| {
+            let end = opened.get(i + 1).map_or(now, |(_, next)| *next);
+            (*id, end.duration_since(*t))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_durations() {
+        let start = Instant::now();
+        let at = |us| start + Duration::from_micros(us);
+        let durations = stage_durations(
+            vec![
+                (WorkBucketStage::Closure, at(10)),
+                (WorkBucketStage::Prepare, at(0)),
+                (WorkBucketStage::Release, at(25)),
+            ],
+            at(30),
+        );
+        assert_eq!(
+            durations,
+            vec![
+                (WorkBucketStage::Prepare, Duration::from_micros(10)),
+                (WorkBucketStage::Closure, Duration::from_micros(15)),
+                (WorkBucketStage::Release, Duration::from_micros(5)),
+            ]
+        );
+        assert!(stage_durations(vec![], at(30)).is_empty());
+    }
+
+    struct Nop;
+
+    impl Work<()> for Nop {
+        fn do_work(&mut self, _worker: &mut Worker<()>, _context: &'static ()) {}
+    }
+
+    /// A scheduler with one parked worker, whose buckets are opened by hand
+    fn scheduler() -> Arc<Scheduler<()>> {
+        let mut scheduler = Scheduler::<()>::new();
+        let group = WorkerGroup::new(1, Arc::downgrade(&scheduler));
+        unsafe { Arc::get_mut_unchecked(&mut scheduler) }.worker_group = Some(group);
+        scheduler
+    }
+
+    #[test]
+    fn test_schedule_sentinels_once_open_buckets_are_empty() {
+        let scheduler = scheduler();
+        let closure = &scheduler.work_buckets[WorkBucketStage::Closure];
+        let final_ref_closure = &scheduler.work_buckets[WorkBucketStage::FinalRefClosure];
+        closure.activate();
+        closure.set_sentinel(Nop);
+        final_ref_closure.set_sentinel(Nop);
+        closure.add(Nop);
+
+        // The sentinel waits for the other work of the bucket, and keeps the later buckets closed
+        scheduler.update_buckets();
+        assert!(closure.has_sentinel());
+        assert!(closure.poll().is_some());
+        assert!(!closure.is_drained());
+        scheduler.update_buckets();
+        assert!(!closure.has_sentinel());
+        assert!(closure.poll().is_some());
+        assert!(closure.is_drained());
+
+        // The sentinel of a bucket only runs once the bucket is open
+        assert!(final_ref_closure.has_sentinel());
+        final_ref_closure.activate();
+        scheduler.update_buckets();
+        assert!(final_ref_closure.poll().is_some());
+        assert!(final_ref_closure.is_drained());
+        assert!(scheduler.all_buckets_empty());
+    }
+
+    #[test]
+    fn test_sentinel_runs_again_until_fixpoint() {
+        let scheduler = scheduler();
+        let closure = &scheduler.work_buckets[WorkBucketStage::Closure];
+        closure.activate();
+        closure.set_sentinel(Nop);
+        for _ in 0..3 {
+            scheduler.update_buckets();
+            assert!(closure.poll().is_some());
+            // A sentinel that found more work sets itself again
+            closure.set_sentinel(Nop);
+            assert!(!closure.is_drained());
+        }
+        scheduler.update_buckets();
+        assert!(closure.poll().is_some());
+        assert!(closure.is_drained());
+    }
+}
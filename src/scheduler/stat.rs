@@ -1,8 +1,19 @@
 use std::any::TypeId;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{Duration, SystemTime};
 
+/// Extract the work-packet name from the full type name.
+/// i.e. simplifies `crate::scheduler::gc_work::SomeWorkPacket<Semispace>` to `SomeWorkPacket`.
+pub fn work_name(name: &str) -> String {
+    let end_index = name.find('<').unwrap_or_else(|| name.len());
+    let name = name[..end_index].to_owned();
+    match name.rfind(':') {
+        Some(start_index) => name[(start_index + 1)..end_index].to_owned(),
+        _ => name,
+    }
+}
+
 #[derive(Default)]
 pub struct SchedulerStat {
     work_id_name_map: HashMap<TypeId, &'static str>,
@@ -11,15 +22,8 @@ pub struct SchedulerStat {
 }
 
 impl SchedulerStat {
-    /// Extract the work-packet name from the full type name.
-    /// i.e. simplifies `crate::scheduler::gc_work::SomeWorkPacket<Semispace>` to `SomeWorkPacket`.
     fn work_name(&self, name: &str) -> String {
-        let end_index = name.find('<').unwrap_or_else(|| name.len());
-        let name = name[..end_index].to_owned();
-        match name.rfind(':') {
-            Some(start_index) => name[(start_index + 1)..end_index].to_owned(),
-            _ => name,
-        }
+        work_name(name)
     }
 
     fn geomean(&self, values: &[f64]) -> f64 {
@@ -47,8 +51,9 @@ impl SchedulerStat {
         max
     }
 
-    pub fn harness_stat(&self) -> HashMap<String, String> {
-        let mut stat = HashMap::new();
+    /// The statistics of the work packets, sorted by name.
+    pub fn harness_stat(&self) -> BTreeMap<String, String> {
+        let mut stat = BTreeMap::new();
         // Work counts
         let mut total_count = 0;
         for (t, c) in &self.work_counts {
@@ -0,0 +1,177 @@
+//! A timeline of the work packets run by each worker, which can be dumped in the
+//! [Chrome Trace Event format](https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU)
+//! and opened in `chrome://tracing` or [Perfetto](https://ui.perfetto.dev).
+//! It is enabled by the `work_trace_file` option.
+
+use super::stat::work_name;
+use super::work_bucket::WorkBucketStage;
+use crate::util::statistics::sink::json_string;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// A work packet run by a worker.
+#[derive(Clone, Debug)]
+pub struct TraceEvent {
+    /// The type name of the work packet.
+    pub name: &'static str,
+    /// The bucket the work packet was taken from, or `None` for coordinator work and
+    /// work from the worker's local bucket.
+    pub bucket: Option<WorkBucketStage>,
+    pub start: Instant,
+    pub end: Instant,
+}
+
+/// A ring buffer of the work packets run by a worker. When the buffer is full, the oldest events are dropped.
+#[derive(Default)]
+pub struct WorkTrace {
+    /// The size of the buffer. Tracing is disabled if this is 0.
+    capacity: AtomicUsize,
+    events: Mutex<VecDeque<TraceEvent>>,
+    dropped: AtomicUsize,
+}
+
+impl WorkTrace {
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.capacity.load(Ordering::Relaxed) != 0
+    }
+
+    pub fn enable(&self, capacity: usize) {
+        self.capacity.store(capacity, Ordering::SeqCst);
+    }
+
+    /// Call before running a work packet. Returns the start time if tracing is enabled.
+    #[inline(always)]
+    pub fn start_work(&self) -> Option<Instant> {
+        if self.is_enabled() {
+            Some(Instant::now())
+        } else {
+            None
+        }
+    }
+
+    /// Call after running a work packet, with the time returned by `start_work()`.
+    #[inline(always)]
+    pub fn end_work(
+        &self,
+        start: Option<Instant>,
+        name: &'static str,
+        bucket: Option<WorkBucketStage>,
+    ) {
+        if let Some(start) = start {
+            self.record(TraceEvent {
+                name,
+                bucket,
+                start,
+                end: Instant::now(),
+            });
+        }
+    }
+
+    fn record(&self, event: TraceEvent) {
+        let capacity = self.capacity.load(Ordering::Relaxed);
+        let mut events = self.events.lock().unwrap();
+        while events.len() >= capacity {
+            events.pop_front();
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        events.push_back(event);
+    }
+
+    /// Take the recorded events, and the number of events dropped since the last call.
+    pub fn take(&self) -> (Vec<TraceEvent>, usize) {
+        let events = self.events.lock().unwrap().drain(..).collect();
+        (events, self.dropped.swap(0, Ordering::Relaxed))
+    }
+}
+
+/// Write the events of each thread in the Chrome Trace Event JSON format. `threads` are the names
+/// and the events of the threads, and the timestamps are in microseconds since `epoch`.
+pub fn write_chrome_trace(
+    out: &mut impl Write,
+    epoch: Instant,
+    threads: &[(String, Vec<TraceEvent>)],
+    dropped: usize,
+) -> io::Result<()> {
+    let micros = |t: Instant| t.saturating_duration_since(epoch).as_nanos() as f64 / 1000.0;
+    let mut records = vec![];
+    for (tid, (thread, events)) in threads.iter().enumerate() {
+        records.push(format!(
+            "{{\"name\": \"thread_name\", \"ph\": \"M\", \"pid\": 0, \"tid\": {}, \"args\": {{\"name\": {}}}}}",
+            tid,
+            json_string(thread)
+        ));
+        for event in events {
+            let category = match event.bucket {
+                Some(bucket) => format!("{:?}", bucket),
+                None => "Local".to_string(),
+            };
+            records.push(format!(
+                "{{\"name\": {}, \"cat\": {}, \"ph\": \"X\", \"ts\": {:.3}, \"dur\": {:.3}, \"pid\": 0, \"tid\": {}, \"args\": {{\"type\": {}}}}}",
+                json_string(&work_name(event.name)),
+                json_string(&category),
+                micros(event.start),
+                micros(event.end) - micros(event.start),
+                tid,
+                json_string(event.name)
+            ));
+        }
+    }
+    writeln!(out, "{{\"traceEvents\": [")?;
+    write!(out, "{}", records.join(",\n"))?;
+    writeln!(out)?;
+    writeln!(
+        out,
+        "], \"displayTimeUnit\": \"ms\", \"otherData\": {{\"dropped_events\": {}}}}}",
+        dropped
+    )?;
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_ring_buffer_drops_oldest() {
+        let trace = WorkTrace::default();
+        assert!(trace.start_work().is_none());
+        trace.enable(2);
+        for name in &["a", "b", "c"] {
+            let start = trace.start_work();
+            trace.end_work(start, *name, None);
+        }
+        let (events, dropped) = trace.take();
+        assert_eq!(dropped, 1);
+        assert_eq!(
+            events.iter().map(|e| e.name).collect::<Vec<_>>(),
+            vec!["b", "c"]
+        );
+        assert!(trace.take().0.is_empty());
+    }
+
+    #[test]
+    fn test_chrome_trace() {
+        let epoch = Instant::now();
+        let event = TraceEvent {
+            name: "mmtk::scheduler::gc_work::Prepare<Foo>",
+            bucket: Some(WorkBucketStage::Prepare),
+            start: epoch + Duration::from_micros(10),
+            end: epoch + Duration::from_micros(15),
+        };
+        let mut out = vec![];
+        write_chrome_trace(&mut out, epoch, &[("Worker 0".to_string(), vec![event])], 0).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"traceEvents\": [\n\
+             {\"name\": \"thread_name\", \"ph\": \"M\", \"pid\": 0, \"tid\": 0, \"args\": {\"name\": \"Worker 0\"}},\n\
+             {\"name\": \"Prepare\", \"cat\": \"Prepare\", \"ph\": \"X\", \"ts\": 10.000, \"dur\": 5.000, \"pid\": 0, \"tid\": 0, \
+             \"args\": {\"type\": \"mmtk::scheduler::gc_work::Prepare<Foo>\"}}\n\
+             ], \"displayTimeUnit\": \"ms\", \"otherData\": {\"dropped_events\": 0}}\n"
+        );
+    }
+}
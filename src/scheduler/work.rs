@@ -14,6 +14,10 @@ pub trait Work<C: Context>: 'static + Send + Sync {
         self.do_work(worker, context);
         stat.end_of_work(&mut worker.stat);
     }
+    /// The type name of the work packet.
+    fn get_type_name(&self) -> &'static str {
+        type_name::<Self>()
+    }
 }
 
 /// A special kind of work that will execute on the coorddinator (i.e. controller) thread
@@ -1,65 +1,23 @@
 use super::work::Work;
 use super::*;
+use crossbeam_deque::{Injector, Steal};
 use enum_map::Enum;
-use spin::RwLock;
-use std::cmp;
-use std::collections::BinaryHeap;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Condvar, Mutex};
-
-/// A unique work-packet id for each instance of work-packet
-#[derive(Eq, PartialEq, Clone, Copy)]
-struct WorkUID(u64);
-
-impl WorkUID {
-    pub fn new() -> Self {
-        static WORK_UID: AtomicU64 = AtomicU64::new(0);
-        Self(WORK_UID.fetch_add(1, Ordering::Relaxed))
-    }
-}
-
-struct PrioritizedWork<C: Context> {
-    priority: usize,
-    work_uid: WorkUID,
-    work: Box<dyn Work<C>>,
-}
-
-impl<C: Context> PrioritizedWork<C> {
-    pub fn new(priority: usize, work: Box<dyn Work<C>>) -> Self {
-        Self {
-            priority,
-            work,
-            work_uid: WorkUID::new(),
-        }
-    }
-}
-
-impl<C: Context> PartialEq for PrioritizedWork<C> {
-    fn eq(&self, other: &Self) -> bool {
-        self.priority == other.priority && self.work_uid == other.work_uid
-    }
-}
-
-impl<C: Context> Eq for PrioritizedWork<C> {}
-
-impl<C: Context> Ord for PrioritizedWork<C> {
-    fn cmp(&self, other: &Self) -> cmp::Ordering {
-        self.priority.cmp(&other.priority)
-    }
-}
-
-impl<C: Context> PartialOrd for PrioritizedWork<C> {
-    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
-        Some(self.cmp(other))
-    }
-}
+use std::time::Instant;
 
 pub struct WorkBucket<C: Context> {
     active: AtomicBool,
-    /// A priority queue
-    queue: RwLock<BinaryHeap<PrioritizedWork<C>>>,
+    /// When the bucket was last activated
+    activated_at: Mutex<Option<Instant>>,
+    /// The packets of this bucket that are not yet taken by a worker. Each worker also keeps the packets
+    /// it generates for an open bucket in its local deque, where idle workers can steal them.
+    queue: Injector<Box<dyn Work<C>>>,
     monitor: Arc<(Mutex<()>, Condvar)>,
     can_open: Option<Box<dyn Fn() -> bool>>,
+    /// A packet that is run once all the work in the open buckets is done, before the later buckets are opened.
+    /// It can set a new sentinel to run again (e.g. to iterate to a fixpoint).
+    sentinel: Mutex<Option<Box<dyn Work<C>>>>,
 }
 
 unsafe impl<C: Context> Send for WorkBucket<C> {}
@@ -69,9 +27,11 @@ impl<C: Context> WorkBucket<C> {
     pub fn new(active: bool, monitor: Arc<(Mutex<()>, Condvar)>) -> Self {
         Self {
             active: AtomicBool::new(active),
-            queue: Default::default(),
+            activated_at: Mutex::new(None),
+            queue: Injector::new(),
             monitor,
             can_open: None,
+            sentinel: Mutex::new(None),
         }
     }
     fn notify_one_worker(&self) {
@@ -87,49 +47,71 @@ impl<C: Context> WorkBucket<C> {
     }
     /// Enable the bucket
     pub fn activate(&self) {
-        self.active.store(true, Ordering::SeqCst);
+        if !self.active.swap(true, Ordering::SeqCst) {
+            *self.activated_at.lock().unwrap() = Some(Instant::now());
+        }
     }
-    /// Test if the bucket is drained
+    /// The time the bucket was last activated
+    pub fn activated_at(&self) -> Option<Instant> {
+        *self.activated_at.lock().unwrap()
+    }
+    /// Test if the bucket is drained. The packets in the local deques of the workers are not counted,
+    /// so this only means the stage is done if all the workers are parked as well.
     pub fn is_empty(&self) -> bool {
-        self.queue.read().len() == 0
+        self.queue.is_empty()
     }
     pub fn is_drained(&self) -> bool {
-        self.is_activated() && self.is_empty()
+        self.is_activated() && self.is_empty() && !self.has_sentinel()
     }
     /// Disable the bucket
     pub fn deactivate(&self) {
-        debug_assert!(
-            self.queue.read().is_empty(),
-            "Bucket not drained before close"
-        );
+        debug_assert!(self.queue.is_empty(), "Bucket not drained before close");
         self.active.store(false, Ordering::SeqCst);
     }
-    /// Add a work packet to this bucket, with a given priority
-    pub fn add_with_priority(&self, priority: usize, work: Box<dyn Work<C>>) {
-        self.queue
-            .write()
-            .push(PrioritizedWork::new(priority, work));
+    /// Add a work packet to this bucket
+    pub fn add_boxed(&self, work: Box<dyn Work<C>>) {
+        self.queue.push(work);
         self.notify_one_worker(); // FIXME: Performance
     }
-    /// Add a work packet to this bucket, with a default priority (1000)
+    /// Add a work packet to this bucket
     pub fn add<W: Work<C>>(&self, work: W) {
-        self.add_with_priority(1000, box work);
+        self.add_boxed(box work);
     }
-    pub fn bulk_add(&self, priority: usize, work_vec: Vec<Box<dyn Work<C>>>) {
-        {
-            let mut queue = self.queue.write();
-            for w in work_vec {
-                queue.push(PrioritizedWork::new(priority, w));
-            }
+    pub fn bulk_add(&self, work_vec: Vec<Box<dyn Work<C>>>) {
+        for w in work_vec {
+            self.queue.push(w);
         }
         self.notify_all_workers(); // FIXME: Performance
     }
-    /// Get a work packet (with the greatest priority) from this bucket
+    /// Get a work packet from this bucket
     pub fn poll(&self) -> Option<Box<dyn Work<C>>> {
         if !self.active.load(Ordering::SeqCst) {
             return None;
         }
-        self.queue.write().pop().map(|v| v.work)
+        loop {
+            match self.queue.steal() {
+                Steal::Success(work) => return Some(work),
+                Steal::Empty => return None,
+                Steal::Retry => continue,
+            }
+        }
+    }
+    /// Set the sentinel of this bucket, which replaces the existing one
+    pub fn set_sentinel<W: Work<C>>(&self, work: W) {
+        *self.sentinel.lock().unwrap() = Some(box work);
+    }
+    pub fn has_sentinel(&self) -> bool {
+        self.sentinel.lock().unwrap().is_some()
+    }
+    /// Add the sentinel to the bucket, if it has one. Returns true if the sentinel is added.
+    pub fn schedule_sentinel(&self) -> bool {
+        match self.sentinel.lock().unwrap().take() {
+            Some(work) => {
+                self.add_boxed(work);
+                true
+            }
+            None => false,
+        }
     }
     pub fn set_open_condition(&mut self, pred: impl Fn() -> bool + 'static) {
         self.can_open = Some(box pred);
@@ -149,7 +131,23 @@ impl<C: Context> WorkBucket<C> {
 pub enum WorkBucketStage {
     Unconstrained,
     Prepare,
+    /// Keep the objects referenced by ambiguous roots in place, before the transitive closure may move them.
+    PinningRoots,
     Closure,
+    /// Retain the referents of the reachable soft references, and compute their closure.
+    SoftRefClosure,
+    /// Clear the unreachable referents of the soft and weak references.
+    WeakRefClosure,
+    /// Resurrect the unreachable finalizable objects, and compute their closure.
+    FinalRefClosure,
+    /// Clear the unreachable referents of the phantom references.
+    PhantomRefClosure,
+    /// Compute the forwarding addresses of the live objects (used by compacting plans).
+    CalculateForwarding,
+    /// Update the references to forwarded objects (used by compacting plans).
+    RefForwarding,
+    /// Move the objects to their forwarding addresses (used by compacting plans).
+    Compact,
     Release,
     Final,
 }
@@ -1,8 +1,10 @@
 use super::stat::WorkerLocalStat;
+use super::trace::WorkTrace;
 use super::work_bucket::*;
 use super::*;
 use crate::mmtk::MMTK;
 use crate::util::OpaquePointer;
+use crossbeam_deque::{Steal, Stealer};
 use std::ffi::c_void;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::Sender;
@@ -42,7 +44,8 @@ impl WorkerLocalPtr {
     }
 }
 
-const LOCALLY_CACHED_WORKS: usize = 1;
+/// A work packet in the local deque of a worker, with the stage of the bucket it belongs to
+pub type LocalWork<C> = (WorkBucketStage, Box<dyn Work<C>>);
 
 pub struct Worker<C: Context> {
     pub tls: OpaquePointer,
@@ -51,11 +54,15 @@ pub struct Worker<C: Context> {
     scheduler: Arc<Scheduler<C>>,
     local: WorkerLocalPtr,
     pub local_work_bucket: WorkBucket<C>,
+    /// The packets generated by this worker for the open buckets. The worker pops the most recent packet first,
+    /// and the other workers steal the oldest ones when they are idle.
+    local_work: crossbeam_deque::Worker<LocalWork<C>>,
     pub sender: Sender<CoordinatorMessage<C>>,
     pub stat: WorkerLocalStat,
+    /// The work packets run by this worker, if `work_trace_file` is set
+    pub trace: WorkTrace,
     context: Option<&'static C>,
     is_coordinator: bool,
-    local_work_buffer: Vec<(WorkBucketStage, Box<dyn Work<C>>)>,
 }
 
 unsafe impl<C: Context> Sync for Worker<C> {}
@@ -72,34 +79,37 @@ impl<C: Context> Worker<C> {
             parked: AtomicBool::new(true),
             local: WorkerLocalPtr::UNINITIALIZED,
             local_work_bucket: WorkBucket::new(true, scheduler.worker_monitor.clone()),
+            local_work: crossbeam_deque::Worker::new_lifo(),
             sender: scheduler.channel.0.clone(),
             scheduler,
             stat: Default::default(),
+            trace: Default::default(),
             context: None,
             is_coordinator,
-            local_work_buffer: Vec::with_capacity(LOCALLY_CACHED_WORKS),
         }
     }
 
+    /// Add a work packet to the bucket `bucket`. If the bucket is open, the packet is pushed to the local deque
+    /// of this worker, so it is likely to be run by this worker while its data is still in the cache.
     #[inline]
     pub fn add_work(&mut self, bucket: WorkBucketStage, work: impl Work<C>) {
-        if !self.scheduler().work_buckets[bucket].is_activated() {
-            self.scheduler.work_buckets[bucket].add_with_priority(1000, box work);
+        // The coordinator does not poll a local deque, and nobody steals from it.
+        if self.is_coordinator || !self.scheduler().work_buckets[bucket].is_activated() {
+            self.scheduler.work_buckets[bucket].add(work);
             return;
         }
-        self.local_work_buffer.push((bucket, box work));
-        if self.local_work_buffer.len() > LOCALLY_CACHED_WORKS {
-            self.flush();
+        // This worker runs its latest packet next, so only wake up a peer if there are more.
+        let has_spare_work = !self.local_work.is_empty();
+        self.local_work.push((bucket, box work));
+        if has_spare_work {
+            self.scheduler.notify_parked_worker();
         }
     }
 
-    #[cold]
-    fn flush(&mut self) {
-        let mut buffer = Vec::with_capacity(LOCALLY_CACHED_WORKS);
-        std::mem::swap(&mut buffer, &mut self.local_work_buffer);
-        for (bucket, work) in buffer {
-            self.scheduler.work_buckets[bucket].add_with_priority(1000, work);
-        }
+    /// Pop a packet from the local deque of this worker.
+    #[inline]
+    pub(crate) fn pop_local_work(&self) -> Option<LocalWork<C>> {
+        self.local_work.pop()
     }
 
     pub fn is_parked(&self) -> bool {
@@ -137,28 +147,45 @@ impl<C: Context> Worker<C> {
         self.context = Some(context);
         self.parked.store(false, Ordering::SeqCst);
         loop {
-            while let Some((bucket, mut work)) = self.local_work_buffer.pop() {
-                debug_assert!(self.scheduler.work_buckets[bucket].is_activated());
-                work.do_work_with_stat(self, context);
-            }
-            let mut work = self.scheduler().poll(self);
+            let (bucket, mut work) = self.scheduler().poll(self);
             debug_assert!(!self.is_parked());
+            let start = self.trace.start_work();
             work.do_work_with_stat(self, context);
+            self.trace.end_work(start, work.get_type_name(), bucket);
         }
     }
 }
 
 pub struct WorkerGroup<C: Context> {
     pub workers: Vec<Worker<C>>,
+    /// The stealers of the local deques of the workers, indexed by the ordinals of the workers
+    stealers: Vec<Stealer<LocalWork<C>>>,
 }
 
 impl<C: Context> WorkerGroup<C> {
     pub fn new(workers: usize, scheduler: Weak<Scheduler<C>>) -> Arc<Self> {
-        Arc::new(Self {
-            workers: (0..workers)
-                .map(|i| Worker::new(i, scheduler.clone(), false))
-                .collect(),
-        })
+        let workers: Vec<Worker<C>> = (0..workers)
+            .map(|i| Worker::new(i, scheduler.clone(), false))
+            .collect();
+        let stealers = workers.iter().map(|w| w.local_work.stealer()).collect();
+        Arc::new(Self { workers, stealers })
+    }
+
+    /// Steal a packet from the local deque of another worker. The victims are tried in a round-robin order,
+    /// starting from the worker after `thief`, so the idle workers do not all go for the same deque.
+    pub(crate) fn steal(&self, thief: &Worker<C>) -> Option<LocalWork<C>> {
+        let n = self.stealers.len();
+        for i in 1..n {
+            let stealer = &self.stealers[(thief.ordinal + i) % n];
+            loop {
+                match stealer.steal() {
+                    Steal::Success(work) => return Some(work),
+                    Steal::Empty => break,
+                    Steal::Retry => continue,
+                }
+            }
+        }
+        None
     }
 
     pub fn worker_count(&self) -> usize {
@@ -180,3 +207,54 @@ impl<C: Context> WorkerGroup<C> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Nop;
+
+    impl Work<()> for Nop {
+        fn do_work(&mut self, _worker: &mut Worker<()>, _context: &'static ()) {}
+    }
+
+    fn push(worker: &Worker<()>, bucket: WorkBucketStage) {
+        worker.local_work.push((bucket, box Nop));
+    }
+
+    #[test]
+    fn test_steal_oldest_work() {
+        let scheduler = Scheduler::<()>::new();
+        let group = WorkerGroup::new(3, Arc::downgrade(&scheduler));
+        let (owner, first_thief, second_thief) =
+            (&group.workers[0], &group.workers[1], &group.workers[2]);
+        push(owner, WorkBucketStage::Prepare);
+        push(owner, WorkBucketStage::Closure);
+        push(owner, WorkBucketStage::Release);
+
+        // The owner runs its latest packet, and the thieves take the oldest ones
+        assert_eq!(owner.pop_local_work().unwrap().0, WorkBucketStage::Release);
+        assert_eq!(
+            group.steal(first_thief).unwrap().0,
+            WorkBucketStage::Prepare
+        );
+        assert_eq!(
+            group.steal(second_thief).unwrap().0,
+            WorkBucketStage::Closure
+        );
+        assert!(group.steal(first_thief).is_none());
+        assert!(owner.pop_local_work().is_none());
+    }
+
+    #[test]
+    fn test_do_not_steal_from_self() {
+        let scheduler = Scheduler::<()>::new();
+        let group = WorkerGroup::new(2, Arc::downgrade(&scheduler));
+        push(&group.workers[1], WorkBucketStage::Closure);
+        assert!(group.steal(&group.workers[1]).is_none());
+        assert_eq!(
+            group.steal(&group.workers[0]).unwrap().0,
+            WorkBucketStage::Closure
+        );
+    }
+}
@@ -131,8 +131,9 @@ pub trait Allocator<VM: VMBinding>: Downcast {
             // Try to allocate using the slow path
             let result = self.alloc_slow_once(size, align, offset);
 
+            // A GC thread cannot wait for a GC. It gets zero if the space is out of memory,
+            // and has to deal with that itself (see `ImmixSpace::trace_object()`).
             if !unsafe { VM::VMActivePlan::is_mutator(tls) } {
-                debug_assert!(!result.is_zero());
                 return result;
             }
 
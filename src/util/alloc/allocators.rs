@@ -1,14 +1,22 @@
 use std::mem::MaybeUninit;
 
 use crate::plan::Plan;
+use crate::policy::immix::ImmixSpace;
 use crate::policy::largeobjectspace::LargeObjectSpace;
+use crate::policy::mallocspace::MallocSpace;
 use crate::policy::space::Space;
-use crate::util::alloc::{Allocator, BumpAllocator, LargeObjectAllocator};
+use crate::util::alloc::{
+    Allocator, BumpAllocator, ImmixAllocator, LargeObjectAllocator, MallocAllocator,
+    MarkCompactAllocator,
+};
 use crate::util::OpaquePointer;
 use crate::vm::VMBinding;
 
 const MAX_BUMP_ALLOCATORS: usize = 5;
 const MAX_LARGE_OBJECT_ALLOCATORS: usize = 1;
+const MAX_MALLOC_ALLOCATORS: usize = 1;
+const MAX_IMMIX_ALLOCATORS: usize = 2;
+const MAX_MARK_COMPACT_ALLOCATORS: usize = 1;
 
 // The allocators set owned by each mutator. We provide a fixed number of allocators for each allocator type in the mutator,
 // and each plan will select part of the allocators to use.
@@ -18,6 +26,9 @@ const MAX_LARGE_OBJECT_ALLOCATORS: usize = 1;
 pub struct Allocators<VM: VMBinding> {
     pub bump_pointer: [MaybeUninit<BumpAllocator<VM>>; MAX_BUMP_ALLOCATORS],
     pub large_object: [MaybeUninit<LargeObjectAllocator<VM>>; MAX_LARGE_OBJECT_ALLOCATORS],
+    pub malloc: [MaybeUninit<MallocAllocator<VM>>; MAX_MALLOC_ALLOCATORS],
+    pub immix: [MaybeUninit<ImmixAllocator<VM>>; MAX_IMMIX_ALLOCATORS],
+    pub markcompact: [MaybeUninit<MarkCompactAllocator<VM>>; MAX_MARK_COMPACT_ALLOCATORS],
 }
 
 impl<VM: VMBinding> Allocators<VM> {
@@ -31,6 +42,11 @@ impl<VM: VMBinding> Allocators<VM> {
             AllocatorSelector::LargeObject(index) => {
                 self.large_object[index as usize].assume_init_ref()
             }
+            AllocatorSelector::Malloc(index) => self.malloc[index as usize].assume_init_ref(),
+            AllocatorSelector::Immix(index) => self.immix[index as usize].assume_init_ref(),
+            AllocatorSelector::MarkCompact(index) => {
+                self.markcompact[index as usize].assume_init_ref()
+            }
         }
     }
 
@@ -47,6 +63,11 @@ impl<VM: VMBinding> Allocators<VM> {
             AllocatorSelector::LargeObject(index) => {
                 self.large_object[index as usize].assume_init_mut()
             }
+            AllocatorSelector::Malloc(index) => self.malloc[index as usize].assume_init_mut(),
+            AllocatorSelector::Immix(index) => self.immix[index as usize].assume_init_mut(),
+            AllocatorSelector::MarkCompact(index) => {
+                self.markcompact[index as usize].assume_init_mut()
+            }
         }
     }
 
@@ -58,6 +79,9 @@ impl<VM: VMBinding> Allocators<VM> {
         let mut ret = Allocators {
             bump_pointer: unsafe { MaybeUninit::uninit().assume_init() },
             large_object: unsafe { MaybeUninit::uninit().assume_init() },
+            malloc: unsafe { MaybeUninit::uninit().assume_init() },
+            immix: unsafe { MaybeUninit::uninit().assume_init() },
+            markcompact: unsafe { MaybeUninit::uninit().assume_init() },
         };
 
         for &(selector, space) in space_mapping.iter() {
@@ -76,6 +100,28 @@ impl<VM: VMBinding> Allocators<VM> {
                         plan,
                     ));
                 }
+                AllocatorSelector::Malloc(index) => {
+                    ret.malloc[index as usize].write(MallocAllocator::new(
+                        mutator_tls,
+                        Some(space.downcast_ref::<MallocSpace<VM>>().unwrap()),
+                        plan,
+                    ));
+                }
+                AllocatorSelector::Immix(index) => {
+                    ret.immix[index as usize].write(ImmixAllocator::new(
+                        mutator_tls,
+                        Some(space.downcast_ref::<ImmixSpace<VM>>().unwrap()),
+                        plan,
+                        false,
+                    ));
+                }
+                AllocatorSelector::MarkCompact(index) => {
+                    ret.markcompact[index as usize].write(MarkCompactAllocator::new(
+                        mutator_tls,
+                        Some(space),
+                        plan,
+                    ));
+                }
             }
         }
 
@@ -94,10 +140,16 @@ impl<VM: VMBinding> Allocators<VM> {
 // enum AllocatorSelectorTag {
 //   BumpPointer,
 //   LargeObject,
+//   Malloc,
+//   Immix,
+//   MarkCompact,
 // }
 #[repr(C, u8)]
 #[derive(Copy, Clone, Debug)]
 pub enum AllocatorSelector {
     BumpPointer(u8),
     LargeObject(u8),
+    Malloc(u8),
+    Immix(u8),
+    MarkCompact(u8),
 }
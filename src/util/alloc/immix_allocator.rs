@@ -0,0 +1,191 @@
+use super::allocator::{align_allocation_no_fill, fill_alignment_gap};
+use crate::plan::Plan;
+use crate::policy::immix::{Block, ImmixSpace, BYTES_IN_LINE, MAX_IMMIX_OBJECT_SIZE};
+use crate::policy::space::Space;
+use crate::util::alloc::Allocator;
+use crate::util::memory;
+use crate::util::Address;
+use crate::util::OpaquePointer;
+use crate::vm::VMBinding;
+
+/// A bump pointer allocator for the Immix space, which fills holes (runs of free lines)
+/// in partially occupied blocks before it asks for clean blocks.
+#[repr(C)]
+pub struct ImmixAllocator<VM: VMBinding> {
+    pub tls: OpaquePointer,
+    cursor: Address,
+    limit: Address,
+    space: Option<&'static ImmixSpace<VM>>,
+    plan: &'static dyn Plan<VM = VM>,
+    /// Is this allocator used by GC workers to evacuate objects?
+    copy: bool,
+    /// A separate bump pointer region (always a clean block) for medium objects that
+    /// do not fit in the current hole. This avoids skipping many small holes.
+    large_cursor: Address,
+    large_limit: Address,
+    /// Is the current slow path allocation for the medium object region?
+    request_for_large: bool,
+    /// The reusable block we are filling holes in, if any.
+    block: Option<Block>,
+    /// The index of the line in `block` where the next hole search starts.
+    line: usize,
+}
+
+impl<VM: VMBinding> ImmixAllocator<VM> {
+    pub fn new(
+        tls: OpaquePointer,
+        space: Option<&'static ImmixSpace<VM>>,
+        plan: &'static dyn Plan<VM = VM>,
+        copy: bool,
+    ) -> Self {
+        ImmixAllocator {
+            tls,
+            cursor: unsafe { Address::zero() },
+            limit: unsafe { Address::zero() },
+            space,
+            plan,
+            copy,
+            large_cursor: unsafe { Address::zero() },
+            large_limit: unsafe { Address::zero() },
+            request_for_large: false,
+            block: None,
+            line: 0,
+        }
+    }
+
+    /// Drop the current regions. This needs to be called before the blocks are swept.
+    pub fn reset(&mut self) {
+        self.cursor = unsafe { Address::zero() };
+        self.limit = unsafe { Address::zero() };
+        self.large_cursor = unsafe { Address::zero() };
+        self.large_limit = unsafe { Address::zero() };
+        self.request_for_large = false;
+        self.block = None;
+        self.line = 0;
+    }
+
+    pub fn rebind(&mut self, space: Option<&'static ImmixSpace<VM>>) {
+        self.reset();
+        self.space = space;
+    }
+
+    /// Allocate a medium object in the medium object region.
+    fn overflow_alloc(&mut self, size: usize, align: usize, offset: isize) -> Address {
+        trace!("{:?}: overflow_alloc", self.tls);
+        let start = align_allocation_no_fill::<VM>(self.large_cursor, align, offset);
+        let end = start + size;
+        if end > self.large_limit {
+            self.request_for_large = true;
+            let rtn = self.alloc_slow_inline(size, align, offset);
+            self.request_for_large = false;
+            rtn
+        } else {
+            fill_alignment_gap::<VM>(self.large_cursor, start);
+            self.large_cursor = end;
+            start
+        }
+    }
+
+    /// Try the next hole, and fall back to the slow path.
+    fn alloc_slow_hot(&mut self, size: usize, align: usize, offset: isize) -> Address {
+        trace!("{:?}: alloc_slow_hot", self.tls);
+        if self.acquire_recyclable_lines() {
+            self.alloc(size, align, offset)
+        } else {
+            self.alloc_slow_inline(size, align, offset)
+        }
+    }
+
+    /// Move the cursor to the next hole in a reusable block. Returns false if there are no holes left.
+    fn acquire_recyclable_lines(&mut self) -> bool {
+        // Evacuation only goes to clean blocks, so a defrag source can never receive copies.
+        if self.copy {
+            return false;
+        }
+        loop {
+            if let Some(block) = self.block {
+                if let Some((start, end)) = block.next_hole(self.line) {
+                    self.cursor = block.line(start).start();
+                    self.limit = block.line(end - 1).end();
+                    self.line = end;
+                    memory::zero(self.cursor, self.limit - self.cursor);
+                    trace!(
+                        "{:?}: acquired hole [{}, {}) in {:?}",
+                        self.tls,
+                        self.cursor,
+                        self.limit,
+                        block
+                    );
+                    return true;
+                }
+                self.block = None;
+            }
+            match self.space.unwrap().get_reusable_block() {
+                Some(block) => {
+                    self.block = Some(block);
+                    self.line = 0;
+                }
+                None => return false,
+            }
+        }
+    }
+}
+
+impl<VM: VMBinding> Allocator<VM> for ImmixAllocator<VM> {
+    fn get_tls(&self) -> OpaquePointer {
+        self.tls
+    }
+    fn get_plan(&self) -> &'static dyn Plan<VM = VM> {
+        self.plan
+    }
+
+    fn get_space(&self) -> Option<&'static dyn Space<VM>> {
+        // Casting the interior of the Option: from &ImmixSpace to &dyn Space
+        self.space.map(|s| s as &'static dyn Space<VM>)
+    }
+
+    fn alloc(&mut self, size: usize, align: usize, offset: isize) -> Address {
+        // No block can fit a larger object, so the slow path would never succeed. The mutator sends
+        // these objects to the large object space (see `PlanConstraints::max_non_los_default_alloc_bytes`),
+        // and so must the fast path of the binding.
+        if size > MAX_IMMIX_OBJECT_SIZE {
+            warn!(
+                "Object of {} bytes is too large for the Immix space. Allocate it in the large object space",
+                size
+            );
+            return unsafe { Address::zero() };
+        }
+        let result = align_allocation_no_fill::<VM>(self.cursor, align, offset);
+        let new_cursor = result + size;
+
+        if new_cursor > self.limit {
+            if size > BYTES_IN_LINE {
+                self.overflow_alloc(size, align, offset)
+            } else {
+                self.alloc_slow_hot(size, align, offset)
+            }
+        } else {
+            fill_alignment_gap::<VM>(self.cursor, result);
+            self.cursor = new_cursor;
+            result
+        }
+    }
+
+    /// Acquire a clean block, either for the medium object region or for the normal region.
+    fn alloc_slow_once(&mut self, size: usize, align: usize, offset: isize) -> Address {
+        trace!("{:?}: alloc_slow_once", self.tls);
+        match self.space.unwrap().get_clean_block(self.tls, self.copy) {
+            None => unsafe { Address::zero() },
+            Some(block) => {
+                if self.request_for_large {
+                    self.large_cursor = block.start();
+                    self.large_limit = block.end();
+                } else {
+                    self.cursor = block.start();
+                    self.limit = block.end();
+                }
+                self.alloc(size, align, offset)
+            }
+        }
+    }
+}
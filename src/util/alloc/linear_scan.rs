@@ -1,6 +1,69 @@
-use crate::util::ObjectReference;
-use crate::vm::VMBinding;
+use crate::util::constants::BYTES_IN_WORD;
+use crate::util::side_metadata::{load_atomic, SideMetadataSpec};
+use crate::util::{Address, ObjectReference};
+use crate::vm::{ObjectModel, VMBinding};
 
 pub trait LinearScan {
     fn scan<VM: VMBinding>(&self, object: ObjectReference);
 }
+
+/// Visit each object in `[start, end)` in address order. `alloc_bits` is a side metadata with one bit
+/// per word, which is set for each object reference allocated in the region. The metadata for the
+/// region must be mapped.
+pub fn scan_alloc_bits(
+    alloc_bits: SideMetadataSpec,
+    start: Address,
+    end: Address,
+    f: &mut dyn FnMut(ObjectReference),
+) {
+    debug_assert_eq!(alloc_bits.log_num_of_bits, 0);
+    debug_assert_eq!(1 << alloc_bits.log_min_obj_size, BYTES_IN_WORD);
+    let mut address = start;
+    while address < end {
+        if load_atomic(alloc_bits, address) == 1 {
+            f(unsafe { address.to_object_reference() });
+        }
+        address += BYTES_IN_WORD;
+    }
+}
+
+/// Visit each object in the bump pointer region `[start, end)` in address order. This uses the global
+/// alloc bit if it is enabled, and walks the region with `walk_bump_region()` otherwise.
+pub fn scan_bump_region<VM: VMBinding>(
+    start: Address,
+    end: Address,
+    f: &mut dyn FnMut(ObjectReference),
+) {
+    #[cfg(feature = "global_alloc_bit")]
+    scan_alloc_bits(
+        crate::util::alloc_bit::ALLOC_SIDE_METADATA_SPEC,
+        start,
+        end,
+        f,
+    );
+    #[cfg(not(feature = "global_alloc_bit"))]
+    walk_bump_region::<VM>(start, end, f);
+}
+
+/// Visit each object in the bump pointer region `[start, end)` in address order, without any side metadata.
+/// The objects are laid out one after another, with alignment gaps between them, and the parts of the
+/// region that were never handed out by an allocator are zeroed. `ObjectModel::get_object_from_start_address()`
+/// tells the objects from the gaps.
+pub fn walk_bump_region<VM: VMBinding>(
+    start: Address,
+    end: Address,
+    f: &mut dyn FnMut(ObjectReference),
+) {
+    let mut address = start;
+    while address < end {
+        match VM::VMObjectModel::get_object_from_start_address(address) {
+            Some(object) => {
+                f(object);
+                let object_end = VM::VMObjectModel::object_start_ref(object)
+                    + VM::VMObjectModel::get_current_size(object);
+                address = object_end.align_up(VM::MIN_ALIGNMENT);
+            }
+            None => address += VM::MIN_ALIGNMENT,
+        }
+    }
+}
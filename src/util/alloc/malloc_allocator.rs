@@ -0,0 +1,59 @@
+use crate::plan::Plan;
+use crate::policy::mallocspace::MallocSpace;
+use crate::policy::space::Space;
+use crate::util::alloc::Allocator;
+use crate::util::Address;
+use crate::util::OpaquePointer;
+use crate::vm::VMBinding;
+
+#[repr(C)]
+pub struct MallocAllocator<VM: VMBinding> {
+    pub tls: OpaquePointer,
+    space: Option<&'static MallocSpace<VM>>,
+    plan: &'static dyn Plan<VM = VM>,
+}
+
+impl<VM: VMBinding> Allocator<VM> for MallocAllocator<VM> {
+    fn get_tls(&self) -> OpaquePointer {
+        self.tls
+    }
+    fn get_plan(&self) -> &'static dyn Plan<VM = VM> {
+        self.plan
+    }
+
+    fn get_space(&self) -> Option<&'static dyn Space<VM>> {
+        // Casting the interior of the Option: from &MallocSpace to &dyn Space
+        self.space.map(|s| s as &'static dyn Space<VM>)
+    }
+
+    fn alloc(&mut self, size: usize, align: usize, offset: isize) -> Address {
+        self.alloc_slow(size, align, offset)
+    }
+
+    fn alloc_slow(&mut self, size: usize, align: usize, offset: isize) -> Address {
+        self.alloc_slow_inline(size, align, offset)
+    }
+
+    fn alloc_slow_once(&mut self, size: usize, align: usize, offset: isize) -> Address {
+        // Malloc only guarantees its own alignment (16 bytes on 64-bit glibc), and we do not pad cells.
+        assert!(
+            offset == 0,
+            "MallocAllocator does not support allocation offsets"
+        );
+        assert!(
+            align <= 16,
+            "MallocAllocator does not support alignment larger than 16 bytes"
+        );
+        self.space.unwrap().alloc(self.tls, size)
+    }
+}
+
+impl<VM: VMBinding> MallocAllocator<VM> {
+    pub fn new(
+        tls: OpaquePointer,
+        space: Option<&'static MallocSpace<VM>>,
+        plan: &'static dyn Plan<VM = VM>,
+    ) -> Self {
+        MallocAllocator { tls, space, plan }
+    }
+}
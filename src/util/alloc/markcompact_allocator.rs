@@ -0,0 +1,66 @@
+use crate::plan::Plan;
+use crate::policy::markcompactspace::GC_EXTRA_HEADER_BYTES;
+use crate::policy::space::Space;
+use crate::util::alloc::{Allocator, BumpAllocator};
+use crate::util::Address;
+use crate::util::OpaquePointer;
+use crate::vm::VMBinding;
+
+/// A bump pointer allocator for the mark compact space, which reserves the hidden
+/// header word in front of each object.
+#[repr(C)]
+pub struct MarkCompactAllocator<VM: VMBinding> {
+    bump_allocator: BumpAllocator<VM>,
+}
+
+impl<VM: VMBinding> MarkCompactAllocator<VM> {
+    pub fn new(
+        tls: OpaquePointer,
+        space: Option<&'static dyn Space<VM>>,
+        plan: &'static dyn Plan<VM = VM>,
+    ) -> Self {
+        MarkCompactAllocator {
+            bump_allocator: BumpAllocator::new(tls, space, plan),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.bump_allocator.reset();
+    }
+
+    pub fn rebind(&mut self, space: Option<&'static dyn Space<VM>>) {
+        self.bump_allocator.rebind(space);
+    }
+}
+
+impl<VM: VMBinding> Allocator<VM> for MarkCompactAllocator<VM> {
+    fn get_tls(&self) -> OpaquePointer {
+        self.bump_allocator.get_tls()
+    }
+    fn get_plan(&self) -> &'static dyn Plan<VM = VM> {
+        self.bump_allocator.get_plan()
+    }
+
+    fn get_space(&self) -> Option<&'static dyn Space<VM>> {
+        self.bump_allocator.get_space()
+    }
+
+    fn alloc(&mut self, size: usize, align: usize, offset: isize) -> Address {
+        // Allocate the header word together with the object, and keep the object itself aligned.
+        let rtn = self.bump_allocator.alloc(
+            size + GC_EXTRA_HEADER_BYTES,
+            align,
+            offset + GC_EXTRA_HEADER_BYTES as isize,
+        );
+        if rtn.is_zero() {
+            rtn
+        } else {
+            rtn + GC_EXTRA_HEADER_BYTES
+        }
+    }
+
+    fn alloc_slow_once(&mut self, size: usize, align: usize, offset: isize) -> Address {
+        // The bump allocator never sends us here, as it runs its own slow path.
+        self.bump_allocator.alloc_slow_once(size, align, offset)
+    }
+}
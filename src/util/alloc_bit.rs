@@ -0,0 +1,59 @@
+//! The global alloc bit: one bit of side metadata per word, which is set if an object allocated by MMTk
+//! (and not reclaimed yet) starts at that word. It is maintained if the `global_alloc_bit` feature is enabled,
+//! and lets us check whether an arbitrary address is an object (see `memory_manager::is_mmtk_object()`).
+//!
+//! The bit is set when an object is allocated (in `post_alloc()`) or copied by a GC,
+//! and each policy clears the bit when it reclaims the object.
+
+use crate::util::constants::{BYTES_IN_WORD, LOG_BYTES_IN_WORD};
+use crate::util::side_metadata::{
+    bzero_metadata_for_chunk, load_atomic, meta_bytes_per_chunk, store_atomic, SideMetadataScope,
+    SideMetadataSpec,
+};
+use crate::util::{Address, ObjectReference};
+
+/// The alloc bit is the first global side metadata. The plans put their own global side metadata
+/// after `ALLOC_SIDE_METADATA_PER_CHUNK` bytes.
+pub const ALLOC_SIDE_METADATA_SPEC: SideMetadataSpec = SideMetadataSpec {
+    scope: SideMetadataScope::Global,
+    offset: 0,
+    log_num_of_bits: 0,
+    log_min_obj_size: LOG_BYTES_IN_WORD as usize,
+};
+
+/// The bytes of global side metadata per chunk used by the alloc bit, which is 0 if the feature is disabled.
+pub const ALLOC_SIDE_METADATA_PER_CHUNK: usize = if cfg!(feature = "global_alloc_bit") {
+    meta_bytes_per_chunk(
+        ALLOC_SIDE_METADATA_SPEC.log_min_obj_size,
+        ALLOC_SIDE_METADATA_SPEC.log_num_of_bits,
+    )
+} else {
+    0
+};
+
+#[inline]
+pub fn set_alloc_bit(object: ObjectReference) {
+    debug_assert!(!is_alloced(object), "{} already has the alloc bit", object);
+    store_atomic(ALLOC_SIDE_METADATA_SPEC, object.to_address(), 1);
+}
+
+#[inline]
+pub fn unset_alloc_bit(object: ObjectReference) {
+    store_atomic(ALLOC_SIDE_METADATA_SPEC, object.to_address(), 0);
+}
+
+#[inline]
+pub fn is_alloced(object: ObjectReference) -> bool {
+    load_atomic(ALLOC_SIDE_METADATA_SPEC, object.to_address()) == 1
+}
+
+/// Is there an object at the address? The side metadata for the address must be mapped.
+#[inline]
+pub fn is_alloced_object(address: Address) -> bool {
+    address.is_aligned_to(BYTES_IN_WORD) && is_alloced(unsafe { address.to_object_reference() })
+}
+
+/// Clear the alloc bits of all the objects in a chunk.
+pub fn bzero_alloc_bits(chunk: Address) {
+    bzero_metadata_for_chunk(ALLOC_SIDE_METADATA_SPEC, chunk);
+}
@@ -0,0 +1,461 @@
+//! The ephemerons registered with `memory_manager::add_ephemeron_candidate()`.
+//!
+//! An ephemeron is an entry of a weak key-value container (e.g. a WeakMap): its value is only kept alive
+//! if its key and its container are reachable. The VM should not report the key and the value of an entry
+//! as edges when it scans the container. Instead, after the transitive closure, MMTk reads the key and the value
+//! of each entry through `ReferenceGlue`, traces the values of the entries whose keys and containers are found
+//! reachable, and repeats this with the closure of those values until no more values are traced. This is done
+//! again after the finalizable objects are resurrected. The entries whose keys are not reachable are cleared
+//! afterwards, and the others are updated to the new addresses of their objects (see `ReferenceGlue::update_ephemeron()`).
+//!
+//! The ephemerons are processed even if `no_reference_types` is set. The plans that cannot process them
+//! (see `PlanConstraints::supports_ephemerons`) do not accept any.
+
+use std::marker::PhantomData;
+use std::mem;
+use std::sync::Mutex;
+
+use crate::plan::TraceLocal;
+use crate::scheduler::gc_work::{ProcessEdgesTraceLocal, ProcessEdgesWork};
+use crate::scheduler::{GCWork, GCWorker, MMTkScheduler, WorkBucketStage};
+use crate::util::{Address, ObjectReference};
+use crate::vm::{ReferenceGlue, VMBinding};
+use crate::MMTK;
+
+/// An entry of a container. `id` is chosen by the VM, and identifies the entry within the container.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Ephemeron {
+    container: ObjectReference,
+    id: usize,
+}
+
+/// An ephemeron whose value is traced in the current GC, with the key and the value read in this GC.
+#[derive(Debug, Clone, Copy)]
+struct TracedEphemeron {
+    ephemeron: Ephemeron,
+    key: ObjectReference,
+    value: ObjectReference,
+}
+
+#[derive(Default)]
+struct EphemeronProcessorSync {
+    /// The ephemerons whose keys or containers are not found reachable yet in the current GC.
+    /// All the ephemerons are pending between GCs.
+    pending: Vec<Ephemeron>,
+    /// The ephemerons whose values are traced in the current GC
+    traced: Vec<TracedEphemeron>,
+}
+
+pub struct EphemeronProcessor {
+    sync: Mutex<EphemeronProcessorSync>,
+}
+
+impl EphemeronProcessor {
+    pub fn new() -> Self {
+        Self {
+            sync: Mutex::new(EphemeronProcessorSync::default()),
+        }
+    }
+
+    pub fn clear(&self) {
+        let mut sync = self.sync.lock().unwrap();
+        sync.pending.clear();
+        sync.traced.clear();
+    }
+
+    pub fn add_candidate(&self, container: ObjectReference, id: usize) {
+        self.sync
+            .lock()
+            .unwrap()
+            .pending
+            .push(Ephemeron { container, id });
+    }
+
+    /// Trace the values of the pending ephemerons whose keys and containers are reachable.
+    /// Returns true if any value is traced, in which case the closure of the values needs to be computed,
+    /// and this needs to be called again.
+    fn trace_values<VM: VMBinding, T: TraceLocal>(&self, trace: &mut T) -> bool {
+        self.trace_values_with(
+            trace,
+            |object| object.is_live(),
+            |container, id| {
+                (
+                    VM::VMReferenceGlue::get_ephemeron_key(container, id),
+                    VM::VMReferenceGlue::get_ephemeron_value(container, id),
+                )
+            },
+        )
+    }
+
+    /// `read` returns the key and the value of an entry, given the current address of its container.
+    fn trace_values_with<T: TraceLocal, L, R>(&self, trace: &mut T, is_live: L, read: R) -> bool
+    where
+        L: Fn(ObjectReference) -> bool,
+        R: Fn(ObjectReference, usize) -> (ObjectReference, ObjectReference),
+    {
+        let mut sync = self.sync.lock().unwrap();
+        let mut traced = false;
+        let mut pending = vec![];
+        for ephemeron in mem::take(&mut sync.pending) {
+            if !is_live(ephemeron.container) {
+                pending.push(ephemeron);
+                continue;
+            }
+            let container = trace.get_forwarded_reference(ephemeron.container);
+            let (key, value) = read(container, ephemeron.id);
+            // The VM has removed the entry since it was added
+            if key.is_null() {
+                continue;
+            }
+            if !is_live(key) {
+                pending.push(ephemeron);
+                continue;
+            }
+            let value = if value.is_null() {
+                value
+            } else {
+                trace.trace_object(value)
+            };
+            sync.traced.push(TracedEphemeron {
+                ephemeron,
+                key,
+                value,
+            });
+            traced = true;
+        }
+        sync.pending = pending;
+        traced
+    }
+
+    /// Clear the ephemerons whose keys are not reachable, and update the others to the new addresses of their objects.
+    /// The ephemerons in the containers that are not reachable are dropped.
+    fn clear_dead_entries<VM: VMBinding, T: TraceLocal>(&self, trace: &mut T) {
+        self.clear_dead_entries_with(
+            trace,
+            |object| object.is_live(),
+            VM::VMReferenceGlue::update_ephemeron,
+        )
+    }
+
+    fn clear_dead_entries_with<T: TraceLocal, L, U>(&self, trace: &mut T, is_live: L, update: U)
+    where
+        L: Fn(ObjectReference) -> bool,
+        U: Fn(ObjectReference, usize, ObjectReference, ObjectReference),
+    {
+        let mut sync = self.sync.lock().unwrap();
+        let null = unsafe { Address::ZERO.to_object_reference() };
+        for ephemeron in mem::take(&mut sync.pending) {
+            if is_live(ephemeron.container) {
+                let container = trace.get_forwarded_reference(ephemeron.container);
+                update(container, ephemeron.id, null, null);
+            }
+        }
+        for traced in mem::take(&mut sync.traced) {
+            let container = trace.get_forwarded_reference(traced.ephemeron.container);
+            let key = trace.get_forwarded_reference(traced.key);
+            update(container, traced.ephemeron.id, key, traced.value);
+            sync.pending.push(Ephemeron {
+                container,
+                id: traced.ephemeron.id,
+            });
+        }
+    }
+
+    /// Update the ephemerons to the new addresses of their objects. This is used by plans that move objects
+    /// in a separate phase after the closure (e.g. MarkCompact), and runs before the objects are moved. So the
+    /// VM updates each entry at the current address of its container, which then moves along with the container.
+    fn forward<VM: VMBinding, T: TraceLocal>(&self, trace: &mut T) {
+        self.forward_with(
+            trace,
+            |container, id| {
+                (
+                    VM::VMReferenceGlue::get_ephemeron_key(container, id),
+                    VM::VMReferenceGlue::get_ephemeron_value(container, id),
+                )
+            },
+            VM::VMReferenceGlue::update_ephemeron,
+        )
+    }
+
+    fn forward_with<T: TraceLocal, R, U>(&self, trace: &mut T, read: R, update: U)
+    where
+        R: Fn(ObjectReference, usize) -> (ObjectReference, ObjectReference),
+        U: Fn(ObjectReference, usize, ObjectReference, ObjectReference),
+    {
+        let mut sync = self.sync.lock().unwrap();
+        for ephemeron in sync.pending.iter_mut() {
+            let (key, value) = read(ephemeron.container, ephemeron.id);
+            let key = trace.get_forwarded_reference(key);
+            let value = if value.is_null() {
+                value
+            } else {
+                trace.get_forwarded_reference(value)
+            };
+            update(ephemeron.container, ephemeron.id, key, value);
+            ephemeron.container = trace.get_forwarded_reference(ephemeron.container);
+        }
+    }
+}
+
+impl Default for EphemeronProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Trace the values of the ephemerons whose keys are reachable. This is set as the sentinel of the bucket
+/// `stage`, so it runs once the closure is done, and it sets itself again until no more values are traced.
+pub struct ProcessEphemerons<E: ProcessEdgesWork> {
+    stage: WorkBucketStage,
+    phantom: PhantomData<E>,
+}
+
+impl<E: ProcessEdgesWork> ProcessEphemerons<E> {
+    pub fn new(stage: WorkBucketStage) -> Self {
+        Self {
+            stage,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E: ProcessEdgesWork> GCWork<E::VM> for ProcessEphemerons<E> {
+    fn do_work(&mut self, worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
+        trace!("ProcessEphemerons");
+        let mut closure = E::new(vec![], false, mmtk);
+        closure.set_worker(worker);
+        let traced = mmtk
+            .reference_processors
+            .ephemerons
+            .trace_values::<E::VM, _>(&mut ProcessEdgesTraceLocal(&mut closure));
+        if traced {
+            if !closure.nodes.is_empty() {
+                closure.flush();
+            }
+            mmtk.scheduler.work_buckets[self.stage].set_sentinel(Self::new(self.stage));
+        }
+    }
+}
+
+/// Clear the ephemerons whose keys are not reachable, and update the others.
+#[derive(Default)]
+pub struct ClearEphemerons<E: ProcessEdgesWork>(PhantomData<E>);
+
+impl<E: ProcessEdgesWork> ClearEphemerons<E> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<E: ProcessEdgesWork> GCWork<E::VM> for ClearEphemerons<E> {
+    fn do_work(&mut self, worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
+        trace!("ClearEphemerons");
+        let mut closure = E::new(vec![], false, mmtk);
+        closure.set_worker(worker);
+        mmtk.reference_processors
+            .ephemerons
+            .clear_dead_entries::<E::VM, _>(&mut ProcessEdgesTraceLocal(&mut closure));
+        debug_assert!(closure.nodes.is_empty());
+    }
+}
+
+/// Update the ephemerons to the new addresses of their objects.
+#[derive(Default)]
+pub struct ForwardEphemerons<E: ProcessEdgesWork>(PhantomData<E>);
+
+impl<E: ProcessEdgesWork> ForwardEphemerons<E> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<E: ProcessEdgesWork> GCWork<E::VM> for ForwardEphemerons<E> {
+    fn do_work(&mut self, worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
+        trace!("ForwardEphemerons");
+        let mut closure = E::new(vec![], false, mmtk);
+        closure.set_worker(worker);
+        mmtk.reference_processors
+            .ephemerons
+            .forward::<E::VM, _>(&mut ProcessEdgesTraceLocal(&mut closure));
+        // The values are not reachable from the roots, so their fields are updated here
+        if !closure.nodes.is_empty() {
+            closure.flush();
+        }
+    }
+}
+
+/// Schedule the ephemeron processing for a GC that traces objects with `E`.
+pub fn schedule_ephemeron_processing<E: ProcessEdgesWork>(scheduler: &MMTkScheduler<E::VM>) {
+    // The values are traced after the closure, after the closure of the retained soft referents,
+    // and after the closure of the resurrected finalizable objects
+    for stage in &[
+        WorkBucketStage::Closure,
+        WorkBucketStage::SoftRefClosure,
+        WorkBucketStage::FinalRefClosure,
+    ] {
+        scheduler.work_buckets[*stage].set_sentinel(ProcessEphemerons::<E>::new(*stage));
+    }
+    scheduler.work_buckets[WorkBucketStage::PhantomRefClosure].add(ClearEphemerons::<E>::new());
+}
+
+/// Schedule updating the ephemerons to the new addresses of the objects, for the plans that move objects
+/// in a separate phase after the closure (e.g. MarkCompact). `E` returns the new address of an object.
+pub fn schedule_ephemeron_forwarding<E: ProcessEdgesWork>(scheduler: &MMTkScheduler<E::VM>) {
+    scheduler.work_buckets[WorkBucketStage::RefForwarding].add(ForwardEphemerons::<E>::new());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::test_util::{moved, object, MockTrace};
+    use std::cell::RefCell;
+
+    type Update = (ObjectReference, usize, ObjectReference, ObjectReference);
+
+    fn null() -> ObjectReference {
+        unsafe { Address::ZERO.to_object_reference() }
+    }
+
+    fn pending(processor: &EphemeronProcessor) -> Vec<Ephemeron> {
+        processor.sync.lock().unwrap().pending.clone()
+    }
+
+    #[test]
+    fn test_trace_values_to_fixpoint() {
+        // Entry 0 maps `k0` to `k1`, and entry 1 maps `k1` to `v`. Entry 2 has a dead key.
+        let (container, k0, k1, k2, v) = (
+            object(0x10000),
+            object(0x20000),
+            object(0x30000),
+            object(0x40000),
+            object(0x50000),
+        );
+        let entries = [(k0, k1), (k1, v), (k2, v)];
+        let processor = EphemeronProcessor::new();
+        for id in 0..entries.len() {
+            processor.add_candidate(container, id);
+        }
+        let live = RefCell::new(vec![container, k0]);
+        let is_live = |o: ObjectReference| live.borrow().contains(&o);
+        let read = |c: ObjectReference, id: usize| {
+            assert_eq!(c, container);
+            entries[id]
+        };
+        let mut trace = MockTrace::new(false);
+
+        // Each round traces the values of the entries whose keys were traced in the last round
+        assert!(processor.trace_values_with(&mut trace, is_live, read));
+        assert_eq!(trace.traced, vec![k1]);
+        live.borrow_mut().push(k1);
+        assert!(processor.trace_values_with(&mut trace, is_live, read));
+        assert_eq!(trace.traced, vec![k1, v]);
+        live.borrow_mut().push(v);
+        assert!(!processor.trace_values_with(&mut trace, is_live, read));
+        assert_eq!(trace.traced, vec![k1, v]);
+        assert_eq!(pending(&processor), vec![Ephemeron { container, id: 2 }]);
+    }
+
+    #[test]
+    fn test_read_entries_in_each_gc() {
+        let (container, key, old_value, new_value) = (
+            object(0x10000),
+            object(0x20000),
+            object(0x30000),
+            object(0x40000),
+        );
+        let processor = EphemeronProcessor::new();
+        processor.add_candidate(container, 0);
+
+        // The VM may change the value of the entry between GCs, and remove the entry
+        for (value, traced) in &[(old_value, vec![old_value]), (new_value, vec![new_value])] {
+            let mut trace = MockTrace::new(false);
+            assert!(processor.trace_values_with(&mut trace, |_| true, |_, _| (key, *value)));
+            assert_eq!(&trace.traced, traced);
+            processor.clear_dead_entries_with(&mut trace, |_| true, |_, _, _, _| {});
+        }
+        let mut trace = MockTrace::new(false);
+        assert!(!processor.trace_values_with(&mut trace, |_| true, |_, _| (null(), new_value)));
+        assert!(pending(&processor).is_empty());
+    }
+
+    #[test]
+    fn test_clear_dead_entries() {
+        let (container, dead_container, key, dead_key, value) = (
+            object(0x10000),
+            object(0x20000),
+            object(0x30000),
+            object(0x40000),
+            object(0x50000),
+        );
+        let processor = EphemeronProcessor::new();
+        processor.add_candidate(container, 0);
+        processor.add_candidate(container, 1);
+        processor.add_candidate(dead_container, 0);
+        let is_live = |o: ObjectReference| o == container || o == key;
+        let read = |c: ObjectReference, id: usize| {
+            assert_eq!(c, moved(container));
+            if id == 0 {
+                (key, value)
+            } else {
+                (dead_key, value)
+            }
+        };
+        let mut trace = MockTrace::new(true);
+        assert!(processor.trace_values_with(&mut trace, is_live, read));
+        assert_eq!(trace.traced, vec![value]);
+
+        // The entries are updated at the new address of the container, and the dead container is left alone
+        let updates = RefCell::new(Vec::<Update>::new());
+        processor.clear_dead_entries_with(&mut trace, is_live, |c, id, k, v| {
+            updates.borrow_mut().push((c, id, k, v))
+        });
+        assert_eq!(
+            updates.into_inner(),
+            vec![
+                (moved(container), 1, null(), null()),
+                (moved(container), 0, moved(key), moved(value)),
+            ]
+        );
+        assert_eq!(
+            pending(&processor),
+            vec![Ephemeron {
+                container: moved(container),
+                id: 0
+            }]
+        );
+    }
+
+    #[test]
+    fn test_forward_before_objects_move() {
+        let (container, key, value) = (object(0x10000), object(0x20000), object(0x30000));
+        let processor = EphemeronProcessor::new();
+        processor.add_candidate(container, 0);
+
+        // The marking closure does not move objects
+        let mut trace = MockTrace::new(false);
+        processor.trace_values_with(&mut trace, |_| true, |_, _| (key, value));
+        processor.clear_dead_entries_with(&mut trace, |_| true, |_, _, _, _| {});
+
+        // The entry is updated in the container before the container moves
+        let updates = RefCell::new(Vec::<Update>::new());
+        processor.forward_with(
+            &mut MockTrace::new(true),
+            |c, _| {
+                assert_eq!(c, container);
+                (key, value)
+            },
+            |c, id, k, v| updates.borrow_mut().push((c, id, k, v)),
+        );
+        assert_eq!(
+            updates.into_inner(),
+            vec![(container, 0, moved(key), moved(value))]
+        );
+        assert_eq!(
+            pending(&processor),
+            vec![Ephemeron {
+                container: moved(container),
+                id: 0
+            }]
+        );
+    }
+}
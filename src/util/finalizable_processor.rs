@@ -0,0 +1,219 @@
+//! The finalizable objects registered with `memory_manager::add_finalizer()`.
+//!
+//! After the transitive closure, the finalizable objects that are not reachable are resurrected
+//! (along with everything they reach), and queued for the VM to run their finalizers on a mutator thread.
+//! The RC and ConcMarkSweep plans do not process finalizable objects yet, so `memory_manager::add_finalizer()`
+//! does not register any (see `PlanConstraints::supports_finalizers`).
+
+use crate::plan::TraceLocal;
+use crate::scheduler::gc_work::{ProcessEdgesTraceLocal, ProcessEdgesWork};
+use crate::scheduler::{GCWork, GCWorker};
+use crate::util::ObjectReference;
+use crate::vm::{Collection, VMBinding};
+use crate::MMTK;
+use std::marker::PhantomData;
+
+pub struct FinalizableProcessor {
+    /// The objects that have finalizers and have not been found unreachable yet
+    candidates: Vec<ObjectReference>,
+    /// The candidates from this index were added since the last GC. A nursery GC only needs to scan them.
+    nursery_index: usize,
+    /// The unreachable objects whose finalizers are ready to run
+    ready_for_finalize: Vec<ObjectReference>,
+}
+
+impl FinalizableProcessor {
+    pub fn new() -> Self {
+        Self {
+            candidates: vec![],
+            nursery_index: 0,
+            ready_for_finalize: vec![],
+        }
+    }
+
+    pub fn add(&mut self, object: ObjectReference) {
+        self.candidates.push(object);
+    }
+
+    /// Find the unreachable candidates, and resurrect them so their finalizers can run.
+    /// This must be called after the transitive closure. The objects resurrected here are buffered in
+    /// `trace`, and their closure needs to be computed before any weaker references are processed.
+    pub fn scan<T: TraceLocal>(&mut self, trace: &mut T, nursery: bool) {
+        self.scan_with_liveness(trace, nursery, |object| object.is_live())
+    }
+
+    fn scan_with_liveness<T: TraceLocal, F: Fn(ObjectReference) -> bool>(
+        &mut self,
+        trace: &mut T,
+        nursery: bool,
+        is_live: F,
+    ) {
+        // The objects waiting for their finalizers are kept alive until they are fetched by the VM
+        for object in self.ready_for_finalize.iter_mut() {
+            *object = trace.retain_referent(*object);
+        }
+
+        let start = if nursery { self.nursery_index } else { 0 };
+        let mut dead = vec![];
+        let mut live = start;
+        for i in start..self.candidates.len() {
+            let object = self.candidates[i];
+            if is_live(object) {
+                self.candidates[live] = trace.get_forwarded_reference(object);
+                live += 1;
+            } else {
+                dead.push(object);
+            }
+        }
+        self.candidates.truncate(live);
+        self.nursery_index = live;
+
+        for object in dead {
+            let object = trace.retain_referent(object);
+            self.ready_for_finalize.push(object);
+        }
+    }
+
+    /// Update the candidates and the ready objects to the new locations of the objects.
+    /// This is used by plans that move objects in a separate phase after the closure (e.g. MarkCompact).
+    pub fn forward<T: TraceLocal>(&mut self, trace: &mut T, nursery: bool) {
+        let start = if nursery { self.nursery_index } else { 0 };
+        for object in self.candidates[start..]
+            .iter_mut()
+            .chain(self.ready_for_finalize.iter_mut())
+        {
+            *object = trace.get_forwarded_reference(*object);
+        }
+    }
+
+    pub fn get_ready_object(&mut self) -> Option<ObjectReference> {
+        self.ready_for_finalize.pop()
+    }
+}
+
+impl Default for FinalizableProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Resurrect the unreachable finalizable objects. This should be added to the `FinalRefClosure` bucket,
+/// so the closure of the resurrected objects is computed before the stages after it.
+#[derive(Default)]
+pub struct Finalization<E: ProcessEdgesWork>(PhantomData<E>);
+
+impl<E: ProcessEdgesWork> Finalization<E> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<E: ProcessEdgesWork> GCWork<E::VM> for Finalization<E> {
+    fn do_work(&mut self, worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
+        trace!("Finalization");
+        let mut processor = mmtk.finalizable_processor.lock().unwrap();
+        let num_ready = processor.ready_for_finalize.len();
+
+        let mut closure = E::new(vec![], false, mmtk);
+        closure.set_worker(worker);
+        processor.scan(
+            &mut ProcessEdgesTraceLocal(&mut closure),
+            mmtk.plan.in_nursery(),
+        );
+        if !closure.nodes.is_empty() {
+            closure.flush();
+        }
+
+        if processor.ready_for_finalize.len() > num_ready {
+            <E::VM as VMBinding>::VMCollection::schedule_finalization(worker.tls);
+        }
+        trace!("Finalization End");
+    }
+}
+
+/// Update the references in the finalizable processor to the new locations of the objects.
+#[derive(Default)]
+pub struct ForwardFinalization<E: ProcessEdgesWork>(PhantomData<E>);
+
+impl<E: ProcessEdgesWork> ForwardFinalization<E> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<E: ProcessEdgesWork> GCWork<E::VM> for ForwardFinalization<E> {
+    fn do_work(&mut self, worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
+        trace!("ForwardFinalization");
+        let mut closure = E::new(vec![], false, mmtk);
+        closure.set_worker(worker);
+        mmtk.finalizable_processor.lock().unwrap().forward(
+            &mut ProcessEdgesTraceLocal(&mut closure),
+            mmtk.plan.in_nursery(),
+        );
+        // The resurrected objects are not reachable from the roots, so their fields are updated here
+        if !closure.nodes.is_empty() {
+            closure.flush();
+        }
+        trace!("ForwardFinalization End");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::test_util::{moved, object, MockTrace};
+
+    #[test]
+    fn test_scan_resurrects_dead_candidates() {
+        let (a, b, c) = (object(0x10000), object(0x20000), object(0x30000));
+        let mut processor = FinalizableProcessor::new();
+        processor.add(a);
+        processor.add(b);
+        processor.add(c);
+
+        let mut trace = MockTrace::new(true);
+        processor.scan_with_liveness(&mut trace, false, |o| o != b);
+        assert_eq!(processor.candidates, vec![moved(a), moved(c)]);
+        assert_eq!(processor.ready_for_finalize, vec![moved(b)]);
+        assert_eq!(trace.retained, vec![b]);
+
+        // The ready objects are kept alive (and moved) by the next GC, until the VM takes them
+        let mut trace = MockTrace::new(true);
+        processor.scan_with_liveness(&mut trace, false, |_| true);
+        assert_eq!(trace.retained, vec![moved(b)]);
+        assert_eq!(processor.get_ready_object(), Some(moved(moved(b))));
+        assert_eq!(processor.get_ready_object(), None);
+    }
+
+    #[test]
+    fn test_nursery_scan_only_checks_new_candidates() {
+        let (a, b) = (object(0x10000), object(0x20000));
+        let mut processor = FinalizableProcessor::new();
+        processor.add(a);
+        processor.scan_with_liveness(&mut MockTrace::new(false), false, |_| true);
+        processor.add(b);
+
+        // A nursery GC does not trace the mature objects, so it does not check them
+        let mut trace = MockTrace::new(true);
+        processor.scan_with_liveness(&mut trace, true, |o| o != a && o != b);
+        assert_eq!(processor.candidates, vec![a]);
+        assert_eq!(processor.ready_for_finalize, vec![moved(b)]);
+        assert_eq!(trace.retained, vec![b]);
+    }
+
+    #[test]
+    fn test_forward_after_scan() {
+        let (a, b) = (object(0x10000), object(0x20000));
+        let mut processor = FinalizableProcessor::new();
+        processor.add(a);
+        processor.add(b);
+
+        // The marking closure does not move objects, and they move after the forwarding addresses are known
+        processor.scan_with_liveness(&mut MockTrace::new(false), false, |o| o != b);
+        assert_eq!(processor.candidates, vec![a]);
+        assert_eq!(processor.ready_for_finalize, vec![b]);
+        processor.forward(&mut MockTrace::new(true), false);
+        assert_eq!(processor.candidates, vec![moved(a)]);
+        assert_eq!(processor.ready_for_finalize, vec![moved(b)]);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::util::gc_byte;
+use crate::util::pin_bit;
 /// https://github.com/JikesRVM/JikesRVM/blob/master/MMTk/src/org/mmtk/utility/ForwardingWord.java
 use crate::util::{constants, Address, ObjectReference};
 use crate::vm::ObjectModel;
@@ -40,7 +41,10 @@ pub fn spin_and_get_forwarded_object<VM: VMBinding>(
     while gc_byte & FORWARDING_MASK == BEING_FORWARDED {
         gc_byte = gc_byte::read_gc_byte::<VM>(object);
     }
-    if gc_byte & FORWARDING_MASK == FORWARDED {
+    if gc_byte & FORWARDING_MASK == FORWARDING_NOT_TRIGGERED_YET && pin_bit::is_pinned(object) {
+        // The object is pinned, so `forward_object()` left it in place
+        object
+    } else if gc_byte & FORWARDING_MASK == FORWARDED {
         let status_word = read_forwarding_word::<VM>(object);
         unsafe {
             match gc_byte_offset_in_forwarding_word::<VM>() {
@@ -66,12 +70,22 @@ pub fn spin_and_get_forwarded_object<VM: VMBinding>(
     }
 }
 
+/// Copy an object that we have started forwarding, and install the forwarding pointer.
+/// A pinned object is not copied: its forwarding bits are cleared, and the object itself is returned.
+/// The caller should check `pin_bit::is_pinned()` and mark pinned objects in place before it attempts
+/// to forward them, as an object left in place here may be traced (and returned) more than once.
 pub fn forward_object<VM: VMBinding, CC: CopyContext>(
     object: ObjectReference,
     semantics: AllocationSemantics,
     copy_context: &mut CC,
 ) -> ObjectReference {
+    if pin_bit::is_pinned(object) {
+        clear_forwarding_bits::<VM>(object);
+        return object;
+    }
     let new_object = VM::VMObjectModel::copy(object, semantics, copy_context);
+    #[cfg(feature = "global_alloc_bit")]
+    crate::util::alloc_bit::set_alloc_bit(new_object);
     match gc_byte_offset_in_forwarding_word::<VM>() {
         Some(fw_offset) => {
             write_forwarding_word::<VM>(
@@ -88,6 +102,31 @@ pub fn forward_object<VM: VMBinding, CC: CopyContext>(
     new_object
 }
 
+/// Like `forward_object()`, but the object stays where it is if the copy context cannot allocate
+/// a copy. Returns `None` in that case, and leaves the forwarding bits for the caller to clear.
+/// This does not use `ObjectModel::copy()`, as that cannot fail.
+pub fn try_forward_object<VM: VMBinding, CC: CopyContext>(
+    object: ObjectReference,
+    semantics: AllocationSemantics,
+    copy_context: &mut CC,
+) -> Option<ObjectReference> {
+    debug_assert!(!pin_bit::is_pinned(object));
+    let bytes = VM::VMObjectModel::get_size_when_copied(object);
+    let align = VM::VMObjectModel::get_align_when_copied(object);
+    let offset = VM::VMObjectModel::get_align_offset_when_copied(object);
+    let region = copy_context.alloc_copy(object, bytes, align, offset, semantics);
+    if region.is_zero() {
+        return None;
+    }
+    let new_object = VM::VMObjectModel::get_reference_when_copied_to(object, region);
+    VM::VMObjectModel::copy_to(object, new_object, region);
+    copy_context.post_copy(new_object, unsafe { Address::zero() }, bytes, semantics);
+    #[cfg(feature = "global_alloc_bit")]
+    crate::util::alloc_bit::set_alloc_bit(new_object);
+    set_forwarding_pointer::<VM>(object, new_object);
+    Some(new_object)
+}
+
 pub fn set_forwarding_pointer<VM: VMBinding>(object: ObjectReference, ptr: ObjectReference) {
     match gc_byte_offset_in_forwarding_word::<VM>() {
         Some(fw_offset) => {
@@ -104,6 +143,33 @@ pub fn set_forwarding_pointer<VM: VMBinding>(object: ObjectReference, ptr: Objec
     }
 }
 
+/// Get the new address of an object, if it was forwarded in the current GC.
+pub fn get_forwarded_object<VM: VMBinding>(object: ObjectReference) -> Option<ObjectReference> {
+    let gc_byte = gc_byte::read_gc_byte::<VM>(object);
+    if gc_byte & FORWARDING_MASK == FORWARDED {
+        Some(spin_and_get_forwarded_object::<VM>(object, gc_byte))
+    } else {
+        None
+    }
+}
+
+/// Wait for another thread to decide whether to copy an object. Returns the copy, or the object
+/// itself if that thread left it in place and cleared the forwarding bits.
+pub fn spin_and_get_forwarded_or_retained_object<VM: VMBinding>(
+    object: ObjectReference,
+    gc_byte: u8,
+) -> ObjectReference {
+    let mut gc_byte = gc_byte;
+    while gc_byte & FORWARDING_MASK == BEING_FORWARDED {
+        gc_byte = gc_byte::read_gc_byte::<VM>(object);
+    }
+    if gc_byte & FORWARDING_MASK == FORWARDING_NOT_TRIGGERED_YET {
+        object
+    } else {
+        spin_and_get_forwarded_object::<VM>(object, gc_byte)
+    }
+}
+
 pub fn is_forwarded<VM: VMBinding>(object: ObjectReference) -> bool {
     gc_byte::read_gc_byte::<VM>(object) & FORWARDING_MASK == FORWARDED
 }
@@ -1,3 +1,4 @@
+use crate::plan::global::CORE_GLOBAL_SIDE_METADATA_PER_CHUNK;
 use crate::util::side_metadata::*;
 use crate::util::ObjectReference;
 use crate::vm::ObjectModel;
@@ -8,7 +9,7 @@ use super::constants;
 
 const SIDE_GC_BYTE_SPEC: SideMetadataSpec = SideMetadataSpec {
     scope: SideMetadataScope::Global,
-    offset: 0,
+    offset: CORE_GLOBAL_SIDE_METADATA_PER_CHUNK,
     log_num_of_bits: 1,
     log_min_obj_size: constants::LOG_BYTES_IN_WORD as usize,
 };
@@ -0,0 +1,341 @@
+//! A log with a record for each GC, for offline analysis of the collections (similar to `-Xlog:gc` in HotSpot).
+//! The log is written to the file set by the `gc_log_file` option, as newline-delimited JSON. A record looks like:
+//!
+//! ```text
+//! {"gc": 3, "plan": "GenCopy", "kind": "nursery", "trigger": "space_full", "trigger_space": "nursery",
+//!  "emergency": false, "start_us": 1608422400000000, "end_us": 1608422400001234, "pause_us": 1234,
+//!  "spaces": {"nursery": {"pages_before": 512, "pages_after": 0, "bytes_before": 2097152, "bytes_after": 0,
+//!                         "resident_pages_before": 512, "resident_pages_after": 512}, ...},
+//!  "stages": {"Prepare": 120, "Closure": 1000, "Release": 114}}
+//! ```
+//!
+//! `pages_*` are the reserved pages of the space, `resident_pages_*` are its pages that are backed by
+//! physical memory (which only drop below the committed pages with the `decommit_policy` option), and `stages`
+//! are the time (in microseconds) from when each work bucket was opened until the next one was opened
+//! (or the GC finished).
+
+use crate::plan::Plan;
+use crate::scheduler::WorkBucketStage;
+use crate::util::constants::BYTES_IN_PAGE;
+use crate::util::heap::PageResource;
+use crate::util::statistics::sink::json_string;
+use crate::vm::VMBinding;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Why a GC was triggered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GCTrigger {
+    /// The VM requested a GC (`memory_manager::handle_user_collection_request()`).
+    User,
+    /// The `stress_factor` bytes were allocated since the last GC.
+    Stress,
+    /// An allocation failed in the given space.
+    SpaceFull(&'static str),
+    /// The plan decided the heap (or the nursery) is full.
+    HeapFull,
+    /// The initial pause of a concurrent collection.
+    Concurrent,
+}
+
+impl GCTrigger {
+    fn name(&self) -> &'static str {
+        match self {
+            GCTrigger::User => "user",
+            GCTrigger::Stress => "stress",
+            GCTrigger::SpaceFull(_) => "space_full",
+            GCTrigger::HeapFull => "heap_full",
+            GCTrigger::Concurrent => "concurrent",
+        }
+    }
+}
+
+/// The state recorded at the start of a GC.
+struct GCStart {
+    gc: usize,
+    trigger: Option<GCTrigger>,
+    start_time: SystemTime,
+    start: Instant,
+    /// The reserved and the resident pages of each space
+    pages_before: Vec<(&'static str, usize, usize)>,
+}
+
+/// The record of a finished GC, as written to the log.
+struct GCRecord<'a> {
+    gc: usize,
+    plan: &'a str,
+    kind: &'a str,
+    trigger: Option<GCTrigger>,
+    emergency: bool,
+    start_us: u128,
+    pause: Duration,
+    /// The name, the reserved pages and the resident pages of each space, before and after the GC
+    pages_before: &'a [(&'static str, usize, usize)],
+    pages_after: &'a [(&'static str, usize, usize)],
+    stages: &'a [(WorkBucketStage, Duration)],
+}
+
+impl GCRecord<'_> {
+    /// Format the record as one line of JSON.
+    fn to_json(&self) -> String {
+        let mut record = String::new();
+        record.push_str(&format!(
+            "{{\"gc\": {}, \"plan\": {}, \"kind\": {}",
+            self.gc,
+            json_string(self.plan),
+            json_string(self.kind)
+        ));
+        match self.trigger {
+            Some(trigger) => {
+                record.push_str(&format!(", \"trigger\": {}", json_string(trigger.name())));
+                if let GCTrigger::SpaceFull(space) = trigger {
+                    record.push_str(&format!(", \"trigger_space\": {}", json_string(space)));
+                }
+            }
+            None => record.push_str(", \"trigger\": null"),
+        }
+        record.push_str(&format!(
+            ", \"emergency\": {}, \"start_us\": {}, \"end_us\": {}, \"pause_us\": {}",
+            self.emergency,
+            self.start_us,
+            self.start_us + self.pause.as_micros(),
+            self.pause.as_micros()
+        ));
+        record.push_str(", \"spaces\": {");
+        for (i, ((name, before, resident_before), (_, after, resident_after))) in self
+            .pages_before
+            .iter()
+            .zip(self.pages_after.iter())
+            .enumerate()
+        {
+            if i > 0 {
+                record.push_str(", ");
+            }
+            record.push_str(&format!(
+                "{}: {{\"pages_before\": {}, \"pages_after\": {}, \"bytes_before\": {}, \"bytes_after\": {}, \
+                 \"resident_pages_before\": {}, \"resident_pages_after\": {}}}",
+                json_string(name),
+                before,
+                after,
+                before * BYTES_IN_PAGE,
+                after * BYTES_IN_PAGE,
+                resident_before,
+                resident_after
+            ));
+        }
+        record.push_str("}, \"stages\": {");
+        for (i, (stage, duration)) in self.stages.iter().enumerate() {
+            if i > 0 {
+                record.push_str(", ");
+            }
+            record.push_str(&format!(
+                "{}: {}",
+                json_string(&format!("{:?}", stage)),
+                duration.as_micros()
+            ));
+        }
+        record.push_str("}}");
+        record
+    }
+}
+
+pub struct GCLog {
+    trigger: Mutex<Option<GCTrigger>>,
+    current: Mutex<Option<GCStart>>,
+    gc_count: AtomicUsize,
+    out: Mutex<Option<BufWriter<File>>>,
+    /// Set if the log file could not be created or written, so we stop trying.
+    failed: AtomicBool,
+}
+
+impl GCLog {
+    pub fn new() -> Self {
+        Self {
+            trigger: Mutex::new(None),
+            current: Mutex::new(None),
+            gc_count: AtomicUsize::new(0),
+            out: Mutex::new(None),
+            failed: AtomicBool::new(false),
+        }
+    }
+
+    /// Record the reason for the next GC, replacing any reason recorded before.
+    pub fn set_trigger(&self, trigger: GCTrigger) {
+        *self.trigger.lock().unwrap() = Some(trigger);
+    }
+
+    /// Record the reason for the next GC, unless a reason is already recorded.
+    pub fn set_trigger_if_unset(&self, trigger: GCTrigger) {
+        let mut current = self.trigger.lock().unwrap();
+        if current.is_none() {
+            *current = Some(trigger);
+        }
+    }
+
+    fn is_enabled<VM: VMBinding>(&self, plan: &dyn Plan<VM = VM>) -> bool {
+        !plan.options().gc_log_file.is_empty() && !self.failed.load(Ordering::Relaxed)
+    }
+
+    /// Called at the start of each GC, before the plan schedules its work.
+    pub fn gc_started<VM: VMBinding>(&self, plan: &dyn Plan<VM = VM>) {
+        let gc = self.gc_count.fetch_add(1, Ordering::SeqCst);
+        let trigger = self.trigger.lock().unwrap().take();
+        if !self.is_enabled(plan) {
+            return;
+        }
+        let pages_before = Self::space_pages(plan);
+        *self.current.lock().unwrap() = Some(GCStart {
+            gc,
+            trigger,
+            start_time: SystemTime::now(),
+            start: Instant::now(),
+            pages_before,
+        });
+    }
+
+    /// Called at the end of each GC, before the mutators are resumed. `kind` is the kind of the
+    /// collection (such as `nursery` or `full`), and `stages` the time spent in each work bucket.
+    pub fn gc_finished<VM: VMBinding>(
+        &self,
+        plan: &dyn Plan<VM = VM>,
+        kind: &str,
+        stages: &[(WorkBucketStage, Duration)],
+    ) {
+        let start = match self.current.lock().unwrap().take() {
+            Some(start) => start,
+            None => return,
+        };
+        if !self.is_enabled(plan) {
+            return;
+        }
+        let pause = start.start.elapsed();
+        let pages_after = Self::space_pages(plan);
+        let record = GCRecord {
+            gc: start.gc,
+            plan: &format!("{:?}", plan.options().plan),
+            kind,
+            trigger: start.trigger,
+            emergency: plan.is_emergency_collection(),
+            start_us: start
+                .start_time
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_micros(),
+            pause,
+            pages_before: &start.pages_before,
+            pages_after: &pages_after,
+            stages,
+        }
+        .to_json();
+
+        if let Err(e) = self.write_record(&plan.options().gc_log_file, &record) {
+            warn!("Failed to write the GC log: {}", e);
+            self.failed.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn space_pages<VM: VMBinding>(plan: &dyn Plan<VM = VM>) -> Vec<(&'static str, usize, usize)> {
+        let mut pages = vec![];
+        plan.for_each_space(&mut |space| {
+            pages.push((
+                space.get_name(),
+                space.reserved_pages(),
+                space.resident_pages(),
+            ))
+        });
+        pages
+    }
+
+    fn write_record(&self, file: &str, record: &str) -> io::Result<()> {
+        let mut out = self.out.lock().unwrap();
+        if out.is_none() {
+            *out = Some(BufWriter::new(File::create(file)?));
+        }
+        let out = out.as_mut().unwrap();
+        writeln!(out, "{}", record)?;
+        out.flush()
+    }
+}
+
+impl Default for GCLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_to_json() {
+        let record = GCRecord {
+            gc: 3,
+            plan: "GenCopy",
+            kind: "nursery",
+            trigger: Some(GCTrigger::SpaceFull("nursery")),
+            emergency: false,
+            start_us: 1000,
+            pause: Duration::from_micros(234),
+            pages_before: &[("nursery", 2, 2), ("los", 1, 1)],
+            pages_after: &[("nursery", 0, 2), ("los", 1, 0)],
+            stages: &[
+                (WorkBucketStage::Prepare, Duration::from_micros(12)),
+                (WorkBucketStage::Closure, Duration::from_micros(200)),
+            ],
+        };
+        assert_eq!(
+            record.to_json(),
+            format!(
+                "{{\"gc\": 3, \"plan\": \"GenCopy\", \"kind\": \"nursery\", \"trigger\": \"space_full\", \
+                 \"trigger_space\": \"nursery\", \"emergency\": false, \"start_us\": 1000, \"end_us\": 1234, \
+                 \"pause_us\": 234, \"spaces\": {{\
+                 \"nursery\": {{\"pages_before\": 2, \"pages_after\": 0, \"bytes_before\": {}, \"bytes_after\": 0, \
+                 \"resident_pages_before\": 2, \"resident_pages_after\": 2}}, \
+                 \"los\": {{\"pages_before\": 1, \"pages_after\": 1, \"bytes_before\": {}, \"bytes_after\": {}, \
+                 \"resident_pages_before\": 1, \"resident_pages_after\": 0}}}}, \
+                 \"stages\": {{\"Prepare\": 12, \"Closure\": 200}}}}",
+                2 * BYTES_IN_PAGE,
+                BYTES_IN_PAGE,
+                BYTES_IN_PAGE
+            )
+        );
+    }
+
+    #[test]
+    fn test_record_without_trigger() {
+        let record = GCRecord {
+            gc: 0,
+            plan: "NoGC",
+            kind: "full",
+            trigger: None,
+            emergency: true,
+            start_us: 0,
+            pause: Duration::from_micros(5),
+            pages_before: &[],
+            pages_after: &[],
+            stages: &[],
+        };
+        assert_eq!(
+            record.to_json(),
+            "{\"gc\": 0, \"plan\": \"NoGC\", \"kind\": \"full\", \"trigger\": null, \"emergency\": true, \
+             \"start_us\": 0, \"end_us\": 5, \"pause_us\": 5, \"spaces\": {}, \"stages\": {}}"
+        );
+    }
+
+    #[test]
+    fn test_write_records() {
+        let file = std::env::temp_dir().join(format!("mmtk-gc-log-{}.ndjson", std::process::id()));
+        let file = file.to_str().unwrap();
+        let log = GCLog::new();
+        log.write_record(file, "{\"gc\": 0}").unwrap();
+        log.write_record(file, "{\"gc\": 1}").unwrap();
+        let content = std::fs::read_to_string(file).unwrap();
+        std::fs::remove_file(file).unwrap();
+        // One record per line
+        assert_eq!(content, "{\"gc\": 0}\n{\"gc\": 1}\n");
+    }
+}
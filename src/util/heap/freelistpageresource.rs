@@ -316,6 +316,7 @@ impl<VM: VMBinding> FreeListPageResource<VM> {
             sync.pages_currently_on_freelist += pages as usize;
             freed
         };
+        self.decommit_pages(first, pages as _);
         if !self.common.contiguous {
             // only discontiguous spaces use chunks
             me.release_free_chunks(first, freed as _);
@@ -0,0 +1,263 @@
+use crate::util::constants::BYTES_IN_MBYTE;
+use crate::util::conversions::{bytes_to_pages_up, pages_to_bytes, raw_align_up};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The heap size passed to `memory_manager::gc_init()`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum HeapSize {
+    /// The heap always has this many bytes.
+    Fixed(usize),
+    /// The heap starts with `min` bytes, and is resized between `min` and `max` bytes after each GC.
+    Dynamic {
+        min: usize,
+        max: usize,
+        policy: HeapGrowthPolicy,
+    },
+}
+
+impl HeapSize {
+    pub fn initial_bytes(&self) -> usize {
+        match *self {
+            HeapSize::Fixed(bytes) => bytes,
+            HeapSize::Dynamic { min, .. } => min,
+        }
+    }
+
+    pub fn max_bytes(&self) -> usize {
+        match *self {
+            HeapSize::Fixed(bytes) => bytes,
+            HeapSize::Dynamic { max, .. } => max,
+        }
+    }
+}
+
+/// How a dynamic heap is resized. Both policies follow MMTk's Java `HeapGrowthManager`: the heap grows
+/// when the GC load (the fraction of time spent in GC since the last resize) is high and a large fraction of
+/// the heap is live after GC, and shrinks when both are low.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HeapGrowthPolicy {
+    /// The growth function for non-generational plans.
+    GCLoad,
+    /// The growth function for generational plans, which grows the heap at a lower GC load,
+    /// as each nursery GC only takes a short time.
+    GenerationalGCLoad,
+}
+
+/// The live ratios of the columns in a growth function.
+const LIVE_RATIOS: [f64; 6] = [0.00, 0.10, 0.30, 0.60, 0.80, 1.00];
+
+/// The heap change ratio for each GC load (row) and live ratio (column).
+const HEAP_CHANGE_RATIOS: [[f64; 6]; 7] = [
+    [0.90, 0.90, 0.95, 1.00, 1.00, 1.00],
+    [0.90, 0.90, 0.95, 1.00, 1.00, 1.00],
+    [0.95, 0.95, 1.00, 1.00, 1.00, 1.00],
+    [1.00, 1.00, 1.10, 1.15, 1.20, 1.20],
+    [1.00, 1.00, 1.20, 1.25, 1.35, 1.30],
+    [1.00, 1.00, 1.25, 1.30, 1.50, 1.50],
+    [1.00, 1.00, 1.25, 1.30, 1.50, 1.50],
+];
+
+impl HeapGrowthPolicy {
+    /// The GC loads of the rows in the growth function.
+    fn gc_loads(self) -> [f64; 7] {
+        match self {
+            HeapGrowthPolicy::GCLoad => [0.00, 0.02, 0.05, 0.15, 0.30, 0.50, 1.00],
+            HeapGrowthPolicy::GenerationalGCLoad => [0.00, 0.01, 0.02, 0.07, 0.15, 0.40, 1.00],
+        }
+    }
+
+    /// Interpolate the growth function. Both arguments are clamped to `[0, 1]`.
+    pub fn heap_change_ratio(self, gc_load: f64, live_ratio: f64) -> f64 {
+        let gc_loads = self.gc_loads();
+        let (row, row_fraction) = find_interval(&gc_loads, gc_load.max(0.0).min(1.0));
+        let (col, col_fraction) = find_interval(&LIVE_RATIOS, live_ratio.max(0.0).min(1.0));
+        let ratio_at = |r: usize| {
+            HEAP_CHANGE_RATIOS[r][col]
+                + col_fraction * (HEAP_CHANGE_RATIOS[r][col + 1] - HEAP_CHANGE_RATIOS[r][col])
+        };
+        ratio_at(row) + row_fraction * (ratio_at(row + 1) - ratio_at(row))
+    }
+}
+
+/// Find `i` such that `points[i] <= value <= points[i + 1]`, and where `value` lies between the two (from 0 to 1).
+fn find_interval(points: &[f64], value: f64) -> (usize, f64) {
+    let mut i = 0;
+    while i + 2 < points.len() && points[i + 1] < value {
+        i += 1;
+    }
+    (i, (value - points[i]) / (points[i + 1] - points[i]))
+}
+
+struct GrowthState {
+    /// The time spent in GC since the last resize.
+    gc_time: Duration,
+    /// When the current GC started.
+    gc_start: Option<Instant>,
+    last_resize: Instant,
+}
+
+/// Decides the size of a dynamic heap after each GC. The heap size is kept in `HeapMeta::total_pages`.
+pub struct HeapGrowthManager {
+    heap_size: Mutex<HeapSize>,
+    state: Mutex<GrowthState>,
+}
+
+impl HeapGrowthManager {
+    pub fn new() -> Self {
+        Self {
+            heap_size: Mutex::new(HeapSize::Fixed(0)),
+            state: Mutex::new(GrowthState {
+                gc_time: Duration::default(),
+                gc_start: None,
+                last_resize: Instant::now(),
+            }),
+        }
+    }
+
+    /// Set the heap size. This is called once by `memory_manager::gc_init()`.
+    pub fn init(&self, heap_size: HeapSize) {
+        if let HeapSize::Dynamic { min, max, .. } = heap_size {
+            assert!(
+                min <= max,
+                "The min heap size is larger than the max heap size"
+            );
+        }
+        *self.heap_size.lock().unwrap() = heap_size;
+        self.state.lock().unwrap().last_resize = Instant::now();
+    }
+
+    pub fn heap_size(&self) -> HeapSize {
+        *self.heap_size.lock().unwrap()
+    }
+
+    /// The most pages the heap may grow to.
+    pub fn max_heap_pages(&self) -> usize {
+        bytes_to_pages_up(self.heap_size().max_bytes())
+    }
+
+    pub fn gc_started(&self) {
+        self.state.lock().unwrap().gc_start = Some(Instant::now());
+    }
+
+    pub fn gc_finished(&self) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(start) = state.gc_start.take() {
+            state.gc_time += start.elapsed();
+        }
+    }
+
+    /// Called after a GC. Returns the new heap size in pages if the heap should be resized.
+    pub fn consider_heap_size(&self, total_pages: usize, reserved_pages: usize) -> Option<usize> {
+        let (min, max, policy) = match self.heap_size() {
+            HeapSize::Fixed(_) => return None,
+            HeapSize::Dynamic { min, max, policy } => (min, max, policy),
+        };
+        let mut state = self.state.lock().unwrap();
+        let gc_load =
+            state.gc_time.as_secs_f64() / state.last_resize.elapsed().as_secs_f64().max(1e-9);
+        let live_ratio = reserved_pages as f64 / total_pages as f64;
+        let ratio = policy.heap_change_ratio(gc_load, live_ratio);
+
+        let reserved_bytes = pages_to_bytes(reserved_pages);
+        let new_bytes = ((pages_to_bytes(total_pages) as f64 * ratio) as usize).max(reserved_bytes);
+        // Round up to the next megabyte, and stay within the bounds
+        let new_bytes = raw_align_up(new_bytes, BYTES_IN_MBYTE).max(min).min(max);
+        let new_pages = bytes_to_pages_up(new_bytes);
+        trace!(
+            "consider_heap_size: gc_load = {}, live_ratio = {}, ratio = {}, {} -> {} pages",
+            gc_load,
+            live_ratio,
+            ratio,
+            total_pages,
+            new_pages
+        );
+        if new_pages == total_pages {
+            return None;
+        }
+        state.gc_time = Duration::default();
+        state.last_resize = Instant::now();
+        Some(new_pages)
+    }
+}
+
+impl Default for HeapGrowthManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heap_change_ratio_at_grid_points() {
+        let policy = HeapGrowthPolicy::GCLoad;
+        assert!((policy.heap_change_ratio(0.0, 0.0) - 0.90).abs() < 1e-9);
+        assert!((policy.heap_change_ratio(0.15, 0.30) - 1.10).abs() < 1e-9);
+        assert!((policy.heap_change_ratio(1.0, 1.0) - 1.50).abs() < 1e-9);
+        let policy = HeapGrowthPolicy::GenerationalGCLoad;
+        assert!((policy.heap_change_ratio(0.07, 0.30) - 1.10).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_heap_change_ratio_interpolates_and_clamps() {
+        let policy = HeapGrowthPolicy::GCLoad;
+        // Halfway between 1.10 and 1.15
+        assert!((policy.heap_change_ratio(0.15, 0.45) - 1.125).abs() < 1e-9);
+        // Halfway between 1.00 and 1.10
+        assert!((policy.heap_change_ratio(0.10, 0.30) - 1.05).abs() < 1e-9);
+        assert!(
+            (policy.heap_change_ratio(2.0, 1.5) - policy.heap_change_ratio(1.0, 1.0)).abs() < 1e-9
+        );
+        assert!(
+            (policy.heap_change_ratio(-1.0, 0.0) - policy.heap_change_ratio(0.0, 0.0)).abs() < 1e-9
+        );
+    }
+
+    #[test]
+    fn test_fixed_heap_is_never_resized() {
+        let manager = HeapGrowthManager::new();
+        manager.init(HeapSize::Fixed(1 << 30));
+        assert_eq!(manager.consider_heap_size(1000, 1000), None);
+    }
+
+    #[test]
+    fn test_dynamic_heap_stays_within_bounds() {
+        let manager = HeapGrowthManager::new();
+        let min = 60 * BYTES_IN_MBYTE;
+        let max = 128 * BYTES_IN_MBYTE;
+        manager.init(HeapSize::Dynamic {
+            min,
+            max,
+            policy: HeapGrowthPolicy::GCLoad,
+        });
+        let min_pages = bytes_to_pages_up(min);
+        let max_pages = bytes_to_pages_up(max);
+        // Everything is live, so the heap must grow, but not beyond the max
+        assert_eq!(manager.consider_heap_size(max_pages, max_pages * 2), None);
+        assert_eq!(
+            manager.consider_heap_size(min_pages, max_pages * 2),
+            Some(max_pages)
+        );
+        // Nothing is live, and no time is spent in GC, so the heap shrinks (by 10%), but not below the min
+        let pages_64m = bytes_to_pages_up(64 * BYTES_IN_MBYTE);
+        assert_eq!(manager.consider_heap_size(pages_64m, 0), Some(min_pages));
+    }
+
+    #[test]
+    fn test_dynamic_heap_is_stable_at_ratio_one() {
+        let manager = HeapGrowthManager::new();
+        manager.init(HeapSize::Dynamic {
+            min: 60 * BYTES_IN_MBYTE,
+            max: 128 * BYTES_IN_MBYTE,
+            policy: HeapGrowthPolicy::GCLoad,
+        });
+        // No time is spent in GC, and 60% of the heap is live, so the ratio is 1.0 and the heap keeps its size
+        let pages_100m = bytes_to_pages_up(100 * BYTES_IN_MBYTE);
+        let pages_60m = bytes_to_pages_up(60 * BYTES_IN_MBYTE);
+        assert_eq!(manager.consider_heap_size(pages_100m, pages_60m), None);
+        assert_eq!(manager.consider_heap_size(pages_100m, pages_60m), None);
+    }
+}
@@ -43,6 +43,10 @@ pub trait Map: Sized {
 
     fn is_finalized(&self) -> bool;
 
+    /// Forget the spaces in the address range from `start` to `end`, so another MMTk instance can create
+    /// its spaces there. This is called when an instance is dropped.
+    fn release_range(&self, start: Address, end: Address);
+
     fn get_discontig_freelist_pr_ordinal(&self, pr: &CommonFreeListPageResource) -> usize;
 
     fn get_descriptor_for_address(&self, address: Address) -> SpaceDescriptor;
@@ -212,6 +212,11 @@ impl Map for Map32 {
         self.finalized
     }
 
+    fn release_range(&self, _start: Address, _end: Address) {
+        // The discontiguous spaces share one range of chunks, so the map can not be reused by
+        // another instance. Only one instance is created on 32-bit (see `InstanceRegistry`).
+    }
+
     fn get_discontig_freelist_pr_ordinal(&self, pr: &CommonFreeListPageResource) -> usize {
         // This is only called during creating a page resource/space/plan/mmtk instance, which is single threaded.
         let self_mut: &mut Self = unsafe { self.mut_self() };
@@ -9,13 +9,22 @@ use crate::util::heap::space_descriptor::SpaceDescriptor;
 use crate::util::raw_memory_freelist::RawMemoryFreeList;
 use crate::util::Address;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
 
 const NON_MAP_FRACTION: f64 = 1.0 - 8.0 / 4096.0;
 
 pub struct Map64 {
     fl_page_resources: Vec<Option<&'static CommonFreeListPageResource>>,
     fl_map: Vec<Option<&'static RawMemoryFreeList>>,
+    /// Whether the free list of each space is booted. Each MMTk instance boots the map when it is initialized,
+    /// and the free lists of the spaces of the other instances must not be booted again.
+    fl_booted: Vec<bool>,
+    /// Whether the free list page resource of each space is resized to its free list. As with `fl_booted`,
+    /// each instance only finalizes its own spaces.
+    fl_finalized: Vec<bool>,
     finalized: bool,
+    /// Serializes the updates from different MMTk instances, which may create and boot their spaces concurrently.
+    sync: Mutex<()>,
     descriptor_map: Vec<SpaceDescriptor>,
     base_address: Vec<Address>,
     high_water: Vec<Address>,
@@ -46,7 +55,10 @@ impl Map for Map64 {
             base_address,
             fl_page_resources: vec![None; MAX_SPACES],
             fl_map: vec![None; MAX_SPACES],
+            fl_booted: vec![false; MAX_SPACES],
+            fl_finalized: vec![false; MAX_SPACES],
             finalized: false,
+            sync: Mutex::new(()),
             cumulative_committed_pages: AtomicUsize::new(0),
         }
     }
@@ -54,9 +66,8 @@ impl Map for Map64 {
     fn insert(&self, start: Address, extent: usize, descriptor: SpaceDescriptor) {
         debug_assert!(Self::is_space_start(start));
         debug_assert!(extent <= SPACE_SIZE_64);
-        // Each space will call this on exclusive address ranges. It is fine to mutate the descriptor map,
-        // as each space will update different indices.
-        let self_mut = unsafe { self.mut_self() };
+        // Each space will call this on exclusive address ranges, but the spaces of other instances may be created at the same time.
+        let (_sync, self_mut) = self.mut_self_with_sync();
         let index = Self::space_index(start).unwrap();
         self_mut.descriptor_map[index] = descriptor;
     }
@@ -72,8 +83,8 @@ impl Map for Map64 {
         mut units: usize,
         grain: i32,
     ) -> Box<Self::FreeList> {
-        // This is only called during creating a page resource/space/plan/mmtk instance, which is single threaded.
-        let self_mut = unsafe { self.mut_self() };
+        // This is called when creating a page resource/space/plan/mmtk instance. Other instances may be created at the same time.
+        let (_sync, self_mut) = self.mut_self_with_sync();
         let start = pr.get_start();
         let index = Self::space_index(start).unwrap();
 
@@ -161,35 +172,56 @@ impl Map for Map64 {
     }
 
     fn boot(&self) {
-        // This is only called during boot process by a single thread calling gc_init().
-        // It is fine to get a mutable reference.
-        let self_mut: &mut Self = unsafe { self.mut_self() };
+        // This is called by gc_init(). Other instances may be booted at the same time.
+        let (_sync, self_mut) = self.mut_self_with_sync();
         for pr in 0..MAX_SPACES {
             if let Some(fl) = self_mut.fl_map[pr] {
+                if self_mut.fl_booted[pr] {
+                    continue;
+                }
                 #[allow(clippy::cast_ref_to_mut)]
                 let fl_mut: &mut RawMemoryFreeList = unsafe { &mut *(fl as *const _ as *mut _) };
                 fl_mut.grow_freelist(0);
+                self_mut.fl_booted[pr] = true;
             }
         }
     }
 
     fn finalize_static_space_map(&self, _from: Address, _to: Address) {
-        // This is only called during boot process by a single thread calling gc_init().
-        // It is fine to get a mutable reference.
-        let self_mut: &mut Self = unsafe { self.mut_self() };
+        // This is called by gc_init(). Other instances may be booted at the same time, and their
+        // page resources may already be in use, so we only finalize the spaces that are not finalized yet.
+        let (_sync, self_mut) = self.mut_self_with_sync();
         for pr in 0..MAX_SPACES {
             if let Some(fl) = self_mut.fl_page_resources[pr] {
+                if self_mut.fl_finalized[pr] {
+                    continue;
+                }
                 #[allow(clippy::cast_ref_to_mut)]
                 let fl_mut: &mut CommonFreeListPageResource =
                     unsafe { &mut *(fl as *const _ as *mut _) };
                 fl_mut.resize_freelist(conversions::chunk_align_up(
-                    self.fl_map[pr].unwrap().get_limit(),
+                    self_mut.fl_map[pr].unwrap().get_limit(),
                 ));
+                self_mut.fl_finalized[pr] = true;
             }
         }
         self_mut.finalized = true;
     }
 
+    fn release_range(&self, start: Address, end: Address) {
+        let (_sync, self_mut) = self.mut_self_with_sync();
+        for index in Self::space_index(start).unwrap()..Self::space_index(end).unwrap() {
+            let base = unsafe { Address::from_usize(index << LOG_SPACE_SIZE_64) };
+            self_mut.descriptor_map[index] = SpaceDescriptor::UNINITIALIZED;
+            self_mut.fl_page_resources[index] = None;
+            self_mut.fl_map[index] = None;
+            self_mut.fl_booted[index] = false;
+            self_mut.fl_finalized[index] = false;
+            self_mut.high_water[index] = base;
+            self_mut.base_address[index] = base;
+        }
+    }
+
     fn is_finalized(&self) -> bool {
         self.finalized
     }
@@ -222,6 +254,11 @@ impl Map64 {
         &mut *(self as *const _ as *mut _)
     }
 
+    fn mut_self_with_sync(&self) -> (MutexGuard<()>, &mut Self) {
+        let guard = self.sync.lock().unwrap();
+        (guard, unsafe { self.mut_self() })
+    }
+
     fn space_index(addr: Address) -> Option<usize> {
         if addr > HEAP_END {
             return None;
@@ -1,6 +1,7 @@
 use super::layout::vm_layout_constants::BYTES_IN_CHUNK;
 use crate::policy::space::required_chunks;
 use crate::util::address::Address;
+use crate::util::constants::BYTES_IN_PAGE;
 use crate::util::conversions::*;
 use std::sync::{Mutex, MutexGuard};
 
@@ -257,6 +258,60 @@ impl<VM: VMBinding> MonotonePageResource<VM> {
         drop(guard);
     }
 
+    /// The address of the next page to be allocated.
+    pub fn cursor(&self) -> Address {
+        self.sync.lock().unwrap().cursor
+    }
+
+    /// The start and end of each region of memory allocated since the page resource was last reset.
+    /// The end of a region may include some pages that were never handed out.
+    pub fn allocated_regions(&self) -> Vec<(Address, Address)> {
+        let guard = self.sync.lock().unwrap();
+        let mut regions = vec![];
+        if self.common().contiguous {
+            let start = match guard.conditional {
+                MonotonePageResourceConditional::Contiguous { start: _start, .. } => _start,
+                _ => unreachable!(),
+            };
+            if guard.cursor > start {
+                regions.push((start, guard.cursor));
+            }
+        } else if !guard.cursor.is_zero() {
+            // The current chunk is the head of the space's list of regions. See release_pages().
+            regions.push((guard.current_chunk, guard.cursor));
+            let mut region = self
+                .vm_map()
+                .get_next_contiguous_region(guard.current_chunk);
+            while !region.is_zero() {
+                regions.push((
+                    region,
+                    region + self.vm_map().get_contiguous_region_size(region),
+                ));
+                region = self.vm_map().get_next_contiguous_region(region);
+            }
+        }
+        regions
+    }
+
+    /// Move the cursor of a contiguous page resource back to `top` (rounded up to a page),
+    /// releasing all the pages above it. Compacting spaces use this once they have slid
+    /// their objects down.
+    ///
+    /// # Safety
+    /// All the memory above `top` must be free.
+    pub unsafe fn reset_cursor(&self, top: Address) {
+        debug_assert!(self.common().contiguous);
+        let mut guard = self.sync.lock().unwrap();
+        let cursor = top.align_up(BYTES_IN_PAGE);
+        debug_assert!(cursor <= guard.cursor);
+        let pages = bytes_to_pages(guard.cursor - cursor);
+        self.common().release_reserved(pages);
+        self.common().release_committed(pages);
+        self.release_pages_extent(cursor, guard.cursor - cursor);
+        guard.cursor = cursor;
+        guard.current_chunk = chunk_align_down(cursor);
+    }
+
     /*/**
     * Release all pages associated with this page resource, optionally
     * zeroing on release and optionally memory protecting on release.
@@ -295,10 +350,12 @@ impl<VM: VMBinding> MonotonePageResource<VM> {
     unsafe fn release_pages(&self, guard: &mut MutexGuard<MonotonePageResourceSync>) {
         // TODO: concurrent zeroing
         if self.common().contiguous {
-            guard.cursor = match guard.conditional {
+            let start = match guard.conditional {
                 MonotonePageResourceConditional::Contiguous { start: _start, .. } => _start,
                 _ => unreachable!(),
             };
+            self.release_pages_extent(start, guard.cursor - start);
+            guard.cursor = start;
         } else if !guard.cursor.is_zero() {
             let bytes = guard.cursor - guard.current_chunk;
             self.release_pages_extent(guard.current_chunk, bytes);
@@ -314,9 +371,10 @@ impl<VM: VMBinding> MonotonePageResource<VM> {
         }
     }
 
-    fn release_pages_extent(&self, _first: Address, bytes: usize) {
+    fn release_pages_extent(&self, first: Address, bytes: usize) {
         let pages = crate::util::conversions::bytes_to_pages(bytes);
         debug_assert!(bytes == crate::util::conversions::pages_to_bytes(pages));
+        self.decommit_pages(first, pages);
         // FIXME ZERO_PAGES_ON_RELEASE
         // FIXME Options.protectOnRelease
         // FIXME VM.events.tracePageReleased
@@ -1,5 +1,8 @@
 use crate::policy::space::Space;
 use crate::util::address::Address;
+use crate::util::conversions::pages_to_bytes;
+use crate::util::memory;
+use crate::util::options::DecommitPolicy;
 use crate::util::OpaquePointer;
 use crate::vm::ActivePlan;
 
@@ -79,9 +82,12 @@ pub trait PageResource<VM: VMBinding>: 'static {
     fn commit_pages(&self, reserved_pages: usize, actual_pages: usize, tls: OpaquePointer) {
         let delta = actual_pages - reserved_pages;
         self.common().reserved.fetch_add(delta, Ordering::Relaxed);
-        self.common()
+        let committed = self
+            .common()
             .committed
-            .fetch_add(actual_pages, Ordering::Relaxed);
+            .fetch_add(actual_pages, Ordering::Relaxed)
+            + actual_pages;
+        self.common().resident.commit(committed);
         if unsafe { VM::VMActivePlan::is_mutator(tls) } {
             self.vm_map()
                 .add_to_cumulative_committed_pages(actual_pages);
@@ -96,6 +102,20 @@ pub trait PageResource<VM: VMBinding>: 'static {
         self.common().committed.load(Ordering::Relaxed)
     }
 
+    /// The pages that are backed by physical memory (see `ResidentPages`). This is reported in the GC log.
+    fn resident_pages(&self) -> usize {
+        self.common().resident.get()
+    }
+
+    /// Return the memory of `pages` released pages from `start` to the OS, as set by the
+    /// `decommit_policy` option. The pages stay mapped, and can be allocated again.
+    fn decommit_pages(&self, start: Address, pages: usize) {
+        let policy = VM::VMActivePlan::global().options().decommit_policy;
+        if let Err(e) = self.common().resident.decommit(start, pages, policy) {
+            warn!("Failed to decommit {} pages at {}: {}", pages, start, e);
+        }
+    }
+
     fn bind_space(&mut self, space: &'static dyn Space<VM>) {
         self.common_mut().space = Some(space);
     }
@@ -110,6 +130,7 @@ pub trait PageResource<VM: VMBinding>: 'static {
 pub struct CommonPageResource<VM: VMBinding> {
     reserved: AtomicUsize,
     committed: AtomicUsize,
+    resident: ResidentPages,
 
     pub contiguous: bool,
     pub growable: bool,
@@ -121,6 +142,7 @@ impl<VM: VMBinding> CommonPageResource<VM> {
         CommonPageResource {
             reserved: AtomicUsize::new(0),
             committed: AtomicUsize::new(0),
+            resident: ResidentPages::new(),
 
             contiguous,
             growable,
@@ -159,4 +181,135 @@ impl<VM: VMBinding> CommonPageResource<VM> {
     pub fn reset_committed(&self) {
         self.committed.store(0, Ordering::Relaxed);
     }
+
+    pub fn get_resident(&self) -> usize {
+        self.resident.get()
+    }
+}
+
+/// The number of pages of a page resource that are backed by physical memory: the committed pages,
+/// and the released pages that have not been decommitted yet.
+pub struct ResidentPages(AtomicUsize);
+
+impl ResidentPages {
+    pub fn new() -> Self {
+        ResidentPages(AtomicUsize::new(0))
+    }
+
+    pub fn get(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Called after pages are committed, with the number of committed pages of the resource.
+    /// We assume the new pages reuse the released pages that are still resident first.
+    pub fn commit(&self, committed: usize) {
+        self.0.fetch_max(committed, Ordering::Relaxed);
+    }
+
+    /// Return the memory of `pages` released pages from `start` to the OS with the given policy.
+    /// The count only drops if the pages are actually decommitted.
+    pub fn decommit(
+        &self,
+        start: Address,
+        pages: usize,
+        policy: DecommitPolicy,
+    ) -> std::io::Result<()> {
+        if policy == DecommitPolicy::Never || pages == 0 {
+            return Ok(());
+        }
+        memory::decommit(start, pages_to_bytes(pages), policy)?;
+        let _ = self
+            .0
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |resident| {
+                Some(resident.saturating_sub(pages))
+            });
+        Ok(())
+    }
+}
+
+impl Default for ResidentPages {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::constants::BYTES_IN_PAGE;
+
+    const PAGES: usize = 16;
+
+    fn mmap_pages() -> Address {
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                PAGES * BYTES_IN_PAGE,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        assert_ne!(ptr, libc::MAP_FAILED);
+        let start = Address::from_mut_ptr(ptr);
+        for i in 0..PAGES {
+            unsafe { (start + i * BYTES_IN_PAGE).store(0xdead_usize) };
+        }
+        start
+    }
+
+    fn munmap_pages(start: Address) {
+        unsafe { libc::munmap(start.to_mut_ptr(), PAGES * BYTES_IN_PAGE) };
+    }
+
+    #[test]
+    fn test_resident_pages_never() {
+        let start = mmap_pages();
+        let resident = ResidentPages::new();
+        resident.commit(PAGES);
+        resident.decommit(start, 4, DecommitPolicy::Never).unwrap();
+        assert_eq!(resident.get(), PAGES);
+        assert_eq!(unsafe { start.load::<usize>() }, 0xdead);
+        munmap_pages(start);
+    }
+
+    #[test]
+    fn test_resident_pages_dont_need() {
+        let start = mmap_pages();
+        let resident = ResidentPages::new();
+        resident.commit(PAGES);
+        resident
+            .decommit(start, 4, DecommitPolicy::DontNeed)
+            .unwrap();
+        assert_eq!(resident.get(), PAGES - 4);
+        // The decommitted pages read as zero, and the others keep their contents
+        assert_eq!(unsafe { start.load::<usize>() }, 0);
+        assert_eq!(
+            unsafe { (start + 4 * BYTES_IN_PAGE).load::<usize>() },
+            0xdead
+        );
+        // Committing the released pages again reuses them
+        resident.commit(PAGES);
+        assert_eq!(resident.get(), PAGES);
+        munmap_pages(start);
+    }
+
+    #[test]
+    fn test_resident_pages_free() {
+        let start = mmap_pages();
+        let resident = ResidentPages::new();
+        resident.commit(PAGES);
+        resident.decommit(start, 4, DecommitPolicy::Free).unwrap();
+        resident
+            .decommit(start + 4 * BYTES_IN_PAGE, 4, DecommitPolicy::Free)
+            .unwrap();
+        assert_eq!(resident.get(), PAGES - 8);
+        // The count never goes below zero
+        resident
+            .decommit(start, PAGES, DecommitPolicy::Free)
+            .unwrap();
+        assert_eq!(resident.get(), 0);
+        munmap_pages(start);
+    }
 }
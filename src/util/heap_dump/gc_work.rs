@@ -0,0 +1,224 @@
+//! The work packets that take a heap dump. A heap dump is taken instead of a GC: the mutators are stopped,
+//! the objects reachable from the roots are traced without being moved or marked, and the heap dump is
+//! written before the mutators are resumed.
+
+use super::{HeapObject, HeapSnapshot};
+use crate::plan::global::GcStatus;
+use crate::plan::transitive_closure::EdgeCollector;
+use crate::scheduler::gc_work::*;
+use crate::scheduler::*;
+use crate::util::{Address, ObjectReference};
+use crate::vm::*;
+use crate::MMTK;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::sync::Mutex;
+
+#[derive(Default)]
+struct HeapDumpState {
+    visited: HashSet<ObjectReference>,
+    roots: HashSet<ObjectReference>,
+    objects: Vec<HeapObject>,
+}
+
+/// Takes the heap dumps requested by `memory_manager::dump_heap()`.
+#[derive(Default)]
+pub struct HeapDumper {
+    /// The file to write the requested heap dump to
+    request: Mutex<Option<String>>,
+    state: Mutex<HeapDumpState>,
+    /// The result of writing the last heap dump
+    result: Mutex<Option<io::Result<()>>>,
+}
+
+impl HeapDumper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take a heap dump to `file` in the next pause, instead of a GC.
+    pub fn request(&self, file: &str) {
+        *self.result.lock().unwrap() = None;
+        *self.request.lock().unwrap() = Some(file.to_owned());
+    }
+
+    /// The result of the last heap dump, or `None` if it was not taken.
+    pub fn take_result(&self) -> Option<io::Result<()>> {
+        self.result.lock().unwrap().take()
+    }
+
+    /// If a heap dump is requested, schedule the work to take it and return true.
+    pub fn schedule_if_requested<VM: VMBinding>(
+        &self,
+        scheduler: &MMTkScheduler<VM>,
+        mmtk: &'static MMTK<VM>,
+    ) -> bool {
+        let file = match self.request.lock().unwrap().take() {
+            Some(file) => file,
+            None => return false,
+        };
+        mmtk.plan.base().set_gc_status(GcStatus::GcPrepare);
+        *self.state.lock().unwrap() = HeapDumpState::default();
+        // Stop & scan mutators
+        scheduler.work_buckets[WorkBucketStage::Unconstrained]
+            .add(StopMutators::<HeapDumpProcessEdges<VM>>::new());
+        // Write the heap dump once the transitive closure is done
+        scheduler.work_buckets[WorkBucketStage::Release].add(WriteHeapDump(file));
+        scheduler.set_finalizer(Some(EndOfHeapDump));
+        true
+    }
+
+    /// Mark an object as visited. Returns true if it was not visited before.
+    fn visit(&self, object: ObjectReference, root: bool) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if root {
+            state.roots.insert(object);
+        }
+        state.visited.insert(object)
+    }
+
+    fn add_objects(&self, objects: Vec<HeapObject>) {
+        self.state.lock().unwrap().objects.extend(objects);
+    }
+
+    fn take_snapshot(&self) -> HeapSnapshot {
+        let state = mem::take(&mut *self.state.lock().unwrap());
+        let mut roots: Vec<Address> = state.roots.iter().map(|o| o.to_address()).collect();
+        roots.sort_by_key(|a| a.as_usize());
+        HeapSnapshot {
+            roots,
+            objects: state.objects,
+        }
+    }
+}
+
+/// Trace the objects for a heap dump. The objects are only recorded, and never moved.
+pub struct HeapDumpProcessEdges<VM: VMBinding> {
+    base: ProcessEdgesBase<HeapDumpProcessEdges<VM>>,
+    roots: bool,
+}
+
+impl<VM: VMBinding> Deref for HeapDumpProcessEdges<VM> {
+    type Target = ProcessEdgesBase<Self>;
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl<VM: VMBinding> DerefMut for HeapDumpProcessEdges<VM> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.base
+    }
+}
+
+impl<VM: VMBinding> ProcessEdgesWork for HeapDumpProcessEdges<VM> {
+    type VM = VM;
+    const OVERWRITE_REFERENCE: bool = false;
+    fn new(edges: Vec<Address>, roots: bool, mmtk: &'static MMTK<VM>) -> Self {
+        Self {
+            base: ProcessEdgesBase::new(edges, mmtk),
+            roots,
+        }
+    }
+
+    #[inline]
+    fn trace_object(&mut self, object: ObjectReference) -> ObjectReference {
+        if !object.is_null() && self.mmtk().heap_dumper.visit(object, self.roots) {
+            ProcessEdgesWork::process_node(self, object);
+        }
+        object
+    }
+
+    /// Scan the objects with `ScanHeapDumpObjects`, which records the edges of each object.
+    fn flush(&mut self) {
+        let nodes = mem::take(&mut self.nodes);
+        self.mmtk().scheduler.work_buckets[WorkBucketStage::Closure]
+            .add(ScanHeapDumpObjects::<VM>::new(nodes));
+    }
+}
+
+/// Record the objects in the heap dump, and trace their fields.
+pub struct ScanHeapDumpObjects<VM: VMBinding> {
+    buffer: Vec<ObjectReference>,
+    phantom: std::marker::PhantomData<VM>,
+}
+
+impl<VM: VMBinding> ScanHeapDumpObjects<VM> {
+    pub fn new(buffer: Vec<ObjectReference>) -> Self {
+        Self {
+            buffer,
+            phantom: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<VM: VMBinding> GCWork<VM> for ScanHeapDumpObjects<VM> {
+    fn do_work(&mut self, worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        let mut objects = Vec::with_capacity(self.buffer.len());
+        let mut slots = vec![];
+        for object in &self.buffer {
+            let mut edges = vec![];
+            VM::VMScanning::scan_object(
+                &mut EdgeCollector { edges: &mut edges },
+                *object,
+                worker.tls,
+            );
+            objects.push(HeapObject {
+                address: object.to_address(),
+                size: VM::VMObjectModel::get_current_size(*object),
+                type_descriptor: VM::VMObjectModel::get_type_descriptor(*object)
+                    .iter()
+                    .map(|b| *b as u8)
+                    .collect(),
+                edges: edges
+                    .iter()
+                    .map(|slot| unsafe { slot.load::<ObjectReference>() })
+                    .filter(|referent| !referent.is_null())
+                    .map(|referent| referent.to_address())
+                    .collect(),
+            });
+            slots.extend(edges);
+        }
+        mmtk.heap_dumper.add_objects(objects);
+        for chunk in slots.chunks(HeapDumpProcessEdges::<VM>::CAPACITY) {
+            worker.add_work(
+                WorkBucketStage::Closure,
+                HeapDumpProcessEdges::<VM>::new(chunk.to_vec(), false, mmtk),
+            );
+        }
+    }
+}
+
+/// Write the heap dump to a file.
+pub struct WriteHeapDump(String);
+
+impl<VM: VMBinding> GCWork<VM> for WriteHeapDump {
+    fn do_work(&mut self, _worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        let snapshot = mmtk.heap_dumper.take_snapshot();
+        info!(
+            "Writing a heap dump of {} objects ({} bytes) to {}",
+            snapshot.objects.len(),
+            snapshot.total_size(),
+            self.0
+        );
+        let result =
+            File::create(&self.0).and_then(|file| snapshot.write(&mut BufWriter::new(file)));
+        *mmtk.heap_dumper.result.lock().unwrap() = Some(result);
+    }
+}
+
+/// Resume the mutators after a heap dump.
+#[derive(Default)]
+pub struct EndOfHeapDump;
+
+impl<VM: VMBinding> GCWork<VM> for EndOfHeapDump {
+    fn do_work(&mut self, worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        mmtk.plan.base().set_gc_status(GcStatus::NotInGC);
+        <VM as VMBinding>::VMCollection::resume_mutators(worker.tls);
+    }
+}
+
+impl<VM: VMBinding> CoordinatorWork<MMTK<VM>> for EndOfHeapDump {}
@@ -0,0 +1,84 @@
+//! Heap dumps: a snapshot of the objects reachable from the roots, taken in a stop-the-world pause
+//! (see `memory_manager::dump_heap()`), and a reader to load it back.
+//!
+//! A heap dump file is written in this binary format. All the integers are little endian.
+//!
+//! ```text
+//! magic:       8 bytes, "MMTKHEAP"
+//! version:     u32, currently 1
+//! roots:       u64 count, followed by the address of each root object (u64)
+//! objects:     u64 count, followed by a record for each object:
+//!   address:   u64, the object reference
+//!   size:      u64, in bytes (ObjectModel::get_current_size())
+//!   type:      u32 length, followed by that many bytes (ObjectModel::get_type_descriptor())
+//!   edges:     u32 count, followed by the object reference in each non-null reference field (u64)
+//! ```
+//!
+//! The objects are in no particular order, and each reachable object appears exactly once.
+
+pub mod gc_work;
+pub mod reader;
+
+pub use self::gc_work::HeapDumper;
+pub use self::reader::read_heap_dump;
+
+use crate::util::Address;
+use std::io::{self, Write};
+
+/// The first bytes of a heap dump file.
+pub const MAGIC: &[u8; 8] = b"MMTKHEAP";
+/// The version of the heap dump format.
+pub const VERSION: u32 = 1;
+
+/// An object in a heap dump.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeapObject {
+    pub address: Address,
+    pub size: usize,
+    /// The type descriptor of the object, given by the VM.
+    pub type_descriptor: Vec<u8>,
+    /// The objects referenced by the (non-null) reference fields of the object.
+    pub edges: Vec<Address>,
+}
+
+/// The objects reachable from the roots.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HeapSnapshot {
+    /// The objects referenced by the roots.
+    pub roots: Vec<Address>,
+    pub objects: Vec<HeapObject>,
+}
+
+impl HeapSnapshot {
+    /// Find the object at the given address.
+    pub fn find(&self, address: Address) -> Option<&HeapObject> {
+        self.objects.iter().find(|o| o.address == address)
+    }
+
+    /// The total size of the objects, in bytes.
+    pub fn total_size(&self) -> usize {
+        self.objects.iter().map(|o| o.size).sum()
+    }
+
+    /// Write the snapshot in the heap dump format.
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        out.write_all(&(self.roots.len() as u64).to_le_bytes())?;
+        for root in &self.roots {
+            out.write_all(&(root.as_usize() as u64).to_le_bytes())?;
+        }
+        out.write_all(&(self.objects.len() as u64).to_le_bytes())?;
+        for object in &self.objects {
+            out.write_all(&(object.address.as_usize() as u64).to_le_bytes())?;
+            out.write_all(&(object.size as u64).to_le_bytes())?;
+            out.write_all(&(object.type_descriptor.len() as u32).to_le_bytes())?;
+            out.write_all(&object.type_descriptor)?;
+            out.write_all(&(object.edges.len() as u32).to_le_bytes())?;
+            for edge in &object.edges {
+                out.write_all(&(edge.as_usize() as u64).to_le_bytes())?;
+            }
+        }
+        out.flush()
+    }
+}
@@ -0,0 +1,107 @@
+//! Load a heap dump written by `memory_manager::dump_heap()`.
+
+use super::{HeapObject, HeapSnapshot, MAGIC, VERSION};
+use crate::util::Address;
+use std::io::{self, Read};
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_u32(input: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(input: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_address(input: &mut impl Read) -> io::Result<Address> {
+    Ok(unsafe { Address::from_usize(read_u64(input)? as usize) })
+}
+
+/// Read a heap dump. See the `heap_dump` module for the format.
+pub fn read_heap_dump(input: &mut impl Read) -> io::Result<HeapSnapshot> {
+    let mut magic = [0u8; 8];
+    input.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid("Not a heap dump".to_string()));
+    }
+    let version = read_u32(input)?;
+    if version != VERSION {
+        return Err(invalid(format!(
+            "Unsupported heap dump version {} (expected {})",
+            version, VERSION
+        )));
+    }
+    let roots = (0..read_u64(input)?)
+        .map(|_| read_address(input))
+        .collect::<io::Result<_>>()?;
+    let mut objects = vec![];
+    for _ in 0..read_u64(input)? {
+        let address = read_address(input)?;
+        let size = read_u64(input)? as usize;
+        let mut type_descriptor = vec![0u8; read_u32(input)? as usize];
+        input.read_exact(&mut type_descriptor)?;
+        let edges = (0..read_u32(input)?)
+            .map(|_| read_address(input))
+            .collect::<io::Result<_>>()?;
+        objects.push(HeapObject {
+            address,
+            size,
+            type_descriptor,
+            edges,
+        });
+    }
+    Ok(HeapSnapshot { roots, objects })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(a: usize) -> Address {
+        unsafe { Address::from_usize(a) }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let snapshot = HeapSnapshot {
+            roots: vec![address(0x1000)],
+            objects: vec![
+                HeapObject {
+                    address: address(0x1000),
+                    size: 24,
+                    type_descriptor: b"Node".to_vec(),
+                    edges: vec![address(0x2000)],
+                },
+                HeapObject {
+                    address: address(0x2000),
+                    size: 16,
+                    type_descriptor: b"Leaf".to_vec(),
+                    edges: vec![],
+                },
+            ],
+        };
+        let mut bytes = vec![];
+        snapshot.write(&mut bytes).unwrap();
+        let read = read_heap_dump(&mut &bytes[..]).unwrap();
+        assert_eq!(read, snapshot);
+        assert_eq!(read.total_size(), 40);
+        assert_eq!(read.find(address(0x2000)).unwrap().type_descriptor, b"Leaf");
+    }
+
+    #[test]
+    fn test_rejects_other_files() {
+        let err = read_heap_dump(&mut &b"NOTAHEAPDUMP"[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let mut truncated = vec![];
+        HeapSnapshot::default().write(&mut truncated).unwrap();
+        truncated.pop();
+        assert!(read_heap_dump(&mut &truncated[..]).is_err());
+    }
+}
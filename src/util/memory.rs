@@ -1,3 +1,4 @@
+use crate::util::options::DecommitPolicy;
 use crate::util::Address;
 use libc::{c_void, PROT_EXEC, PROT_NONE, PROT_READ, PROT_WRITE};
 use std::io::{Error, Result};
@@ -33,6 +34,23 @@ pub fn dzmmap(start: Address, size: usize) -> Result<Address> {
     }
 }
 
+/// Return the physical memory behind a mapped, page-aligned range to the OS. The range stays mapped.
+/// With `DecommitPolicy::DontNeed`, the pages read as zero when they are next touched. With
+/// `DecommitPolicy::Free`, the kernel frees the pages lazily, so they may keep their contents until then.
+pub fn decommit(start: Address, size: usize, policy: DecommitPolicy) -> Result<()> {
+    let advice = match policy {
+        DecommitPolicy::Never => return Ok(()),
+        DecommitPolicy::DontNeed => libc::MADV_DONTNEED,
+        DecommitPolicy::Free => libc::MADV_FREE,
+    };
+    let result = unsafe { libc::madvise(start.to_mut_ptr(), size, advice) };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(Error::last_os_error())
+    }
+}
+
 pub fn munprotect(start: Address, size: usize) -> Result<()> {
     let result =
         unsafe { libc::mprotect(start.to_mut_ptr(), size, PROT_READ | PROT_WRITE | PROT_EXEC) };
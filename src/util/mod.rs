@@ -6,20 +6,26 @@ pub mod macros;
 pub mod conversions;
 pub mod address;
 pub mod alloc;
+pub mod alloc_bit;
 #[cfg(feature = "analysis")]
 pub mod analysis;
 pub mod constants;
+pub mod ephemeron_processor;
+pub mod finalizable_processor;
 pub mod forwarding_word;
 pub mod gc_byte;
+pub mod gc_log;
 pub mod generic_freelist;
 pub mod header_byte;
 pub mod heap;
+pub mod heap_dump;
 pub mod int_array_freelist;
 pub mod logger;
 pub mod malloc;
 pub mod memory;
 pub mod opaque_pointer;
 pub mod options;
+pub mod pin_bit;
 pub mod raw_memory_freelist;
 pub mod reference_processor;
 #[cfg(feature = "sanity")]
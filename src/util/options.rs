@@ -1,10 +1,11 @@
 use crate::util::constants::DEFAULT_STRESS_FACTOR;
 use std::cell::UnsafeCell;
 use std::default::Default;
+use std::fmt;
 use std::ops::Deref;
 
 custom_derive! {
-    #[derive(Copy, Clone, EnumFromStr)]
+    #[derive(Copy, Clone, EnumFromStr, Debug)]
     pub enum NurseryZeroingOptions {
         Temporal,
         Nontemporal,
@@ -13,15 +14,90 @@ custom_derive! {
     }
 }
 
+// How the pages released by a GC are returned to the OS (see `util::memory::decommit()`):
+// * Never: keep the pages. They still count towards the RSS of the process.
+// * DontNeed: `madvise(MADV_DONTNEED)`. The RSS drops right away, and the pages read as zero when reused.
+// * Free: `madvise(MADV_FREE)`. The kernel only reclaims the pages when it is short of memory.
+custom_derive! {
+    #[derive(Copy, Clone, EnumFromStr, Debug, PartialEq, Eq)]
+    pub enum DecommitPolicy {
+        Never,
+        DontNeed,
+        Free,
+    }
+}
+
+// The format of the statistics reported at the end of a harness run (see `util::statistics::sink`):
+// * Text: a tab separated table of the totals, as printed by `Stats::print_stats()`.
+// * Json: the totals and a record for each GC, as a JSON object.
+// * Csv: a row for each GC, and a row for the totals.
+custom_derive! {
+    #[derive(Copy, Clone, EnumFromStr, Debug, PartialEq, Eq)]
+    pub enum StatsFormat {
+        Text,
+        Json,
+        Csv,
+    }
+}
+
 custom_derive! {
     #[derive(Copy, Clone, EnumFromStr, Debug)]
     pub enum PlanSelector {
         NoGC,
         SemiSpace,
-        GenCopy
+        GenCopy,
+        GenImmix,
+        MarkSweep,
+        Immix,
+        MarkCompact,
+        ConcMarkSweep,
+        RC
+    }
+}
+
+/// An error from setting an option.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OptionError {
+    /// There is no option with this name.
+    UnknownOption(String),
+    /// The value cannot be parsed as a value of the option.
+    InvalidValue { name: String, value: String },
+    /// The value is rejected by the validator of the option.
+    ValidationFailed { name: String, value: String },
+    /// The option can only be set before the MMTk instance is created, with `MMTKBuilder`.
+    SetAfterCreation(String),
+}
+
+impl fmt::Display for OptionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OptionError::UnknownOption(name) => write!(f, "Unknown option {}", name),
+            OptionError::InvalidValue { name, value } => {
+                write!(f, "Unable to parse {} as a value of option {}", value, name)
+            }
+            OptionError::ValidationFailed { name, value } => {
+                write!(f, "{} is not a valid value of option {}", value, name)
+            }
+            OptionError::SetAfterCreation(name) => write!(
+                f,
+                "Option {} cannot be changed once the MMTk instance is created",
+                name
+            ),
+        }
     }
 }
 
+impl std::error::Error for OptionError {}
+
+/// The name, value and documentation of an option, as returned by `Options::describe()`.
+#[derive(Clone, Debug)]
+pub struct OptionDescription {
+    pub name: &'static str,
+    pub value: String,
+    pub default: String,
+    pub doc: String,
+}
+
 pub struct UnsafeOptionsWrapper(UnsafeCell<Options>);
 unsafe impl Sync for UnsafeOptionsWrapper {}
 
@@ -35,6 +111,14 @@ impl UnsafeOptionsWrapper {
     pub unsafe fn process(&self, name: &str, value: &str) -> bool {
         (&mut *self.0.get()).set_from_camelcase_str(name, value)
     }
+    /// Same as `process()`, but returns the reason if the option is not set.
+    ///
+    /// # Safety
+    /// This method is not thread safe, as internally it acquires a mutable reference to self.
+    /// It is supposed to be used by one thread during boot time.
+    pub unsafe fn try_process(&self, name: &str, value: &str) -> Result<(), OptionError> {
+        (&mut *self.0.get()).try_set_from_str(&to_snake_case(name), value)
+    }
 }
 impl Deref for UnsafeOptionsWrapper {
     type Target = Options;
@@ -46,28 +130,72 @@ impl Deref for UnsafeOptionsWrapper {
 fn always_valid<T>(_: T) -> bool {
     true
 }
+
+/// Check a new value of an option with its validator.
+fn validate<T: Clone + fmt::Debug>(
+    name: &str,
+    value: T,
+    validator: impl Fn(T) -> bool,
+) -> Result<T, OptionError> {
+    if validator(value.clone()) {
+        Ok(value)
+    } else {
+        Err(OptionError::ValidationFailed {
+            name: name.to_string(),
+            value: format!("{:?}", value),
+        })
+    }
+}
+
 macro_rules! options {
-    ($($name:ident: $type:ty[$validator:expr] = $default:expr),*,) => [
-        options!($($name: $type[$validator] = $default),*);
+    ($($(#[doc = $doc:literal])* $name:ident: $type:ty[$validator:expr] = $default:expr),*,) => [
+        options!($($(#[doc = $doc])* $name: $type[$validator] = $default),*);
     ];
-    ($($name:ident: $type:ty[$validator:expr] = $default:expr),*) => [
+    ($($(#[doc = $doc:literal])* $name:ident: $type:ty[$validator:expr] = $default:expr),*) => [
         pub struct Options {
-            $(pub $name: $type),*
+            $($(#[doc = $doc])* pub $name: $type),*
         }
         impl Options {
+            /// Set an option from a string. Returns false (and the option is not changed) if
+            /// the value is invalid.
             pub fn set_from_str(&mut self, s: &str, val: &str)->bool {
-                match s {
-                    $(stringify!($name) => if let Ok(val) = val.parse() {
-                        self.$name = val;
-                        let validate_fn = $validator;
-                        validate_fn(val)
-                    } else {
-                        eprintln!("Warn: unable to set {}={}. Default value will be used.", s, val);
+                match self.try_set_from_str(s, val) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        warn!("{}. The option is not changed.", e);
                         false
+                    }
+                }
+            }
+
+            /// Set an option from a string, and validate the value.
+            pub fn try_set_from_str(&mut self, s: &str, val: &str) -> Result<(), OptionError> {
+                match s {
+                    $(stringify!($name) => {
+                        let value: $type = val.parse().map_err(|_| OptionError::InvalidValue {
+                            name: s.to_string(),
+                            value: val.to_string(),
+                        })?;
+                        self.$name = validate(s, value, $validator)?;
+                        Ok(())
                     })*
-                    _ => panic!("Invalid Options key")
+                    _ => Err(OptionError::UnknownOption(s.to_string()))
                 }
             }
+
+            /// All the options with their current values, default values and documentation,
+            /// e.g. for the help output of a VM.
+            pub fn describe(&self) -> Vec<OptionDescription> {
+                vec![$({
+                    let doc: &[&str] = &[$($doc),*];
+                    OptionDescription {
+                        name: stringify!($name),
+                        value: format!("{:?}", self.$name),
+                        default: format!("{:?}", $default),
+                        doc: doc.iter().map(|line| line.trim()).collect::<Vec<_>>().join(" "),
+                    }
+                }),*]
+            }
         }
         impl Default for Options {
             fn default() -> Self {
@@ -91,25 +219,89 @@ macro_rules! options {
                 return options;
             }
         }
+
+        /// Builds `Options` with typed setters, which validate each value as it is set.
+        /// The builder starts from the default options, including those set by `MMTK_*` env vars.
+        pub struct OptionsBuilder {
+            options: Options,
+        }
+        impl OptionsBuilder {
+            pub fn new() -> Self {
+                OptionsBuilder {
+                    options: Options::default(),
+                }
+            }
+
+            $(
+                $(#[doc = $doc])*
+                pub fn $name(&mut self, value: $type) -> Result<&mut Self, OptionError> {
+                    self.options.$name = validate(stringify!($name), value, $validator)?;
+                    Ok(self)
+                }
+            )*
+
+            /// Set an option from strings, e.g. from the command line of a VM. The name can be
+            /// in snake case (`stress_factor`) or camel case (`stressFactor`).
+            pub fn set(&mut self, name: &str, value: &str) -> Result<&mut Self, OptionError> {
+                self.options.try_set_from_str(&to_snake_case(name), value)?;
+                Ok(self)
+            }
+
+            pub fn build(self) -> Options {
+                self.options
+            }
+        }
+        impl Default for OptionsBuilder {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+        impl Deref for OptionsBuilder {
+            type Target = Options;
+            fn deref(&self) -> &Options {
+                &self.options
+            }
+        }
     ]
 }
 options! {
+    /// The GC plan. It can only be set before the MMTk instance is created.
     plan:                  PlanSelector         [always_valid] = PlanSelector::NoGC,
+    /// The number of GC worker threads.
     threads:               usize                [|v| v > 0]    = num_cpus::get(),
     use_short_stack_scans: bool                 [always_valid] = false,
     use_return_barrier:    bool                 [always_valid] = false,
     eager_complete_sweep:  bool                 [always_valid] = false,
+    /// Ignore the GCs requested by the VM (`memory_manager::handle_user_collection_request()`).
     ignore_system_g_c:     bool                 [always_valid] = false,
-    // Note: Not used. To workaround cmd args passed by the running script
+    /// Resize the heap after each GC, if gc_init() is given a dynamic heap size.
     variable_size_heap:    bool                 [always_valid] = true,
     no_finalizer:          bool                 [always_valid] = false,
     no_reference_types:    bool                 [always_valid] = false,
+    /// Keep the referent of a reachable soft reference for this many milliseconds per MB of free heap after it was last accessed
+    /// (see `ReferenceGlue::get_soft_reference_timestamp()`). The referents that are not accessed for longer are cleared.
+    soft_ref_lru_policy_ms_per_mb: usize        [always_valid] = 1000,
     nursery_zeroing:       NurseryZeroingOptions[always_valid] = NurseryZeroingOptions::Temporal,
+    /// How the pages released by a GC are returned to the OS: Never, DontNeed or Free.
+    decommit_policy:       DecommitPolicy       [always_valid] = DecommitPolicy::Never,
     // Note: This gets ignored. Use RUST_LOG to specify log level.
     // TODO: Delete this option.
     verbose:               usize                [always_valid] = 0,
+    /// Trigger a GC after this many bytes are allocated, if it is smaller than the heap size.
     stress_factor:         usize                [always_valid] = DEFAULT_STRESS_FACTOR,
+    /// Run the analysis routines after this many bytes are allocated.
     analysis_factor:       usize                [always_valid] = DEFAULT_STRESS_FACTOR,
+    /// The format of the statistics reported at the end of a harness run: Text, Json or Csv.
+    stats_format:          StatsFormat          [always_valid] = StatsFormat::Text,
+    /// The file to write the statistics to. They are printed to stdout if this is empty.
+    stats_file:            String               [always_valid] = String::new(),
+    /// The file to write a record for each GC to, as newline-delimited JSON (see `util::gc_log`). No log is written if this is empty.
+    gc_log_file:           String               [always_valid] = String::new(),
+    /// Write a Chrome trace of the work packets run by each GC worker to `<work_trace_file>.<n>.json` after the n-th GC.
+    /// No trace is written if this is empty.
+    work_trace_file:       String               [always_valid] = String::new(),
+    /// The number of work packets recorded by each GC worker for the work packet trace. The oldest ones are dropped.
+    work_trace_buffer_size: usize               [|v| v > 0]    = 1 << 16,
     // vmspace
     // FIXME: These options are set for JikesRVM. We need a proper way to set options.
     //   We need to set these values programmatically in VM specific code.
@@ -117,21 +309,27 @@ options! {
     vm_space_size:         usize                [|v| v > 0]    = 0x7cc_cccc,
 }
 
+/// Turn a camel case option name (`stressFactor`) into the snake case name of the option (`stress_factor`).
+fn to_snake_case(s: &str) -> String {
+    let mut sr = String::with_capacity(s.len());
+    for c in s.chars() {
+        if c.is_uppercase() {
+            sr.push('_');
+            for c in c.to_lowercase() {
+                sr.push(c);
+            }
+        } else {
+            sr.push(c)
+        }
+    }
+    sr
+}
+
 impl Options {
     fn set_from_camelcase_str(&mut self, s: &str, val: &str) -> bool {
         trace!("Trying to process option pair: ({}, {})", s, val);
 
-        let mut sr = String::with_capacity(s.len());
-        for c in s.chars() {
-            if c.is_uppercase() {
-                sr.push('_');
-                for c in c.to_lowercase() {
-                    sr.push(c);
-                }
-            } else {
-                sr.push(c)
-            }
-        }
+        let sr = to_snake_case(s);
 
         let result = self.set_from_str(sr.as_str(), val);
 
@@ -149,7 +347,7 @@ impl Options {
 #[cfg(test)]
 mod tests {
     use crate::util::constants::DEFAULT_STRESS_FACTOR;
-    use crate::util::options::Options;
+    use crate::util::options::{OptionError, Options, OptionsBuilder, PlanSelector};
     use crate::util::test_util::serial_test;
 
     #[test]
@@ -224,4 +422,66 @@ mod tests {
             std::env::remove_var("MMTK_ABC");
         })
     }
+
+    #[test]
+    fn builder_typed_setters() {
+        serial_test(|| {
+            let mut builder = OptionsBuilder::new();
+            builder
+                .stress_factor(4096)
+                .unwrap()
+                .plan(PlanSelector::Immix)
+                .unwrap();
+            assert_eq!(
+                builder.threads(0).err(),
+                Some(OptionError::ValidationFailed {
+                    name: "threads".to_string(),
+                    value: "0".to_string()
+                })
+            );
+            let options = builder.build();
+            assert_eq!(options.stress_factor, 4096);
+            assert!(matches!(options.plan, PlanSelector::Immix));
+            assert!(options.threads > 0);
+        })
+    }
+
+    #[test]
+    fn builder_set_from_str() {
+        serial_test(|| {
+            let mut builder = OptionsBuilder::new();
+            assert!(builder.set("stressFactor", "4096").is_ok());
+            assert!(builder.set("vm_space", "false").is_ok());
+            assert_eq!(
+                builder.set("abc", "42").err(),
+                Some(OptionError::UnknownOption("abc".to_string()))
+            );
+            assert_eq!(
+                builder.set("stress_factor", "abc").err(),
+                Some(OptionError::InvalidValue {
+                    name: "stress_factor".to_string(),
+                    value: "abc".to_string()
+                })
+            );
+            assert_eq!(builder.stress_factor, 4096);
+            assert_eq!(builder.vm_space, false);
+        })
+    }
+
+    #[test]
+    fn describe_options() {
+        serial_test(|| {
+            let mut options = Options::default();
+            options.set_from_str("stress_factor", "4096");
+            let descriptions = options.describe();
+            let stress_factor = descriptions
+                .iter()
+                .find(|d| d.name == "stress_factor")
+                .unwrap();
+            assert_eq!(stress_factor.value, "4096");
+            assert_eq!(stress_factor.default, format!("{}", DEFAULT_STRESS_FACTOR));
+            assert!(!stress_factor.doc.is_empty());
+            assert!(descriptions.iter().any(|d| d.name == "plan"));
+        })
+    }
 }
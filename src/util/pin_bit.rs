@@ -0,0 +1,77 @@
+//! The pin bit: one bit of global side metadata per word, which is set for each object that must not be
+//! moved by a GC (see `memory_manager::pin_object()`). It is maintained if the `object_pinning` feature is enabled.
+//!
+//! A moving policy that finds a pinned object during a GC marks it in place instead of copying it.
+//!
+//! Each policy clears the pin bit when it reclaims the object (wherever it clears the alloc bit), so a new
+//! object at the same address is not pinned. The functions that clear pin bits are no-ops if the feature is
+//! disabled, so policies can call them unconditionally.
+
+use crate::util::alloc_bit::ALLOC_SIDE_METADATA_PER_CHUNK;
+use crate::util::constants::LOG_BYTES_IN_WORD;
+use crate::util::side_metadata::{
+    band_metadata_for_chunk, bzero_metadata_for_chunk, compare_exchange_atomic, load_atomic,
+    meta_bytes_per_chunk, store_atomic, SideMetadataScope, SideMetadataSpec,
+};
+use crate::util::{Address, ObjectReference};
+
+/// The pin bit comes right after the alloc bit.
+pub const PIN_SIDE_METADATA_SPEC: SideMetadataSpec = SideMetadataSpec {
+    scope: SideMetadataScope::Global,
+    offset: ALLOC_SIDE_METADATA_PER_CHUNK,
+    log_num_of_bits: 0,
+    log_min_obj_size: LOG_BYTES_IN_WORD as usize,
+};
+
+/// The bytes of global side metadata per chunk used by the pin bit, which is 0 if the feature is disabled.
+pub const PIN_SIDE_METADATA_PER_CHUNK: usize = if cfg!(feature = "object_pinning") {
+    meta_bytes_per_chunk(
+        PIN_SIDE_METADATA_SPEC.log_min_obj_size,
+        PIN_SIDE_METADATA_SPEC.log_num_of_bits,
+    )
+} else {
+    0
+};
+
+/// Pin an object. Returns true if this call pinned the object, and false if it was already pinned.
+#[inline]
+pub fn pin_object(object: ObjectReference) -> bool {
+    compare_exchange_atomic(PIN_SIDE_METADATA_SPEC, object.to_address(), 0, 1)
+}
+
+/// Unpin an object. Returns true if this call unpinned the object, and false if it was not pinned.
+#[inline]
+pub fn unpin_object(object: ObjectReference) -> bool {
+    compare_exchange_atomic(PIN_SIDE_METADATA_SPEC, object.to_address(), 1, 0)
+}
+
+/// Is the object pinned? This is always false if the `object_pinning` feature is disabled, in which case
+/// the pin bit is not mapped, so moving policies can call it unconditionally.
+#[inline]
+pub fn is_pinned(object: ObjectReference) -> bool {
+    cfg!(feature = "object_pinning")
+        && load_atomic(PIN_SIDE_METADATA_SPEC, object.to_address()) == 1
+}
+
+/// Clear the pin bit of a dead or moved object.
+#[inline]
+pub fn unset_pin_bit(object: ObjectReference) {
+    if cfg!(feature = "object_pinning") {
+        store_atomic(PIN_SIDE_METADATA_SPEC, object.to_address(), 0);
+    }
+}
+
+/// Clear the pin bits of all the objects in a chunk.
+pub fn bzero_pin_bits(chunk: Address) {
+    if cfg!(feature = "object_pinning") {
+        bzero_metadata_for_chunk(PIN_SIDE_METADATA_SPEC, chunk);
+    }
+}
+
+/// Clear the pin bits of the objects in a chunk that are not marked in `mark_spec`, which must
+/// have one bit per word like the pin bit.
+pub fn bzero_pin_bits_of_unmarked(mark_spec: SideMetadataSpec, chunk: Address) {
+    if cfg!(feature = "object_pinning") {
+        band_metadata_for_chunk(PIN_SIDE_METADATA_SPEC, mark_spec, chunk);
+    }
+}
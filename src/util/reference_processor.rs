@@ -1,17 +1,39 @@
-use std::cell::UnsafeCell;
+//! The soft, weak and phantom references registered with `memory_manager::add_*_candidate()`.
+//! The ephemerons are kept along with them, but they are scheduled separately (see `util::ephemeron_processor`).
+//!
+//! The references are processed by work packets after the transitive closure: the referents of the
+//! reachable soft references are retained first (unless this is an emergency collection, or they have
+//! not been accessed recently, see `SoftRefPolicy`), then the soft and weak references are scanned, and
+//! the phantom references are scanned after finalization. The candidates of each semantics are split
+//! between the workers. A nursery GC only processes the candidates that were added since the last GC.
+
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
 use std::vec::Vec;
 
-use crate::plan::{MutatorContext, TraceLocal};
-use crate::util::OpaquePointer;
-use crate::util::{Address, ObjectReference};
+use crate::plan::TraceLocal;
+use crate::scheduler::gc_work::{ProcessEdgesTraceLocal, ProcessEdgesWork};
+use crate::scheduler::{GCWork, GCWorker, MMTkScheduler, Work, WorkBucketStage};
+use crate::util::constants::{LOG_BYTES_IN_MBYTE, LOG_BYTES_IN_PAGE};
+use crate::util::ephemeron_processor::EphemeronProcessor;
+use crate::util::ObjectReference;
+use crate::vm::ReferenceGlue;
 use crate::vm::VMBinding;
-use crate::vm::{ActivePlan, ReferenceGlue};
+use crate::MMTK;
 
 pub struct ReferenceProcessors {
     soft: ReferenceProcessor,
     weak: ReferenceProcessor,
     phantom: ReferenceProcessor,
+    pub ephemerons: EphemeronProcessor,
+    /// Are the references processed in the current GC? The plans that do not schedule reference
+    /// processing drop all the candidates at the end of each GC.
+    processed: AtomicBool,
+    /// The free pages at the end of the last GC, which size the `SoftRefPolicy` of the next GC.
+    /// `usize::MAX` before the first GC.
+    free_pages_after_last_gc: AtomicUsize,
 }
 
 impl ReferenceProcessors {
@@ -20,6 +42,9 @@ impl ReferenceProcessors {
             soft: ReferenceProcessor::new(Semantics::SOFT),
             weak: ReferenceProcessor::new(Semantics::WEAK),
             phantom: ReferenceProcessor::new(Semantics::PHANTOM),
+            ephemerons: EphemeronProcessor::new(),
+            processed: AtomicBool::new(false),
+            free_pages_after_last_gc: AtomicUsize::new(usize::MAX),
         }
     }
 
@@ -31,6 +56,7 @@ impl ReferenceProcessors {
         }
     }
 
+    /// Drop all the soft, weak and phantom references. The ephemerons are kept.
     pub fn clear(&self) {
         self.soft.clear();
         self.weak.clear();
@@ -61,27 +87,19 @@ impl ReferenceProcessors {
         self.phantom.add_candidate::<VM>(reff, referent);
     }
 
-    pub fn forward_refs<VM: VMBinding, T: TraceLocal>(&self, trace: &mut T) {
-        self.soft.forward::<VM, T>(trace, false);
-        self.weak.forward::<VM, T>(trace, false);
-        self.phantom.forward::<VM, T>(trace, false);
-    }
-
-    pub fn scan_weak_refs<VM: VMBinding, T: TraceLocal>(&self, trace: &mut T, tls: OpaquePointer) {
-        self.soft.scan::<VM, T>(trace, false, false, tls);
-        self.weak.scan::<VM, T>(trace, false, false, tls);
-    }
-
-    pub fn scan_soft_refs<VM: VMBinding, T: TraceLocal>(&self, trace: &mut T, tls: OpaquePointer) {
-        self.soft.scan::<VM, T>(trace, false, false, tls);
-    }
-
-    pub fn scan_phantom_refs<VM: VMBinding, T: TraceLocal>(
-        &self,
-        trace: &mut T,
-        tls: OpaquePointer,
-    ) {
-        self.phantom.scan::<VM, T>(trace, false, false, tls);
+    /// Called at the end of each GC. The references that survived this GC will not be processed by the
+    /// following nursery GCs. If the references were not processed, they are all dropped.
+    /// `free_pages` is the number of free pages once the spaces are released.
+    pub fn release(&self, free_pages: usize) {
+        self.free_pages_after_last_gc
+            .store(free_pages, Ordering::SeqCst);
+        if self.processed.swap(false, Ordering::SeqCst) {
+            self.soft.end_of_gc();
+            self.weak.end_of_gc();
+            self.phantom.end_of_gc();
+        } else {
+            self.clear();
+        }
     }
 }
 
@@ -91,26 +109,10 @@ impl Default for ReferenceProcessors {
     }
 }
 
-// Debug flags
-pub const TRACE: bool = false;
-pub const TRACE_UNREACHABLE: bool = false;
-pub const TRACE_DETAIL: bool = false;
-pub const TRACE_FORWARD: bool = false;
-
-// XXX: We differ from the original implementation
-//      by ignoring "stress," i.e. where the array
-//      of references is grown by 1 each time. We
-//      can't do this here b/c std::vec::Vec doesn't
-//      allow us to customize its behaviour like that.
-//      (Similarly, GROWTH_FACTOR is locked at 2.0, but
-//      luckily this is also the value used by Java MMTk.)
 const INITIAL_SIZE: usize = 256;
 
 pub struct ReferenceProcessor {
-    // XXX: To support the possibility of the collector working
-    //      on the reference in parallel, we wrap the structure
-    //      in an UnsafeCell.
-    sync: UnsafeCell<Mutex<ReferenceProcessorSync>>,
+    sync: Mutex<ReferenceProcessorSync>,
 
     /**
      * Semantics
@@ -118,9 +120,7 @@ pub struct ReferenceProcessor {
     semantics: Semantics,
 }
 
-unsafe impl Sync for ReferenceProcessor {}
-
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Semantics {
     SOFT,
     WEAK,
@@ -128,20 +128,10 @@ pub enum Semantics {
 }
 
 struct ReferenceProcessorSync {
-    // XXX: A data race on any of these fields is UB. If
-    //      parallelizing this code, change the types to
-    //      have the correct semantics.
     /**
      * The table of reference objects for the current semantics
      */
-    references: Vec<Address>,
-
-    /**
-     * In a MarkCompact (or similar) collector, we need to update the {@code references}
-     * field, and then update its contents.  We implement this by saving the pointer in
-     * this untraced field for use during the {@code forward} pass.
-     */
-    unforwarded_references: Option<Vec<Address>>,
+    references: Vec<ObjectReference>,
 
     /**
      * Index into the <code>references</code> table for the start of
@@ -153,179 +143,369 @@ struct ReferenceProcessorSync {
 impl ReferenceProcessor {
     pub fn new(semantics: Semantics) -> Self {
         ReferenceProcessor {
-            sync: UnsafeCell::new(Mutex::new(ReferenceProcessorSync {
+            sync: Mutex::new(ReferenceProcessorSync {
                 references: Vec::with_capacity(INITIAL_SIZE),
-                unforwarded_references: None,
                 nursery_index: 0,
-            })),
+            }),
             semantics,
         }
     }
 
-    fn sync(&self) -> &Mutex<ReferenceProcessorSync> {
-        unsafe { &*self.sync.get() }
-    }
-
-    // UNSAFE: Bypasses mutex
-    // It is designed to allow getting mut ref from UnsafeCell.
-    // TODO: We may need to rework on this to remove the unsafety.
-    #[allow(clippy::mut_from_ref)]
-    unsafe fn sync_mut(&self) -> &mut ReferenceProcessorSync {
-        (&mut *self.sync.get()).get_mut().unwrap()
-    }
-
     pub fn clear(&self) {
-        let mut sync = self.sync().lock().unwrap();
+        let mut sync = self.sync.lock().unwrap();
         sync.references.clear();
-        sync.unforwarded_references = None;
         sync.nursery_index = 0;
     }
 
     pub fn add_candidate<VM: VMBinding>(&self, reff: ObjectReference, referent: ObjectReference) {
-        let mut sync = self.sync().lock().unwrap();
+        let mut sync = self.sync.lock().unwrap();
         VM::VMReferenceGlue::set_referent(reff, referent);
-        sync.references.push(reff.to_address());
+        sync.references.push(reff);
     }
 
-    pub fn forward<VM: VMBinding, T: TraceLocal>(&self, trace: &mut T, _nursery: bool) {
-        let mut sync = unsafe { self.sync_mut() };
-        let references: &mut Vec<Address> = &mut sync.references;
-        // XXX: Copies `unforwarded_references` out. Should be fine since it's not accessed
-        //      concurrently & it's set to `None` at the end anyway..
-        let mut unforwarded_references: Vec<Address> = sync.unforwarded_references.clone().unwrap();
-        if TRACE {
-            trace!("Starting ReferenceProcessor.forward({:?})", self.semantics);
-        }
-        if TRACE_DETAIL {
-            trace!("{:?} Reference table is {:?}", self.semantics, references);
-            trace!(
-                "{:?} unforwardedReferences is {:?}",
-                self.semantics,
-                unforwarded_references
-            );
+    /// The references to process in this GC: the ones added since the last GC for a nursery GC, or all of them.
+    /// If `remove` is true, they are removed from the table, and the live ones need to be added back with `add_references()`.
+    fn references_to_process(&self, nursery: bool, remove: bool) -> Vec<ObjectReference> {
+        let mut sync = self.sync.lock().unwrap();
+        let from_index = if nursery { sync.nursery_index } else { 0 };
+        if remove {
+            sync.references.split_off(from_index)
+        } else {
+            sync.references[from_index..].to_vec()
         }
+    }
 
-        for (i, unforwarded_ref) in unforwarded_references
-            .iter_mut()
-            .enumerate()
-            .take(references.len())
-        {
-            let reference = unsafe { unforwarded_ref.to_object_reference() };
-            if TRACE_DETAIL {
-                trace!("slot {:?}: forwarding {:?}", i, reference);
-            }
-            VM::VMReferenceGlue::set_referent(
-                reference,
-                trace.get_forwarded_referent(VM::VMReferenceGlue::get_referent(reference)),
-            );
-            let new_reference = trace.get_forwarded_reference(reference);
-            *unforwarded_ref = new_reference.to_address();
+    fn add_references(&self, references: Vec<ObjectReference>) {
+        let mut sync = self.sync.lock().unwrap();
+        sync.references.extend(references);
+    }
+
+    fn end_of_gc(&self) {
+        let mut sync = self.sync.lock().unwrap();
+        trace!(
+            "{:?} references: {} mature",
+            self.semantics,
+            sync.references.len()
+        );
+        sync.nursery_index = sync.references.len();
+    }
+}
+
+/// Schedule the reference processing for a GC that traces objects with `E`.
+pub fn schedule_reference_processing<E: ProcessEdgesWork>(scheduler: &MMTkScheduler<E::VM>) {
+    scheduler.work_buckets[WorkBucketStage::SoftRefClosure].add(RefProcessing::<E>::new(
+        WorkBucketStage::SoftRefClosure,
+        Semantics::SOFT,
+        RefAction::Retain,
+    ));
+    for semantics in &[Semantics::SOFT, Semantics::WEAK] {
+        scheduler.work_buckets[WorkBucketStage::WeakRefClosure].add(RefProcessing::<E>::new(
+            WorkBucketStage::WeakRefClosure,
+            *semantics,
+            RefAction::Scan,
+        ));
+    }
+    scheduler.work_buckets[WorkBucketStage::PhantomRefClosure].add(RefProcessing::<E>::new(
+        WorkBucketStage::PhantomRefClosure,
+        Semantics::PHANTOM,
+        RefAction::Scan,
+    ));
+}
+
+/// Schedule updating the references to the new addresses of the objects, for the plans that move objects
+/// in a separate phase after the closure (e.g. MarkCompact). `E` returns the new address of an object.
+pub fn schedule_reference_forwarding<E: ProcessEdgesWork>(scheduler: &MMTkScheduler<E::VM>) {
+    for semantics in &[Semantics::SOFT, Semantics::WEAK, Semantics::PHANTOM] {
+        scheduler.work_buckets[WorkBucketStage::RefForwarding].add(RefProcessing::<E>::new(
+            WorkBucketStage::RefForwarding,
+            *semantics,
+            RefAction::Forward,
+        ));
+    }
+}
+
+/// What is done to each reference
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum RefAction {
+    /// Keep the referents of the reachable references alive
+    Retain,
+    /// Let the VM clear the referents that are not reachable, and drop the references that are not reachable
+    Scan,
+    /// Update the references and their referents to the new addresses of the objects
+    Forward,
+}
+
+/// Decides whether the referent of a reachable soft reference is retained, like the `SoftRefLRUPolicyMSPerMB`
+/// policy of HotSpot: a referent is cleared once it has not been accessed for longer than `soft_ref_lru_policy_ms_per_mb`
+/// milliseconds per MB of free heap, so the referents are cleared sooner as the heap fills up.
+#[derive(Debug, Clone, Copy)]
+struct SoftRefPolicy {
+    /// The current time, in milliseconds since the Unix epoch
+    now: u64,
+    /// The referents that were accessed longer ago than this (in milliseconds) are cleared
+    max_age: u64,
+}
+
+impl SoftRefPolicy {
+    fn new(free_pages: usize, ms_per_mb: usize) -> Self {
+        let free_mb = (free_pages >> (LOG_BYTES_IN_MBYTE - LOG_BYTES_IN_PAGE)) as u64;
+        Self {
+            now: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis() as u64),
+            max_age: free_mb.saturating_mul(ms_per_mb as u64),
         }
+    }
 
-        if TRACE {
-            trace!("Ending ReferenceProcessor.forward({:?})", self.semantics)
+    /// Should the referent of a reachable soft reference that was last accessed at `timestamp` be retained?
+    /// The referents are always retained if the VM does not record when they are accessed.
+    fn should_retain(&self, timestamp: Option<u64>) -> bool {
+        match timestamp {
+            Some(timestamp) => self.now.saturating_sub(timestamp) <= self.max_age,
+            None => true,
         }
-        sync.unforwarded_references = None;
     }
+}
 
-    fn scan<VM: VMBinding, T: TraceLocal>(
-        &self,
-        trace: &mut T,
-        nursery: bool,
-        retain: bool,
-        tls: OpaquePointer,
-    ) {
-        let sync = unsafe { self.sync_mut() };
-        sync.unforwarded_references = Some(sync.references.clone());
-        let references: &mut Vec<Address> = &mut sync.references;
+/// Split the references of one semantics into a `ProcessReferences` packet for each worker.
+pub struct RefProcessing<E: ProcessEdgesWork> {
+    stage: WorkBucketStage,
+    semantics: Semantics,
+    action: RefAction,
+    phantom: PhantomData<E>,
+}
 
-        if TRACE {
-            trace!("Starting ReferenceProcessor.scan({:?})", self.semantics);
+impl<E: ProcessEdgesWork> RefProcessing<E> {
+    fn new(stage: WorkBucketStage, semantics: Semantics, action: RefAction) -> Self {
+        Self {
+            stage,
+            semantics,
+            action,
+            phantom: PhantomData,
         }
-        let mut to_index = if nursery { sync.nursery_index } else { 0 };
-        let from_index = to_index;
+    }
+}
 
-        if TRACE_DETAIL {
-            trace!("{:?} Reference table is {:?}", self.semantics, references);
+impl<E: ProcessEdgesWork> GCWork<E::VM> for RefProcessing<E> {
+    fn do_work(&mut self, _worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
+        trace!("RefProcessing {:?} {:?}", self.semantics, self.action);
+        mmtk.reference_processors
+            .processed
+            .store(true, Ordering::SeqCst);
+        // The soft references are cleared like the weak references in an emergency collection
+        if self.action == RefAction::Retain && mmtk.plan.is_emergency_collection() {
+            return;
         }
-        if retain {
-            for addr in references.iter().skip(from_index) {
-                let reference = unsafe { addr.to_object_reference() };
-                self.retain_referent::<VM, T>(trace, reference);
-            }
-        } else {
-            for i in from_index..references.len() {
-                let reference = unsafe { references[i].to_object_reference() };
-
-                /* Determine liveness (and forward if necessary) the reference */
-                let new_reference = VM::VMReferenceGlue::process_reference(trace, reference, tls);
-                if !new_reference.is_null() {
-                    references[to_index] = new_reference.to_address();
-                    to_index += 1;
-                    if TRACE_DETAIL {
-                        let index = to_index - 1;
-                        trace!(
-                            "SCANNED {} {:?} -> {:?}",
-                            index,
-                            references[index],
-                            unsafe { references[index].to_object_reference() }
+        let nursery = self.action != RefAction::Forward && mmtk.plan.in_nursery();
+        let references = mmtk
+            .reference_processors
+            .get(self.semantics)
+            .references_to_process(nursery, self.action != RefAction::Retain);
+        if references.is_empty() {
+            return;
+        }
+        // The heap is only partly released at this point, so we size the policy with the free pages
+        // after the last GC. The heap is empty before the first GC.
+        let free_pages = match mmtk
+            .reference_processors
+            .free_pages_after_last_gc
+            .load(Ordering::SeqCst)
+        {
+            usize::MAX => mmtk.plan.get_total_pages(),
+            free_pages => free_pages,
+        };
+        let soft_ref_policy =
+            SoftRefPolicy::new(free_pages, mmtk.options.soft_ref_lru_policy_ms_per_mb);
+        let num_workers = mmtk.scheduler.num_workers();
+        let chunk_size = (references.len() + num_workers - 1) / num_workers;
+        let packets = references
+            .chunks(chunk_size)
+            .map(|chunk| {
+                box ProcessReferences::<E>::new(
+                    self.semantics,
+                    self.action,
+                    soft_ref_policy,
+                    chunk.to_vec(),
+                ) as Box<dyn Work<MMTK<E::VM>>>
+            })
+            .collect();
+        mmtk.scheduler.work_buckets[self.stage].bulk_add(packets);
+    }
+}
+
+/// Process a part of the references of one semantics.
+pub struct ProcessReferences<E: ProcessEdgesWork> {
+    semantics: Semantics,
+    action: RefAction,
+    soft_ref_policy: SoftRefPolicy,
+    references: Vec<ObjectReference>,
+    phantom: PhantomData<E>,
+}
+
+impl<E: ProcessEdgesWork> ProcessReferences<E> {
+    fn new(
+        semantics: Semantics,
+        action: RefAction,
+        soft_ref_policy: SoftRefPolicy,
+        references: Vec<ObjectReference>,
+    ) -> Self {
+        Self {
+            semantics,
+            action,
+            soft_ref_policy,
+            references,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E: ProcessEdgesWork> GCWork<E::VM> for ProcessReferences<E> {
+    fn do_work(&mut self, worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
+        let tls = worker.tls;
+        let mut closure = E::new(vec![], false, mmtk);
+        closure.set_worker(worker);
+        let mut trace = ProcessEdgesTraceLocal(&mut closure);
+        let processor = mmtk.reference_processors.get(self.semantics);
+        match self.action {
+            RefAction::Retain => {
+                for reference in &self.references {
+                    // An unreachable reference may still become reachable through the closure of
+                    // the retained referents, but we do not wait for it.
+                    if !reference.is_live() {
+                        continue;
+                    }
+                    // The referents that are not retained are cleared in the weak stage, unless they are strongly reachable
+                    let timestamp =
+                        <E::VM as VMBinding>::VMReferenceGlue::get_soft_reference_timestamp(
+                            *reference,
                         );
+                    if !self.soft_ref_policy.should_retain(timestamp) {
+                        continue;
+                    }
+                    let referent = <E::VM as VMBinding>::VMReferenceGlue::get_referent(*reference);
+                    if !referent.is_null() {
+                        trace.retain_referent(referent);
                     }
                 }
             }
-            trace!(
-                "{:?} references: {} -> {}",
-                self.semantics,
-                references.len(),
-                to_index
-            );
-            sync.nursery_index = to_index;
-            references.truncate(to_index);
+            RefAction::Scan => {
+                let live = self
+                    .references
+                    .iter()
+                    .map(|reference| {
+                        <E::VM as VMBinding>::VMReferenceGlue::process_reference(
+                            &mut trace, *reference, tls,
+                        )
+                    })
+                    .filter(|reference| !reference.is_null())
+                    .collect();
+                processor.add_references(live);
+            }
+            RefAction::Forward => {
+                let forwarded = self
+                    .references
+                    .iter()
+                    .map(|reference| {
+                        let referent =
+                            <E::VM as VMBinding>::VMReferenceGlue::get_referent(*reference);
+                        if !referent.is_null() {
+                            <E::VM as VMBinding>::VMReferenceGlue::set_referent(
+                                *reference,
+                                trace.get_forwarded_referent(referent),
+                            );
+                        }
+                        trace.get_forwarded_reference(*reference)
+                    })
+                    .collect();
+                processor.add_references(forwarded);
+            }
         }
-
-        /* flush out any remset entries generated during the above activities */
-        unsafe { VM::VMActivePlan::mutator(tls) }.flush_remembered_sets();
-        if TRACE {
-            trace!("Ending ReferenceProcessor.scan({:?})", self.semantics);
+        if !closure.nodes.is_empty() {
+            closure.flush();
         }
     }
+}
 
-    /**
-     * This method deals only with soft references. It retains the referent
-     * if the reference is definitely reachable.
-     * @param reference the address of the reference. This may or may not
-     * be the address of a heap object, depending on the VM.
-     * @param trace the thread local trace element.
-     */
-    fn retain_referent<VM: VMBinding, T: TraceLocal>(
-        &self,
-        trace: &mut T,
-        reference: ObjectReference,
-    ) {
-        debug_assert!(!reference.is_null());
-        debug_assert!(self.semantics == Semantics::SOFT);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::Address;
 
-        if TRACE_DETAIL {
-            trace!("Processing reference: {:?}", reference);
-        }
+    fn object(addr: usize) -> ObjectReference {
+        unsafe { Address::from_usize(addr).to_object_reference() }
+    }
 
-        if !reference.is_live() {
-            /*
-             * Reference is currently unreachable but may get reachable by the
-             * following trace. We postpone the decision.
-             */
-            return;
-        }
+    fn references(processor: &ReferenceProcessor) -> Vec<ObjectReference> {
+        processor.sync.lock().unwrap().references.clone()
+    }
 
-        /*
-         * Reference is definitely reachable.  Retain the referent.
-         */
-        let referent = VM::VMReferenceGlue::get_referent(reference);
-        if !referent.is_null() {
-            trace.retain_referent(referent);
-        }
-        if TRACE_DETAIL {
-            trace!(" ~> {:?} (retained)", referent.to_address());
-        }
+    #[test]
+    fn test_nursery_gc_processes_new_references() {
+        let (a, b, c, d) = (
+            object(0x10000),
+            object(0x20000),
+            object(0x30000),
+            object(0x40000),
+        );
+        let processor = ReferenceProcessor::new(Semantics::WEAK);
+        processor.add_references(vec![a, b]);
+
+        // A full heap GC processes all the references. `b` is dropped, and `a` survives.
+        assert_eq!(processor.references_to_process(false, true), vec![a, b]);
+        processor.add_references(vec![a]);
+        processor.end_of_gc();
+
+        // A nursery GC only processes the references added since the last GC, and keeps the mature ones.
+        processor.add_references(vec![c, d]);
+        assert_eq!(processor.references_to_process(true, false), vec![c, d]);
+        assert_eq!(processor.references_to_process(true, true), vec![c, d]);
+        assert_eq!(references(&processor), vec![a]);
+        processor.add_references(vec![d]);
+        processor.end_of_gc();
+        assert_eq!(references(&processor), vec![a, d]);
+
+        // The references that survived the nursery GC are mature too
+        assert!(processor.references_to_process(true, true).is_empty());
+        assert_eq!(processor.references_to_process(false, false), vec![a, d]);
+    }
+
+    #[test]
+    fn test_release_drops_unprocessed_references() {
+        let (a, b) = (object(0x10000), object(0x20000));
+        let processors = ReferenceProcessors::new();
+        processors.get(Semantics::SOFT).add_references(vec![a]);
+        processors.processed.store(true, Ordering::SeqCst);
+        processors.release(0);
+        assert_eq!(references(processors.get(Semantics::SOFT)), vec![a]);
+
+        // The plans that do not process the references cannot keep them up to date
+        processors.get(Semantics::WEAK).add_references(vec![b]);
+        processors.release(0);
+        assert!(references(processors.get(Semantics::SOFT)).is_empty());
+        assert!(references(processors.get(Semantics::WEAK)).is_empty());
+    }
+
+    #[test]
+    fn test_soft_ref_policy() {
+        // 4 MB free, 1000 ms per MB
+        let policy = SoftRefPolicy {
+            now: 10_000,
+            max_age: 4_000,
+        };
+        assert!(policy.should_retain(None));
+        assert!(policy.should_retain(Some(10_000)));
+        assert!(policy.should_retain(Some(6_000)));
+        assert!(!policy.should_retain(Some(5_999)));
+        assert!(!policy.should_retain(Some(0)));
+        // A timestamp in the future counts as just accessed
+        assert!(policy.should_retain(Some(20_000)));
+    }
+
+    #[test]
+    fn test_soft_ref_policy_max_age() {
+        let pages_in_mb = 1 << (LOG_BYTES_IN_MBYTE - LOG_BYTES_IN_PAGE);
+        assert_eq!(SoftRefPolicy::new(4 * pages_in_mb, 1000).max_age, 4_000);
+        assert_eq!(SoftRefPolicy::new(pages_in_mb - 1, 1000).max_age, 0);
+        assert_eq!(SoftRefPolicy::new(usize::MAX, usize::MAX).max_age, u64::MAX);
     }
 }
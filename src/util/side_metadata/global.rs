@@ -310,19 +310,24 @@ pub fn compare_exchange_atomic(
         let lshift = meta_byte_lshift(metadata_spec, data_addr);
         let mask = meta_byte_mask(metadata_spec) << lshift;
 
-        let real_old_byte = unsafe { meta_addr.atomic_load::<AtomicU8>(Ordering::SeqCst) };
-        let expected_old_byte = (real_old_byte & !mask) | ((old_metadata as u8) << lshift);
-        let expected_new_byte = expected_old_byte | ((new_metadata as u8) << lshift);
+        let mut real_old_byte = unsafe { meta_addr.atomic_load::<AtomicU8>(Ordering::SeqCst) };
+        loop {
+            let expected_old_byte = (real_old_byte & !mask) | ((old_metadata as u8) << lshift);
+            let expected_new_byte = (real_old_byte & !mask) | ((new_metadata as u8) << lshift);
 
-        unsafe {
-            meta_addr
-                .compare_exchange::<AtomicU8>(
+            match unsafe {
+                meta_addr.compare_exchange::<AtomicU8>(
                     expected_old_byte,
                     expected_new_byte,
                     Ordering::SeqCst,
                     Ordering::SeqCst,
                 )
-                .is_ok()
+            } {
+                Ok(_) => return true,
+                // Only fail if our bits differ. The other bits in the byte may belong to other objects.
+                Err(actual_byte) if actual_byte & mask != expected_old_byte & mask => return false,
+                Err(actual_byte) => real_old_byte = actual_byte,
+            }
         }
     } else if bits_num_log == 3 {
         unsafe {
@@ -573,6 +578,67 @@ pub fn bzero_metadata_for_chunk(metadata_spec: SideMetadataSpec, chunk_start: Ad
     memory::zero(meta_start, meta_size);
 }
 
+/// Bulk-copy one metadata to another for a chunk. Both metadata must have the same
+/// number of bits and the same minimum object size.
+///
+/// # Arguments
+///
+/// * `dst_spec` - The specification of the side metadata being overwritten.
+///
+/// * `src_spec` - The specification of the side metadata being copied.
+///
+/// * `chunk_start` - The starting address of the chunk whose metadata is being copied.
+///
+pub fn bcopy_metadata_for_chunk(
+    dst_spec: SideMetadataSpec,
+    src_spec: SideMetadataSpec,
+    chunk_start: Address,
+) {
+    debug_assert!(chunk_start.is_aligned_to(BYTES_IN_CHUNK));
+    debug_assert_eq!(dst_spec.log_num_of_bits, src_spec.log_num_of_bits);
+    debug_assert_eq!(dst_spec.log_min_obj_size, src_spec.log_min_obj_size);
+
+    let dst = address_to_meta_address(dst_spec, chunk_start);
+    let src = address_to_meta_address(src_spec, chunk_start);
+    let meta_size = meta_bytes_per_chunk(src_spec.log_min_obj_size, src_spec.log_num_of_bits);
+    unsafe {
+        std::ptr::copy_nonoverlapping(src.to_ptr::<u8>(), dst.to_mut_ptr::<u8>(), meta_size);
+    }
+}
+
+/// Bulk-and one metadata with another for a chunk, so a bit of `dst_spec` stays set only if the
+/// same bit of `src_spec` is set. Both metadata must have the same number of bits and the same
+/// minimum object size.
+///
+/// # Arguments
+///
+/// * `dst_spec` - The specification of the side metadata being updated.
+///
+/// * `src_spec` - The specification of the side metadata used as the mask.
+///
+/// * `chunk_start` - The starting address of the chunk whose metadata is being updated.
+///
+pub fn band_metadata_for_chunk(
+    dst_spec: SideMetadataSpec,
+    src_spec: SideMetadataSpec,
+    chunk_start: Address,
+) {
+    debug_assert!(chunk_start.is_aligned_to(BYTES_IN_CHUNK));
+    debug_assert_eq!(dst_spec.log_num_of_bits, src_spec.log_num_of_bits);
+    debug_assert_eq!(dst_spec.log_min_obj_size, src_spec.log_min_obj_size);
+
+    let dst = address_to_meta_address(dst_spec, chunk_start);
+    let src = address_to_meta_address(src_spec, chunk_start);
+    let meta_size = meta_bytes_per_chunk(src_spec.log_min_obj_size, src_spec.log_num_of_bits);
+    for i in 0..meta_size {
+        unsafe {
+            let mask = (src + i).load::<u8>();
+            let byte = (dst + i).load::<u8>();
+            (dst + i).store::<u8>(byte & mask);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -777,6 +843,55 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_side_metadata_compare_exchange_4bits() {
+        serial_test(|| {
+            let data_addr =
+                vm_layout_constants::HEAP_START + (vm_layout_constants::BYTES_IN_CHUNK << 3);
+            // Shares a metadata byte with `data_addr`
+            let neighbour_addr = data_addr + constants::BYTES_IN_WORD;
+
+            let metadata_1_spec = SideMetadataSpec {
+                scope: SideMetadataScope::Global,
+                offset: 0,
+                log_num_of_bits: 2,
+                log_min_obj_size: constants::LOG_BYTES_IN_WORD as usize,
+            };
+
+            assert!(try_map_metadata_space(
+                data_addr,
+                constants::BYTES_IN_PAGE,
+                helpers::meta_bytes_per_chunk(
+                    metadata_1_spec.log_min_obj_size,
+                    metadata_1_spec.log_num_of_bits
+                ),
+                0
+            ));
+
+            store_atomic(metadata_1_spec, neighbour_addr, 9);
+
+            assert!(compare_exchange_atomic(metadata_1_spec, data_addr, 0, 5));
+            assert_eq!(load_atomic(metadata_1_spec, data_addr), 5);
+            assert!(!compare_exchange_atomic(metadata_1_spec, data_addr, 4, 3));
+            assert_eq!(load_atomic(metadata_1_spec, data_addr), 5);
+            assert!(compare_exchange_atomic(metadata_1_spec, data_addr, 5, 2));
+            assert_eq!(load_atomic(metadata_1_spec, data_addr), 2);
+            assert!(compare_exchange_atomic(metadata_1_spec, data_addr, 2, 0));
+            assert_eq!(load_atomic(metadata_1_spec, data_addr), 0);
+
+            assert_eq!(load_atomic(metadata_1_spec, neighbour_addr), 9);
+
+            ensure_munmap_metadata_chunk(
+                data_addr,
+                helpers::meta_bytes_per_chunk(
+                    metadata_1_spec.log_min_obj_size,
+                    metadata_1_spec.log_num_of_bits,
+                ),
+                0,
+            );
+        })
+    }
+
     #[test]
     fn test_side_metadata_bzero_metadata_for_chunk() {
         serial_test(|| {
@@ -851,4 +966,87 @@ mod tests {
             );
         })
     }
+
+    #[test]
+    fn test_side_metadata_bcopy_metadata_for_chunk() {
+        serial_test(|| {
+            let data_addr =
+                vm_layout_constants::HEAP_START + (vm_layout_constants::BYTES_IN_CHUNK << 4);
+            let neighbour_addr = data_addr + constants::BYTES_IN_WORD;
+
+            let src_spec = SideMetadataSpec {
+                scope: SideMetadataScope::PolicySpecific,
+                offset: 0,
+                log_num_of_bits: 0,
+                log_min_obj_size: constants::LOG_BYTES_IN_WORD as usize,
+            };
+            let src_bytes =
+                helpers::meta_bytes_per_chunk(src_spec.log_min_obj_size, src_spec.log_num_of_bits);
+            let dst_spec = SideMetadataSpec {
+                offset: src_bytes,
+                ..src_spec
+            };
+            assert!(try_map_metadata_space(
+                data_addr,
+                constants::BYTES_IN_PAGE,
+                0,
+                src_bytes * 2
+            ));
+
+            store_atomic(src_spec, data_addr, 1);
+            store_atomic(dst_spec, neighbour_addr, 1);
+
+            bcopy_metadata_for_chunk(dst_spec, src_spec, data_addr);
+
+            assert_eq!(load_atomic(dst_spec, data_addr), 1);
+            assert_eq!(load_atomic(dst_spec, neighbour_addr), 0);
+            assert_eq!(load_atomic(src_spec, data_addr), 1);
+
+            ensure_munmap_metadata_chunk(data_addr, 0, src_bytes * 2);
+        })
+    }
+
+    #[test]
+    fn test_side_metadata_band_metadata_for_chunk() {
+        serial_test(|| {
+            let data_addr =
+                vm_layout_constants::HEAP_START + (vm_layout_constants::BYTES_IN_CHUNK << 4);
+            let neighbour_addr = data_addr + constants::BYTES_IN_WORD;
+            let other_addr = data_addr + 2 * constants::BYTES_IN_WORD;
+
+            let src_spec = SideMetadataSpec {
+                scope: SideMetadataScope::PolicySpecific,
+                offset: 0,
+                log_num_of_bits: 0,
+                log_min_obj_size: constants::LOG_BYTES_IN_WORD as usize,
+            };
+            let src_bytes =
+                helpers::meta_bytes_per_chunk(src_spec.log_min_obj_size, src_spec.log_num_of_bits);
+            let dst_spec = SideMetadataSpec {
+                offset: src_bytes,
+                ..src_spec
+            };
+            assert!(try_map_metadata_space(
+                data_addr,
+                constants::BYTES_IN_PAGE,
+                0,
+                src_bytes * 2
+            ));
+
+            store_atomic(src_spec, data_addr, 1);
+            store_atomic(dst_spec, data_addr, 1);
+            store_atomic(dst_spec, neighbour_addr, 1);
+            store_atomic(src_spec, other_addr, 1);
+
+            band_metadata_for_chunk(dst_spec, src_spec, data_addr);
+
+            assert_eq!(load_atomic(dst_spec, data_addr), 1);
+            assert_eq!(load_atomic(dst_spec, neighbour_addr), 0);
+            assert_eq!(load_atomic(dst_spec, other_addr), 0);
+            assert_eq!(load_atomic(src_spec, data_addr), 1);
+            assert_eq!(load_atomic(src_spec, other_addr), 1);
+
+            ensure_munmap_metadata_chunk(data_addr, 0, src_bytes * 2);
+        })
+    }
 }
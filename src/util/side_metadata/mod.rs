@@ -87,6 +87,9 @@
 //!
 //! For this purpose, each plan should override `fn global_side_metadata_per_chunk(&self) -> usize;` to return the size of the global side metadata it needs per chunk. This can be calculated using the `meta_bytes_per_chunk` function.
 //!
+//! Note that the global alloc bit (see `util::alloc_bit`) and the pin bit (see `util::pin_bit`) always come first, so the first global bit-set of a plan starts at `CORE_GLOBAL_SIDE_METADATA_PER_CHUNK` rather than zero,
+//! and `global_side_metadata_per_chunk` needs to include `CORE_GLOBAL_SIDE_METADATA_PER_CHUNK`.
+//!
 //! For the local metadata bit-sets, each policy needs to follow the same pattern as the global metadata, with two differences:
 //!
 //! 1. scope should be `SideMetadataScope::PolicySpecific`,
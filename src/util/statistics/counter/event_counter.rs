@@ -101,24 +101,7 @@ impl Counter for EventCounter {
     }
 
     fn print_total(&self, mutator: Option<bool>) {
-        match mutator {
-            None => {
-                let mut total = 0;
-                for p in 0..=self.stats.get_phase() {
-                    total += self.count[p];
-                }
-                self.print_value(total);
-            }
-            Some(m) => {
-                let mut total = 0;
-                let mut p = if m { 0 } else { 1 };
-                while p <= self.stats.get_phase() {
-                    total += self.count[p];
-                    p += 2;
-                }
-                self.print_value(total);
-            }
-        };
+        self.print_value(self.get_total(mutator));
     }
 
     fn print_min(&self, mutator: bool) {
@@ -152,6 +135,35 @@ impl Counter for EventCounter {
         }
     }
 
+    fn get_count(&self, phase: usize) -> u64 {
+        self.count[phase]
+    }
+
+    fn get_total(&self, mutator: Option<bool>) -> u64 {
+        match mutator {
+            None => {
+                let mut total = 0;
+                for p in 0..=self.stats.get_phase() {
+                    total += self.count[p];
+                }
+                total
+            }
+            Some(m) => {
+                let mut total = 0;
+                let mut p = if m { 0 } else { 1 };
+                while p <= self.stats.get_phase() {
+                    total += self.count[p];
+                    p += 2;
+                }
+                total
+            }
+        }
+    }
+
+    fn format_value(&self, val: u64) -> String {
+        format!("{}", val)
+    }
+
     fn merge_phases(&self) -> bool {
         self.merge_phases
     }
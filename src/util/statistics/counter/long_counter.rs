@@ -61,18 +61,7 @@ impl<T: Diffable> Counter for LongCounter<T> {
     }
 
     fn print_total(&self, mutator: Option<bool>) {
-        match mutator {
-            None => self.print_value(self.total_count),
-            Some(m) => {
-                let mut total = 0;
-                let mut p = if m { 0 } else { 1 };
-                while p <= self.stats.get_phase() {
-                    total += self.count[p];
-                    p += 2;
-                }
-                self.print_value(total);
-            }
-        };
+        self.print_value(self.get_total(mutator));
     }
 
     fn print_min(&self, mutator: bool) {
@@ -106,6 +95,29 @@ impl<T: Diffable> Counter for LongCounter<T> {
         }
     }
 
+    fn get_count(&self, phase: usize) -> u64 {
+        self.count[phase]
+    }
+
+    fn get_total(&self, mutator: Option<bool>) -> u64 {
+        match mutator {
+            None => self.total_count,
+            Some(m) => {
+                let mut total = 0;
+                let mut p = if m { 0 } else { 1 };
+                while p <= self.stats.get_phase() {
+                    total += self.count[p];
+                    p += 2;
+                }
+                total
+            }
+        }
+    }
+
+    fn format_value(&self, val: u64) -> String {
+        T::format_diff(val)
+    }
+
     fn merge_phases(&self) -> bool {
         self.merge_phases
     }
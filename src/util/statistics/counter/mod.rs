@@ -17,6 +17,12 @@ pub trait Counter {
     fn print_min(&self, mutator: bool);
     fn print_max(&self, mutator: bool);
     fn print_last(&self);
+    /// The value of the counter in a phase. Unlike `print_count()`, this is never merged with the next phase.
+    fn get_count(&self, phase: usize) -> u64;
+    /// The total over the mutator phases (`Some(true)`), the GC phases (`Some(false)`), or all phases (`None`).
+    fn get_total(&self, mutator: Option<bool>) -> u64;
+    /// Format a value of this counter, in the same way as the `print_*()` methods.
+    fn format_value(&self, val: u64) -> String;
     fn merge_phases(&self) -> bool;
     fn implicitly_start(&self) -> bool;
     fn name(&self) -> &String;
@@ -26,7 +32,10 @@ pub trait Diffable {
     type Val;
     fn current_value() -> Self::Val;
     fn diff(current: &Self::Val, earlier: &Self::Val) -> u64;
-    fn print_diff(val: u64);
+    fn format_diff(val: u64) -> String;
+    fn print_diff(val: u64) {
+        print!("{}", Self::format_diff(val));
+    }
 }
 
 pub struct MonotoneNanoTime;
@@ -43,7 +52,7 @@ impl Diffable for MonotoneNanoTime {
         delta.as_secs() * 1_000_000_000 + u64::from(delta.subsec_nanos())
     }
 
-    fn print_diff(val: u64) {
-        print!("{}", format!("{:.*}", 2, val as f64 / 1e6f64));
+    fn format_diff(val: u64) -> String {
+        format!("{:.*}", 2, val as f64 / 1e6f64)
     }
 }
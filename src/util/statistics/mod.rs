@@ -2,4 +2,5 @@ pub use self::counter::Counter;
 pub use self::counter::Timer;
 
 pub mod counter;
+pub mod sink;
 pub mod stats;
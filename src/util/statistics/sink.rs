@@ -0,0 +1,247 @@
+//! Writers for the statistics reported at the end of a harness run (`Stats::stop_all()`).
+//! The format and the output file are set by the `stats_format` and `stats_file` options.
+
+use crate::util::options::StatsFormat;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+/// The statistics gathered since the start of a harness run. See `Stats::snapshot()`.
+#[derive(Clone, Debug, Default)]
+pub struct StatsSnapshot {
+    /// The number of GCs while the statistics were gathered.
+    pub gc_count: usize,
+    /// The total time in milliseconds, formatted as the `time` counter.
+    pub total_time: String,
+    /// The names of the counter columns, in the order the counters were created. A counter that
+    /// does not merge phases has two columns: `<name>.mu` for the mutator phases, and `<name>.gc` for the GC phases.
+    pub columns: Vec<String>,
+    /// The totals of the counter columns.
+    pub totals: Vec<String>,
+    /// One record for each GC, with the values of the counter columns in the GC and in the mutator phase before it.
+    pub gcs: Vec<Vec<String>>,
+    /// The totals of the scheduler statistics, sorted by name. There are no per-GC records for these.
+    pub scheduler: Vec<(String, String)>,
+}
+
+/// Writes a statistics snapshot in some format.
+pub trait StatsSink {
+    fn write(&mut self, snapshot: &StatsSnapshot) -> io::Result<()>;
+}
+
+/// Create the sink for the given format, writing to `file`, or to stdout if `file` is empty.
+pub fn create_sink(format: StatsFormat, file: &str) -> io::Result<Box<dyn StatsSink>> {
+    let out: Box<dyn Write> = if file.is_empty() {
+        Box::new(io::stdout())
+    } else {
+        Box::new(BufWriter::new(File::create(file)?))
+    };
+    Ok(match format {
+        StatsFormat::Text => Box::new(TextSink(out)),
+        StatsFormat::Json => Box::new(JsonSink(out)),
+        StatsFormat::Csv => Box::new(CsvSink(out)),
+    })
+}
+
+/// A tab separated table of the totals.
+pub struct TextSink<W: Write>(pub W);
+
+impl<W: Write> StatsSink for TextSink<W> {
+    fn write(&mut self, snapshot: &StatsSnapshot) -> io::Result<()> {
+        let out = &mut self.0;
+        writeln!(
+            out,
+            "============================ MMTk Statistics Totals ============================"
+        )?;
+        write!(out, "GC\t")?;
+        for name in snapshot
+            .columns
+            .iter()
+            .chain(snapshot.scheduler.iter().map(|(name, _)| name))
+        {
+            write!(out, "{}\t", name)?;
+        }
+        writeln!(out)?;
+        write!(out, "{}\t", snapshot.gc_count)?;
+        for value in snapshot
+            .totals
+            .iter()
+            .chain(snapshot.scheduler.iter().map(|(_, value)| value))
+        {
+            write!(out, "{}\t", value)?;
+        }
+        writeln!(out)?;
+        writeln!(out, "Total time: {} ms", snapshot.total_time)?;
+        writeln!(
+            out,
+            "------------------------------ End MMTk Statistics -----------------------------"
+        )?;
+        out.flush()
+    }
+}
+
+/// A JSON object with the totals and a record for each GC.
+pub struct JsonSink<W: Write>(pub W);
+
+impl<W: Write> JsonSink<W> {
+    fn write_record<'a>(
+        out: &mut W,
+        fields: impl Iterator<Item = (&'a String, &'a String)>,
+    ) -> io::Result<()> {
+        write!(out, "{{")?;
+        for (i, (name, value)) in fields.enumerate() {
+            if i > 0 {
+                write!(out, ", ")?;
+            }
+            write!(out, "{}: {}", json_string(name), json_value(value))?;
+        }
+        write!(out, "}}")
+    }
+}
+
+impl<W: Write> StatsSink for JsonSink<W> {
+    fn write(&mut self, snapshot: &StatsSnapshot) -> io::Result<()> {
+        let out = &mut self.0;
+        writeln!(out, "{{")?;
+        writeln!(out, "  \"gc_count\": {},", snapshot.gc_count)?;
+        writeln!(
+            out,
+            "  \"total_time_ms\": {},",
+            json_value(&snapshot.total_time)
+        )?;
+        write!(out, "  \"totals\": ")?;
+        Self::write_record(
+            out,
+            snapshot
+                .columns
+                .iter()
+                .zip(snapshot.totals.iter())
+                .chain(snapshot.scheduler.iter().map(|(name, value)| (name, value))),
+        )?;
+        writeln!(out, ",")?;
+        writeln!(out, "  \"gcs\": [")?;
+        for (i, gc) in snapshot.gcs.iter().enumerate() {
+            write!(out, "    ")?;
+            Self::write_record(out, snapshot.columns.iter().zip(gc.iter()))?;
+            writeln!(out, "{}", if i + 1 < snapshot.gcs.len() { "," } else { "" })?;
+        }
+        writeln!(out, "  ]")?;
+        writeln!(out, "}}")?;
+        out.flush()
+    }
+}
+
+/// A row for each GC, and a last row (with `total` in the `gc` column) for the totals.
+/// The scheduler columns are only filled in the totals row.
+pub struct CsvSink<W: Write>(pub W);
+
+impl<W: Write> StatsSink for CsvSink<W> {
+    fn write(&mut self, snapshot: &StatsSnapshot) -> io::Result<()> {
+        let out = &mut self.0;
+        let header: Vec<_> = std::iter::once("gc")
+            .chain(snapshot.columns.iter().map(|s| s.as_str()))
+            .chain(snapshot.scheduler.iter().map(|(name, _)| name.as_str()))
+            .map(csv_field)
+            .collect();
+        writeln!(out, "{}", header.join(","))?;
+        for (i, gc) in snapshot.gcs.iter().enumerate() {
+            let row: Vec<_> = std::iter::once(i.to_string())
+                .chain(gc.iter().map(|v| csv_field(v)))
+                .chain(snapshot.scheduler.iter().map(|_| String::new()))
+                .collect();
+            writeln!(out, "{}", row.join(","))?;
+        }
+        let totals: Vec<_> = std::iter::once("total".to_string())
+            .chain(snapshot.totals.iter().map(|v| csv_field(v)))
+            .chain(snapshot.scheduler.iter().map(|(_, v)| csv_field(v)))
+            .collect();
+        writeln!(out, "{}", totals.join(","))?;
+        out.flush()
+    }
+}
+
+pub(crate) fn json_string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escaped.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// Numbers are written as they are, and anything else as a string.
+fn json_value(s: &str) -> String {
+    match s.parse::<f64>() {
+        Ok(v) if v.is_finite() => s.to_string(),
+        _ => json_string(s),
+    }
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains(',') || s.contains('"') || s.contains('\n') {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot() -> StatsSnapshot {
+        StatsSnapshot {
+            gc_count: 2,
+            total_time: "12.50".to_string(),
+            columns: vec!["time.mu".to_string(), "time.gc".to_string()],
+            totals: vec!["10.00".to_string(), "2.50".to_string()],
+            gcs: vec![
+                vec!["4.00".to_string(), "1.00".to_string()],
+                vec!["6.00".to_string(), "1.50".to_string()],
+            ],
+            scheduler: vec![("total-work.count".to_string(), "42".to_string())],
+        }
+    }
+
+    fn write_to_string(sink: impl FnOnce(&mut Vec<u8>) -> io::Result<()>) -> String {
+        let mut out = vec![];
+        sink(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_csv_sink() {
+        let csv = write_to_string(|out| CsvSink(out).write(&snapshot()));
+        assert_eq!(
+            csv,
+            "gc,time.mu,time.gc,total-work.count\n\
+             0,4.00,1.00,\n\
+             1,6.00,1.50,\n\
+             total,10.00,2.50,42\n"
+        );
+    }
+
+    #[test]
+    fn test_json_sink() {
+        let json = write_to_string(|out| JsonSink(out).write(&snapshot()));
+        assert_eq!(
+            json,
+            "{\n  \"gc_count\": 2,\n  \"total_time_ms\": 12.50,\n  \
+             \"totals\": {\"time.mu\": 10.00, \"time.gc\": 2.50, \"total-work.count\": 42},\n  \
+             \"gcs\": [\n    {\"time.mu\": 4.00, \"time.gc\": 1.00},\n    \
+             {\"time.mu\": 6.00, \"time.gc\": 1.50}\n  ]\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_json_escapes_strings() {
+        assert_eq!(json_value("1.5"), "1.5");
+        assert_eq!(json_value("NaN"), "\"NaN\"");
+        assert_eq!(json_string("a\"b\\c\n"), "\"a\\\"b\\\\c\\u000a\"");
+    }
+}
@@ -1,8 +1,8 @@
 use crate::mmtk::MMTK;
 use crate::util::statistics::counter::*;
+use crate::util::statistics::sink::{create_sink, StatsSink, StatsSnapshot, TextSink};
 use crate::util::statistics::Timer;
 use crate::vm::VMBinding;
-use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::sync::Mutex;
@@ -143,51 +143,65 @@ impl Stats {
         }
     }
 
+    /// Print a table of the totals to stdout.
     pub fn print_stats<VM: VMBinding>(&self, mmtk: &'static MMTK<VM>) {
-        println!(
-            "============================ MMTk Statistics Totals ============================"
-        );
-        let scheduler_stat = mmtk.scheduler.statistics();
-        self.print_column_names(&scheduler_stat);
-        print!("{}\t", self.get_phase() / 2);
-        let counter = self.counters.lock().unwrap();
-        for iter in &(*counter) {
-            let c = iter.lock().unwrap();
-            if c.merge_phases() {
-                c.print_total(None);
-                print!("\t");
-            } else {
-                c.print_total(Some(true));
-                print!("\t");
-                c.print_total(Some(false));
-                print!("\t");
-            }
+        let snapshot = self.snapshot(mmtk);
+        if let Err(e) = TextSink(std::io::stdout()).write(&snapshot) {
+            warn!("Failed to print the statistics: {}", e);
         }
-        for value in scheduler_stat.values() {
-            print!("{}\t", value);
+    }
+
+    /// Write the statistics in the format and to the file set by the `stats_format` and `stats_file` options.
+    pub fn write_stats<VM: VMBinding>(&self, mmtk: &'static MMTK<VM>) {
+        let snapshot = self.snapshot(mmtk);
+        let options = &mmtk.options;
+        let result = create_sink(options.stats_format, &options.stats_file)
+            .and_then(|mut sink| sink.write(&snapshot));
+        if let Err(e) = result {
+            warn!("Failed to write the statistics: {}", e);
         }
-        println!();
-        print!("Total time: ");
-        self.total_time.lock().unwrap().print_total(None);
-        println!(" ms");
-        println!("------------------------------ End MMTk Statistics -----------------------------")
     }
 
-    pub fn print_column_names(&self, scheduler_stat: &HashMap<String, String>) {
-        print!("GC\t");
-        let counter = self.counters.lock().unwrap();
-        for iter in &(*counter) {
+    /// Take a snapshot of the statistics gathered so far. If the statistics are still being gathered,
+    /// the values of the current phase are not included yet.
+    pub fn snapshot<VM: VMBinding>(&self, mmtk: &'static MMTK<VM>) -> StatsSnapshot {
+        let phase = self.get_phase();
+        let gc_count = phase / 2;
+        let mut snapshot = StatsSnapshot {
+            gc_count,
+            gcs: vec![vec![]; gc_count],
+            ..Default::default()
+        };
+        let counters = self.counters.lock().unwrap();
+        for iter in &(*counters) {
             let c = iter.lock().unwrap();
+            // GC i is phase 2i + 1, after the mutator phase 2i.
             if c.merge_phases() {
-                print!("{}\t", c.name());
+                snapshot.columns.push(c.name().clone());
+                snapshot.totals.push(c.format_value(c.get_total(None)));
+                for (i, gc) in snapshot.gcs.iter_mut().enumerate() {
+                    gc.push(c.format_value(c.get_count(2 * i) + c.get_count(2 * i + 1)));
+                }
             } else {
-                print!("{}.mu\t{}.gc\t", c.name(), c.name());
+                snapshot.columns.push(format!("{}.mu", c.name()));
+                snapshot.columns.push(format!("{}.gc", c.name()));
+                snapshot
+                    .totals
+                    .push(c.format_value(c.get_total(Some(true))));
+                snapshot
+                    .totals
+                    .push(c.format_value(c.get_total(Some(false))));
+                for (i, gc) in snapshot.gcs.iter_mut().enumerate() {
+                    gc.push(c.format_value(c.get_count(2 * i)));
+                    gc.push(c.format_value(c.get_count(2 * i + 1)));
+                }
             }
         }
-        for name in scheduler_stat.keys() {
-            print!("{}\t", name);
-        }
-        println!();
+        drop(counters);
+        let total_time = self.total_time.lock().unwrap();
+        snapshot.total_time = total_time.format_value(total_time.get_total(None));
+        snapshot.scheduler = mmtk.scheduler.statistics().into_iter().collect();
+        snapshot
     }
 
     pub fn start_all(&self) {
@@ -206,7 +220,7 @@ impl Stats {
 
     pub fn stop_all<VM: VMBinding>(&self, mmtk: &'static MMTK<VM>) {
         self.stop_all_counters();
-        self.print_stats(mmtk);
+        self.write_stats(mmtk);
     }
 
     fn stop_all_counters(&self) {
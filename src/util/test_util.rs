@@ -1,3 +1,5 @@
+use crate::plan::{TraceLocal, TransitiveClosure};
+use crate::util::{Address, ObjectReference};
 use std::sync::mpsc;
 use std::sync::Mutex;
 use std::thread;
@@ -35,3 +37,76 @@ where
     let _lock = SERIAL_TEST_LOCK.lock();
     f();
 }
+
+pub fn object(addr: usize) -> ObjectReference {
+    unsafe { Address::from_usize(addr).to_object_reference() }
+}
+
+pub fn moved(o: ObjectReference) -> ObjectReference {
+    object(o.to_address().as_usize() + 0x1000)
+}
+
+/// A trace that records the objects it traces and retains. If it is moving, every object moves up by 0x1000 bytes.
+pub struct MockTrace {
+    moving: bool,
+    pub traced: Vec<ObjectReference>,
+    pub retained: Vec<ObjectReference>,
+}
+
+impl MockTrace {
+    pub fn new(moving: bool) -> Self {
+        Self {
+            moving,
+            traced: vec![],
+            retained: vec![],
+        }
+    }
+}
+
+impl TransitiveClosure for MockTrace {
+    fn process_edge(&mut self, _slot: Address) {
+        unreachable!()
+    }
+    fn process_node(&mut self, _object: ObjectReference) {
+        unreachable!()
+    }
+}
+
+impl TraceLocal for MockTrace {
+    fn process_roots(&mut self) {
+        unreachable!()
+    }
+    fn process_root_edge(&mut self, _slot: Address, _untraced: bool) {
+        unreachable!()
+    }
+    fn trace_object(&mut self, object: ObjectReference) -> ObjectReference {
+        self.traced.push(object);
+        self.get_forwarded_reference(object)
+    }
+    fn get_forwarded_reference(&mut self, object: ObjectReference) -> ObjectReference {
+        if self.moving {
+            moved(object)
+        } else {
+            object
+        }
+    }
+    fn complete_trace(&mut self) {
+        unreachable!()
+    }
+    fn release(&mut self) {
+        unreachable!()
+    }
+    fn process_interior_edge(&mut self, _target: ObjectReference, _slot: Address, _root: bool) {
+        unreachable!()
+    }
+    fn report_delayed_root_edge(&mut self, _slot: Address) {
+        unreachable!()
+    }
+    fn will_not_move_in_current_collection(&self, _obj: ObjectReference) -> bool {
+        !self.moving
+    }
+    fn retain_referent(&mut self, object: ObjectReference) -> ObjectReference {
+        self.retained.push(object);
+        self.trace_object(object)
+    }
+}
@@ -26,8 +26,8 @@ impl<'a, VM: VMBinding> Iterator for SynchronizedMutatorIterator<'a, VM> {
 
 /// VM-specific methods for the current plan.
 pub trait ActivePlan<VM: VMBinding> {
-    /// Return a reference to the current plan.
-    // TODO: I don't know how this can be implemented when we have multiple MMTk instances.
+    /// Return a reference to the current plan. With multiple MMTk instances, this is the plan of the
+    /// instance that the current thread (a mutator, or a GC thread spawned by the instance) works for.
     // This function is used by space and phase to refer to the current plan.
     // Possibly we should remove the use of this function, and remove this function?
     fn global() -> &'static dyn Plan<VM = VM>;
@@ -56,4 +56,12 @@ pub trait Collection<VM: VMBinding> {
     fn out_of_memory(_tls: OpaquePointer) {
         panic!("Out of memory!");
     }
+
+    /// Inform the VM that some finalizable objects became unreachable in the current GC, and can be fetched
+    /// with [`get_finalized_object()`](../memory_manager/fn.get_finalized_object.html). This is called during GC,
+    /// so the VM should only wake up the thread that runs the finalizers here, rather than running them.
+    ///
+    /// Arguments:
+    /// * `tls`: The thread pointer for the GC worker.
+    fn schedule_finalization(_tls: OpaquePointer) {}
 }
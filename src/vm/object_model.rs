@@ -60,6 +60,25 @@ pub trait ObjectModel<VM: VMBinding> {
     /// * `to`: The region to be copied to.
     fn get_reference_when_copied_to(from: ObjectReference, to: Address) -> ObjectReference;
 
+    /// Return the size of an object once it is copied. This may differ from its current size, e.g.
+    /// if the VM adds a field to the copy of a hashed object.
+    ///
+    /// Arguments:
+    /// * `object`: The object to be copied.
+    fn get_size_when_copied(object: ObjectReference) -> usize;
+
+    /// Return the alignment of an object once it is copied.
+    ///
+    /// Arguments:
+    /// * `object`: The object to be copied.
+    fn get_align_when_copied(object: ObjectReference) -> usize;
+
+    /// Return the alignment offset of an object once it is copied.
+    ///
+    /// Arguments:
+    /// * `object`: The object to be copied.
+    fn get_align_offset_when_copied(object: ObjectReference) -> isize;
+
     /// Return the size used by an object.
     ///
     /// Arguments:
@@ -74,6 +93,15 @@ pub trait ObjectModel<VM: VMBinding> {
     /// * `reference`: The object to be queried.
     fn get_type_descriptor(reference: ObjectReference) -> &'static [i8];
 
+    /// Return the object whose storage starts at `start`, or `None` if there is no object there.
+    /// MMTk uses this to walk the objects in a bump pointer region (see `memory_manager::enumerate_objects()`).
+    /// `start` is aligned to `VMBinding::MIN_ALIGNMENT`, and it is either the start of an object, in an
+    /// alignment gap filled with `VMBinding::ALIGNMENT_VALUE`, or in zeroed memory that was never allocated.
+    ///
+    /// Arguments:
+    /// * `start`: The address to be queried.
+    fn get_object_from_start_address(start: Address) -> Option<ObjectReference>;
+
     /// Return the lowest address of the storage associated with an object.
     ///
     /// Arguments:
@@ -30,6 +30,59 @@ pub trait ReferenceGlue<VM: VMBinding> {
     /// * `referent`: The referent object reference.
     fn set_referent(reff: ObjectReference, referent: ObjectReference);
 
+    /// Get the time when the referent of a soft reference was last accessed, in milliseconds since the Unix epoch.
+    /// MMTk clears the referent once it is not accessed for longer than `soft_ref_lru_policy_ms_per_mb` milliseconds
+    /// per MB of free heap, even if the soft reference is reachable. By default, the VM does not record the time, and
+    /// MMTk keeps the referents of all the reachable soft references, unless the GC is an emergency collection.
+    ///
+    /// Arguments:
+    /// * `reff`: The object reference for the soft reference.
+    fn get_soft_reference_timestamp(_reff: ObjectReference) -> Option<u64> {
+        None
+    }
+
+    /// Get the key of an ephemeron registered with `add_ephemeron_candidate()`. MMTk reads the key and the value
+    /// during each GC, so the VM may change the value of the entry between GCs. If the entry has been removed from
+    /// its container, this should return a null object reference, and MMTk drops the entry. A VM that never registers
+    /// ephemerons does not need to implement this, or `get_ephemeron_value()` and `update_ephemeron()`.
+    ///
+    /// Arguments:
+    /// * `container`: The object reference for the container of the ephemeron. The container may have been copied
+    ///   to this address in the current GC, but its ephemerons are not updated yet.
+    /// * `id`: The id of the ephemeron, as given to `add_ephemeron_candidate()`.
+    fn get_ephemeron_key(_container: ObjectReference, _id: usize) -> ObjectReference {
+        panic!("binding does not register ephemerons")
+    }
+
+    /// Get the value of an ephemeron registered with `add_ephemeron_candidate()`. The value may be null.
+    ///
+    /// Arguments:
+    /// * `container`: The object reference for the container of the ephemeron.
+    /// * `id`: The id of the ephemeron, as given to `add_ephemeron_candidate()`.
+    fn get_ephemeron_value(_container: ObjectReference, _id: usize) -> ObjectReference {
+        panic!("binding does not register ephemerons")
+    }
+
+    /// Update an ephemeron registered with `add_ephemeron_candidate()` near the end of a GC. If the key is not reachable,
+    /// the entry should be removed from its container, and the key and the value are null. Otherwise, the key and the value
+    /// are the addresses of the objects after the GC, and they should be stored in the entry.
+    ///
+    /// Arguments:
+    /// * `container`: The object reference for the container of the ephemeron. This is where the container is now,
+    ///   which is its address after the GC, unless the plan moves objects later (e.g. MarkCompact moves the container
+    ///   along with the updated entry afterwards).
+    /// * `id`: The id of the ephemeron, as given to `add_ephemeron_candidate()`.
+    /// * `key`: The key of the ephemeron.
+    /// * `value`: The value of the ephemeron.
+    fn update_ephemeron(
+        _container: ObjectReference,
+        _id: usize,
+        _key: ObjectReference,
+        _value: ObjectReference,
+    ) {
+        panic!("binding does not register ephemerons")
+    }
+
     /// Process a reference with the current semantics and return an updated reference (e.g. with a new address)
     /// if the reference is still alive, otherwise return a null object reference.
     ///
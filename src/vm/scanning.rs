@@ -1,5 +1,5 @@
 use crate::plan::{Mutator, TransitiveClosure};
-use crate::scheduler::gc_work::ProcessEdgesWork;
+use crate::scheduler::gc_work::{ProcessEdgesWork, WeakRootTracer};
 use crate::scheduler::GCWorker;
 use crate::util::ObjectReference;
 use crate::util::OpaquePointer;
@@ -48,9 +48,13 @@ pub trait Scanning<VM: VMBinding> {
     );
 
     /// Scan all the mutators for roots.
+    ///
+    /// The roots are usually reported as slots in `W` work packets. A VM that cannot find the slots
+    /// precisely (e.g. without stack maps) can report the words on its stacks as ambiguous roots with
+    /// `ProcessAmbiguousRoots` work packets instead. The objects they refer to are not moved during the GC.
     fn scan_thread_roots<W: ProcessEdgesWork<VM = VM>>();
 
-    /// Scan one mutator for roots.
+    /// Scan one mutator for roots. The roots can be reported as in `scan_thread_roots()`.
     ///
     /// Arguments:
     /// * `mutator`: The reference to the mutator whose roots will be scanned.
@@ -64,6 +68,18 @@ pub trait Scanning<VM: VMBinding> {
     /// goes here.
     fn scan_vm_specific_roots<W: ProcessEdgesWork<VM = VM>>();
 
+    /// Process the weak roots of the VM (e.g. weak global handles and interned string tables) after the
+    /// transitive closure. `tracer` tells whether each object survived the GC, and where it is after the GC,
+    /// so the VM can remove the dead objects from its tables and update the others. The VM can split the work
+    /// by adding work packets to the bucket of `tracer.stage()`. Unless it does a backup trace, the RC plan calls
+    /// this at the end of the GC, once it has freed the dead objects, so the VM must not read the objects that
+    /// `tracer` reports dead. By default, the VM has no weak roots.
+    ///
+    /// Arguments:
+    /// * `tracer`: Tells whether an object is alive, and its new address.
+    /// * `worker`: The GC worker that is processing the weak roots.
+    fn process_weak_roots(_tracer: WeakRootTracer, _worker: &mut GCWorker<VM>) {}
+
     /// Return whether the VM supports return barriers. This is unused at the moment.
     fn supports_return_barrier() -> bool;
 }
@@ -6,6 +6,7 @@ use std::ffi::CStr;
 use mmtk::memory_manager;
 use mmtk::AllocationSemantics;
 use mmtk::util::{ObjectReference, OpaquePointer, Address};
+use mmtk::util::heap::HeapSize;
 use mmtk::scheduler::GCWorker;
 use mmtk::Mutator;
 use mmtk::MMTK;
@@ -18,7 +19,7 @@ pub extern "C" fn gc_init(heap_size: usize) {
     // Casting `SINGLETON` as mutable is safe because `gc_init` will only be executed once by a single thread during startup.
     #[allow(clippy::cast_ref_to_mut)]
     let singleton_mut = unsafe { &mut *(&*SINGLETON as *const MMTK<DummyVM> as *mut MMTK<DummyVM>) };
-    memory_manager::gc_init(singleton_mut, heap_size)
+    memory_manager::gc_init(singleton_mut, HeapSize::Fixed(heap_size))
 }
 
 #[no_mangle]
@@ -50,7 +51,7 @@ pub extern "C" fn post_alloc(mutator: *mut Mutator<DummyVM>, refer: ObjectRefere
 
 #[no_mangle]
 pub extern "C" fn will_never_move(object: ObjectReference) -> bool {
-    !object.is_movable()
+    memory_manager::will_never_move(object)
 }
 
 #[no_mangle]
@@ -93,6 +94,30 @@ pub extern "C" fn is_mapped_address(address: Address) -> bool {
     address.is_mapped()
 }
 
+#[cfg(feature = "is_mmtk_object")]
+#[no_mangle]
+pub extern "C" fn is_mmtk_object(addr: Address) -> bool {
+    memory_manager::is_mmtk_object(addr)
+}
+
+#[cfg(feature = "object_pinning")]
+#[no_mangle]
+pub extern "C" fn pin_object(object: ObjectReference) -> bool {
+    memory_manager::pin_object(object)
+}
+
+#[cfg(feature = "object_pinning")]
+#[no_mangle]
+pub extern "C" fn unpin_object(object: ObjectReference) -> bool {
+    memory_manager::unpin_object(object)
+}
+
+#[cfg(feature = "object_pinning")]
+#[no_mangle]
+pub extern "C" fn is_pinned(object: ObjectReference) -> bool {
+    memory_manager::is_pinned(object)
+}
+
 #[no_mangle]
 pub extern "C" fn modify_check(object: ObjectReference) {
     memory_manager::modify_check(&SINGLETON, object)
@@ -118,6 +143,24 @@ pub extern "C" fn add_phantom_candidate(reff: ObjectReference, referent: ObjectR
     memory_manager::add_phantom_candidate(&SINGLETON, reff, referent)
 }
 
+#[no_mangle]
+pub extern "C" fn add_ephemeron_candidate(container: ObjectReference, id: usize) {
+    memory_manager::add_ephemeron_candidate(&SINGLETON, container, id)
+}
+
+#[no_mangle]
+pub extern "C" fn add_finalizer(object: ObjectReference) {
+    memory_manager::add_finalizer(&SINGLETON, object)
+}
+
+#[no_mangle]
+pub extern "C" fn get_finalized_object() -> ObjectReference {
+    match memory_manager::get_finalized_object(&SINGLETON) {
+        Some(object) => object,
+        None => unsafe { Address::ZERO.to_object_reference() },
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn harness_begin(tls: OpaquePointer) {
     memory_manager::harness_begin(&SINGLETON, tls)
@@ -2,40 +2,76 @@ use mmtk::vm::ObjectModel;
 use mmtk::util::{Address, ObjectReference};
 use mmtk::AllocationSemantics;
 use mmtk::CopyContext;
+use mmtk::vm::VMBinding;
 use DummyVM;
 
+/// The objects of DummyVM start with a word that holds the size of the object in bytes, followed by a word
+/// for the GC byte (and the forwarding pointer), and a word that holds the alignment of the object.
+/// The tests write the size and the alignment after allocating an object. As the size is never zero,
+/// MMTk can tell the objects from the unused memory when it walks a region.
+pub const OBJECT_SIZE_OFFSET: usize = 0;
+pub const OBJECT_ALIGN_OFFSET: usize = 16;
+
 pub struct VMObjectModel {}
 
 impl ObjectModel<DummyVM> for VMObjectModel {
     const HAS_GC_BYTE: bool = true;
-    const GC_BYTE_OFFSET: isize = 0;
+    const GC_BYTE_OFFSET: isize = 8;
     
     fn copy(_from: ObjectReference, _semantics: AllocationSemantics, _copy_context: &mut impl CopyContext) -> ObjectReference {
         unimplemented!()
     }
 
-    fn copy_to(_from: ObjectReference, _to: ObjectReference, _region: Address) -> Address {
-        unimplemented!()
+    fn copy_to(from: ObjectReference, to: ObjectReference, region: Address) -> Address {
+        // The compactor may slide an object over itself.
+        let size = Self::get_current_size(from);
+        unsafe { std::ptr::copy(from.to_address().to_ptr::<u8>(), to.to_address().to_mut_ptr::<u8>(), size) };
+        region + size
     }
 
-    fn get_current_size(_object: ObjectReference) -> usize {
-        unimplemented!()
+    fn get_size_when_copied(object: ObjectReference) -> usize {
+        Self::get_current_size(object)
     }
 
-    fn get_reference_when_copied_to(_from: ObjectReference, _to: Address) -> ObjectReference {
-        unimplemented!()
+    fn get_align_when_copied(object: ObjectReference) -> usize {
+        unsafe { (object.to_address() + OBJECT_ALIGN_OFFSET).load::<usize>() }
+    }
+
+    fn get_align_offset_when_copied(_object: ObjectReference) -> isize {
+        0
+    }
+
+    fn get_current_size(object: ObjectReference) -> usize {
+        unsafe { (object.to_address() + OBJECT_SIZE_OFFSET).load::<usize>() }
+    }
+
+    fn get_reference_when_copied_to(_from: ObjectReference, to: Address) -> ObjectReference {
+        unsafe { to.to_object_reference() }
     }
 
     fn get_type_descriptor(_reference: ObjectReference) -> &'static [i8] {
         unimplemented!()
     }
 
-    fn object_start_ref(_object: ObjectReference) -> Address {
-        unimplemented!()
+    fn get_object_from_start_address(start: Address) -> Option<ObjectReference> {
+        if !start.is_aligned_to(std::mem::size_of::<usize>()) {
+            return None;
+        }
+        // An alignment gap is filled with `ALIGNMENT_VALUE`, one int at a time.
+        let size = unsafe { start.load::<usize>() };
+        if size == 0 || size as u32 == DummyVM::ALIGNMENT_VALUE as u32 {
+            None
+        } else {
+            Some(unsafe { start.to_object_reference() })
+        }
     }
 
-    fn ref_to_address(_object: ObjectReference) -> Address {
-        unimplemented!()
+    fn object_start_ref(object: ObjectReference) -> Address {
+        object.to_address()
+    }
+
+    fn ref_to_address(object: ObjectReference) -> Address {
+        object.to_address()
     }
 
     fn dump_object(_object: ObjectReference) {
@@ -0,0 +1,26 @@
+use crate::api::*;
+use crate::SINGLETON;
+use mmtk::memory_manager;
+use mmtk::util::OpaquePointer;
+use mmtk::AllocationSemantics;
+
+#[test]
+pub fn alloc_bit_is_mmtk_object_and_enumerate_objects() {
+    gc_init(200*1024*1024);
+    let handle = bind_mutator(OpaquePointer::UNINITIALIZED);
+
+    let addr = alloc(handle, 16, 8, 0, AllocationSemantics::Default);
+    let object = unsafe { addr.to_object_reference() };
+    // The object is only known to MMTk once it is initialized
+    assert!(!is_mmtk_object(addr));
+    post_alloc(handle, object, 16, AllocationSemantics::Default);
+    assert!(is_mmtk_object(addr));
+    // Addresses inside the object, or outside the heap, are not objects
+    assert!(!is_mmtk_object(addr + 8usize));
+    assert!(!is_mmtk_object(addr + 1usize));
+    assert!(!is_mmtk_object(unsafe { mmtk::util::Address::from_usize(8) }));
+
+    let mut objects = vec![];
+    memory_manager::enumerate_objects(&SINGLETON, |o| objects.push(o));
+    assert_eq!(objects, vec![object]);
+}
@@ -0,0 +1,33 @@
+use crate::api::*;
+use crate::tests::{alloc_aligned_object, alloc_object};
+use crate::SINGLETON;
+use mmtk::memory_manager;
+use mmtk::util::OpaquePointer;
+use mmtk::AllocationSemantics;
+
+#[test]
+pub fn enumerate_objects() {
+    std::env::set_var("MMTK_PLAN", "SemiSpace");
+    gc_init(200*1024*1024);
+    let handle = bind_mutator(OpaquePointer::UNINITIALIZED);
+
+    // Allocate objects in the copy space, the LOS, the immortal space and the non-moving space
+    let mut allocated = vec![];
+    for semantics in [
+        AllocationSemantics::Default,
+        AllocationSemantics::Default,
+        AllocationSemantics::Los,
+        AllocationSemantics::Immortal,
+        AllocationSemantics::NonMoving,
+    ] {
+        allocated.push(alloc_object(handle, 32, semantics));
+    }
+    // An object whose alignment leaves a gap before it
+    allocated.push(alloc_aligned_object(handle, 24, 16, AllocationSemantics::Default));
+
+    let mut objects = vec![];
+    memory_manager::enumerate_objects(&SINGLETON, |o| objects.push(o));
+    objects.sort_by_key(|o| o.to_address().as_usize());
+    allocated.sort_by_key(|o| o.to_address().as_usize());
+    assert_eq!(objects, allocated);
+}
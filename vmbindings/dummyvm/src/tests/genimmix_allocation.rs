@@ -0,0 +1,28 @@
+use crate::api::*;
+use crate::tests::alloc_object;
+use crate::{DummyVM, SINGLETON};
+use mmtk::plan::genimmix::GenImmix;
+use mmtk::policy::immix::MAX_IMMIX_OBJECT_SIZE;
+use mmtk::policy::space::Space;
+use mmtk::util::OpaquePointer;
+use mmtk::AllocationSemantics;
+
+#[test]
+pub fn genimmix_allocation() {
+    std::env::set_var("MMTK_PLAN", "GenImmix");
+    gc_init(200*1024*1024);
+    let handle = bind_mutator(OpaquePointer::UNINITIALIZED);
+    let genimmix = SINGLETON.plan.downcast_ref::<GenImmix<DummyVM>>().unwrap();
+
+    // New objects go to the nursery, and are only copied into the immix space by a GC
+    let object = alloc_object(handle, 32, AllocationSemantics::Default);
+    assert!(genimmix.nursery.in_space(object));
+    assert!(!genimmix.immix_space.in_space(object));
+
+    // An object that could not be promoted into the immix space goes to the large object space
+    let fits = alloc_object(handle, MAX_IMMIX_OBJECT_SIZE, AllocationSemantics::Default);
+    assert!(genimmix.nursery.in_space(fits));
+    let large = alloc_object(handle, MAX_IMMIX_OBJECT_SIZE + 8, AllocationSemantics::Default);
+    assert!(!genimmix.nursery.in_space(large));
+    assert!(genimmix.common.get_los().in_space(large));
+}
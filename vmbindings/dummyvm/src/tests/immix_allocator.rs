@@ -0,0 +1,32 @@
+use crate::api::*;
+use crate::tests::alloc_object;
+use crate::{DummyVM, SINGLETON};
+use mmtk::plan::immix::Immix;
+use mmtk::policy::immix::{Block, BYTES_IN_LINE, MAX_IMMIX_OBJECT_SIZE};
+use mmtk::policy::space::Space;
+use mmtk::util::OpaquePointer;
+use mmtk::AllocationSemantics;
+
+#[test]
+pub fn immix_allocator() {
+    std::env::set_var("MMTK_PLAN", "Immix");
+    gc_init(200*1024*1024);
+    let handle = bind_mutator(OpaquePointer::UNINITIALIZED);
+    let immix = SINGLETON.plan.downcast_ref::<Immix<DummyVM>>().unwrap();
+
+    // Small objects are bump allocated into a clean block
+    let first = alloc_object(handle, 32, AllocationSemantics::Default);
+    let second = alloc_object(handle, 32, AllocationSemantics::Default);
+    assert!(immix.immix_space.in_space(first));
+    assert_eq!(second.to_address(), first.to_address() + 32usize);
+
+    // A medium object that fits in the current region is allocated there too
+    let medium = alloc_object(handle, 2 * BYTES_IN_LINE, AllocationSemantics::Default);
+    assert!(immix.immix_space.in_space(medium));
+    assert_eq!(Block::containing(medium.to_address()), Block::containing(first.to_address()));
+
+    // No block can fit a larger object
+    let large = alloc_object(handle, MAX_IMMIX_OBJECT_SIZE + 8, AllocationSemantics::Default);
+    assert!(!immix.immix_space.in_space(large));
+    assert!(SINGLETON.plan.common().get_los().in_space(large));
+}
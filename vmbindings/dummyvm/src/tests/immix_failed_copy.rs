@@ -0,0 +1,61 @@
+use crate::api::*;
+use crate::tests::{alloc_object, Scanned};
+use crate::{DummyVM, SINGLETON};
+use mmtk::plan::immix::{Immix, IMMIX_CONSTRAINTS};
+use mmtk::plan::PlanConstraints;
+use mmtk::policy::immix::Block;
+use mmtk::policy::space::SFT;
+use mmtk::util::{Address, ObjectReference, OpaquePointer};
+use mmtk::{AllocationSemantics, CopyContext};
+
+/// A copy context for a GC thread that finds no clean block to evacuate into.
+struct ExhaustedCopyContext;
+
+impl CopyContext for ExhaustedCopyContext {
+    type VM = DummyVM;
+    fn constraints(&self) -> &'static PlanConstraints {
+        &IMMIX_CONSTRAINTS
+    }
+    fn init(&mut self, _tls: OpaquePointer) {}
+    fn prepare(&mut self) {}
+    fn release(&mut self) {}
+    fn alloc_copy(
+        &mut self,
+        _original: ObjectReference,
+        _bytes: usize,
+        _align: usize,
+        _offset: isize,
+        _semantics: AllocationSemantics,
+    ) -> Address {
+        unsafe { Address::zero() }
+    }
+}
+
+#[test]
+pub fn immix_failed_copy() {
+    std::env::set_var("MMTK_PLAN", "Immix");
+    gc_init(200*1024*1024);
+    let handle = bind_mutator(OpaquePointer::UNINITIALIZED);
+    let object = alloc_object(handle, 32, AllocationSemantics::Default);
+
+    // An emergency GC defragments, and the block of the object is to be evacuated
+    let immix = SINGLETON.plan.downcast_ref::<Immix<DummyVM>>().unwrap();
+    immix.immix_space.prepare(true);
+    Block::containing(object.to_address()).set_as_defrag_source(true);
+
+    // The object cannot be copied, so it is marked in place and scanned once
+    let mut copy_context = ExhaustedCopyContext;
+    let mut scanned = Scanned::default();
+    for _ in 0..2 {
+        let traced = immix.immix_space.trace_object(
+            &mut scanned,
+            object,
+            AllocationSemantics::Default,
+            &mut copy_context,
+        );
+        assert_eq!(traced, object);
+    }
+    assert_eq!(scanned.0, vec![object]);
+    assert!(immix.immix_space.is_live(object));
+    assert_eq!(immix.immix_space.get_forwarded_object(object), Some(object));
+}
@@ -0,0 +1,23 @@
+use crate::api::*;
+use crate::tests::alloc_object;
+use crate::{DummyVM, SINGLETON};
+use mmtk::plan::immix::Immix;
+use mmtk::policy::space::SFT;
+use mmtk::util::OpaquePointer;
+use mmtk::AllocationSemantics;
+
+#[test]
+pub fn immix_pin_for_gc() {
+    std::env::set_var("MMTK_PLAN", "Immix");
+    gc_init(200*1024*1024);
+    let handle = bind_mutator(OpaquePointer::UNINITIALIZED);
+    let object = alloc_object(handle, 32, AllocationSemantics::Default);
+
+    let immix = SINGLETON.plan.downcast_ref::<Immix<DummyVM>>().unwrap();
+    assert!(immix.immix_space.is_movable());
+    // An ambiguous root keeps the object in place, and only the first one needs to scan it
+    assert!(immix.immix_space.pin_for_gc(object));
+    assert!(!immix.immix_space.pin_for_gc(object));
+    assert!(immix.immix_space.is_live(object));
+    assert_eq!(immix.immix_space.get_forwarded_object(object), Some(object));
+}
@@ -0,0 +1,50 @@
+use crate::api::*;
+use crate::object_model::OBJECT_SIZE_OFFSET;
+use crate::tests::{alloc_aligned_object, alloc_object, Scanned};
+use crate::{DummyVM, SINGLETON};
+use mmtk::plan::markcompact::MarkCompact;
+use mmtk::policy::markcompactspace::GC_EXTRA_HEADER_BYTES;
+use mmtk::policy::space::{Space, SFT};
+use mmtk::util::OpaquePointer;
+use mmtk::AllocationSemantics;
+
+#[test]
+pub fn markcompact_compact() {
+    std::env::set_var("MMTK_PLAN", "MarkCompact");
+    gc_init(200*1024*1024);
+    let handle = bind_mutator(OpaquePointer::UNINITIALIZED);
+    let live = alloc_object(handle, 40, AllocationSemantics::Default);
+    let dead = alloc_object(handle, 32, AllocationSemantics::Default);
+    let moving = alloc_aligned_object(handle, 48, 16, AllocationSemantics::Default);
+    unsafe { (moving.to_address() + 24usize).store(0xdead_beefusize) };
+
+    // Each object is preceded by a header word for its forwarding address
+    let mc = &SINGLETON.plan.downcast_ref::<MarkCompact<DummyVM>>().unwrap().mc_space;
+    assert!(mc.in_space(live));
+    assert_eq!(live.to_address(), mc.common().start + GC_EXTRA_HEADER_BYTES);
+    assert_eq!(dead.to_address(), live.to_address() + 40usize + GC_EXTRA_HEADER_BYTES);
+
+    // An object is only scanned the first time it is marked
+    let mut scanned = Scanned::default();
+    mc.trace_mark_object(&mut scanned, live);
+    mc.trace_mark_object(&mut scanned, moving);
+    mc.trace_mark_object(&mut scanned, live);
+    assert_eq!(scanned.0, vec![live, moving]);
+    assert!(mc.is_live(live));
+    assert!(!mc.is_live(dead));
+
+    // The live objects slide down over the dead one, and the updating closure scans each of them once.
+    // The dead object is not 16-byte aligned, so the aligned object goes to the next word.
+    mc.calculate_forwarding_pointer();
+    let to = dead.to_address() + 8usize;
+    assert!(to.is_aligned_to(16));
+    let mut scanned = Scanned::default();
+    assert_eq!(mc.trace_forward_object(&mut scanned, live), live);
+    assert_eq!(mc.trace_forward_object(&mut scanned, moving).to_address(), to);
+    assert_eq!(mc.trace_forward_object(&mut scanned, moving).to_address(), to);
+    assert_eq!(scanned.0, vec![live, moving]);
+
+    mc.compact();
+    assert_eq!(unsafe { (to + OBJECT_SIZE_OFFSET).load::<usize>() }, 48);
+    assert_eq!(unsafe { (to + 24usize).load::<usize>() }, 0xdead_beef);
+}
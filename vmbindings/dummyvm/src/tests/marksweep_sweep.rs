@@ -0,0 +1,45 @@
+use crate::api::*;
+use crate::tests::{alloc_object, Scanned};
+use crate::{DummyVM, SINGLETON};
+use mmtk::plan::marksweep::MarkSweep;
+use mmtk::policy::space::{Space, SFT};
+use mmtk::util::OpaquePointer;
+use mmtk::AllocationSemantics;
+
+#[test]
+pub fn marksweep_sweep() {
+    std::env::set_var("MMTK_PLAN", "MarkSweep");
+    gc_init(200*1024*1024);
+    let handle = bind_mutator(OpaquePointer::UNINITIALIZED);
+    let live = alloc_object(handle, 32, AllocationSemantics::Default);
+    let dead = alloc_object(handle, 64, AllocationSemantics::Default);
+
+    let ms = &SINGLETON.plan.downcast_ref::<MarkSweep<DummyVM>>().unwrap().ms;
+    assert!(ms.in_space(live));
+    assert!(ms.in_space(dead));
+    let mut objects = vec![];
+    for chunk in ms.active_chunks() {
+        ms.for_each_object_in_chunk(chunk, |object| objects.push(object));
+    }
+    assert!(objects.contains(&live));
+    assert!(objects.contains(&dead));
+
+    // An object is only scanned the first time it is marked
+    let mut scanned = Scanned::default();
+    assert_eq!(ms.trace_object(&mut scanned, live), live);
+    assert_eq!(ms.trace_object(&mut scanned, live), live);
+    assert_eq!(scanned.0, vec![live]);
+    assert!(ms.is_live(live));
+    assert!(!ms.is_live(dead));
+
+    // Sweeping frees the unmarked object, and unmarks the live one for the next GC
+    for chunk in ms.active_chunks() {
+        ms.sweep_chunk(chunk);
+    }
+    assert!(ms.in_space(live));
+    assert!(!ms.in_space(dead));
+    assert!(!ms.is_live(live));
+    let mut scanned = Scanned::default();
+    ms.trace_object(&mut scanned, live);
+    assert_eq!(scanned.0, vec![live]);
+}
@@ -1,4 +1,56 @@
 // Each module should only contain one #[test] function.
 // We should run each module in a separate test process, as we do not have proper
 // setup/teardown procedure for MMTk instances.
-mod issue139;
\ No newline at end of file
+mod issue139;
+mod multiple_instances;
+#[cfg(feature = "is_mmtk_object")]
+mod alloc_bit;
+mod enumerate_objects;
+mod immix_pin_for_gc;
+mod immix_allocator;
+mod immix_failed_copy;
+#[cfg(feature = "object_pinning")]
+mod pin_bit;
+mod non_moving_immix_space;
+mod non_moving_malloc_space;
+mod weak_root_tracer;
+mod marksweep_sweep;
+mod markcompact_compact;
+mod genimmix_allocation;
+
+use crate::api::{alloc, post_alloc};
+use crate::object_model::{OBJECT_ALIGN_OFFSET, OBJECT_SIZE_OFFSET};
+use crate::DummyVM;
+use mmtk::util::{Address, ObjectReference};
+use mmtk::{AllocationSemantics, Mutator, TransitiveClosure};
+
+/// Allocate an object of `size` bytes (at least three words), and record its size in the object.
+pub(crate) fn alloc_object(handle: *mut Mutator<DummyVM>, size: usize, semantics: AllocationSemantics) -> ObjectReference {
+    alloc_aligned_object(handle, size, 8, semantics)
+}
+
+/// Allocate an object of `size` bytes (at least three words) aligned to `align`, and record its size
+/// and alignment in the object.
+pub(crate) fn alloc_aligned_object(handle: *mut Mutator<DummyVM>, size: usize, align: usize, semantics: AllocationSemantics) -> ObjectReference {
+    let addr = alloc(handle, size, align, 0, semantics);
+    assert!(!addr.is_zero());
+    assert!(addr.is_aligned_to(align));
+    unsafe { (addr + OBJECT_SIZE_OFFSET).store(size) };
+    unsafe { (addr + OBJECT_ALIGN_OFFSET).store(align) };
+    let object = unsafe { addr.to_object_reference() };
+    post_alloc(handle, object, size, semantics);
+    object
+}
+
+/// A closure that records the objects that a space asks us to scan.
+#[derive(Default)]
+pub(crate) struct Scanned(pub Vec<ObjectReference>);
+
+impl TransitiveClosure for Scanned {
+    fn process_edge(&mut self, _slot: Address) {
+        unreachable!()
+    }
+    fn process_node(&mut self, object: ObjectReference) {
+        self.0.push(object);
+    }
+}
@@ -0,0 +1,24 @@
+use crate::DummyVM;
+use mmtk::util::options::PlanSelector;
+use mmtk::{MMTKBuilder, MMTK};
+
+fn create() -> MMTK<DummyVM> {
+    let mut builder = MMTKBuilder::new();
+    builder.options.plan(PlanSelector::SemiSpace).unwrap();
+    builder.build::<DummyVM>()
+}
+
+#[test]
+pub fn multiple_instances_with_default_heap_ranges() {
+    let a = create();
+    let b = create();
+    assert!(a.heap_start < a.heap_end);
+    assert!(b.heap_start < b.heap_end);
+    // The default ranges do not overlap
+    assert!(a.heap_end <= b.heap_start || b.heap_end <= a.heap_start);
+
+    // Dropping an instance gives its range back
+    drop(b);
+    let c = create();
+    assert!(c.heap_end <= a.heap_start || a.heap_end <= c.heap_start);
+}
@@ -0,0 +1,45 @@
+use crate::api::*;
+use crate::tests::{alloc_object, Scanned};
+use crate::SINGLETON;
+use mmtk::policy::immix::MAX_IMMIX_OBJECT_SIZE;
+use mmtk::policy::space::{Space, SFT};
+use mmtk::util::OpaquePointer;
+use mmtk::AllocationSemantics;
+
+#[test]
+pub fn non_moving_immix_space() {
+    std::env::set_var("MMTK_PLAN", "SemiSpace");
+    gc_init(200*1024*1024);
+    let handle = bind_mutator(OpaquePointer::UNINITIALIZED);
+    let live = alloc_object(handle, 32, AllocationSemantics::NonMoving);
+    let dead = alloc_object(handle, 32, AllocationSemantics::NonMoving);
+
+    let nonmoving = SINGLETON.plan.common().get_nonmoving();
+    assert!(nonmoving.in_space(live));
+    assert!(nonmoving.in_space(dead));
+    assert!(will_never_move(live));
+    // No block can fit a larger object, so it goes to the large object space, which does not move objects either
+    let large = alloc_object(handle, MAX_IMMIX_OBJECT_SIZE + 8, AllocationSemantics::NonMoving);
+    assert!(SINGLETON.plan.common().get_los().in_space(large));
+    assert!(will_never_move(large));
+
+    // A full-heap GC that only reaches `live` marks it in place
+    nonmoving.prepare(false);
+    let mut scanned = Scanned::default();
+    assert_eq!(nonmoving.trace_object_without_moving(&mut scanned, live), live);
+    assert_eq!(nonmoving.trace_object_without_moving(&mut scanned, live), live);
+    assert_eq!(scanned.0, vec![live]);
+    nonmoving.release();
+    assert!(nonmoving.is_live(live));
+    assert!(!nonmoving.is_live(dead));
+    let mut objects = vec![];
+    nonmoving.enumerate_objects(&mut |object| objects.push(object));
+    assert_eq!(objects, vec![live]);
+
+    // Once `live` is dead too, the GC frees its block
+    assert!(nonmoving.reserved_pages() > 0);
+    nonmoving.prepare(false);
+    nonmoving.release();
+    assert!(!nonmoving.is_live(live));
+    assert_eq!(nonmoving.reserved_pages(), 0);
+}
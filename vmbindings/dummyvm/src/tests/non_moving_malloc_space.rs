@@ -0,0 +1,37 @@
+use crate::api::*;
+use crate::tests::{alloc_object, Scanned};
+use crate::{DummyVM, SINGLETON};
+use mmtk::plan::marksweep::MarkSweep;
+use mmtk::policy::space::{Space, SFT};
+use mmtk::util::OpaquePointer;
+use mmtk::AllocationSemantics;
+
+#[test]
+pub fn non_moving_malloc_space() {
+    std::env::set_var("MMTK_PLAN", "MarkSweep");
+    gc_init(200*1024*1024);
+    let handle = bind_mutator(OpaquePointer::UNINITIALIZED);
+    let live = alloc_object(handle, 32, AllocationSemantics::NonMoving);
+    let dead = alloc_object(handle, 32, AllocationSemantics::NonMoving);
+
+    // The malloc-based plans allocate non-moving objects in their malloc space
+    let ms = &SINGLETON.plan.downcast_ref::<MarkSweep<DummyVM>>().unwrap().ms;
+    assert!(ms.in_space(live));
+    assert!(ms.in_space(dead));
+    assert!(will_never_move(live));
+
+    // A GC that only reaches `live` keeps it where it is, and frees `dead`
+    let mut scanned = Scanned::default();
+    assert_eq!(ms.trace_object(&mut scanned, live), live);
+    assert_eq!(scanned.0, vec![live]);
+    assert!(ms.is_live(live));
+    assert!(!ms.is_live(dead));
+    for chunk in ms.active_chunks() {
+        ms.sweep_chunk(chunk);
+    }
+    assert!(ms.in_space(live));
+    assert!(!ms.in_space(dead));
+    let mut objects = vec![];
+    ms.enumerate_objects(&mut |object| objects.push(object));
+    assert_eq!(objects, vec![live]);
+}
@@ -0,0 +1,30 @@
+use crate::api::*;
+use crate::tests::alloc_object;
+use crate::{DummyVM, SINGLETON};
+use mmtk::plan::semispace::SemiSpace;
+use mmtk::util::OpaquePointer;
+use mmtk::AllocationSemantics;
+
+#[test]
+pub fn pin_bit_pin_unpin_and_release() {
+    std::env::set_var("MMTK_PLAN", "SemiSpace");
+    gc_init(200*1024*1024);
+    let handle = bind_mutator(OpaquePointer::UNINITIALIZED);
+    let object = alloc_object(handle, 32, AllocationSemantics::Default);
+
+    assert!(!is_pinned(object));
+    assert!(pin_object(object));
+    assert!(!pin_object(object));
+    assert!(is_pinned(object));
+    assert!(unpin_object(object));
+    assert!(!unpin_object(object));
+    assert!(!is_pinned(object));
+
+    // Releasing the space as a from-space without marking the object reclaims it, so a
+    // new object at the same address must not be pinned.
+    assert!(pin_object(object));
+    let ss = SINGLETON.plan.downcast_ref::<SemiSpace<DummyVM>>().unwrap();
+    ss.tospace().prepare(true);
+    ss.tospace().release();
+    assert!(!is_pinned(object));
+}
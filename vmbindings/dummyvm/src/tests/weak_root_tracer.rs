@@ -0,0 +1,36 @@
+use crate::api::*;
+use crate::tests::alloc_object;
+use crate::{DummyVM, SINGLETON};
+use mmtk::plan::semispace::SemiSpace;
+use mmtk::policy::space::SFT;
+use mmtk::scheduler::gc_work::WeakRootTracer;
+use mmtk::scheduler::WorkBucketStage;
+use mmtk::util::forwarding_word;
+use mmtk::util::OpaquePointer;
+use mmtk::AllocationSemantics;
+
+#[test]
+pub fn weak_root_tracer_get_forwarded_object() {
+    std::env::set_var("MMTK_PLAN", "SemiSpace");
+    gc_init(200*1024*1024);
+    let handle = bind_mutator(OpaquePointer::UNINITIALIZED);
+    let forwarded = alloc_object(handle, 32, AllocationSemantics::Default);
+    let copy = alloc_object(handle, 32, AllocationSemantics::Default);
+    let kept = alloc_object(handle, 32, AllocationSemantics::Default);
+    let dead = alloc_object(handle, 32, AllocationSemantics::Default);
+
+    // Pretend the space is the from-space of a GC that copied one object and kept one in place.
+    // The copy would be in the to-space, but the tracer does not look at it.
+    let ss = SINGLETON.plan.downcast_ref::<SemiSpace<DummyVM>>().unwrap();
+    let space = ss.tospace();
+    space.prepare(true);
+    forwarding_word::set_forwarding_pointer::<DummyVM>(forwarded, copy);
+    assert!(space.pin_for_gc(kept));
+
+    let tracer = WeakRootTracer::new(WorkBucketStage::PhantomRefClosure);
+    assert_eq!(tracer.get_forwarded_object(forwarded), Some(copy));
+    assert_eq!(tracer.get_forwarded_object(kept), Some(kept));
+    assert_eq!(tracer.get_forwarded_object(dead), None);
+    assert!(tracer.is_live(forwarded));
+    assert!(!tracer.is_live(dead));
+}